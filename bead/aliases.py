@@ -0,0 +1,94 @@
+'''
+Team shared names of input beads.
+
+A `bead-names.toml` file in the workspace directory or in any of its parents
+(e.g. the root of a git repository) maps logical input names to bead names:
+
+    population = "census-population-2020"
+
+    [gdp]
+    bead = "worldbank-gdp"
+    kind = "..."  # optional, only beads of this kind are accepted
+
+Inputs named after an alias are loaded and updated from the bead named in the file,
+so the whole team can be repointed to another bead by editing this one file.
+An input explicitly set to another bead (its input map entry) is not affected.
+'''
+
+from typing import Dict, Optional
+
+import attr
+
+try:
+    import tomllib
+except ImportError:  # Python < 3.11
+    import tomli as tomllib
+
+from .exceptions import UserError
+from .tech.fs import Path
+
+ALIASES_FILE = 'bead-names.toml'
+
+
+class AliasError(UserError):
+    """The bead names file is not valid"""
+
+
+@attr.s(frozen=True, auto_attribs=True)
+class Alias:
+    name: str
+    bead_name: str
+    kind: Optional[str] = None
+
+
+def find_aliases_file(directory) -> Optional[Path]:
+    '''
+    The nearest bead names file in directory or its parents.
+    '''
+    directory = Path(directory).absolute()
+    for candidate in (directory, *directory.parents):
+        path = candidate / ALIASES_FILE
+        if path.is_file():
+            return path
+    return None
+
+
+def _parse_alias(name, value, path) -> Alias:
+    if isinstance(value, str):
+        return Alias(name, value)
+    if isinstance(value, dict) and isinstance(value.get('bead'), str):
+        kind = value.get('kind')
+        if kind is None or isinstance(kind, str):
+            return Alias(name, value['bead'], kind)
+    raise AliasError(
+        f'Alias "{name}" must be a bead name or a table with "bead" (and "kind")',
+        operation='load aliases', path=path)
+
+
+def load_aliases(path) -> Dict[str, Alias]:
+    try:
+        with open(path, 'rb') as f:
+            content = tomllib.load(f)
+    except tomllib.TOMLDecodeError as e:
+        raise AliasError(f'Invalid TOML: {e}', operation='load aliases', path=path)
+    return {name: _parse_alias(name, value, path) for name, value in content.items()}
+
+
+def aliases_for(directory) -> Dict[str, Alias]:
+    '''
+    Aliases in effect for a workspace directory - empty without a bead names file.
+    '''
+    path = find_aliases_file(directory)
+    if path is None:
+        return {}
+    return load_aliases(path)
+
+
+def check_kind(alias: Optional[Alias], bead):
+    '''
+    Raise AliasError, if alias is restricted to a kind, that bead is not of.
+    '''
+    if alias is not None and alias.kind is not None and alias.kind != bead.kind:
+        raise AliasError(
+            f'Bead {bead.name} is not of the kind given for alias "{alias.name}"',
+            operation='check alias kind', bead_name=bead.name)
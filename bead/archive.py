@@ -1,23 +1,47 @@
 import os
 import re
+import unicodedata
+import zipfile
+from typing import Optional, Sequence, Tuple
 
+import attr
 from cached_property import cached_property
 
 from tracelog import TRACELOG
 from .bead import UnpackableBead
+from . import layouts
+from .tech.cancellation import NEVER_CANCELLED
+from .tech.progress import NO_PROGRESS
 from . import meta
+from . import signing
 from . import tech
 
-from .ziparchive import ZipArchive
+from .ziparchive import ZipArchive, content_id_of, read_manifest_bytes
 from .exceptions import InvalidArchive
 
 persistence = tech.persistence
 
-__all__ = ('Archive', 'InvalidArchive')
+__all__ = (
+    'Archive', 'ArchiveSummary', 'InvalidArchive', 'peek_archive_summary', 'read_bead_meta')
 
 
 CACHE_CONTENT_ID = 'content_id'
 CACHE_INPUT_MAP = 'input_map'
+# size and modification time of the zip file the cache was made for - a changed zip
+# invalidates the cache (caches of older versions have no such stamp, they are trusted
+# once and stamped)
+CACHE_FILE_SIZE = 'file_size'
+CACHE_MTIME = 'mtime_ns'
+# the parsed central directory of big zip files - opening them skips parsing it again
+# it is kept in its own file (ENTRIES_SUFFIX), as the meta cache is read on every box scan
+CACHE_ENTRIES = 'entries'
+ENTRIES_SUFFIX = '.xentries'
+# SHA-256 of the zip file, recorded when it is stored - copies are verified against it
+CACHE_ARCHIVE_SHA256 = 'archive_sha256'
+# smaller zip files are parsed quickly, their entry tables would only bloat the cache
+CACHE_ENTRIES_MIN_COUNT = 1000
+# the meta data needed for using an archive without opening its zip
+CACHE_META_KEYS = (meta.META_VERSION, CACHE_CONTENT_ID, meta.KIND, meta.FREEZE_TIME, meta.INPUTS)
 
 
 def _cached_zip_attribute(cache_key: str, ziparchive_attribute):
@@ -35,13 +59,17 @@ def _cached_zip_attribute(cache_key: str, ziparchive_attribute):
 
 
 class Archive(UnpackableBead):
-    def __init__(self, filename: tech.fs.Path, box_name=''):
+    def __init__(self, filename: tech.fs.Path, box_name='', known_meta=None):
+        '''
+        known_meta: already known meta cache values, e.g. from a box index
+        '''
         self.archive_filename = filename
         self.archive_path = tech.fs.Path(filename)
         self.box_name = box_name
         self.name = bead_name_from_file_path(filename)
         self.cache = {}
         self.load_cache()
+        self.cache.update(known_meta or {})
 
         # Check that we can get access to metadata
         #  - either through the cache or through the archive
@@ -54,17 +82,47 @@ class Archive(UnpackableBead):
     def load_cache(self):
         try:
             try:
-                self.cache = persistence.loads(self.cache_path.read_text())
+                cache = persistence.loads(self.cache_path.read_text())
             except persistence.ReadError:
                 TRACELOG(f"Ignoring existing, malformed bead meta cache {self.cache_path}")
+            else:
+                if _is_cache_current(cache, self.archive_path):
+                    self.cache = cache
+                    # entry tables were cached here by earlier versions
+                    self.cache.pop(CACHE_ENTRIES, None)
+                    self._normalize_cached_freeze_time()
+                    if not _is_stamped(cache):
+                        self.save_cache()
+                else:
+                    TRACELOG(f"Ignoring stale bead meta cache {self.cache_path}")
         except FileNotFoundError:
             pass
 
+    def _normalize_cached_freeze_time(self):
+        # caches written for legacy archives might have the original timestamp
+        try:
+            freeze_time_str = self.cache[meta.FREEZE_TIME]
+        except LookupError:
+            return
+        try:
+            self.cache[meta.FREEZE_TIME] = tech.timestamp.normalize_timestamp(freeze_time_str)
+        except ValueError:
+            del self.cache[meta.FREEZE_TIME]
+
     def save_cache(self):
+        self._save_stamped(self.cache, self.cache_path)
+
+    def _save_stamped(self, cache, path):
         try:
-            self.cache_path.write_text(persistence.dumps(self.cache))
+            stat = self.archive_path.stat()
+            cache[CACHE_FILE_SIZE] = stat.st_size
+            cache[CACHE_MTIME] = stat.st_mtime_ns
+            persistence.file_dump(cache, path)
         except FileNotFoundError:
             pass
+        except OSError as e:
+            # the cache is an optimization, e.g. read-only boxes do without it
+            TRACELOG(f"Could not save bead meta cache {path}: {e}")
 
     @property
     def cache_path(self):
@@ -73,6 +131,21 @@ class Archive(UnpackableBead):
 
         return self.archive_path.with_suffix('.xmeta')
 
+    @property
+    def entries_path(self):
+        return self.cache_path.with_suffix(ENTRIES_SUFFIX)
+
+    def _load_entries(self) -> Optional[list]:
+        try:
+            cache = persistence.loads(self.entries_path.read_text())
+        except (OSError, persistence.ReadError):
+            return None
+        if not isinstance(cache, dict) or not _is_stamped(cache):
+            return None
+        if not _is_cache_current(cache, self.archive_path):
+            return None
+        return cache.get(CACHE_ENTRIES)
+
     meta_version = _cached_zip_attribute(meta.META_VERSION, 'meta_version')
     content_id = _cached_zip_attribute(CACHE_CONTENT_ID, 'content_id')
     kind = _cached_zip_attribute(meta.KIND, 'kind')
@@ -90,20 +163,38 @@ class Archive(UnpackableBead):
         self.cache[CACHE_INPUT_MAP] = input_map
         self.save_cache()
 
+    @property
+    def archive_sha256(self) -> Optional[str]:
+        '''
+        Checksum of the zip file - None for archives stored before checksums were recorded.
+        '''
+        return self.cache.get(CACHE_ARCHIVE_SHA256)
+
+    def record_archive_sha256(self, cancel=NEVER_CANCELLED) -> str:
+        '''
+        Compute the checksum of the zip file and save it in the meta cache.
+        '''
+        self.cache[CACHE_ARCHIVE_SHA256] = tech.securehash.file_sha256(self.archive_path, cancel)
+        self.save_cache()
+        return self.cache[CACHE_ARCHIVE_SHA256]
+
     @cached_property
     def ziparchive(self):
-        ziparchive = ZipArchive(self.archive_filename, self.box_name)
+        ziparchive = ZipArchive(self.archive_filename, self.box_name, self._load_entries())
 
         self._check_and_populate_cache(ziparchive)
 
         return ziparchive
 
     def _check_and_populate_cache(self, ziparchive):
+        was_complete = self.has_complete_cache
+
         def ensure(cache_key, value):
             try:
                 if self.cache[cache_key] != value:
                     raise InvalidArchive(
-                        'Cache disagrees with zip meta', self.archive_filename, cache_key)
+                        f'Cache disagrees with zip meta on {cache_key}',
+                        path=self.archive_filename)
             except KeyError:
                 self.cache[cache_key] = value
 
@@ -112,12 +203,47 @@ class Archive(UnpackableBead):
         ensure(meta.KIND, ziparchive.kind)
         ensure(meta.FREEZE_TIME, ziparchive.freeze_time_str)
         ensure(meta.INPUTS, ziparchive.meta[meta.INPUTS])
+        ensure(meta.TAGS, list(ziparchive.tags))
+        ensure(meta.PROVENANCE, ziparchive.meta.get(meta.PROVENANCE))
 
         # need not match
         self.cache.setdefault(CACHE_INPUT_MAP, ziparchive.input_map)
+        # archives in boxes are looked at again and again, loose files are left alone
+        if self.box_name:
+            if not was_complete:
+                self.save_cache()
+            self._cache_entries(ziparchive)
+
+    def _cache_entries(self, ziparchive):
+        if ziparchive.has_cached_entries:
+            return
+        entries = ziparchive.entry_table
+        if len(entries) >= CACHE_ENTRIES_MIN_COUNT:
+            self._save_stamped({CACHE_ENTRIES: entries}, self.entries_path)
+
+    @property
+    def has_complete_cache(self):
+        return all(key in self.cache for key in CACHE_META_KEYS)
+
+    def validate(self, cancel=NEVER_CANCELLED, progress=NO_PROGRESS, checkpoint=None):
+        self.ziparchive.validate(cancel, progress, checkpoint)
+
+    def check(self, cancel=NEVER_CANCELLED, progress=NO_PROGRESS, checkpoint=None):
+        return self.ziparchive.check(cancel, progress, checkpoint)
+
+    @property
+    def signature_path(self):
+        return signing.signature_path(self.archive_path)
+
+    @property
+    def is_signed(self):
+        return self.signature_path.exists()
 
-    def validate(self):
-        self.ziparchive.validate()
+    def verify_signature(self, public_key):
+        '''
+        Raise SignatureError, unless the archive is signed by the owner of public_key.
+        '''
+        signing.verify(self.archive_path, public_key)
 
     @property
     def inputs(self):
@@ -126,23 +252,147 @@ class Archive(UnpackableBead):
         except LookupError:
             return self.ziparchive.inputs
 
-    def extract_dir(self, zip_dir, fs_dir):
-        return self.ziparchive.extract_dir(zip_dir, fs_dir)
+    @property
+    def tags(self) -> Tuple[str, ...]:
+        try:
+            return tuple(self.cache[meta.TAGS])
+        except LookupError:
+            return self.ziparchive.tags
 
-    def extract_file(self, zip_path, fs_path):
-        return self.ziparchive.extract_file(zip_path, fs_path)
+    @property
+    def description(self) -> Optional[str]:
+        return self.ziparchive.meta.get(meta.DESCRIPTION)
 
-    def unpack_code_to(self, fs_dir):
-        self.ziparchive.unpack_code_to(fs_dir)
+    @property
+    def author(self) -> Optional[str]:
+        return self.ziparchive.meta.get(meta.AUTHOR)
 
-    def unpack_data_to(self, fs_dir):
-        self.ziparchive.unpack_data_to(fs_dir)
+    @property
+    def provenance(self) -> Optional[meta.Provenance]:
+        '''
+        How a pipeline runner made the bead - None for manually saved beads.
+        '''
+        if meta.PROVENANCE in self.cache:
+            return meta.parse_provenance(self.cache)
+        return meta.parse_provenance(self.ziparchive.meta)
+
+    def extract_dir(
+            self, zip_dir, fs_dir, verify=False, cancel=NEVER_CANCELLED, progress=NO_PROGRESS,
+            patterns=None):
+        return self.ziparchive.extract_dir(zip_dir, fs_dir, verify, cancel, progress, patterns)
+
+    def extract_file(
+            self, zip_path, fs_path, expected_hash=None, cancel=NEVER_CANCELLED,
+            progress=NO_PROGRESS):
+        return self.ziparchive.extract_file(zip_path, fs_path, expected_hash, cancel, progress)
+
+    def unpack_code_to(self, fs_dir, cancel=NEVER_CANCELLED, progress=NO_PROGRESS):
+        self.ziparchive.unpack_code_to(fs_dir, cancel, progress)
+
+    def unpack_data_to(
+            self, fs_dir, cancel=NEVER_CANCELLED, progress=NO_PROGRESS, patterns=None) -> int:
+        return self.ziparchive.unpack_data_to(fs_dir, cancel, progress, patterns)
 
     def unpack_meta_to(self, workspace):
         workspace.meta = self.ziparchive.meta
         workspace.input_map = self.input_map
 
 
+def _is_stamped(cache) -> bool:
+    return CACHE_FILE_SIZE in cache and CACHE_MTIME in cache
+
+
+def _is_cache_current(cache, archive_path) -> bool:
+    if not _is_stamped(cache):
+        return True
+    try:
+        stat = os.stat(archive_path)
+    except OSError:
+        return False
+    return cache[CACHE_FILE_SIZE] == stat.st_size and cache[CACHE_MTIME] == stat.st_mtime_ns
+
+
+def read_cache(path) -> Optional[dict]:
+    '''
+    The meta cache of the archive at path, if it is up to date and complete - without the zip.
+    '''
+    cache_path = tech.fs.Path(path).with_suffix('.xmeta')
+    try:
+        cache = persistence.loads(cache_path.read_text())
+    except (OSError, persistence.ReadError):
+        return None
+    if not isinstance(cache, dict) or not _is_stamped(cache):
+        return None
+    if not _is_cache_current(cache, path):
+        return None
+    if not all(key in cache for key in CACHE_META_KEYS):
+        return None
+    return cache
+
+
+def read_bead_meta(path) -> dict:
+    '''
+    The bead meta of the archive at path - reading only that entry of the zip.
+    '''
+    try:
+        with zipfile.ZipFile(path) as zf:
+            return persistence.zip_load(zf, layouts.Archive.BEAD_META)
+    except (zipfile.BadZipFile, OSError, KeyError, persistence.ReadError, UnicodeDecodeError):
+        raise InvalidArchive(path=path)
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class ArchiveSummary:
+    name: str
+    meta_version: str
+    kind: str
+    content_id: str
+    freeze_time_str: str
+    inputs: Sequence[meta.InputSpec]
+
+    @property
+    def archive_cache(self):
+        '''
+        Values in the form of an Archive's meta cache.
+        '''
+        return {
+            meta.META_VERSION: self.meta_version,
+            meta.KIND: self.kind,
+            CACHE_CONTENT_ID: self.content_id,
+            meta.FREEZE_TIME: self.freeze_time_str,
+            meta.INPUTS: {
+                input.name: {
+                    meta.INPUT_KIND: input.kind,
+                    meta.INPUT_CONTENT_ID: input.content_id,
+                    meta.INPUT_FREEZE_TIME: input.freeze_time_str}
+                for input in self.inputs}}
+
+
+def peek_archive_summary(path) -> ArchiveSummary:
+    '''
+    Identifying meta data of the archive at path.
+
+    Only the meta and manifest entries of the zip are read, nothing is cached -
+    cheap enough for tools looking at thousands of archives.
+    The archive is not validated.
+    '''
+    try:
+        with zipfile.ZipFile(path) as zf:
+            bead_meta = persistence.zip_load(zf, layouts.Archive.BEAD_META)
+            content_id = content_id_of(read_manifest_bytes(zf, path), path)
+        return ArchiveSummary(
+            name=bead_name_from_file_path(path),
+            meta_version=bead_meta[meta.META_VERSION],
+            kind=bead_meta[meta.KIND],
+            content_id=content_id,
+            freeze_time_str=tech.timestamp.normalize_timestamp(bead_meta[meta.FREEZE_TIME]),
+            inputs=tuple(meta.parse_inputs(bead_meta)))
+    except (
+            zipfile.BadZipFile, OSError, KeyError, TypeError, ValueError,
+            persistence.ReadError, UnicodeDecodeError):
+        raise InvalidArchive(path=path)
+
+
 def bead_name_from_file_path(path):
     '''
     Parse bead name from a file path.
@@ -152,7 +402,7 @@ def bead_name_from_file_path(path):
     name_with_timestamp, ext = os.path.splitext(os.path.basename(path))
     # assert ext == '.zip'  # not enforced to allow having beads with different extensions
     name = re.sub('_[0-9]{8}(?:[tT][-+0-9]*)?$', '', name_with_timestamp)
-    return meta.BeadName(name)
+    return meta.BeadName(unicodedata.normalize('NFC', name))
 
 
 assert 'bead-2015v3' == bead_name_from_file_path('bead-2015v3.zip')
@@ -160,3 +410,6 @@ assert 'bead-2015v3' == bead_name_from_file_path('bead-2015v3_20150923.zip')
 assert 'bead-2015v3' == bead_name_from_file_path('bead-2015v3_20150923T010203012345+0200.zip')
 assert 'bead-2015v3' == bead_name_from_file_path('bead-2015v3_20150923T010203012345-0200.zip')
 assert 'bead-2015v3' == bead_name_from_file_path('path/to/bead-2015v3_20150923.zip')
+assert 'bead-2015v3' == bead_name_from_file_path('bead-2015v3_20150923T0102.zip')
+assert 'bead-2015v3' == bead_name_from_file_path('bead-2015v3_20150923T010203.zip')
+assert '\u00e1' == bead_name_from_file_path('a\u0301_20150923.zip')
@@ -1,7 +1,9 @@
 from abc import ABCMeta, abstractmethod
-from typing import Sequence
+from typing import Mapping, Optional, Sequence
 
-from .tech.timestamp import time_from_timestamp
+from .tech.cancellation import NEVER_CANCELLED
+from .tech.progress import NO_PROGRESS
+from .tech.timestamp import time_from_legacy_timestamp
 from .meta import BeadName, InputSpec
 
 
@@ -29,15 +31,32 @@ class Bead:
     content_id: str
     freeze_time_str: str
     box_name: str
+    # input name -> name of the bead to update the input from
+    input_map: Mapping[str, str]
 
     @property
     def freeze_time(self):
-        return time_from_timestamp(self.freeze_time_str)
+        return time_from_legacy_timestamp(self.freeze_time_str)
 
-    def get_input(self, name):
+    def get_input(self, name) -> Optional[InputSpec]:
         for input in self.inputs:
             if name == input.name:
                 return input
+        return None
+
+    def has_input(self, name) -> bool:
+        '''
+        Is there an input defined for name?
+
+        NOTE: it is not necessarily loaded!
+        '''
+        return self.get_input(name) is not None
+
+    def get_input_bead_name(self, input_nick):
+        '''
+        Name of the bead, that the input is updated from.
+        '''
+        return self.input_map.get(input_nick, input_nick)
 
 
 class UnpackableBead(Bead, metaclass=ABCMeta):
@@ -45,17 +64,23 @@ class UnpackableBead(Bead, metaclass=ABCMeta):
     Provide high-level access to content of a bead.
     '''
 
-    def unpack_to(self, workspace):
-        self.unpack_code_to(workspace.directory)
+    def unpack_to(self, workspace, cancel=NEVER_CANCELLED, progress=NO_PROGRESS):
+        self.unpack_code_to(workspace.directory, cancel, progress)
         workspace.create_directories()
         self.unpack_meta_to(workspace)
 
     @abstractmethod
-    def unpack_data_to(self, fs_dir):
+    def unpack_data_to(
+            self, fs_dir, cancel=NEVER_CANCELLED, progress=NO_PROGRESS, patterns=None) -> int:
+        '''
+        Extract the data files - only the ones selected by patterns, when given.
+
+        Returns the number of files extracted.
+        '''
         pass
 
     @abstractmethod
-    def unpack_code_to(self, fs_dir):
+    def unpack_code_to(self, fs_dir, cancel=NEVER_CANCELLED, progress=NO_PROGRESS):
         pass
 
     @abstractmethod
@@ -14,47 +14,134 @@ Boxes can be used to:
   (this is naive access control, but could work)
 '''
 
+from concurrent.futures import ThreadPoolExecutor
+import contextlib
 from datetime import datetime, timedelta
+import fnmatch
+import glob
+import hashlib
+import itertools
 import os
-from typing import Iterator, Iterable, Sequence
+import re
+import threading
+import time
+import uuid
+import warnings
+import zipfile
+from typing import Dict, Iterator, Iterable, List, Optional, Pattern, Sequence, Tuple
 
-from .archive import Archive, InvalidArchive
-from .exceptions import BoxError
+import attr
+from tracelog import TRACELOG
+
+from .archive import (
+    Archive, CACHE_ARCHIVE_SHA256, ENTRIES_SUFFIX, InvalidArchive, peek_archive_summary,
+    read_cache)
+from .box_index import BoxIndex, IndexEntry, INDEX_FILE
+from .exceptions import BoxError, AmbiguousBead, AmbiguousReference, TransferError
+from . import layouts
+from . import notebooks
+from . import signing
 from . import spec as bead_spec
+from . import zipopener
+from .tech.cancellation import NEVER_CANCELLED
+from .tech.progress import NO_PROGRESS
 from .tech.timestamp import time_from_timestamp
+from .ziparchive import Damage, ZipArchive, UNREADABLE, content_id_of, MANIFEST_SIDECAR_SUFFIX
 from .import tech
 Path = tech.fs.Path
 
+# soft-deleted archives are moved under DELETED_DIR/{deletion timestamp}/
+DELETED_DIR = '.deleted'
+DEFAULT_RETENTION = timedelta(days=30)
+# unchanged archives verified more recently are not verified again by default
+DEFAULT_VERIFICATION_MAX_AGE = timedelta(days=30)
+# files next to archives, belonging to them: meta and entry caches, signature,
+# repaired manifest
+SIDECAR_SUFFIXES = (
+    '.xmeta', ENTRIES_SUFFIX, signing.SIGNATURE_SUFFIX, MANIFEST_SIDECAR_SUFFIX)
+# archives are packed under a temporary name, published only when complete and valid
+STAGED_SUFFIX = '.staged'
+# temporary files of atomic writes (see persistence.file_dump)
+TEMP_SUFFIX = '.tmp'
+# staged archives and temporary files older than this are leftovers of interrupted saves
+DEFAULT_LEFTOVER_AGE = timedelta(days=1)
+# held while publishing archives and writing the index - by all users of the box
+LOCK_FILE = '.bead-box.lock'
+
+# which copy UnionBox uses, when the same bead version (name & freeze time) is in multiple boxes
+CONFLICT_PRIORITY = 'priority'  # the one in the first box
+CONFLICT_NEWEST = 'newest'      # the most recently written archive file
+CONFLICT_ERROR = 'error'        # refuse to choose
+CONFLICT_POLICIES = (CONFLICT_PRIORITY, CONFLICT_NEWEST, CONFLICT_ERROR)
+
+# how bead names are matched when finding beads in a box - the same way on every platform,
+# whatever the file system of the box does with names differing only in case
+NAME_MATCHING_STRICT = 'strict'  # names must be equal
+NAME_MATCHING_CI = 'ci'          # names may differ in case
+NAME_MATCHING_MODES = (NAME_MATCHING_STRICT, NAME_MATCHING_CI)
+
+
+# private and specific to Box implementation,
+# conditions are checked on box index entries as well as on archives
+
+
+# the BEAD_NAME condition in boxes with NAME_MATCHING_CI
+_BEAD_NAME_IGNORING_CASE = 'BEAD_NAME_IGNORING_CASE'
+
 
-# private and specific to Box implementation, when Box gains more power,
-# it should change how it handles queries (e.g. using BEAD_NAME, KIND,
-# or CONTENT_ID directly through an index)
+def _has_name(name):
+    def filter(bead):
+        return bead.name == name
+    return filter
 
 
-def _make_checkers():
-    def has_name(name):
-        def filter(bead):
-            return bead.name == name
-        return filter
+def _has_name_ignoring_case(name):
+    name = name.casefold()
 
-    def has_kind(kind):
-        def filter(bead):
-            return bead.kind == kind
-        return filter
+    def filter(bead):
+        return bead.name.casefold() == name
+    return filter
 
-    def has_content_prefix(prefix):
-        def filter(bead):
-            return bead.content_id.startswith(prefix)
-        return filter
 
-    return {
-        bead_spec.BEAD_NAME:  has_name,
-        bead_spec.KIND:       has_kind,
-        bead_spec.CONTENT_ID: has_content_prefix,
-    }
+def _has_kind(kind):
+    def filter(bead):
+        return bead.kind == kind
+    return filter
 
 
-_CHECKERS = _make_checkers()
+def _has_content_prefix(prefix):
+    def filter(bead):
+        return bead.content_id.startswith(prefix)
+    return filter
+
+
+def _has_name_or_kind_matching(pattern):
+    def filter(bead):
+        return bool(pattern.fullmatch(bead.name) or pattern.fullmatch(bead.kind))
+    return filter
+
+
+_CHECKERS = {
+    bead_spec.BEAD_NAME:  _has_name,
+    _BEAD_NAME_IGNORING_CASE: _has_name_ignoring_case,
+    bead_spec.KIND:       _has_kind,
+    bead_spec.CONTENT_ID: _has_content_prefix,
+    bead_spec.NAME_OR_KIND_PATTERN: _has_name_or_kind_matching,
+}
+
+
+def compile_pattern(pattern: str, regex=False) -> Pattern:
+    '''
+    Pattern matching whole names: a glob (e.g. sales-*) or, with regex, a regular expression.
+
+    Raises ValueError for invalid regular expressions.
+    '''
+    if not regex:
+        return re.compile(fnmatch.translate(pattern))
+    try:
+        return re.compile(pattern)
+    except re.error as e:
+        raise ValueError(f'Invalid regular expression {pattern}: {e}')
 
 
 def compile_conditions(conditions):
@@ -71,6 +158,77 @@ def compile_conditions(conditions):
     return match
 
 
+def _name_glob(name: str, ignore_case: bool) -> str:
+    '''
+    Glob pattern of name - matching it in any case with ignore_case.
+    '''
+    if not ignore_case:
+        return glob.escape(name)
+
+    def any_case(c):
+        lower, upper = c.lower(), c.upper()
+        if lower != upper and len(lower) == len(upper) == 1:
+            return f'[{lower}{upper}]'
+        return glob.escape(c)
+    return ''.join(any_case(c) for c in name)
+
+
+# archives missing from the box index are opened by this many threads
+SCAN_THREADS_ENV_VAR = 'BEAD_SCAN_THREADS'
+DEFAULT_SCAN_THREADS = min(8, os.cpu_count() or 1)
+
+
+def scan_threads() -> int:
+    try:
+        return max(1, int(os.environ[SCAN_THREADS_ENV_VAR]))
+    except (KeyError, ValueError):
+        return DEFAULT_SCAN_THREADS
+
+
+def _open_archive(path: Path, box_name, read_only=False) -> Optional[Archive]:
+    '''
+    The archive at path, None if it is not a valid archive.
+
+    Its meta cache is saved for the next scans - unless the box is read-only.
+    '''
+    try:
+        # an up to date meta cache has all the index needs - the zip is not opened
+        if read_cache(path) is not None:
+            return Archive(path, box_name)
+        # only the meta data is read, which is all the index needs
+        summary = peek_archive_summary(path)
+        archive = Archive(path, box_name, summary.archive_cache)
+    except InvalidArchive:
+        # TODO: log/report problem
+        return None
+    if not read_only:
+        archive.save_cache()
+    return archive
+
+
+def _open_archive_in_thread(path: Path, box_name, read_only) -> Optional[Archive]:
+    try:
+        return _open_archive(path, box_name, read_only)
+    finally:
+        zipopener.close_all()
+
+
+def _open_archives(paths: Sequence[Path], box_name, read_only=False) -> List[Archive]:
+    '''
+    Open archives - concurrently, if there are many of them.
+    '''
+    threads = min(scan_threads(), len(paths))
+    if threads <= 1:
+        archives = [_open_archive(path, box_name, read_only) for path in paths]
+    else:
+        with ThreadPoolExecutor(max_workers=threads) as executor:
+            archives = list(
+                executor.map(
+                    _open_archive_in_thread, paths, itertools.repeat(box_name),
+                    itertools.repeat(read_only)))
+    return [archive for archive in archives if archive is not None]
+
+
 ARCHIVE_COMMENT = '''
 This file is a BEAD zip archive.
 
@@ -98,14 +256,151 @@ There {is,will be,was} more info about BEADs at
 '''
 
 
+@attr.s(auto_attribs=True, frozen=True)
+class RepairReport:
+    # staged archives, temporary files and locks of interrupted saves
+    leftover_files: List[Path]
+    # zero-byte archives
+    empty_archives: List[Path]
+    orphaned_sidecars: List[Path]
+    # file names of archives, that were indexed, but no longer exist
+    index_entries: List[str]
+
+    @property
+    def is_clean(self):
+        return not (
+            self.leftover_files or self.empty_archives or self.orphaned_sidecars
+            or self.index_entries)
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class CollectedGarbage:
+    # orphaned sidecar files
+    files: List[Path]
+    # file names of archives, that were indexed, but no longer exist
+    index_entries: List[str]
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class SyncReport:
+    # archives copied (or to copy, on a dry run) - paths in the source box
+    copied: List[Path]
+    # archives, whose bead version is already in the target box
+    present: List[Path]
+    # archives, whose file name is taken in the target box by different content
+    conflicts: List[Path]
+
+
 class Box:
     """
     Store Beads.
     """
 
-    def __init__(self, name: str, location: Path):
+    def __init__(
+            self, name: str, location: Path, file_mode=None, group=None, compression=None,
+            name_matching=NAME_MATCHING_STRICT, read_only=False):
         self.location = location
         self.name = name
+        # compression of stored archives, None: decided by the caller of store()
+        self.compression = compression
+        # one of NAME_MATCHING_MODES
+        self.name_matching = name_matching
+        # e.g. for shared institutional boxes: beads can be found and loaded, but not changed
+        self.read_only = read_only
+        # for boxes shared by a group of users:
+        # mode and group ownership of stored files, None means the system default
+        self.file_mode = file_mode
+        self.group = group
+
+    def as_dict(self):
+        return dict(
+            name=self.name,
+            location=os.fspath(self.location),
+            compression=self.compression,
+            file_mode=self.file_mode,
+            group=self.group,
+            name_matching=self.name_matching,
+            read_only=self.read_only)
+
+    @property
+    def ignores_case(self):
+        return self.name_matching == NAME_MATCHING_CI
+
+    def is_case_sensitive(self) -> Optional[bool]:
+        '''
+        Does the file system of the box tell apart names differing only in case?
+
+        None, if it can not be found out (e.g. the box is read-only).
+        Bead names are matched as configured (name_matching) either way.
+        '''
+        return tech.fs.is_case_sensitive(self.directory)
+
+    @property
+    def is_shared(self):
+        return self.file_mode is not None or self.group is not None
+
+    def share(self, path: Path):
+        '''
+        Give path the group ownership and mode configured for the box - as far as possible.
+
+        Directories also get the setgid bit, so that new files inherit the group.
+        '''
+        if not self.is_shared:
+            return
+        mode = self.file_mode
+        if path.is_dir():
+            if mode is None:
+                mode = path.stat().st_mode & 0o7777
+            mode = tech.fs.directory_mode(mode, setgid=self.group is not None)
+        if not tech.fs.set_group_and_mode(path, self.group, mode):
+            warnings.warn(
+                f'Box "{self.name}": could not set group/mode of {path}'
+                + ' - other users of the box might not be able to access it',
+                tech.fs.PermissionsNotSupported)
+
+    def _check_writable(self, operation, bead_name=None):
+        if self.read_only:
+            raise BoxError(
+                f'Box "{self.name}" is read-only',
+                operation=operation, path=self.directory, bead_name=bead_name)
+
+    def lock(self, timeout=tech.lock.DEFAULT_TIMEOUT) -> tech.lock.FileLock:
+        '''
+        Lock of the box for short modifications - not reentrant.
+        '''
+        return tech.lock.FileLock(self.directory / LOCK_FILE, timeout)
+
+    @contextlib.contextmanager
+    def _locked(self, operation, bead_name=None):
+        '''
+        Hold the box lock for a modification - raise BoxError, if it can not be taken.
+        '''
+        lock = self.lock()
+        try:
+            lock.acquire()
+        except tech.lock.LockTimeout as e:
+            raise BoxError(
+                f'Box "{self.name}": {e}',
+                operation=operation, path=self.directory, bead_name=bead_name)
+        try:
+            yield
+        finally:
+            lock.release()
+
+    def _save_index(self, index: BoxIndex):
+        # the index is only a cache: it is not saved, if the box can not be locked
+        if self.read_only:
+            return
+        try:
+            with self.lock():
+                self._write_index(index)
+        except (tech.lock.LockTimeout, OSError) as e:
+            TRACELOG(f'Could not lock box {self.name} to save its index: {e}')
+
+    def _write_index(self, index: BoxIndex):
+        # needs the box lock
+        if index.save():
+            self.share(index.path)
 
     @property
     def directory(self):
@@ -117,6 +412,11 @@ class Box:
         return Path(self.location)
 
     def find_bead(self, name, content_id):
+        '''
+        The bead with name and content_id (or a prefix of it) - None, if not found.
+
+        Exact content ids in the box index are found without scanning the archives of name.
+        '''
         query = ((bead_spec.BEAD_NAME, name), (bead_spec.CONTENT_ID, content_id))
         for bead in self._beads(query):
             return bead
@@ -127,51 +427,595 @@ class Box:
         '''
         return iter(self._beads([]))
 
+    def find_matching(self, pattern: str, regex=False) -> List[Archive]:
+        '''
+        Beads with name or kind matching pattern (see compile_pattern), by name and freeze time.
+        '''
+        conditions = [(bead_spec.NAME_OR_KIND_PATTERN, compile_pattern(pattern, regex))]
+        return _by_name_and_freeze_time(self._beads(conditions))
+
+    def find_by_tag(self, tag: str) -> List[Archive]:
+        '''
+        Beads tagged with tag, by name and freeze time.
+        '''
+        return _by_name_and_freeze_time(bead for bead in self._beads([]) if tag in bead.tags)
+
+    def aliases(self) -> Dict[str, str]:
+        '''
+        Aliases defined in the box: alias -> kind, see resolve_alias.
+        '''
+        return dict(BoxIndex.load(self.directory).aliases)
+
+    def set_alias(self, alias: str, kind: str):
+        '''
+        Make alias resolve to the newest bead of kind in the box.
+
+        Raises BoxError, if the alias can not be stored (e.g. the box is read-only).
+        '''
+        self._check_writable('alias')
+        index = BoxIndex.load(self.directory)
+        index.set_alias(alias, kind)
+        self._save_aliases(index, alias)
+
+    def remove_alias(self, alias: str) -> bool:
+        '''
+        Forget alias - returns False, if it was not defined.
+
+        Raises BoxError, if the alias is defined, but the box is read-only.
+        '''
+        index = BoxIndex.load(self.directory)
+        if alias not in index.aliases:
+            return False
+        self._check_writable('alias')
+        index.remove_alias(alias)
+        self._save_aliases(index, alias)
+        return True
+
+    def _save_aliases(self, index: BoxIndex, alias):
+        # unlike the rest of the index, aliases must not be lost silently
+        try:
+            with self.lock():
+                saved = index.save()
+        except tech.lock.LockTimeout:
+            saved = False
+        if not saved:
+            raise BoxError(
+                f'Box "{self.name}": could not store alias {alias}',
+                operation='alias', path=index.path)
+        self.share(index.path)
+
+    def resolve_alias(self, alias: str) -> Archive:
+        '''
+        The newest bead of the kind alias stands for.
+
+        Raises LookupError, if alias is not defined or there is no bead of its kind.
+        '''
+        kind = self.aliases()[alias]
+        beads = list(self._beads([(bead_spec.KIND, kind)]))
+        if not beads:
+            raise LookupError(alias)
+        return max(beads, key=lambda bead: bead.freeze_time)
+
+    def indexed_entries(self) -> List[IndexEntry]:
+        '''
+        Entries of the box index - without looking at the archives.
+
+        Fast even for huge boxes, but can be out of date:
+        archives stored by older versions or not yet queried are missing.
+        '''
+        return list(BoxIndex.load(self.directory).entries.values())
+
+    def check(
+            self, cancel=NEVER_CANCELLED, verified_since: Optional[datetime] = None,
+    ) -> Iterator[Tuple[Path, Optional[List[Damage]]]]:
+        '''
+        Validate every archive in the box: (archive path, damages) for each of them.
+
+        Successful verifications are recorded in the box index.
+        Archives unchanged and verified after verified_since are skipped, with damages None.
+        '''
+        index = BoxIndex.load(self.directory)
+        try:
+            for path in sorted(self.directory.glob('*.zip')):
+                if verified_since is not None and index.is_verified_since(path, verified_since):
+                    yield path, None
+                    continue
+                verified_at = tech.timestamp.timestamp()
+                try:
+                    damages = ZipArchive(path, self.name).check(cancel)
+                except InvalidArchive:
+                    damages = [Damage(layouts.Archive.BEAD_META, UNREADABLE)]
+                if not damages:
+                    index.set_verified(Archive(path, self.name), verified_at)
+                yield path, damages
+        finally:
+            self._save_index(index)
+
+    def is_verified_since(self, archive_path, since: datetime) -> bool:
+        return BoxIndex.load(self.directory).is_verified_since(Path(archive_path), since)
+
+    def set_verified(self, archive: Archive, timestamp: str):
+        index = BoxIndex.load(self.directory)
+        index.set_verified(archive, timestamp)
+        self._save_index(index)
+
+    def find_name_collisions(self, name, kind) -> Sequence[Archive]:
+        '''
+        Beads stored under `name`, but having a kind different from `kind`.
+
+        Storing a bead with a colliding name would make name based input updates
+        silently switch to tracking another computation.
+        '''
+        query = [(bead_spec.BEAD_NAME, name)]
+        return [bead for bead in self._beads(query) if bead.kind != kind]
+
     def _beads(self, conditions) -> Iterable[Archive]:
         '''
         Retrieve matching beads.
         '''
+        if self.ignores_case:
+            conditions = [
+                (_BEAD_NAME_IGNORING_CASE if tag == bead_spec.BEAD_NAME else tag, value)
+                for tag, value in conditions]
         match = compile_conditions(conditions)
 
         bead_names = set(
-            value
+            value.casefold() if self.ignores_case else value
             for tag, value in conditions
-            if tag == bead_spec.BEAD_NAME)
+            if tag in (bead_spec.BEAD_NAME, _BEAD_NAME_IGNORING_CASE))
+        content_ids = [value for tag, value in conditions if tag == bead_spec.CONTENT_ID]
+        if bead_names and content_ids:
+            # fast path for the exact versions of inputs (see find_bead)
+            indexed = self._indexed_beads(content_ids[0], match)
+            if indexed:
+                return iter(indexed)
         if bead_names:
             if len(bead_names) > 1:
                 # easy path: names disagree
                 return []
             # beadname_20170615T075813302092+0200.zip
-            glob = bead_names.pop() + '_????????T????????????[-+]????.zip'
+            # or a legacy, shorter form, like beadname_20170615.zip
+            # names are checked by match, as beadname_* can match other beads as well
+            glob = _name_glob(bead_names.pop(), self.ignores_case) + '_????????*.zip'
         else:
             glob = '*'
 
         paths = self.directory.glob(glob)
-        beads = self._archives_from(paths)
-        candidates = (bead for bead in beads if match(bead))
-        return candidates
+        return iter(self._archives_from(paths, match))
+
+    def _indexed_beads(self, content_id, match) -> List[Archive]:
+        '''
+        Matching archives with exactly content_id - found by the box index, if up to date.
+        '''
+        index = BoxIndex.load(self.directory)
+        archives = []
+        for filename in index.with_content_id(content_id):
+            path = self.directory / filename
+            entry = index.get(path)
+            if entry is not None and match(entry):
+                archives.append(Archive(path, self.name, entry.archive_cache))
+        return archives
+
+    def _archives_from(self, paths: Iterable[Path], match) -> List[Archive]:
+        '''
+        Matching archives - using the box index, where it is up to date.
 
-    def _archives_from(self, paths: Iterable[Path]):
+        Archives missing from the index are opened and indexed.
+        '''
+        index = BoxIndex.load(self.directory)
+        archives = []
+        not_indexed = []
         for path in paths:
-            try:
-                archive = Archive(path, self.name)
-            except InvalidArchive:
-                # TODO: log/report problem
-                pass
-            else:
-                yield archive
+            if (
+                path.name.startswith(INDEX_FILE)
+                or path.name.endswith(STAGED_SUFFIX)
+                or path.name == LOCK_FILE
+            ):
+                continue
+            entry = index.get(path)
+            if entry is None:
+                not_indexed.append(path)
+            elif match(entry):
+                archives.append(Archive(path, self.name, entry.archive_cache))
+        for archive in _open_archives(not_indexed, self.name, self.read_only):
+            index.add(archive)
+            if match(archive):
+                archives.append(archive)
+        self._save_index(index)
+        return archives
 
-    def store(self, workspace, freeze_time):
-        # -> Bead
+    def store(
+            self, workspace, freeze_time, compression=None, signing_key=None,
+            cancel=NEVER_CANCELLED, validate=None, progress=NO_PROGRESS,
+            notebook_outputs=notebooks.KEEP, hash_algorithm=tech.securehash.DEFAULT_ALGORITHM,
+            provenance=None, use_hash_cache=True) -> Archive:
+        '''
+        Save workspace as a new archive in the box, return it.
+
+        compression (for this save) overrides the box's compression.
+        notebook_outputs, hash_algorithm, provenance and use_hash_cache
+        are passed on to Workspace.pack.
+        With signing_key (an ssh private key file) a detached signature is also stored.
+        Concurrent saves of the same name never overwrite each other: when freeze_time is
+        already taken, the archive gets the earliest later one.
+        validate is called with the path of the staged archive before it is published,
+        it can reject the archive by raising an exception.
+        The checksum of the archive is recorded (see Archive.archive_sha256).
+        A cancelled or rejected save leaves nothing behind in the box.
+        '''
+        self._check_writable('store', workspace.name)
         if not self.directory.exists():
-            raise BoxError(f'Box "{self.name}": directory {self.directory} does not exist')
+            raise BoxError(
+                f'Box "{self.name}": directory does not exist',
+                operation='store', path=self.directory, bead_name=workspace.name)
         if not self.directory.is_dir():
-            raise BoxError(f'Box "{self.name}": {self.directory} is not a directory')
-        zipfilename = (
-            self.directory / f'{workspace.name}_{freeze_time}.zip')
-        workspace.pack(zipfilename, freeze_time=freeze_time, comment=ARCHIVE_COMMENT)
+            raise BoxError(
+                f'Box "{self.name}": not a directory',
+                operation='store', path=self.directory, bead_name=workspace.name)
+        while True:
+            freeze_time = self._unused_freeze_time(workspace.name, freeze_time)
+            zipfilename = self.directory / f'{workspace.name}_{freeze_time}.zip'
+            # unique to this save: concurrent saves never touch each other's staged archive
+            staged_zipfilename = zipfilename.with_name(
+                f'{zipfilename.name}.{uuid.uuid4().hex}{STAGED_SUFFIX}')
+            workspace.pack(
+                staged_zipfilename, freeze_time=freeze_time, comment=ARCHIVE_COMMENT,
+                compression=compression or self.compression, cancel=cancel, progress=progress,
+                notebook_outputs=notebook_outputs, hash_algorithm=hash_algorithm,
+                provenance=provenance, use_hash_cache=use_hash_cache)
+            with tech.fs.removed_on_error(staged_zipfilename):
+                if validate is not None:
+                    validate(staged_zipfilename)
+                archive_sha256 = tech.securehash.file_sha256(staged_zipfilename, cancel)
+                if self._publish(
+                        staged_zipfilename, zipfilename, workspace.name, archive_sha256):
+                    break
+            # a concurrent save of the same name got this freeze time first
+            os.remove(staged_zipfilename)
+            freeze_time = tech.timestamp.timestamp_after(freeze_time)
+        # unsigned archives must not be published, when a signature was asked for
+        with tech.fs.removed_on_error(zipfilename):
+            self.share(zipfilename)
+            if signing_key is not None:
+                with tech.fs.removed_on_error(signing.signature_path(zipfilename)):
+                    self.share(signing.sign(zipfilename, signing_key))
+        return Archive(zipfilename, self.name)
+
+    def _unused_freeze_time(self, bead_name, freeze_time):
+        '''
+        freeze_time or a later one, that no (staged) archive of bead_name has in the box.
+        '''
+        while True:
+            prefix = glob.escape(f'{bead_name}_{freeze_time}.zip')
+            if not any(self.directory.glob(prefix + '*')):
+                return freeze_time
+            freeze_time = tech.timestamp.timestamp_after(freeze_time)
+
+    def _publish(self, staged_zipfilename, zipfilename, bead_name, archive_sha256) -> bool:
+        '''
+        Rename the staged archive to zipfilename and index it - unless zipfilename is taken.
+
+        archive_sha256 is the checksum of the staged archive, it is recorded with it.
+        '''
+        try:
+            with self.lock():
+                if zipfilename.exists():
+                    return False
+                os.rename(staged_zipfilename, zipfilename)
+                archive = Archive(
+                    zipfilename, self.name, {CACHE_ARCHIVE_SHA256: archive_sha256})
+                archive.save_cache()
+                index = BoxIndex.load(self.directory)
+                index.add(archive)
+                self._write_index(index)
+        except tech.lock.LockTimeout as e:
+            raise BoxError(
+                f'Box "{self.name}": {e}',
+                operation='store', path=self.directory, bead_name=bead_name)
+        return True
+
+    def add_archive(self, archive: Archive, cancel=NEVER_CANCELLED) -> Path:
+        '''
+        Copy an archive (e.g. from another box) into the box under its file name.
+
+        The copy is published only after it is checked against the original:
+        the checksum of the original is computed while it is read for copying,
+        it catches damage done by copying - raising TransferError,
+        and damage to the original since its checksum was recorded (Archive.archive_sha256)
+        - raising InvalidArchive. The content id of the copy catches an original
+        not matching its recorded content id.
+        A detached signature is copied as well.
+        '''
+        self._check_writable('copy', archive.name)
+        zipfilename = self.directory / archive.archive_path.name
+        staged_zipfilename = zipfilename.with_name(
+            f'{zipfilename.name}.{uuid.uuid4().hex}{STAGED_SUFFIX}')
+        with tech.fs.removed_on_error(staged_zipfilename):
+            source_hash = hashlib.sha256()
+            tech.transfer.copy_file(
+                archive.archive_path, staged_zipfilename, cancel, hasher=source_hash)
+            if archive.archive_sha256 not in (None, source_hash.hexdigest()):
+                raise InvalidArchive(
+                    f'Box "{self.name}": {archive.archive_path} is damaged'
+                    + ' - its checksum differs from the one recorded, when it was stored',
+                    operation='copy', path=archive.archive_path, bead_name=archive.name)
+            archive_sha256 = tech.securehash.file_sha256(staged_zipfilename, cancel)
+            if archive_sha256 != source_hash.hexdigest():
+                raise TransferError(
+                    f'Box "{self.name}": copy of {archive.archive_path} is corrupted'
+                    + ' - its checksum differs from the original\'s',
+                    operation='copy', path=staged_zipfilename, bead_name=archive.name)
+            with zipfile.ZipFile(staged_zipfilename) as zf:
+                content_id = content_id_of(zf.read(layouts.Archive.MANIFEST), staged_zipfilename)
+            if content_id != archive.content_id:
+                raise BoxError(
+                    f'Box "{self.name}": content of {archive.archive_path}'
+                    + ' does not match its content id',
+                    operation='copy', path=staged_zipfilename, bead_name=archive.name)
+            if not self._publish(
+                    staged_zipfilename, zipfilename, archive.name, archive_sha256):
+                raise BoxError(
+                    f'Box "{self.name}": archive already exists',
+                    operation='copy', path=zipfilename, bead_name=archive.name)
+        with tech.fs.removed_on_error(zipfilename):
+            self.share(zipfilename)
+            signature = signing.signature_path(archive.archive_path)
+            if signature.exists():
+                copied_signature = signing.signature_path(zipfilename)
+                tech.transfer.copy_file(signature, copied_signature, cancel)
+                self.share(copied_signature)
         return zipfilename
 
+    def sync_from(
+            self, source: 'Box', conditions=(), since: Optional[datetime] = None,
+            until: Optional[datetime] = None, dry_run=False,
+            cancel=NEVER_CANCELLED) -> SyncReport:
+        '''
+        Copy the archives of source missing from this box (e.g. a mirror, or a laptop's box).
+
+        Archives are selected by conditions (as for searches) and freeze time (since, until).
+        A bead version is missing, when no archive in this box has its name and content id.
+        Nothing is ever overwritten: archives with taken file names are reported as conflicts.
+        '''
+        if not dry_run:
+            self._check_writable('sync')
+        present_versions = {(bead.name, bead.content_id) for bead in self._beads(conditions)}
+        report = SyncReport([], [], [])
+        for archive in sorted(source._beads(conditions), key=lambda bead: bead.archive_path):
+            cancel.check()
+            if since is not None and archive.freeze_time < since:
+                continue
+            if until is not None and archive.freeze_time > until:
+                continue
+            if (archive.name, archive.content_id) in present_versions:
+                report.present.append(archive.archive_path)
+            elif (self.directory / archive.archive_path.name).exists():
+                report.conflicts.append(archive.archive_path)
+            else:
+                if not dry_run:
+                    self.add_archive(archive, cancel)
+                report.copied.append(archive.archive_path)
+                present_versions.add((archive.name, archive.content_id))
+        return report
+
+    @property
+    def deleted_directory(self):
+        return self.directory / DELETED_DIR
+
+    def delete(self, archive_filename, retention=DEFAULT_RETENTION) -> Path:
+        '''
+        Soft-delete an archive by moving it (and its cache) to the deleted area.
+
+        It can be restored until it is purged, which happens to archives deleted
+        more than `retention` time ago, when another archive is deleted.
+        '''
+        self._check_writable('delete')
+        path = self.directory / os.path.basename(archive_filename)
+        with self._locked('delete'):
+            if not path.is_file():
+                raise BoxError(
+                    f'Box "{self.name}": no such archive', operation='delete', path=path)
+            trash = self.deleted_directory / tech.timestamp.timestamp()
+            deleted_directory_exists = self.deleted_directory.exists()
+            tech.fs.ensure_directory(trash)
+            if not deleted_directory_exists:
+                self.share(self.deleted_directory)
+            self.share(trash)
+            os.rename(path, trash / path.name)
+            for suffix in SIDECAR_SUFFIXES:
+                sidecar = path.with_suffix(suffix)
+                if sidecar.exists():
+                    os.rename(sidecar, trash / sidecar.name)
+            index = BoxIndex.load(self.directory)
+            index.remove(path.name)
+            self._write_index(index)
+        self.purge_deleted(retention)
+        return trash / path.name
+
+    def deleted_archives(self) -> List[Tuple[datetime, Path]]:
+        '''
+        (deletion time, path) of soft-deleted archives, most recently deleted first.
+        '''
+        deleted = []
+        try:
+            trashes = os.listdir(self.deleted_directory)
+        except FileNotFoundError:
+            trashes = []
+        for trash in trashes:
+            try:
+                deletion_time = time_from_timestamp(trash)
+            except ValueError:
+                continue
+            for path in (self.deleted_directory / trash).glob('*.zip'):
+                deleted.append((deletion_time, path))
+        return sorted(deleted, reverse=True)
+
+    def restore(self, archive_filename) -> Path:
+        '''
+        Move back the most recently deleted archive with the given file name.
+        '''
+        self._check_writable('restore')
+        name = os.path.basename(archive_filename)
+        target = self.directory / name
+        with self._locked('restore'):
+            if target.exists():
+                raise BoxError(
+                    f'Box "{self.name}": archive already exists',
+                    operation='restore', path=target)
+            for _deletion_time, path in self.deleted_archives():
+                if path.name == name:
+                    break
+            else:
+                raise BoxError(
+                    f'Box "{self.name}": not among the deleted archives',
+                    operation='restore', path=target)
+            os.rename(path, target)
+            for suffix in SIDECAR_SUFFIXES:
+                sidecar = path.with_suffix(suffix)
+                if sidecar.exists():
+                    os.rename(sidecar, target.with_suffix(suffix))
+            self.share(target)
+            try:
+                archive = Archive(target, self.name)
+            except InvalidArchive:
+                # not indexed, like other invalid archives
+                return target
+            index = BoxIndex.load(self.directory)
+            index.add(archive)
+            self._write_index(index)
+        return target
+
+    def purge_deleted(self, retention=DEFAULT_RETENTION):
+        '''
+        Permanently remove archives deleted more than retention time ago.
+        '''
+        self._check_writable('purge')
+        limit = time_from_timestamp(tech.timestamp.timestamp()) - retention
+        try:
+            trashes = os.listdir(self.deleted_directory)
+        except FileNotFoundError:
+            return
+        for trash in trashes:
+            try:
+                deletion_time = time_from_timestamp(trash)
+            except ValueError:
+                continue
+            if deletion_time < limit:
+                tech.fs.rmtree(self.deleted_directory / trash)
+
+    def remove_bead(self, name, content_id) -> List[Path]:
+        '''
+        Permanently remove the archives of a bead version - with their sidecars and index entries.
+
+        Unlike delete, there is no way back.
+        Returns the paths of the removed archives.
+        '''
+        self._check_writable('remove', name)
+        # content ids are matched by prefix in queries - removal needs an exact match
+        archives = [
+            archive
+            for archive in self._beads(
+                ((bead_spec.BEAD_NAME, name), (bead_spec.CONTENT_ID, content_id)))
+            if archive.content_id == content_id]
+        if not archives:
+            raise BoxError(
+                f'Box "{self.name}": no archive of {name} with content id {content_id}',
+                operation='remove', path=self.directory, bead_name=name)
+        removed = []
+        # concurrent saves, removals and gc see either all or none of the removal
+        with self._locked('remove', name):
+            index = BoxIndex.load(self.directory)
+            for archive in archives:
+                path = archive.archive_path
+                try:
+                    os.remove(path)
+                except FileNotFoundError:
+                    # removed by someone else since it was found
+                    continue
+                index.remove(path.name)
+                for suffix in SIDECAR_SUFFIXES:
+                    with contextlib.suppress(FileNotFoundError):
+                        os.remove(path.with_suffix(suffix))
+                removed.append(path)
+            self._write_index(index)
+        return removed
+
+    def gc(self) -> CollectedGarbage:
+        '''
+        Remove leftovers of archives gone from the box: sidecar files and index entries.
+        '''
+        self._check_writable('gc')
+        # archives are published under the lock - with their cache, which is no orphan then
+        with self._locked('gc'):
+            files = self._orphaned_sidecars()
+            for sidecar in files:
+                with contextlib.suppress(FileNotFoundError):
+                    os.remove(sidecar)
+            index = BoxIndex.load(self.directory)
+            index_entries = index.remove_missing()
+            self._write_index(index)
+        return CollectedGarbage(files, index_entries)
+
+    def _orphaned_sidecars(self, gone_archives=()) -> List[Path]:
+        gone = set(gone_archives)
+        orphans = []
+        for suffix in SIDECAR_SUFFIXES:
+            for sidecar in sorted(self.directory.glob('*' + suffix)):
+                archive_path = sidecar.with_suffix('.zip')
+                if archive_path in gone or not archive_path.exists():
+                    orphans.append(sidecar)
+        return orphans
+
+    def repair(self, dry_run=False, leftover_age=DEFAULT_LEFTOVER_AGE) -> RepairReport:
+        '''
+        Clean up after interrupted saves and crashes, then rebuild the index.
+
+        Removes staged archives and temporary files, when older than leftover_age
+        (younger ones might belong to saves in progress), the lock of a crashed process,
+        empty archives, orphaned sidecar files and index entries of missing archives.
+        With dry_run nothing is changed, only the report is made.
+        '''
+        lock = self.lock()
+        stale_locks = [lock.path] if lock.is_stale() else []
+        if dry_run:
+            return self._repair_report(stale_locks, leftover_age)
+        self._check_writable('repair')
+        if stale_locks and not lock.remove_if_stale():
+            # taken over by another process meanwhile
+            stale_locks = []
+        with self._locked('repair'):
+            report = self._repair_report(stale_locks, leftover_age)
+            removable = [path for path in report.leftover_files if path not in stale_locks]
+            for path in removable + report.empty_archives + report.orphaned_sidecars:
+                with contextlib.suppress(FileNotFoundError):
+                    os.remove(path)
+            index = BoxIndex.load(self.directory)
+            index.remove_missing()
+            self._write_index(index)
+        # indexes the archives missing from the index
+        list(self.all_beads())
+        return report
+
+    def _repair_report(self, stale_locks, leftover_age) -> RepairReport:
+        limit = time.time() - leftover_age.total_seconds()
+        leftover_files = sorted(
+            [
+                path
+                for pattern in ('*' + STAGED_SUFFIX, '*' + TEMP_SUFFIX)
+                for path in self.directory.glob(pattern)
+                if path.is_file() and path.stat().st_mtime < limit]
+            + stale_locks)
+        empty_archives = sorted(
+            path for path in self.directory.glob('*.zip')
+            if path.is_file() and path.stat().st_size == 0)
+        orphaned_sidecars = self._orphaned_sidecars(empty_archives)
+        index = BoxIndex.load(self.directory)
+        index_entries = sorted(
+            filename for filename in index.entries
+            if not (self.directory / filename).exists()
+            or self.directory / filename in empty_archives)
+        return RepairReport(leftover_files, empty_archives, orphaned_sidecars, index_entries)
+
     def find_names(self, kind, content_id, timestamp):
         '''
         -> (exact_match, best_guess, best_guess_freeze_time, names)
@@ -182,13 +1026,7 @@ class Box:
             names                  = sequence of names (kind matched)
         '''
         assert isinstance(timestamp, datetime)
-        try:
-            filenames = os.listdir(self.directory)
-        except FileNotFoundError:
-            filenames = []
-        paths = (self.directory / fname for fname in filenames)
-        beads = self._archives_from(paths)
-        candidates = (bead for bead in beads if bead.kind == kind)
+        candidates = self._beads([(bead_spec.KIND, kind)])
 
         exact_match            = None
         best_guess             = None
@@ -199,7 +1037,7 @@ class Box:
             if bead.content_id == content_id:
                 exact_match = bead.name
             #
-            bead_freeze_time = time_from_timestamp(bead.freeze_time_str)
+            bead_freeze_time = bead.freeze_time
             bead_timedelta = bead_freeze_time - timestamp
             if bead_timedelta < timedelta():
                 bead_timedelta = -bead_timedelta
@@ -219,6 +1057,12 @@ class Box:
 
         return exact_match, best_guess, best_guess_freeze_time, names
 
+    def find_as_of(self, name, time) -> Archive:
+        '''
+        The newest version of name frozen at or before time - LookupError, if there is none.
+        '''
+        return _as_of(self.get_context(bead_spec.BEAD_NAME, name, time), name, time)
+
     def get_context(self, check_type, check_param, time):
         # in theory timestamps can be [intentionally] duplicated, but let's
         # treat that as an error condition to be fixed ASAP
@@ -226,37 +1070,228 @@ class Box:
         return make_context(time, self._beads(conditions))
 
 
+def _by_name_and_freeze_time(beads: Iterable[Archive]) -> List[Archive]:
+    return sorted(beads, key=lambda bead: (bead.name, bead.freeze_time))
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class SearchResult:
+    # matching beads found in time, in box order
+    beads: List[Archive]
+    # names of the boxes, that did not answer within the time budget
+    timed_out: List[str]
+
+
 class UnionBox:
-    def __init__(self, boxes: Sequence[Box]):
+    def __init__(
+            self, boxes: Sequence[Box], conflict_policy=CONFLICT_PRIORITY,
+            timeout: Optional[float] = None):
+        '''
+        timeout is the time budget of searches in seconds - None: wait for all boxes.
+        '''
+        if conflict_policy not in CONFLICT_POLICIES:
+            raise ValueError(f'Unknown box conflict policy {conflict_policy}')
         self.boxes = tuple(boxes)
+        self.conflict_policy = conflict_policy
+        self.timeout = timeout
+        # boxes, that timed out in the last search - their beads are missing from its results
+        self.timed_out: List[str] = []
 
-    def get_context(self, check_type, check_param, time):
-        context = None
-        for box in self.boxes:
+    def search(self, conditions) -> SearchResult:
+        '''
+        Matching beads from all boxes - within the time budget.
+
+        Boxes are searched in parallel, slow ones (e.g. on an unreachable network drive)
+        are left behind, when the time is over.
+        '''
+        if self.timeout is None:
+            beads = [bead for box in self.boxes for bead in box._beads(conditions)]
+            self.timed_out = []
+            return SearchResult(beads, [])
+        results: Dict[int, object] = {}
+
+        def search_box(i, box):
             try:
-                box_context = box.get_context(check_type, check_param, time)
-            except LookupError:
-                continue
-            else:
-                context = merge_contexts(box_context, context)
+                results[i] = list(box._beads(conditions))
+            except BaseException as e:
+                results[i] = e
+            finally:
+                zipopener.close_all()
+        # daemon threads: a hanging box must not block exiting the program either
+        threads = [
+            threading.Thread(target=search_box, args=(i, box), daemon=True)
+            for i, box in enumerate(self.boxes)]
+        for thread in threads:
+            thread.start()
+        deadline = time.monotonic() + self.timeout
+        for thread in threads:
+            thread.join(max(0, deadline - time.monotonic()))
+        finished = {i: results[i] for i in range(len(threads)) if i in results}
+        beads = []
+        for i in sorted(finished):
+            if isinstance(finished[i], BaseException):
+                raise finished[i]
+            beads.extend(finished[i])
+        self.timed_out = [box.name for i, box in enumerate(self.boxes) if i not in finished]
+        return SearchResult(beads, self.timed_out)
+
+    def _copies(self, conditions) -> Dict[Tuple[str, datetime], List[Archive]]:
+        '''
+        Matching beads from all boxes, grouped by version, in box order.
+        '''
+        copies: Dict[Tuple[str, datetime], List[Archive]] = {}
+        for bead in self.search(conditions).beads:
+            copies.setdefault((bead.name, bead.freeze_time), []).append(bead)
+        return copies
 
-        if context:
-            return context
-        raise LookupError
+    def _choose(self, candidates: List[Archive]) -> Archive:
+        if len(candidates) == 1:
+            return candidates[0]
+        if self.conflict_policy == CONFLICT_ERROR:
+            bead = candidates[0]
+            boxes = ', '.join(candidate.box_name for candidate in candidates)
+            raise AmbiguousBead(
+                f'Version {bead.freeze_time_str} is in multiple boxes: {boxes}',
+                bead_name=bead.name)
+        if self.conflict_policy == CONFLICT_NEWEST:
+            # max() keeps the first of equals - box priority breaks ties
+            return max(candidates, key=lambda bead: os.stat(bead.archive_path).st_mtime)
+        return candidates[0]
+
+    def get_context(self, check_type, check_param, time):
+        return self._context(self._copies([(check_type, check_param)]), time)
+
+    def _context(self, copies, time):
+        # versions are compared by their first copy,
+        # the conflict policy is applied only to the ones in the context
+        context = make_context(time, (candidates[0] for candidates in copies.values()))
+
+        def choose(bead):
+            if bead is None:
+                return None
+            return self._choose(copies[bead.name, bead.freeze_time])
+        return VersionContext(
+            time, choose(context.bead), choose(context.prev), choose(context.next))
+
+    def find_bead(self, name, content_id):
+        query = ((bead_spec.BEAD_NAME, name), (bead_spec.CONTENT_ID, content_id))
+        for candidates in self._copies(query).values():
+            return self._choose(candidates)
 
     def get_at(self, check_type, check_param, time):
         context = self.get_context(check_type, check_param, time)
         return context.best
 
+    def find_as_of(self, name, time) -> Archive:
+        '''
+        The newest version of name frozen at or before time in any of the boxes.
+
+        Unlike get_at, it never chooses a later version - it is the version,
+        that was available at time (e.g. when a result was made from it).
+        Raises LookupError, if there is no such version.
+        '''
+        copies = self._copies([(bead_spec.BEAD_NAME, name)])
+        if not copies:
+            raise LookupError(name, time)
+        return _as_of(self._context(copies, time), name, time)
+
+    def resolve_reference(self, reference, time, fuzzy=True) -> Archive:
+        '''
+        Bead by reference: a bead name, an alias (see Box.resolve_alias), a prefix of a content id
+        or a kind - tried in this order. Without fuzzy only names and aliases are tried.
+
+        Of the versions of a name or kind, the one closest to time is chosen.
+        Raises LookupError, if nothing matches and AmbiguousReference,
+        if a content id prefix matches more beads or a kind is shared by more bead names.
+        '''
+        copies = self._copies([(bead_spec.BEAD_NAME, reference)])
+        if copies:
+            return self._context(copies, time).best
+
+        try:
+            # aliases stand for the newest version, whatever time is
+            return self.resolve_alias(reference)
+        except LookupError:
+            if not fuzzy:
+                raise
+
+        copies = self._copies([(bead_spec.CONTENT_ID, reference)])
+        content_ids = {candidates[0].content_id for candidates in copies.values()}
+        if len(content_ids) > 1:
+            raise AmbiguousReference(
+                f'Content id prefix {reference} matches {len(content_ids)} beads')
+        if copies:
+            return self._choose(next(iter(copies.values())))
+
+        copies = self._copies([(bead_spec.KIND, reference)])
+        names = sorted({name for name, _ in copies})
+        if len(names) > 1:
+            raise AmbiguousReference(
+                f'Kind {reference} is shared by beads {", ".join(names)}')
+        if copies:
+            return self._context(copies, time).best
+        raise LookupError(reference)
+
+    def resolve_alias(self, alias: str) -> Archive:
+        '''
+        The bead alias resolves to in the first box defining it - LookupError, if none does.
+        '''
+        for box in self.boxes:
+            if alias in box.aliases():
+                return box.resolve_alias(alias)
+        raise LookupError(alias)
+
+    def resolve_spec(self, bead_name, offset, time) -> Archive:
+        '''
+        Version of bead_name relative to time.
+
+        offset == 0: the version closest to time
+        offset > 0: the offset-th version after time
+        offset < 0: the (-offset)-th version before time,
+                    e.g. with time in the future -1 is the newest, -2 the one before it
+        '''
+        if offset == 0:
+            return self.get_at(bead_spec.BEAD_NAME, bead_name, time)
+        versions = {
+            freeze_time: candidates
+            for (_, freeze_time), candidates
+            in self._copies([(bead_spec.BEAD_NAME, bead_name)]).items()}
+        if offset > 0:
+            candidates = sorted(t for t in versions if t > time)
+            index = offset - 1
+        else:
+            candidates = sorted(t for t in versions if t < time)
+            index = len(candidates) + offset
+        if 0 <= index < len(candidates):
+            return self._choose(versions[candidates[index]])
+        raise LookupError(bead_name, offset, time)
+
     def all_beads(self) -> Iterator[Archive]:
         '''
-        Iterator for all beads in this Box
+        Iterator for all beads in this Box - all copies, regardless of the conflict policy
         '''
         for box in self.boxes:
             yield from box.all_beads()
 
+    def find_matching(self, pattern: str, regex=False) -> List[Archive]:
+        '''
+        Beads with name or kind matching pattern (see compile_pattern), by name and freeze time.
+
+        Of copies in more boxes, the one chosen by the conflict policy is returned.
+        '''
+        conditions = [(bead_spec.NAME_OR_KIND_PATTERN, compile_pattern(pattern, regex))]
+        return _by_name_and_freeze_time(
+            self._choose(candidates) for candidates in self._copies(conditions).values())
 
-class BeadContext:
+    def find_by_tag(self, tag: str) -> List[Archive]:
+        '''
+        Beads tagged with tag, by name and freeze time - of copies as for find_matching.
+        '''
+        beads = (self._choose(candidates) for candidates in self._copies([]).values())
+        return _by_name_and_freeze_time(bead for bead in beads if tag in bead.tags)
+
+
+class VersionContext:
     def __init__(self, time, bead, prev, next):
         assert bead is None or bead.freeze_time == time
         assert prev is None or prev.freeze_time < time
@@ -280,6 +1315,13 @@ class BeadContext:
         return self.next
 
 
+def _as_of(context, name, time) -> Archive:
+    bead = context.bead or context.prev
+    if bead is None:
+        raise LookupError(name, time)
+    return bead
+
+
 def make_context(time, beads):
     match, prev, next = None, None, None
     for bead in beads:
@@ -295,19 +1337,5 @@ def make_context(time, beads):
                 'multiple beads with same freeze time')
             match = bead
     if match or prev or next:
-        return BeadContext(time, match, prev, next)
+        return VersionContext(time, match, prev, next)
     raise LookupError
-
-
-def merge_contexts(context1, context2):
-    if context1 is None:
-        return context2
-    if context2 is None:
-        return context1
-    assert context1.time == context2.time
-    time = context1.time
-    beads = (
-        context1.bead, context1.prev, context1.next,
-        context2.bead, context2.prev, context2.next)
-    beads = (bead for bead in beads if bead)
-    return make_context(time, beads)
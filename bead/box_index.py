@@ -0,0 +1,281 @@
+'''
+Index of the archives in a box directory.
+
+Queries on boxes need the kind, content_id and freeze time of archives,
+which are expensive to get, as every archive must be opened.
+The index remembers them - along with the size and modification time
+of the archive to detect when an entry is out of date.
+
+The index also remembers when archives were last verified, so that routine checks
+of big boxes can skip unchanged archives verified recently.
+And the checksum of archive files recorded when they were stored (also kept in their
+.xmeta cache, so it survives rebuilding the index), copies are verified against it.
+
+The index is only an optimization: it is rebuilt as needed
+and failure to write it (e.g. read-only boxes) is ignored.
+Except for the aliases of the box (names resolving to the newest bead of a kind),
+which are set by users, so they are kept even when the rest of the index is dropped.
+Concurrent writers merge their changes into the index on disk (see BoxIndex.save),
+the caller is responsible for serializing the saves (see Box.lock).
+'''
+
+from datetime import datetime
+import os
+from typing import Dict, List, Optional
+
+import attr
+from tracelog import TRACELOG
+
+from . import meta
+from .archive import Archive, CACHE_ARCHIVE_SHA256, CACHE_CONTENT_ID, bead_name_from_file_path
+from .tech import persistence
+from .tech.fs import Path
+from .tech.timestamp import time_from_timestamp
+
+
+INDEX_FILE = '.bead-index.json'
+# change it on incompatible format changes - old indices are then dropped
+INDEX_VERSION = 1
+
+_VERSION = 'version'
+_ARCHIVES = 'archives'
+_ALIASES = 'aliases'
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class IndexEntry:
+    name: str
+    meta_version: str
+    kind: str
+    content_id: str
+    freeze_time_str: str
+    size: int
+    mtime_ns: int
+    # timestamp of the last successful verification of the archive
+    verified_at: Optional[str] = None
+    # checksum of the zip file, see Archive.archive_sha256
+    archive_sha256: Optional[str] = None
+
+    @classmethod
+    def from_archive(cls, archive: Archive, stat: os.stat_result):
+        return cls(
+            name=archive.name,
+            meta_version=archive.meta_version,
+            kind=archive.kind,
+            content_id=archive.content_id,
+            freeze_time_str=archive.freeze_time_str,
+            size=stat.st_size,
+            mtime_ns=stat.st_mtime_ns,
+            archive_sha256=archive.archive_sha256)
+
+    def is_current(self, stat: os.stat_result):
+        return self.size == stat.st_size and self.mtime_ns == stat.st_mtime_ns
+
+    @property
+    def archive_cache(self):
+        '''
+        Values known by the index in the form of an Archive's meta cache.
+        '''
+        cache = {
+            meta.META_VERSION: self.meta_version,
+            meta.KIND: self.kind,
+            CACHE_CONTENT_ID: self.content_id,
+            meta.FREEZE_TIME: self.freeze_time_str}
+        if self.archive_sha256 is not None:
+            cache[CACHE_ARCHIVE_SHA256] = self.archive_sha256
+        return cache
+
+    def as_dict(self):
+        entry = attr.asdict(self)
+        del entry['name']
+        for optional in ('verified_at', 'archive_sha256'):
+            if entry[optional] is None:
+                del entry[optional]
+        return entry
+
+
+class BoxIndex:
+    '''
+    Archive meta data of a box directory, keyed by archive file name.
+    '''
+
+    def __init__(self, directory: Path):
+        self.directory = Path(directory)
+        self.entries: Dict[str, IndexEntry] = {}
+        # entries changed by this instance (None: removed) - merged into the index on disk
+        self.updated: Dict[str, Optional[IndexEntry]] = {}
+        self.changed = False
+        # file names by content id, made on first use
+        self._by_content_id: Optional[Dict[str, List[str]]] = None
+        # alias -> kind
+        self.aliases: Dict[str, str] = {}
+        # aliases changed by this instance (None: removed)
+        self.updated_aliases: Dict[str, Optional[str]] = {}
+
+    @property
+    def path(self):
+        return self.directory / INDEX_FILE
+
+    @classmethod
+    def load(cls, directory: Path):
+        index = cls(directory)
+        try:
+            content = persistence.file_load(index.path)
+        except FileNotFoundError:
+            return index
+        except (persistence.ReadError, OSError):
+            TRACELOG(f'Ignoring unreadable box index {index.path}')
+            return index
+        if not isinstance(content, dict):
+            return index
+        index.aliases = _valid_aliases(content.get(_ALIASES))
+        if content.get(_VERSION) != INDEX_VERSION:
+            return index
+        for filename, entry in content.get(_ARCHIVES, {}).items():
+            try:
+                index.entries[filename] = IndexEntry(
+                    name=bead_name_from_file_path(filename), **entry)
+            except (TypeError, ValueError):
+                index.changed = True
+        return index
+
+    def save(self) -> bool:
+        '''
+        Write the index if it was changed, dropping entries of missing archives.
+
+        The changes are merged into the current index on disk,
+        so that changes saved by others since loading this index are kept.
+        Returns True, if the index file was (re)written.
+        '''
+        if not self.changed:
+            return False
+        on_disk = BoxIndex.load(self.directory)
+        merged = on_disk.entries
+        for filename, entry in self.updated.items():
+            if entry is None:
+                merged.pop(filename, None)
+            else:
+                merged[filename] = entry
+        aliases = on_disk.aliases
+        for alias, kind in self.updated_aliases.items():
+            if kind is None:
+                aliases.pop(alias, None)
+            else:
+                aliases[alias] = kind
+        self.aliases = aliases
+        self.entries = {
+            filename: entry
+            for filename, entry in merged.items()
+            if (self.directory / filename).exists()}
+        self._by_content_id = None
+        content = {
+            _VERSION: INDEX_VERSION,
+            _ARCHIVES: {
+                filename: entry.as_dict()
+                for filename, entry in self.entries.items()}}
+        if self.aliases:
+            content[_ALIASES] = self.aliases
+        try:
+            persistence.file_dump(content, self.path)
+        except OSError:
+            TRACELOG(f'Could not write box index {self.path}')
+            return False
+        self.updated = {}
+        self.updated_aliases = {}
+        self.changed = False
+        return True
+
+    def get(self, path: Path) -> Optional[IndexEntry]:
+        '''
+        Entry for the archive at path, if it is indexed and up to date.
+        '''
+        entry = self.entries.get(path.name)
+        if entry is None:
+            return None
+        try:
+            stat = path.stat()
+        except OSError:
+            return None
+        if entry.is_current(stat):
+            return entry
+        return None
+
+    def with_content_id(self, content_id: str) -> List[str]:
+        '''
+        File names of the archives with exactly content_id - without looking at all entries.
+
+        The entries can be out of date, check them with get.
+        '''
+        if self._by_content_id is None:
+            self._by_content_id = {}
+            for filename, entry in self.entries.items():
+                self._by_content_id.setdefault(entry.content_id, []).append(filename)
+        return list(self._by_content_id.get(content_id, []))
+
+    def add(self, archive: Archive):
+        path = archive.archive_path
+        stat = path.stat()
+        self.entries[path.name] = IndexEntry.from_archive(archive, stat)
+        self.updated[path.name] = self.entries[path.name]
+        self.changed = True
+        self._by_content_id = None
+
+    def set_verified(self, archive: Archive, timestamp: str):
+        '''
+        Record a successful verification of archive.
+        '''
+        entry = self.get(archive.archive_path)
+        if entry is None or entry.content_id != archive.content_id:
+            self.add(archive)
+            entry = self.entries[archive.archive_path.name]
+        self.entries[archive.archive_path.name] = attr.evolve(entry, verified_at=timestamp)
+        self.updated[archive.archive_path.name] = self.entries[archive.archive_path.name]
+        self.changed = True
+
+    def is_verified_since(self, path: Path, since: datetime) -> bool:
+        '''
+        Was the archive at path - unchanged since - verified after since?
+        '''
+        entry = self.get(path)
+        if entry is None or entry.verified_at is None:
+            return False
+        return time_from_timestamp(entry.verified_at) >= since
+
+    def remove(self, filename):
+        filename = os.path.basename(filename)
+        if self.entries.pop(filename, None) is not None:
+            self.updated[filename] = None
+            self.changed = True
+            self._by_content_id = None
+
+    def set_alias(self, alias: str, kind: str):
+        self.aliases[alias] = kind
+        self.updated_aliases[alias] = kind
+        self.changed = True
+
+    def remove_alias(self, alias: str) -> bool:
+        if self.aliases.pop(alias, None) is None:
+            return False
+        self.updated_aliases[alias] = None
+        self.changed = True
+        return True
+
+    def remove_missing(self) -> List[str]:
+        '''
+        Drop the entries of archives, that are no longer in the directory.
+
+        Returns the file names of the dropped entries.
+        '''
+        missing = sorted(
+            filename for filename in self.entries if not (self.directory / filename).exists())
+        for filename in missing:
+            self.remove(filename)
+        return missing
+
+
+def _valid_aliases(aliases) -> Dict[str, str]:
+    if not isinstance(aliases, dict):
+        return {}
+    return {
+        alias: kind for alias, kind in aliases.items()
+        if isinstance(alias, str) and isinstance(kind, str)}
@@ -0,0 +1,336 @@
+'''
+High level operations on beads - for tools embedding bead.
+
+A BeadContext holds the boxes (in search order) and the settings, that the bead command
+reads from its environment, so that saving, developing and updating inputs
+do not need wiring Workspace, Box and Archive together by hand:
+
+    ctx = BeadContext([Box('main', '/data/beads')])
+    archive = ctx.save(Workspace('analysis'))
+    workspace = ctx.develop('analysis', 'analysis-copy')
+    ctx.update_input(workspace, 'raw-data')
+
+With a DryRun nothing is changed, the operations only record what they would do -
+for previews in pipelines and GUIs:
+
+    preview = DryRun()
+    BeadContext([Box('main', '/data/beads')], dry_run=preview).save(Workspace('analysis'))
+    for action in preview.actions:
+        print(action)
+'''
+
+import os
+import warnings
+from typing import List, Mapping, Optional, Sequence
+
+import attr
+
+from . import aliases
+from . import hooks
+from . import layouts
+from . import notebooks
+from . import save_policy
+from . import spec as bead_spec
+from . import validators
+from .archive import Archive
+from .box import Box, UnionBox, CONFLICT_PRIORITY
+from .exceptions import BoxError, InvalidArchive, UserError
+from .tech import securehash
+from .tech.cancellation import NEVER_CANCELLED
+from .tech.fs import Path
+from .tech.progress import NO_PROGRESS
+from .tech.timestamp import parse_iso8601, timestamp, timestamp_after
+from .workspace import Workspace
+
+# search time for the newest version
+LATEST = parse_iso8601('9999-12-31')
+
+# DryRun action operations
+SAVE = 'save'
+DEVELOP = 'develop'
+LOAD = 'load'
+UNLOAD = 'unload'
+
+
+@attr.s(frozen=True, auto_attribs=True)
+class Action:
+    '''
+    A change an operation would make.
+    '''
+    operation: str
+    # the archive or workspace directory created or changed
+    path: Path
+    description: str
+
+    def __str__(self):
+        return self.description
+
+
+class DryRun:
+    '''
+    Recorder of the actions of a BeadContext in dry run mode.
+    '''
+
+    def __init__(self):
+        self.actions: List[Action] = []
+
+    def record(self, operation, path, description):
+        self.actions.append(Action(operation, Path(path), description))
+
+
+class BeadContext:
+    '''
+    Boxes and settings for high level operations.
+
+    Boxes are searched in the given order, copies of a bead in more boxes are chosen
+    by the conflict policy (see UnionBox). With a search_timeout, boxes not answering
+    in time are left out of searches.
+
+    With dry_run (a DryRun) the operations check their preconditions as usual,
+    but instead of changing boxes and workspaces, they record the would-be actions.
+    trusted_hooks are the fingerprints of the hooks the user trusted by hooks.trust_key
+    - other hooks are not run.
+    With fuzzy_references a reference, that is not a bead name, may also be
+    a content id prefix or a kind (see resolve).
+    save_policies and name_collision_policy are checked on save (see the save_policy module).
+    '''
+
+    def __init__(
+            self, boxes: Sequence[Box], conflict_policy=CONFLICT_PRIORITY,
+            compression=None, validators: Sequence = (), notebook_outputs=notebooks.KEEP,
+            hash_algorithm=securehash.DEFAULT_ALGORITHM, search_timeout=None,
+            dry_run: Optional[DryRun] = None,
+            trusted_hooks: Optional[Mapping[str, Mapping[str, str]]] = None,
+            fuzzy_references=False, save_policies: Optional[Mapping] = None,
+            name_collision_policy=save_policy.NAME_COLLISION_WARN):
+        self.boxes = list(boxes)
+        self.conflict_policy = conflict_policy
+        self.compression = compression
+        self.validators = list(validators)
+        self.notebook_outputs = notebook_outputs
+        self.hash_algorithm = hash_algorithm
+        self.search_timeout = search_timeout
+        self.dry_run = dry_run
+        self.trusted_hooks = dict(trusted_hooks or {})
+        self.fuzzy_references = fuzzy_references
+        self.save_policies = dict(save_policies or {})
+        self.name_collision_policy = name_collision_policy
+
+    @property
+    def union_box(self) -> UnionBox:
+        return UnionBox(self.boxes, self.conflict_policy, self.search_timeout)
+
+    def get_box(self, box_name: Optional[str] = None) -> Box:
+        '''
+        Box by name - or the only box, when no name is given.
+        '''
+        if box_name is None:
+            if len(self.boxes) != 1:
+                raise BoxError(f'A box must be named, there are {len(self.boxes)} boxes')
+            return self.boxes[0]
+        for box in self.boxes:
+            if box.name == box_name:
+                return box
+        raise BoxError(f'Unknown box: {box_name}')
+
+    def resolve(self, reference, time=LATEST) -> Archive:
+        '''
+        Archive for reference - an archive file, or a bead name or alias searched in the boxes,
+        with fuzzy_references also a content id prefix or kind (see UnionBox.resolve_reference).
+
+        The version of a bead name is the one closest to time, the newest by default.
+        Raises LookupError, if not found.
+        '''
+        if os.path.isfile(reference):
+            return Archive(reference)
+        return self.union_box.resolve_reference(reference, time, self.fuzzy_references)
+
+    def save(
+            self, workspace: Workspace, box_name: Optional[str] = None, freeze_time=None,
+            cancel=NEVER_CANCELLED, progress=NO_PROGRESS,
+            provenance=None, run_hooks=True, use_hash_cache=True, compression=None,
+            signing_key=None, skip_unchanged=False, override=False) -> Optional[Archive]:
+        '''
+        Store workspace in a box as a new version, checked by the validators.
+
+        A name collision refused by name_collision_policy and violated save_policies
+        raise SaveRefused - with override policy violations only warn.
+        The freeze time defaults to now - or just after the newest version in the box,
+        when the clock is behind (with a SaveWarning).
+
+        Pipeline runners pass a provenance (see Workspace.provenance) to mark automated freezes.
+        With run_hooks the trusted hooks of the workspace are run (see the hooks module):
+        a failing pre-save hook raises HookError, a failing post-save hook only warns.
+        Without use_hash_cache all files are hashed (see the hash_cache module).
+        The compression defaults to the one of the box, then to the one of the context.
+        With a signing_key a detached signature is stored next to the archive.
+        Returns None in dry run mode - no hooks are run then - and with skip_unchanged,
+        when inputs, code and output are the same as in the last saved version.
+        '''
+        if not workspace.is_valid:
+            raise UserError(
+                f'{workspace.directory} is not a valid workspace',
+                operation='save', path=workspace.directory)
+        box = self.get_box(box_name)
+        save_policy.check_name_collision(box, workspace, self.name_collision_policy)
+        save_policy.enforce(workspace, self.save_policies, self.union_box, override)
+        freeze_time = self._freeze_time(box, workspace, freeze_time)
+        if self.dry_run is not None:
+            self.dry_run.record(
+                SAVE, box.directory / f'{workspace.name}_{freeze_time}.zip',
+                f'Save {workspace.directory} as a new version of {workspace.name}'
+                + f' in box {box.name}')
+            return None
+        trusted_hooks = self.trusted_hooks.get(hooks.trust_key(workspace))
+        if run_hooks:
+            hooks.run(workspace, hooks.PRE_SAVE, trusted=trusted_hooks)
+        if skip_unchanged:
+            last_saved = workspace.last_saved([box])
+            if last_saved is not None and not workspace.is_dirty(last_saved, cancel):
+                return None
+
+        def validate(archive_path):
+            validators.run(self.validators, archive_path)
+        archive = box.store(
+            workspace, freeze_time, compression or box.compression or self.compression,
+            signing_key, cancel=cancel, validate=validate, progress=progress,
+            notebook_outputs=self.notebook_outputs, hash_algorithm=self.hash_algorithm,
+            provenance=provenance, use_hash_cache=use_hash_cache)
+        if run_hooks:
+            try:
+                hooks.run(
+                    workspace, hooks.POST_SAVE, archive.archive_path, trusted=trusted_hooks)
+            except hooks.HookError as e:
+                warnings.warn(str(e), hooks.HookFailed)
+        return archive
+
+    def _freeze_time(self, box, workspace, freeze_time):
+        if freeze_time is not None:
+            return freeze_time
+        now = timestamp()
+        newest_freeze_time = save_policy.clock_skew(box, workspace, now)
+        if newest_freeze_time is None:
+            return now
+        freeze_time = timestamp_after(newest_freeze_time)
+        warnings.warn(
+            f'The clock is behind: {workspace.name} in box {box.name} was frozen at'
+            + f' {newest_freeze_time}, later than now ({now}), saving it as of {freeze_time}',
+            save_policy.SaveWarning)
+        return freeze_time
+
+    def develop(
+            self, reference, target=None, extract_output=False, time=LATEST,
+            load_inputs=False, vcs_friendly=False,
+            cancel=NEVER_CANCELLED, progress=NO_PROGRESS) -> Optional[Workspace]:
+        '''
+        Create a workspace from a bead, in a directory named after it by default.
+
+        The inputs are defined as in the bead, with load_inputs their data is also loaded
+        - as far as it is found in the boxes (see load_inputs).
+        Returns None in dry run mode.
+        '''
+        archive = self.resolve(reference, time)
+        archive.validate()
+        directory = Path(target if target is not None else archive.name)
+        if directory.exists():
+            raise UserError(
+                f'Workspace directory {directory} already exists',
+                operation='develop', path=directory, bead_name=archive.name)
+        if self.dry_run is not None:
+            self.dry_run.record(
+                DEVELOP, directory,
+                f'Create workspace {directory} from {archive.name} {archive.freeze_time_str}'
+                + f' in box {archive.box_name}')
+            if load_inputs:
+                union_box = self.union_box
+                for input in archive.inputs:
+                    input_archive = self._find_input_archive(union_box, archive, input)
+                    if input_archive is not None:
+                        self._record_load(directory, input.name, input_archive)
+            return None
+        workspace = Workspace.from_archive(
+            archive, directory, extract_output, cancel=cancel, vcs_friendly=vcs_friendly,
+            progress=progress)
+        if load_inputs:
+            self.load_inputs(workspace, cancel, progress)
+        return workspace
+
+    def load_inputs(
+            self, workspace: Workspace, cancel=NEVER_CANCELLED,
+            progress=NO_PROGRESS) -> List[str]:
+        '''
+        Load the exact versions of the not yet loaded inputs, that are found in the boxes.
+
+        Returns the names of inputs not loaded - their bead is missing or damaged.
+        '''
+        union_box = self.union_box
+        not_loaded = []
+        for input in workspace.inputs:
+            if workspace.is_loaded(input.name):
+                continue
+            archive = self._find_input_archive(union_box, workspace, input)
+            if archive is None:
+                not_loaded.append(input.name)
+            elif self.dry_run is not None:
+                self._record_load(workspace.directory, input.name, archive)
+            else:
+                workspace.load(input.name, archive, cancel, progress)
+        return not_loaded
+
+    def _find_input_archive(self, union_box, bead, input) -> Optional[Archive]:
+        '''
+        The valid archive of the exact version of input of bead (a workspace or an archive).
+        '''
+        archive = union_box.find_bead(bead.get_input_bead_name(input.name), input.content_id)
+        if archive is None or archive.kind != input.kind:
+            return None
+        try:
+            archive.validate()
+        except InvalidArchive:
+            return None
+        return archive
+
+    def _record_load(self, directory, input_name, archive):
+        self.dry_run.record(
+            LOAD, Path(directory) / layouts.Workspace.INPUT / input_name,
+            f'Load {archive.name} {archive.freeze_time_str} as input {input_name}'
+            + f' of {directory}')
+
+    def update_input(
+            self, workspace: Workspace, input_name, reference=None, time=LATEST,
+            cancel=NEVER_CANCELLED, progress=NO_PROGRESS) -> Archive:
+        '''
+        Load the version of the input closest to time (the newest by default).
+
+        The input is updated from the bead it was loaded from, or from reference, if given.
+        A bead not of the kind of the shared alias of the input raises AliasError.
+        Returns the loaded archive - in dry run mode the one that would be loaded.
+        '''
+        input = workspace.get_input(input_name)
+        if input is None:
+            raise UserError(
+                f'Workspace does not have input "{input_name}"',
+                operation='update', path=workspace.directory)
+        if reference is None:
+            bead_name = workspace.get_input_bead_name(input_name)
+            archive = self.union_box.get_at(bead_spec.BEAD_NAME, bead_name, time)
+            aliases.check_kind(workspace.input_alias(input_name), archive)
+        else:
+            archive = self.resolve(reference, time)
+        if workspace.is_loaded(input_name) and input.content_id == archive.content_id:
+            return archive
+        archive.validate()
+        if self.dry_run is not None:
+            if workspace.is_loaded(input_name):
+                self.dry_run.record(
+                    UNLOAD, workspace.directory / layouts.Workspace.INPUT / input_name,
+                    f'Unload input {input_name} of {workspace.directory}')
+            self._record_load(workspace.directory, input_name, archive)
+            return archive
+        workspace.set_input_bead_name(input_name, archive.name)
+        # a partially loaded input is updated partially - unload forgets the patterns
+        patterns = workspace.loaded_patterns(input_name)
+        if workspace.is_loaded(input_name):
+            workspace.unload(input_name)
+        workspace.load(input_name, archive, cancel, progress, patterns=patterns)
+        return archive
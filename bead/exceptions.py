@@ -1,6 +1,91 @@
-class InvalidArchive(Exception):
+class BeadError(Exception):
+    """
+    Base of errors raised by bead.
+
+    Context of the error - the operation, the path and the bead involved -
+    can be given as keyword arguments, it is appended to the message.
+    """
+
+    # exit code of the command line tool, when the error is not handled otherwise
+    exit_code = 1
+
+    def __init__(self, *args, operation=None, path=None, bead_name=None):
+        super().__init__(*args)
+        self.operation = operation
+        self.path = path
+        self.bead_name = bead_name
+
+    @property
+    def context(self):
+        context = {
+            'operation': self.operation,
+            'path': self.path,
+            'bead': self.bead_name}
+        return {key: value for key, value in context.items() if value is not None}
+
+    @property
+    def message(self):
+        '''
+        The message without the context.
+        '''
+        return super().__str__() or self.__doc__.strip().splitlines()[0]
+
+    def __str__(self):
+        if not self.context:
+            return self.message
+        context = ', '.join(f'{key}: {value}' for key, value in self.context.items())
+        return f'{self.message} ({context})'
+
+
+class UserError(BeadError):
+    """Misuse, e.g. bad arguments or configuration - the user can fix it"""
+    exit_code = 2
+
+
+class WorkspaceLocked(UserError):
+    """The workspace is being modified by another process"""
+
+
+class InternalError(BeadError):
+    """A bug in bead - it should be reported"""
+    # EX_SOFTWARE from sysexits.h
+    exit_code = 70
+
+
+class InvalidArchive(BeadError):
     """Not a valid bead archive"""
+    exit_code = 3
 
 
-class BoxError(Exception):
+class UnsupportedMetaVersion(InvalidArchive):
+    """Archive meta version is not known - made by an incompatible version of bead?"""
+
+
+class UnsupportedHashAlgorithm(InvalidArchive):
+    """Archive is hashed with an algorithm that is not known or not installed"""
+
+
+class ContentMismatch(InvalidArchive):
+    """Archive content differs from what was expected (manifest or input spec)"""
+
+
+class BoxError(UserError):
     """Box operation related error"""
+
+
+class TransferError(BeadError):
+    """Archive was damaged while copied - its checksum differs from the original's"""
+    # not the user's fault and the original is intact - copying again might succeed
+    exit_code = 4
+
+
+class SignatureError(BeadError):
+    """Archive signature is missing, invalid or can not be made"""
+
+
+class AmbiguousBead(BoxError):
+    """The same bead version is in multiple boxes and the conflict policy refuses to choose"""
+
+
+class AmbiguousReference(BoxError):
+    """A content id prefix or kind refers to different beads"""
@@ -0,0 +1,65 @@
+'''
+Export of bead content to a plain directory or .tar.gz - for people not using bead.
+
+Only the data (and on request the code) is exported, without the bead meta data.
+Without code the data files are at the top of the export, with code they are under
+data/ and code/. A tarball has them under a directory named as the tarball.
+Files are verified against the manifest while extracted.
+'''
+
+import os
+import tarfile
+
+from . import layouts
+from .tech import fs
+from .tech.cancellation import NEVER_CANCELLED
+from .tech.progress import NO_PROGRESS
+
+TARBALL_SUFFIXES = ('.tar.gz', '.tgz')
+
+
+def is_tarball(path) -> bool:
+    return os.fspath(path).endswith(TARBALL_SUFFIXES)
+
+
+def _tarball_root_name(path: fs.Path) -> str:
+    for suffix in TARBALL_SUFFIXES:
+        if path.name.endswith(suffix):
+            return path.name[:-len(suffix)]
+    return path.name
+
+
+def export(
+        archive, destination, include_code=False, cancel=NEVER_CANCELLED,
+        progress=NO_PROGRESS) -> fs.Path:
+    '''
+    Export archive to destination - a tarball (see is_tarball) or a new directory.
+
+    Nothing is left behind on errors and cancellation.
+    '''
+    destination = fs.Path(destination)
+    assert not destination.exists()
+    with fs.removed_on_error(destination):
+        if not is_tarball(destination):
+            _extract(archive, destination, include_code, cancel, progress)
+            return destination
+        with fs.temp_dir(destination.parent) as temp_dir:
+            root_name = _tarball_root_name(destination)
+            _extract(archive, temp_dir / root_name, include_code, cancel, progress)
+
+            def check_cancel(tarinfo):
+                cancel.check()
+                return tarinfo
+            with tarfile.open(destination, 'w:gz') as tar:
+                tar.add(temp_dir / root_name, arcname=root_name, filter=check_cancel)
+    return destination
+
+
+def _extract(archive, directory: fs.Path, include_code, cancel, progress):
+    if not include_code:
+        archive.extract_dir(
+            layouts.Archive.DATA, directory, verify=True, cancel=cancel, progress=progress)
+        return
+    for zip_dir in (layouts.Archive.DATA, layouts.Archive.CODE):
+        archive.extract_dir(
+            zip_dir, directory / zip_dir, verify=True, cancel=cancel, progress=progress)
@@ -0,0 +1,235 @@
+'''
+Specification of the archive format - as data.
+
+Third party implementations and auditors can check conformance with it:
+the archive layout, JSON schemas of the meta files, the timestamp grammar
+and the content id algorithm are given along with test vectors.
+
+(Not to be confused with `bead.spec`, which defines bead query conditions.)
+'''
+
+import re
+import zipfile
+
+from . import layouts
+from . import meta
+from .tech import persistence
+from .workspace import META_VERSION
+
+FORMAT_VERSION = META_VERSION
+
+# zip paths, directories end with /
+ARCHIVE_LAYOUT = {
+    layouts.Archive.BEAD_META: 'bead meta data, see META_SCHEMA',
+    layouts.Archive.MANIFEST: 'file hashes, see MANIFEST_SCHEMA, its hash is the content id',
+    layouts.Archive.INPUT_MAP: 'input name -> bead name, not part of the content id',
+    layouts.Archive.CODE + '/': 'source files of the workspace',
+    layouts.Archive.DATA + '/': 'output files of the workspace',
+}
+
+# full, basic ISO 8601 timestamp with microseconds and mandatory time zone,
+# e.g. 20200913T173910123456+0200
+TIMESTAMP_GRAMMAR = r'[0-9]{8}T[0-9]{6}[0-9]{6}[-+][0-9]{4}'
+# hex digest - untagged for sha512, tagged with the algorithm for the others (see securehash)
+HASH_GRAMMAR = r'(?:[0-9a-f]{128}|blake2b:[0-9a-f]{128}|blake3:[0-9a-f]{64})'
+
+TIMESTAMP_SCHEMA = {'type': 'string', 'pattern': TIMESTAMP_GRAMMAR}
+HASH_SCHEMA = {'type': 'string', 'pattern': HASH_GRAMMAR}
+
+META_SCHEMA = {
+    'type': 'object',
+    'required': [
+        meta.META_VERSION, meta.KIND, meta.FREEZE_TIME, meta.FREEZE_NAME, meta.INPUTS],
+    'properties': {
+        meta.META_VERSION: {'type': 'string', 'enum': [FORMAT_VERSION]},
+        meta.KIND: {'type': 'string'},
+        meta.FREEZE_TIME: TIMESTAMP_SCHEMA,
+        meta.FREEZE_NAME: {'type': 'string'},
+        meta.DESCRIPTION: {'type': 'string'},
+        meta.TAGS: {'type': 'array', 'items': {'type': 'string'}},
+        meta.AUTHOR: {'type': 'string'},
+        meta.INPUTS: {
+            'type': 'object',
+            'additionalProperties': {
+                'type': 'object',
+                'required': [meta.INPUT_KIND, meta.INPUT_CONTENT_ID, meta.INPUT_FREEZE_TIME],
+                'properties': {
+                    meta.INPUT_KIND: {'type': 'string'},
+                    meta.INPUT_CONTENT_ID: HASH_SCHEMA,
+                    meta.INPUT_FREEZE_TIME: TIMESTAMP_SCHEMA,
+                },
+            },
+        },
+    },
+}
+
+# every file under code/ and data/ and the meta/bead file must be listed
+MANIFEST_SCHEMA = {
+    'type': 'object',
+    'required': [layouts.Archive.BEAD_META],
+    'additionalProperties': HASH_SCHEMA,
+}
+
+INPUT_MAP_SCHEMA = {
+    'type': 'object',
+    'additionalProperties': {'type': 'string'},
+}
+
+FILE_HASH_ALGORITHM = (
+    'hex digest of "{size}:" + content + ";{size}",'
+    ' where size is the content length in bytes as a decimal number;'
+    ' sha512 digests are untagged, blake2b (64 bytes) and blake3 (32 bytes) digests'
+    ' are prefixed with the algorithm and a colon, e.g. "blake2b:{digest}"')
+CONTENT_ID_ALGORITHM = (
+    'file hash (see FILE_HASH_ALGORITHM) of the meta/manifest file,'
+    ' as stored in the archive (JSON, 4 spaces indentation, sorted keys, ASCII only),'
+    ' made with the algorithm of the meta/bead hash in the manifest'
+    ' (sha512, if the manifest has none)')
+
+TEST_VECTORS = {
+    'file_hash': [
+        {
+            'content': '',
+            'hash': (
+                '5b60b8cea7aad166b6156699f603f8a539019b1fd3cccaa269ad9a3cf2e8c6bd'
+                '74aad43ba9cb0d0d2fe62f6780fe219ab7c8e7555c7bb409631bca1215777574'),
+        },
+        {
+            'content': 'hello bead',
+            'hash': (
+                '9193daa3022de472d45e1a17f2fdfb8b2b889f1ebabe6f797e50cc2f3f8942b8'
+                'cee31640435ab47807c685c88b6efb06e5e78914023b0d43123139eec2a7ddae'),
+        },
+        {
+            'content': 'hello bead',
+            'algorithm': 'blake2b',
+            'hash': (
+                'blake2b:'
+                '7feaf10cff7381e67488a760cf77442fd8f2678b3528a0f4597a3fbe1734380f'
+                '28addc3f3bc48b10a9994a866890946224e6d65c50e361da3aad2c2328fd983b'),
+        },
+    ],
+    'content_id': [
+        {
+            'manifest': {
+                'code/run.py': (
+                    '1b78f93c817a96642ee8facbb8d3068cc2d910b40cf998ed2f28e02c92be1369'
+                    '7132e1d3c53b7bf56bae57446cf37b3ad5592f798643c0d6c7307136b4ddcd8b'),
+                'data/answer': (
+                    '712e59feb63e158153869b563ac2427a229e91b068dd6eb455ed0a0dc435aea8'
+                    'd09769d64d5c0d517f37aad8c9663922ead7c0cfb7f36fee22de0a62c5efc0f7'),
+            },
+            'content_id': (
+                '6fb541ca779419de4b181e6e1827048d9cc96944a16cd72cddd1bcde4ac3c0ef'
+                '2be71d999e20263a798b247875063364ec27a57a21b02d9b6b1fd83750a4da7b'),
+        },
+        {
+            'manifest': {
+                'meta/bead': (
+                    'blake2b:'
+                    '9798e7bae94d128ed4db8f2301fe42bedd5bdb34b67402d7d179f5cc5b4e893d'
+                    '2808224ef4b5dc7ff300edeb5b9ac41f5df76450ab9177c5b782f3d6bed403b9'),
+                'data/answer': (
+                    'blake2b:'
+                    'f5ee68a604b7caa0279c8d3d3a3b794b6950e15eaebcb58af3dd160f9792c7fb'
+                    'a026d65adc6d6a629f58c49fe286cb67e9b328860878b2dbe2dbeac5616dc860'),
+            },
+            'content_id': (
+                'blake2b:'
+                'c5ccdaf444ef7e2ca2d7f56c32a2da66c4ddc6de67208b820e6dfd23a7e1dc0e'
+                '6f197123aa7de57cf0a54868ee9b73815ff421830c57cad12e49c24343f72b8e'),
+        },
+    ],
+    'timestamp': {
+        'valid': ['20200913T173910123456+0200', '20000101T000000000000-0130'],
+        'invalid': ['20200913T173910+0200', '20200913T173910123456', '2020-09-13T17:39:10Z'],
+    },
+}
+
+
+def schema_errors(instance, schema, path='$'):
+    '''
+    Differences of instance from a schema in this module - as a list of messages.
+
+    Implements the subset of JSON Schema used here:
+    type (object/array/string), required, properties, additionalProperties, items,
+    pattern, enum.
+    '''
+    expected_type = {'object': dict, 'array': list, 'string': str}[schema['type']]
+    if not isinstance(instance, expected_type):
+        return [f'{path}: expected {schema["type"]}']
+    errors = []
+    if 'enum' in schema and instance not in schema['enum']:
+        errors.append(f'{path}: {instance!r} is not one of {schema["enum"]}')
+    if 'pattern' in schema and not re.fullmatch(schema['pattern'], instance):
+        errors.append(f'{path}: {instance!r} does not match {schema["pattern"]}')
+    if expected_type is list and 'items' in schema:
+        for i, item in enumerate(instance):
+            errors.extend(schema_errors(item, schema['items'], f'{path}[{i}]'))
+    if expected_type is dict:
+        errors.extend(_object_errors(instance, schema, path))
+    return errors
+
+
+def _object_errors(instance: dict, schema, path):
+    errors = [
+        f'{path}: missing {key}' for key in schema.get('required', ()) if key not in instance]
+    properties = schema.get('properties', {})
+    for key, value in instance.items():
+        if key in properties:
+            errors.extend(schema_errors(value, properties[key], f'{path}.{key}'))
+        elif 'additionalProperties' in schema:
+            errors.extend(schema_errors(value, schema['additionalProperties'], f'{path}.{key}'))
+    return errors
+
+
+def conformance_errors(archive_path):
+    '''
+    Check the structure of an archive against the specification.
+
+    File contents are not verified, use Archive.validate() for that.
+    '''
+    try:
+        with zipfile.ZipFile(archive_path) as zf:
+            names = set(zf.namelist())
+            for required in (layouts.Archive.BEAD_META, layouts.Archive.MANIFEST):
+                if required not in names:
+                    return [f'missing {required}']
+            errors = []
+            bead_meta = persistence.zip_load(zf, layouts.Archive.BEAD_META)
+            errors.extend(schema_errors(bead_meta, META_SCHEMA, layouts.Archive.BEAD_META))
+            manifest = persistence.zip_load(zf, layouts.Archive.MANIFEST)
+            errors.extend(schema_errors(manifest, MANIFEST_SCHEMA, layouts.Archive.MANIFEST))
+            if layouts.Archive.INPUT_MAP in names:
+                input_map = persistence.zip_load(zf, layouts.Archive.INPUT_MAP)
+                errors.extend(
+                    schema_errors(input_map, INPUT_MAP_SCHEMA, layouts.Archive.INPUT_MAP))
+    except (zipfile.BadZipFile, persistence.ReadError, UnicodeDecodeError) as e:
+        return [f'not readable: {e}']
+    if not isinstance(manifest, dict):
+        return errors
+    content_dirs = (layouts.Archive.CODE + '/', layouts.Archive.DATA + '/')
+    for name in sorted(names):
+        if name.startswith(content_dirs) and not name.endswith('/') and name not in manifest:
+            errors.append(f'{name}: not in manifest')
+    return errors
+
+
+def specification():
+    '''
+    The whole specification as a JSON serializable structure.
+    '''
+    return {
+        'format_version': FORMAT_VERSION,
+        'archive_layout': ARCHIVE_LAYOUT,
+        'timestamp_grammar': TIMESTAMP_GRAMMAR,
+        'hash_grammar': HASH_GRAMMAR,
+        'schemas': {
+            layouts.Archive.BEAD_META: META_SCHEMA,
+            layouts.Archive.MANIFEST: MANIFEST_SCHEMA,
+            layouts.Archive.INPUT_MAP: INPUT_MAP_SCHEMA,
+        },
+        'file_hash_algorithm': FILE_HASH_ALGORITHM,
+        'content_id_algorithm': CONTENT_ID_ALGORITHM,
+        'test_vectors': TEST_VECTORS,
+    }
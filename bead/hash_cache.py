@@ -0,0 +1,87 @@
+'''
+Hashes of workspace files, kept between saves - unchanged files are not hashed again.
+
+A file is taken as unchanged, if its size and modification time are the same,
+like make or rsync do. Changes keeping both are not noticed: save without the cache
+(save --no-hash-cache) to hash everything.
+Files modified just before hashing are not cached, as they might still be written to.
+'''
+
+import os
+import time
+from typing import Dict, Optional
+
+from tracelog import TRACELOG
+from . import layouts
+from .tech import persistence, securehash
+from .tech.fs import Path
+
+# files modified more recently before hashing them are not cached
+RACY_SECONDS = 2
+
+_SIZE = 'size'
+_MTIME = 'mtime_ns'
+_HASH = 'hash'
+
+
+class HashCache:
+    def __init__(self, directory: Path, entries: Optional[Dict[str, dict]] = None):
+        '''
+        directory: of the workspace, files are cached by their path relative to it
+        '''
+        self.directory = Path(directory)
+        self.path = self.directory / layouts.Workspace.HASH_CACHE
+        self._entries = entries or {}
+        # entries of the files looked at - files not in the workspace anymore are forgotten
+        self._used: Dict[str, dict] = {}
+
+    @classmethod
+    def load(cls, workspace) -> 'HashCache':
+        '''
+        The hash cache of workspace - empty, if there is none or it is unreadable.
+        '''
+        cache = cls(workspace.directory)
+        try:
+            entries = persistence.loads(cache.path.read_text())
+        except (OSError, persistence.ReadError):
+            return cache
+        if isinstance(entries, dict):
+            cache._entries = entries
+        return cache
+
+    def _key(self, path) -> str:
+        return Path(os.path.relpath(path, self.directory)).as_posix()
+
+    def get(self, path, algorithm: securehash.HashAlgo) -> Optional[str]:
+        '''
+        The hash of file path made by algorithm - None, if it is not known or the file changed.
+        '''
+        key = self._key(path)
+        entry = self._entries.get(key)
+        if not isinstance(entry, dict):
+            return None
+        stat = os.stat(path)
+        try:
+            if (entry[_SIZE], entry[_MTIME]) != (stat.st_size, stat.st_mtime_ns):
+                return None
+            hash = entry[_HASH]
+            if securehash.algorithm_of(hash) is not algorithm:
+                return None
+        except (KeyError, TypeError, ValueError):
+            return None
+        self._used[key] = entry
+        return hash
+
+    def add(self, path, hash: str):
+        stat = os.stat(path)
+        if time.time_ns() - stat.st_mtime_ns < RACY_SECONDS * 10 ** 9:
+            return
+        self._used[self._key(path)] = {
+            _SIZE: stat.st_size, _MTIME: stat.st_mtime_ns, _HASH: hash}
+
+    def save(self):
+        try:
+            persistence.file_dump(self._used, self.path)
+        except OSError as e:
+            # the cache is an optimization, saving works without it
+            TRACELOG(f'Could not save hash cache {self.path}: {e}')
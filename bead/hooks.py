@@ -0,0 +1,122 @@
+'''
+Freeze hooks - executables in the workspace, run around saving it.
+
+    .bead-meta/hooks/pre-save    before packing - e.g. running tests or regenerating output,
+                                 exiting with non-zero status aborts the save
+    .bead-meta/hooks/post-save   after the archive is stored, gets its path as argument
+
+Hooks run in the workspace directory with BEAD_WORKSPACE and BEAD_NAME
+(and for post-save BEAD_ARCHIVE) in their environment, their output is not captured.
+Hooks are not saved in the archive.
+
+The hooks come with the workspace (e.g. `.bead-meta` committed with vcs_friendly),
+so like git, bead does not run code just because it is there: hooks run only when the user
+trusted them (see fingerprint) - a hook changed since is not run either.
+On Windows hooks need an executable extension (e.g. pre-save.bat, see PATHEXT).
+'''
+
+import os
+import subprocess
+import sys
+import warnings
+from typing import Dict, Mapping, Optional
+
+from . import layouts
+from .exceptions import UserError
+from .tech import securehash
+
+PRE_SAVE = 'pre-save'
+POST_SAVE = 'post-save'
+HOOKS = (PRE_SAVE, POST_SAVE)
+
+
+class HookError(UserError):
+    """A hook failed or could not be run"""
+
+
+class HookFailed(UserWarning):
+    """A post-save hook failed - the archive is stored nevertheless"""
+
+
+class UntrustedHook(UserWarning):
+    """A hook was not run, as the user did not trust it - or it changed since"""
+
+
+def hook_path(workspace, hook):
+    return workspace.directory / layouts.Workspace.HOOKS / hook
+
+
+def _windows_extensions():
+    return [
+        extension.lower()
+        for extension in os.environ.get('PATHEXT', '.COM;.EXE;.BAT;.CMD').split(os.pathsep)
+        if extension]
+
+
+def find(workspace, hook):
+    '''
+    The file of hook in workspace - None, if it has none.
+    '''
+    path = hook_path(workspace, hook)
+    if sys.platform == 'win32':
+        for extension in _windows_extensions():
+            if path.with_name(hook + extension).is_file():
+                return path.with_name(hook + extension)
+    return path if path.is_file() else None
+
+
+def fingerprint(workspace) -> Dict[str, str]:
+    '''
+    Checksums of the hooks of workspace by hook - recorded, when the user trusts them.
+    '''
+    paths = {hook: find(workspace, hook) for hook in HOOKS}
+    return {
+        hook: securehash.file_sha256(path) for hook, path in paths.items() if path is not None}
+
+
+def trust_key(workspace) -> str:
+    '''
+    Key of workspace among the trusted hooks - its real path.
+    '''
+    return os.path.realpath(workspace.directory)
+
+
+def run(
+        workspace, hook, archive_path: Optional[os.PathLike] = None,
+        trusted: Optional[Mapping[str, str]] = None):
+    '''
+    Run hook of workspace - if it has one, raise HookError if it fails.
+
+    trusted is the fingerprint of the hooks, when the user trusted them,
+    an untrusted (or since changed) hook is not run, only warned about (UntrustedHook).
+    '''
+    assert hook in HOOKS
+    path = find(workspace, hook)
+    if path is None:
+        return
+    if (trusted or {}).get(hook) != securehash.file_sha256(path):
+        warnings.warn(
+            f'The {hook} hook {path} is not run, as it is not trusted'
+            + ' (or it changed since it was trusted)', UntrustedHook)
+        return
+    if sys.platform == 'win32':
+        if path.suffix.lower() not in _windows_extensions():
+            raise HookError(
+                f'The {hook} hook {path} needs an executable extension (e.g. .bat) on Windows',
+                operation='save', path=path)
+    elif not os.access(path, os.X_OK):
+        raise HookError(
+            f'The {hook} hook {path} is not executable', operation='save', path=path)
+    command = [os.fspath(path)]
+    env = dict(os.environ, BEAD_WORKSPACE=os.fspath(workspace.directory), BEAD_NAME=workspace.name)
+    if archive_path is not None:
+        command.append(os.fspath(archive_path))
+        env['BEAD_ARCHIVE'] = os.fspath(archive_path)
+    try:
+        result = subprocess.run(command, cwd=workspace.directory, env=env)
+    except OSError as e:
+        raise HookError(f'The {hook} hook can not be run: {e}', operation='save', path=path)
+    if result.returncode != 0:
+        raise HookError(
+            f'The {hook} hook failed (exit status {result.returncode})',
+            operation='save', path=path)
@@ -0,0 +1,124 @@
+'''
+Files left out of archives - `.beadignore` in the workspace directory.
+
+The syntax is that of .gitignore: one pattern per line, `#` comments,
+`!` re-includes, a trailing `/` matches only directories, a `/` elsewhere
+anchors the pattern to the workspace directory, `**` matches any number of directories.
+
+Patterns are applied after the defaults, so they can also re-include
+e.g. `.git/` with `!.git/`.
+'''
+
+import os
+import re
+from typing import Iterable, List
+
+import attr
+
+IGNORE_FILE = '.beadignore'
+
+DEFAULT_IGNORES = (
+    '/temp/',
+    '.git/',
+    '.hg/',
+    '.svn/',
+    '__pycache__/',
+    '*.py[cod]',
+    '.ipynb_checkpoints/',
+    '.venv/',
+    '.DS_Store',
+    '*~',
+    '.*.swp',
+)
+
+
+@attr.s(frozen=True, auto_attribs=True)
+class Rule:
+    regex: re.Pattern
+    negated: bool
+    directory_only: bool
+
+    def matches(self, path: str, is_dir: bool) -> bool:
+        if self.directory_only and not is_dir:
+            return False
+        return self.regex.match(path) is not None
+
+
+def _translate(glob: str) -> str:
+    i, n = 0, len(glob)
+    regex = []
+    while i < n:
+        if glob.startswith('**/', i):
+            regex.append('(?:.*/)?')
+            i += 3
+        elif glob.startswith('**', i):
+            regex.append('.*')
+            i += 2
+        elif glob[i] == '*':
+            regex.append('[^/]*')
+            i += 1
+        elif glob[i] == '?':
+            regex.append('[^/]')
+            i += 1
+        elif glob[i] == '[' and ']' in glob[i + 2:]:
+            end = glob.index(']', i + 2)
+            chars = glob[i + 1:end]
+            if chars.startswith('!'):
+                chars = '^' + chars[1:]
+            regex.append(f'[{chars}]')
+            i = end + 1
+        else:
+            regex.append(re.escape(glob[i]))
+            i += 1
+    return ''.join(regex)
+
+
+def parse_rule(line: str):
+    '''
+    Rule for a line of an ignore file, None for blank lines and comments.
+    '''
+    pattern = line.rstrip()
+    if not pattern or pattern.startswith('#'):
+        return None
+    negated = pattern.startswith('!')
+    if negated:
+        pattern = pattern[1:]
+    directory_only = pattern.endswith('/')
+    pattern = pattern.rstrip('/')
+    if not pattern:
+        return None
+    anchored = '/' in pattern
+    pattern = pattern.lstrip('/')
+    prefix = '^' if anchored else '^(?:.*/)?'
+    return Rule(re.compile(prefix + _translate(pattern) + '$'), negated, directory_only)
+
+
+class IgnoreRules:
+    '''
+    Decide on paths relative to the workspace directory (with / separators).
+    '''
+
+    def __init__(self, patterns: Iterable[str]):
+        self.rules: List[Rule] = [
+            rule for rule in (parse_rule(pattern) for pattern in patterns) if rule]
+
+    def is_ignored(self, path: str, is_dir: bool) -> bool:
+        # the last matching rule decides
+        ignored = False
+        for rule in self.rules:
+            if rule.matches(path, is_dir):
+                ignored = not rule.negated
+        return ignored
+
+
+def load(directory) -> IgnoreRules:
+    '''
+    Defaults and the patterns in the ignore file of the workspace directory.
+    '''
+    patterns = list(DEFAULT_IGNORES)
+    try:
+        with open(os.path.join(directory, IGNORE_FILE), encoding='utf-8') as f:
+            patterns.extend(f.read().splitlines())
+    except FileNotFoundError:
+        pass
+    return IgnoreRules(patterns)
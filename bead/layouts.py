@@ -29,3 +29,13 @@ class Workspace:
 
     BEAD_META = META / 'bead'
     INPUT_MAP = META / 'input.map'
+    # held while modifying the metadata
+    LOCK = META / 'lock'
+    # executables run around saving, see the hooks module
+    HOOKS = META / 'hooks'
+    # patterns of the inputs loaded only partially, input name -> patterns
+    PARTIAL_INPUTS = META / 'partial-inputs.json'
+    # hashes of files by size and modification time, see the hash_cache module
+    HASH_CACHE = META / 'hash-cache.json'
+    # in loaded input directories, telling which bead version is there
+    INPUT_MARKER = '.bead-input.json'
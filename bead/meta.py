@@ -22,10 +22,20 @@ with the following minimum structure:
     kind: ...,
     freeze_time: ...,  # only archives - naive ordering
     freeze_name: ...,  # only archives, bead name for bootstrapping
+    provenance: {  # only archives saved by pipeline runners
+        runner: ...,
+        plan_hash: ...,
+        upstream: {'nick1': content_id, ...},
+    },
+    description: ...,  # optional
+    tags: [...],  # optional
+    author: ...,  # optional
 }
 '''
 
-from .tech.timestamp import time_from_timestamp
+from typing import Dict, Optional, Tuple
+
+from .tech.timestamp import time_from_legacy_timestamp
 import attr
 
 # Metadata versions determine the content_id used and potentially
@@ -81,7 +91,7 @@ class InputSpec:
 
     @property
     def freeze_time(self):
-        return time_from_timestamp(self.freeze_time_str)
+        return time_from_legacy_timestamp(self.freeze_time_str)
 
 
 def parse_inputs(meta):
@@ -101,3 +111,72 @@ def parse_inputs(meta):
 # Archive meta:
 FREEZE_TIME = 'freeze_time'
 FREEZE_NAME = 'freeze_name'
+
+# Optional, set in the workspace and saved with it - absent, when not set:
+DESCRIPTION = 'description'
+TAGS = 'tags'
+AUTHOR = 'author'
+ANNOTATION_KEYS = (DESCRIPTION, TAGS, AUTHOR)
+
+
+def is_valid_tag(tag: str) -> bool:
+    return bool(tag) and not any(c.isspace() for c in tag)
+
+
+def parse_tags(bead_meta) -> Tuple[str, ...]:
+    return tuple(bead_meta.get(TAGS, ()))
+
+
+# Archive meta of automated freezes - manually saved beads have no provenance:
+PROVENANCE = 'provenance'
+PROVENANCE_RUNNER = 'runner'
+PROVENANCE_PLAN_HASH = 'plan_hash'
+PROVENANCE_UPSTREAM = 'upstream'
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class Provenance:
+    '''
+    Who saved the bead and from what - for beads saved by pipeline runners.
+    '''
+    # identity of the runner, e.g. `ci-nightly@build-host`
+    runner: str
+    # hash of the pipeline plan, that was executed
+    plan_hash: str
+    # content ids of the input beads the plan was executed with, by input name
+    upstream: Dict[str, str] = attr.ib(factory=dict)
+
+    def as_meta(self):
+        return {
+            PROVENANCE_RUNNER: self.runner,
+            PROVENANCE_PLAN_HASH: self.plan_hash,
+            PROVENANCE_UPSTREAM: dict(self.upstream)}
+
+
+def parse_provenance(bead_meta) -> Optional[Provenance]:
+    '''
+    Provenance of automated freezes, None for manually saved beads.
+    '''
+    provenance = bead_meta.get(PROVENANCE)
+    if provenance is None:
+        return None
+    return Provenance(
+        provenance[PROVENANCE_RUNNER],
+        provenance[PROVENANCE_PLAN_HASH],
+        dict(provenance.get(PROVENANCE_UPSTREAM, {})))
+
+
+# Workspace meta:
+# keep .bead-meta files friendly for version control systems (clean diffs)
+VCS_FRIENDLY = 'vcs_friendly'
+# write a marker (layouts.Workspace.INPUT_MARKER) into loaded input directories
+INPUT_MARKERS = 'input_markers'
+# content id of the archive the workspace was developed from
+ORIGIN = 'origin'
+
+# Input marker:
+MARKER_BEAD_NAME = 'bead_name'
+MARKER_KIND = 'kind'
+MARKER_CONTENT_ID = 'content_id'
+MARKER_FREEZE_TIME = 'freeze_time'
+MARKER_LOADED_AT = 'loaded_at'
@@ -0,0 +1,41 @@
+'''
+Jupyter notebooks in code - outputs of cells can be left out of archives.
+
+Notebook outputs are results (plots, tables, logs), which bloat code/
+and make diffs of code between versions meaningless.
+Results belong to output/ anyway.
+'''
+
+import json
+
+NOTEBOOK_SUFFIX = '.ipynb'
+
+# what to do with the outputs of notebooks in code
+KEEP = 'keep'
+STRIP = 'strip'
+NOTEBOOK_OUTPUT_MODES = (KEEP, STRIP)
+
+
+def is_notebook(path: str) -> bool:
+    return path.endswith(NOTEBOOK_SUFFIX)
+
+
+def strip_outputs(content: bytes) -> bytes:
+    '''
+    Notebook content without cell outputs and execution counts.
+
+    Content that is not a notebook is returned unchanged.
+    '''
+    try:
+        notebook = json.loads(content.decode('utf-8'))
+    except (UnicodeDecodeError, ValueError):
+        return content
+    if not isinstance(notebook, dict) or not isinstance(notebook.get('cells'), list):
+        return content
+    for cell in notebook['cells']:
+        if isinstance(cell, dict) and cell.get('cell_type') == 'code':
+            cell['outputs'] = []
+            cell['execution_count'] = None
+    # formatted as Jupyter writes notebooks
+    formatted = json.dumps(notebook, indent=1, sort_keys=True, ensure_ascii=False)
+    return (formatted + '\n').encode('utf-8')
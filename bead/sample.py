@@ -0,0 +1,120 @@
+'''
+Deterministic samples of bead output data - for a quick look at big beads.
+
+Tabular files (CSV, TSV, JSON lines) are sampled by rows, keeping their header
+and the original order of rows. The random choices are seeded by the content id
+of the bead, so every reviewer of a bead gets the same sample.
+
+Other files are not sampled - they are listed as skipped.
+Sampled data is not verified against the manifest, as only part of it is read.
+'''
+
+import csv
+import io
+import os
+import random
+from typing import List, Optional, Tuple
+
+import attr
+
+from . import layouts
+from .tech import fs
+from .tech.cancellation import NEVER_CANCELLED
+
+DELIMITED = {'.csv': ',', '.tsv': '\t'}
+LINE_BASED = ('.jsonl', '.ndjson')
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class SampleResult:
+    # data paths (relative to output/) with the number of sampled rows
+    sampled: List[Tuple[str, int]]
+    skipped: List[str]
+
+
+def is_tabular(path: str) -> bool:
+    extension = os.path.splitext(path)[1].lower()
+    return extension in DELIMITED or extension in LINE_BASED
+
+
+def choose_rows(rows, n: int, rng: random.Random) -> list:
+    '''
+    n rows chosen uniformly (reservoir sampling), in their original order.
+    '''
+    reservoir = []
+    for index, row in enumerate(rows):
+        if index < n:
+            reservoir.append((index, row))
+        else:
+            slot = rng.randrange(index + 1)
+            if slot < n:
+                reservoir[slot] = (index, row)
+    return [row for _, row in sorted(reservoir, key=lambda item: item[0])]
+
+
+def _sample_delimited(source, target, delimiter, n, rng, cancel):
+    reader = csv.reader(source, delimiter=delimiter)
+    writer = csv.writer(target, delimiter=delimiter, lineterminator='\n')
+    header = next(reader, None)
+    if header is None:
+        return 0
+    writer.writerow(header)
+
+    def rows():
+        for row in reader:
+            cancel.check()
+            yield row
+    sample = choose_rows(rows(), n, rng)
+    writer.writerows(sample)
+    return len(sample)
+
+
+def _sample_lines(source, target, n, rng, cancel):
+    def lines():
+        for line in source:
+            cancel.check()
+            if line.strip():
+                yield line if line.endswith('\n') else line + '\n'
+    sample = choose_rows(lines(), n, rng)
+    target.writelines(sample)
+    return len(sample)
+
+
+def sample(
+        archive, n: int, out_dir, max_files: Optional[int] = None,
+        cancel=NEVER_CANCELLED) -> SampleResult:
+    '''
+    Write at most n rows of every tabular output file of archive under out_dir.
+
+    With max_files, only a (deterministically chosen) subset of the tabular files is sampled.
+    '''
+    prefix = f'{layouts.Archive.DATA}/'
+    zipfile = archive.ziparchive.zipfile
+    data_paths = sorted(
+        name[len(prefix):] for name in zipfile.namelist()
+        if name.startswith(prefix) and not name.endswith('/'))
+    tabular = [path for path in data_paths if is_tabular(path)]
+    skipped = [path for path in data_paths if not is_tabular(path)]
+    if max_files is not None and len(tabular) > max_files:
+        chosen = set(random.Random(archive.content_id).sample(tabular, max_files))
+        skipped = sorted(skipped + [path for path in tabular if path not in chosen])
+        tabular = [path for path in tabular if path in chosen]
+
+    out_dir = fs.Path(out_dir)
+    sampled = []
+    for path in tabular:
+        # every file has its own seed: the sample of a file does not depend on other files
+        rng = random.Random(f'{archive.content_id}:{path}')
+        target_path = out_dir / path
+        fs.ensure_directory(target_path.parent)
+        with fs.removed_on_error(target_path):
+            with zipfile.open(prefix + path) as raw:
+                source = io.TextIOWrapper(raw, encoding='utf-8', newline='')
+                with open(target_path, 'w', encoding='utf-8', newline='') as target:
+                    delimiter = DELIMITED.get(os.path.splitext(path)[1].lower())
+                    if delimiter is not None:
+                        row_count = _sample_delimited(source, target, delimiter, n, rng, cancel)
+                    else:
+                        row_count = _sample_lines(source, target, n, rng, cancel)
+        sampled.append((path, row_count))
+    return SampleResult(sampled, skipped)
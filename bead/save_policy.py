@@ -0,0 +1,207 @@
+'''
+Save policies - lab data standards checked before a workspace is saved.
+
+Policies are configured as a mapping from policy name to its parameter:
+
+    non-empty-output: true          # output/ must have at least one file
+    max-file-size: "10GB"           # no saved file may be bigger (bytes, or with a unit)
+    required-files: ["README.md"]   # code files, that must exist
+    inputs-resolve: true            # all inputs must be available in a box
+
+A policy with a false or null parameter is not checked.
+
+The other checks before saving are here too: beads of another kind under the same name
+and a clock running behind the freeze times in the box.
+'''
+
+import os
+import re
+import warnings
+from typing import Dict, Iterator, List, Optional
+
+import attr
+
+from .exceptions import UserError
+from . import ignore
+from . import layouts
+from . import spec as bead_spec
+from .tech.timestamp import parse_iso8601, time_from_timestamp
+
+NON_EMPTY_OUTPUT = 'non-empty-output'
+MAX_FILE_SIZE = 'max-file-size'
+REQUIRED_FILES = 'required-files'
+INPUTS_RESOLVE = 'inputs-resolve'
+
+SIZE_UNITS = {
+    '': 1, 'B': 1,
+    'KB': 1000, 'MB': 1000 ** 2, 'GB': 1000 ** 3, 'TB': 1000 ** 4,
+    'KIB': 1024, 'MIB': 1024 ** 2, 'GIB': 1024 ** 3, 'TIB': 1024 ** 4,
+}
+_SIZE_RE = re.compile(r'^\s*(\d+(?:\.\d+)?)\s*([a-zA-Z]*)\s*$')
+
+_LATEST = parse_iso8601('9999-12-31')
+
+# directories in the workspace, that are not saved
+_NOT_SAVED = {
+    layouts.Workspace.INPUT.as_posix(),
+    layouts.Workspace.META.as_posix(),
+    layouts.Workspace.TEMP.as_posix()}
+
+
+# what to do, when saving a bead under a name already used by another kind
+NAME_COLLISION_WARN = 'warn'
+NAME_COLLISION_REFUSE = 'refuse'
+NAME_COLLISION_POLICIES = (NAME_COLLISION_WARN, NAME_COLLISION_REFUSE)
+
+
+class PolicyError(UserError):
+    """Invalid save policy configuration"""
+
+
+class SaveRefused(UserError):
+    """The workspace is not saved - its name collides or it violates the save policies"""
+
+
+class SaveWarning(UserWarning):
+    """The workspace is saved despite a name collision, policy violations or clock skew"""
+
+
+@attr.s(frozen=True, auto_attribs=True)
+class Violation:
+    policy: str
+    message: str
+
+    def __str__(self):
+        return f'{self.policy}: {self.message}'
+
+
+def parse_size(size) -> int:
+    '''
+    Size in bytes from e.g. 1000, "10GB" or "1.5 GiB".
+    '''
+    if isinstance(size, int) and not isinstance(size, bool):
+        return size
+    match = _SIZE_RE.match(str(size))
+    if match is None or match.group(2).upper() not in SIZE_UNITS:
+        raise PolicyError(f'Invalid size "{size}"', operation='check save policies')
+    number, unit = match.groups()
+    return int(float(number) * SIZE_UNITS[unit.upper()])
+
+
+def _saved_files(workspace) -> Iterator[str]:
+    '''
+    Paths relative to the workspace directory of files, that would be saved.
+    '''
+    ignore_rules = ignore.load(workspace.directory)
+    for dirpath, dirnames, filenames in os.walk(workspace.directory):
+        directory = os.path.relpath(dirpath, workspace.directory).replace(os.sep, '/')
+        prefix = '' if directory == '.' else f'{directory}/'
+        if not prefix:
+            dirnames[:] = [d for d in dirnames if d not in _NOT_SAVED]
+        dirnames[:] = [d for d in dirnames if not ignore_rules.is_ignored(prefix + d, True)]
+        for filename in filenames:
+            if not ignore_rules.is_ignored(prefix + filename, False):
+                yield prefix + filename
+
+
+def _check_non_empty_output(workspace, _param, _boxes):
+    output = workspace.directory / layouts.Workspace.OUTPUT
+    for _dirpath, _dirnames, filenames in os.walk(output):
+        if filenames:
+            return
+    yield 'output is empty'
+
+
+def _check_max_file_size(workspace, param, _boxes):
+    max_size = parse_size(param)
+    for path in _saved_files(workspace):
+        size = os.path.getsize(workspace.directory / path)
+        if size > max_size:
+            yield f'{path} is {size} bytes, more than {max_size}'
+
+
+def _check_required_files(workspace, param, _boxes):
+    required_files = [param] if isinstance(param, str) else param
+    for path in required_files:
+        if not (workspace.directory / path).is_file():
+            yield f'{path} is missing'
+
+
+def _check_inputs_resolve(workspace, _param, boxes):
+    for input in workspace.inputs:
+        bead_name = workspace.get_input_bead_name(input.name)
+        if boxes is None or boxes.find_bead(bead_name, input.content_id) is None:
+            yield f'input {input.name} ({bead_name}@{input.freeze_time_str}) is not in any box'
+
+
+_CHECKS = {
+    NON_EMPTY_OUTPUT: _check_non_empty_output,
+    MAX_FILE_SIZE: _check_max_file_size,
+    REQUIRED_FILES: _check_required_files,
+    INPUTS_RESOLVE: _check_inputs_resolve,
+}
+POLICIES = tuple(_CHECKS)
+
+
+def check(workspace, policies: Dict, boxes=None) -> List[Violation]:
+    '''
+    Violations of the configured policies by workspace, an empty list if it can be saved.
+
+    boxes (e.g. a UnionBox) are searched for inputs.
+    '''
+    unknown = sorted(set(policies) - set(_CHECKS))
+    if unknown:
+        raise PolicyError(
+            f'Unknown save policies: {", ".join(unknown)}', operation='check save policies')
+    return [
+        Violation(policy, message)
+        for policy, param in policies.items()
+        if param is not None and param is not False
+        for message in _CHECKS[policy](workspace, param, boxes)]
+
+
+def enforce(workspace, policies: Dict, boxes=None, override=False):
+    '''
+    Raise SaveRefused, if workspace violates policies - with override only warn (SaveWarning).
+    '''
+    violations = check(workspace, policies, boxes)
+    if not violations:
+        return
+    msg = 'Save policy violations:\n' + '\n'.join(f'  {v}' for v in violations)
+    if not override:
+        raise SaveRefused(f'{msg}\nRefusing to save, fix them or override them.')
+    warnings.warn(msg, SaveWarning)
+
+
+def check_name_collision(box, workspace, policy=NAME_COLLISION_WARN):
+    '''
+    Guard against silently mixing different computations under the same name.
+
+    Raises SaveRefused with NAME_COLLISION_REFUSE, warns (SaveWarning) otherwise.
+    '''
+    if not box.find_name_collisions(workspace.name, workspace.kind):
+        return
+    msg = (
+        f'Box "{box.name}" already has beads named "{workspace.name}"'
+        + ' of a different kind - name based input updates might pick up the wrong bead')
+    if policy == NAME_COLLISION_REFUSE:
+        raise SaveRefused(f'{msg}\nRefusing to save, rename the workspace or save to another box.')
+    warnings.warn(msg, SaveWarning)
+
+
+def clock_skew(box, workspace, freeze_time) -> Optional[str]:
+    '''
+    Freeze time of the newest version of workspace in box, if it is not earlier than
+    freeze_time - the clock is behind -, None otherwise.
+
+    A clock running behind would make the new bead look older than existing ones,
+    timestamp_after(the returned freeze time) makes it the newest.
+    '''
+    try:
+        context = box.get_context(bead_spec.BEAD_NAME, workspace.name, _LATEST)
+    except LookupError:
+        return None
+    newest = context.best
+    if newest.freeze_time < time_from_timestamp(freeze_time):
+        return None
+    return newest.freeze_time_str
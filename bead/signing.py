@@ -0,0 +1,77 @@
+'''
+Detached signatures of archives, made and checked by OpenSSH (`ssh-keygen -Y`).
+
+Any key type supported by ssh-keygen can be used, ed25519 keys are recommended.
+The signature is stored next to the archive (ARCHIVE.sig, like the .xmeta cache),
+so the archive - and its content id - is not changed by signing.
+'''
+
+import os
+import subprocess
+import tempfile
+
+from .exceptions import SignatureError
+from .tech.fs import Path
+
+SIGNATURE_SUFFIX = '.sig'
+# signatures made for other purposes with the same key are not accepted
+NAMESPACE = 'bead-archive'
+SSH_KEYGEN = 'ssh-keygen'
+_PRINCIPAL = 'bead'
+
+
+def signature_path(archive_path) -> Path:
+    return Path(archive_path).with_suffix(SIGNATURE_SUFFIX)
+
+
+def _ssh_keygen(args, **kwargs):
+    try:
+        return subprocess.run(
+            [SSH_KEYGEN, '-Y', *args], capture_output=True, text=True, **kwargs)
+    except FileNotFoundError:
+        raise SignatureError(f'{SSH_KEYGEN} (OpenSSH) is needed for signatures')
+
+
+def sign(archive_path, private_key) -> Path:
+    '''
+    Sign the archive with private_key (a key file for ssh-keygen), return the signature path.
+    '''
+    archive_path = Path(archive_path)
+    # ssh-keygen writes ARCHIVE.zip.sig
+    ssh_signature = archive_path.with_name(archive_path.name + SIGNATURE_SUFFIX)
+    result = _ssh_keygen(['sign', '-f', os.fspath(private_key), '-n', NAMESPACE, archive_path])
+    if result.returncode != 0:
+        raise SignatureError(
+            f'Could not sign: {result.stderr.strip()}', operation='sign', path=archive_path)
+    path = signature_path(archive_path)
+    os.replace(ssh_signature, path)
+    return path
+
+
+def verify(archive_path, public_key) -> None:
+    '''
+    Check that archive is signed by the owner of public_key (an ssh public key file).
+
+    Raises SignatureError if it is not.
+    '''
+    archive_path = Path(archive_path)
+    path = signature_path(archive_path)
+    if not path.exists():
+        raise SignatureError(
+            f'Not signed ({path.name} is missing)', operation='verify', path=archive_path)
+    try:
+        key = Path(public_key).read_text().strip()
+    except OSError as e:
+        raise SignatureError(
+            f'Can not read public key: {e}', operation='verify', path=archive_path)
+    with tempfile.TemporaryDirectory() as directory:
+        allowed_signers = Path(directory) / 'allowed_signers'
+        allowed_signers.write_text(f'{_PRINCIPAL} {key}\n')
+        with open(archive_path, 'rb') as archive:
+            result = _ssh_keygen(
+                ['verify', '-f', allowed_signers, '-I', _PRINCIPAL, '-n', NAMESPACE, '-s', path],
+                stdin=archive)
+    if result.returncode != 0:
+        reason = result.stderr.strip() or result.stdout.strip()
+        raise SignatureError(
+            f'Invalid signature: {reason}', operation='verify', path=archive_path)
@@ -2,3 +2,5 @@
 BEAD_NAME  = 'BEAD_NAME'
 KIND       = 'KIND'
 CONTENT_ID = 'CONTENT_ID'
+# a re.Pattern matching the whole bead name or kind, see box.compile_pattern
+NAME_OR_KIND_PATTERN = 'NAME_OR_KIND_PATTERN'
@@ -4,8 +4,11 @@
 Technologies
 '''
 
+from . import cancellation
 from . import identifier
+from . import lock
 from . import fs
 from . import persistence
 from . import securehash
 from . import timestamp
+from . import transfer
@@ -0,0 +1,76 @@
+'''
+Cooperative cancellation (and pausing) of long running operations.
+
+The controlling side calls cancel(), pause() or resume() on a token,
+the operation calls check() between units of work (e.g. blocks copied).
+'''
+
+import threading
+from typing import Optional
+
+
+class Cancelled(Exception):
+    '''
+    The operation was cancelled through its CancellationToken.
+    '''
+
+
+class CancellationToken:
+
+    def __init__(self, parent: Optional['CancellationToken'] = None):
+        # cancelling or pausing the parent also affects this token
+        self.parent = parent
+        self._cancelled = threading.Event()
+        self._running = threading.Event()
+        self._running.set()
+
+    @property
+    def is_cancelled(self):
+        if self.parent is not None and self.parent.is_cancelled:
+            return True
+        return self._cancelled.is_set()
+
+    def child(self):
+        '''
+        Token for a part of the operation, that can be cancelled separately.
+        '''
+        return CancellationToken(self)
+
+    def cancel(self):
+        self._cancelled.set()
+        # paused operations must wake up to notice
+        self._running.set()
+
+    def pause(self):
+        self._running.clear()
+
+    def resume(self):
+        self._running.set()
+
+    def check(self):
+        '''
+        Block while paused, raise Cancelled if cancelled.
+        '''
+        if self.parent is not None:
+            self.parent.check()
+        self._running.wait()
+        if self._cancelled.is_set():
+            raise Cancelled
+
+
+class _NeverCancelled(CancellationToken):
+    '''
+    Null token - it is shared by all operations, so it can not be cancelled or paused.
+
+    Its children can.
+    '''
+
+    def cancel(self):
+        raise TypeError('NEVER_CANCELLED can not be cancelled, cancel a child() of it')
+
+    def pause(self):
+        raise TypeError('NEVER_CANCELLED can not be paused, pause a child() of it')
+
+
+# for operations without a controlling side
+NEVER_CANCELLED = _NeverCancelled()
@@ -0,0 +1,65 @@
+'''
+Checkpoints of long operations - to continue an interrupted one, where it stopped.
+'''
+
+import os
+
+from . import fs
+
+
+class Checkpoint:
+    '''
+    Persistent set of items already done.
+
+    Items are appended to the file one per line as they are done,
+    so an interruption loses at most the item in progress.
+    The first line is the key of the operation, a checkpoint with another key is ignored.
+    '''
+
+    def __init__(self, path, key: str):
+        self.path = fs.Path(path)
+        self.key = key
+        self.done = set()
+        try:
+            with open(self.path, encoding='utf-8') as f:
+                lines = f.read().splitlines()
+        except FileNotFoundError:
+            lines = []
+        if lines and lines[0] == key:
+            self.done = set(lines[1:])
+        self._file = None
+
+    def __contains__(self, item):
+        return item in self.done
+
+    def __len__(self):
+        return len(self.done)
+
+    def add(self, item: str):
+        assert '\n' not in item
+        if self._file is None:
+            fs.ensure_directory(self.path.parent)
+            if self.done:
+                self._file = open(self.path, 'a', encoding='utf-8')
+            else:
+                self._file = open(self.path, 'w', encoding='utf-8')
+                self._file.write(f'{self.key}\n')
+        self._file.write(f'{item}\n')
+        self._file.flush()
+        self.done.add(item)
+
+    def close(self):
+        if self._file is not None:
+            self._file.close()
+            self._file = None
+
+    def remove(self):
+        '''
+        Forget the checkpoint - the operation is complete or is to be started over.
+        '''
+        self.close()
+        self.done = set()
+        try:
+            os.remove(self.path)
+        except FileNotFoundError:
+            pass
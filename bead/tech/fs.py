@@ -3,17 +3,55 @@ import os
 import stat
 import contextlib
 import shutil
+import subprocess
 import tempfile
+import unicodedata
 from pathlib import Path
+from typing import Optional
 
 
 def ensure_directory(path: Path):
-    if not os.path.exists(path):
-        os.makedirs(path)
+    # exist_ok: other threads might create it concurrently
+    os.makedirs(path, exist_ok=True)
 
     assert os.path.isdir(path)
 
 
+def portable_name(name: str) -> str:
+    '''
+    Form of a file name, that is the same on all platforms.
+
+    Names are normalized to NFC, as e.g. macOS might report names decomposed (NFD).
+    Names, that are not valid unicode (on POSIX: not valid UTF-8 bytes, which
+    Python represents with surrogate escapes) can not be stored portably,
+    for them ValueError is raised, showing the raw bytes.
+    '''
+    try:
+        name.encode('utf-8')
+    except UnicodeEncodeError:
+        raise ValueError(f'File name is not valid unicode: {os.fsencode(name)!r}')
+    return unicodedata.normalize('NFC', name)
+
+
+def is_case_sensitive(directory: Path) -> Optional[bool]:
+    '''
+    Does the file system of directory tell apart names differing only in case?
+
+    It is found out by creating a temporary file - None, if that is not possible
+    (e.g. in a read-only directory).
+    '''
+    try:
+        fd, probe = tempfile.mkstemp(prefix='.bead-case-probe-', suffix='-aB', dir=directory)
+    except OSError:
+        return None
+    os.close(fd)
+    try:
+        probe_dir, probe_name = os.path.split(probe)
+        return not os.path.exists(os.path.join(probe_dir, probe_name.swapcase()))
+    finally:
+        os.remove(probe)
+
+
 def write_file(path: Path, content: bytes | str):
     if isinstance(content, bytes):
         f = open(path, 'wb')
@@ -42,22 +80,119 @@ def temp_dir(dir=None):
     try:
         yield Path(temp_dir)
     finally:
-        shutil.rmtree(temp_dir, ignore_errors=True)
+        rmtree(Path(temp_dir), ignore_errors=True)
+
+
+def remove(path: Path):
+    '''
+    Remove file or directory tree - if it exists.
+    '''
+    path = Path(path)
+    if path.is_dir() and not path.is_symlink():
+        rmtree(path)
+    elif path.exists() or path.is_symlink():
+        path.unlink()
+
+
+@contextlib.contextmanager
+def removed_on_error(path: Path):
+    '''
+    Guard for an artifact (partial file, staging directory) created in the with block.
 
+    On any exit by exception - including KeyboardInterrupt and SystemExit -
+    whatever exists at path is removed, so no half made artifacts are left behind.
+    '''
+    try:
+        yield path
+    except BaseException:
+        remove(path)
+        raise
+
+
+class PermissionsNotSupported(UserWarning):
+    '''
+    File permissions could not be changed - e.g. on exFAT or NFS with squashed permissions.
+    '''
+
+
+def _change_mode(path: Path, change) -> bool:
+    try:
+        mode = os.stat(path)[stat.ST_MODE]
+        os.chmod(path, change(mode))
+        return os.stat(path)[stat.ST_MODE] == change(mode)
+    except OSError:
+        # e.g. EPERM/ENOTSUP from file systems without permissions
+        if not os.path.exists(path):
+            raise
+        return False
+
+
+# Windows ignores the read-only attribute of folders,
+# creating files in them is denied with an access control entry instead
+IS_WINDOWS = os.name == 'nt'
+EVERYONE_SID = '*S-1-1-0'
+# write data/add file, append data/add subdirectory, write (extended) attributes
+DENY_WRITE_PERMISSIONS = '(WD,AD,WEA,WA)'
+
+
+def _change_windows_folder_protection(path: Path, readonly: bool) -> bool:
+    if readonly:
+        change = ['/deny', f'{EVERYONE_SID}:{DENY_WRITE_PERMISSIONS}']
+    else:
+        change = ['/remove:d', EVERYONE_SID]
+    try:
+        result = subprocess.run(
+            ['icacls', os.fspath(path), *change],
+            stdout=subprocess.DEVNULL, stderr=subprocess.DEVNULL)
+    except OSError:
+        return False
+    return result.returncode == 0
 
-def make_readonly(path: Path):
+
+def make_readonly(path: Path) -> bool:
+    '''
+    Best effort: returns False, if the file system did not allow it.
     '''
-    WARNING: It does not work for Windows folders.
+    if IS_WINDOWS and os.path.isdir(path):
+        return _change_windows_folder_protection(path, readonly=True)
+    return _change_mode(path, lambda mode: mode & ~stat.S_IWRITE)
+
 
-    Might fail (silently) on other systems as well.
+def make_writable(path: Path) -> bool:
     '''
-    mode = os.stat(path)[stat.ST_MODE]
-    os.chmod(path, mode & ~stat.S_IWRITE)
+    Best effort: returns False, if the file system did not allow it.
+    '''
+    if IS_WINDOWS and os.path.isdir(path):
+        return _change_windows_folder_protection(path, readonly=False)
+    return _change_mode(path, lambda mode: mode | stat.S_IWRITE)
 
 
-def make_writable(path: Path):
-    mode = os.stat(path)[stat.ST_MODE]
-    os.chmod(path, mode | stat.S_IWRITE)
+def directory_mode(file_mode: int, setgid: bool = False) -> int:
+    '''
+    Mode for directories holding files with file_mode: searchable, where readable.
+    '''
+    mode = file_mode | ((file_mode & 0o444) >> 2)
+    if setgid:
+        mode |= stat.S_ISGID
+    return mode
+
+
+def set_group_and_mode(path: Path, group=None, mode=None) -> bool:
+    '''
+    Best effort: returns False, if the group is unknown or the file system did not allow it.
+    '''
+    try:
+        if group is not None:
+            shutil.chown(path, group=group)
+        if mode is not None:
+            os.chmod(path, mode)
+    except LookupError:
+        return False
+    except OSError:
+        if not os.path.exists(path):
+            raise
+        return False
+    return True
 
 
 def all_subpaths(dir: Path, followlinks=False):
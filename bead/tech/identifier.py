@@ -26,3 +26,26 @@ def uuid():
     # + add a random uuid to remain collision free
     scrambled_uuid1 = hashlib.sha256(uuid1().bytes).hexdigest()[:32]
     return str(scrambled_uuid1) + '-' + str(uuid4())
+
+
+# content ids are long hashes, it is enough to display a prefix of them,
+# that is still unique among the ids known (like git does for commit ids)
+MIN_PREFIX_LENGTH = 8
+
+
+def unique_prefix_length(ids, min_length=MIN_PREFIX_LENGTH):
+    '''
+    Return the shortest prefix length, that still distinguishes all different ids.
+
+    The result is at least min_length, unless all ids are shorter.
+    '''
+    ids = sorted(set(ids))
+    length = min_length
+    for id1, id2 in zip(ids, ids[1:]):
+        common = 0
+        for c1, c2 in zip(id1, id2):
+            if c1 != c2:
+                break
+            common += 1
+        length = max(length, common + 1)
+    return min(length, max((len(id) for id in ids), default=length))
@@ -0,0 +1,178 @@
+'''
+Lock files - mutual exclusion of processes (and users) sharing a directory.
+
+A lock is a file created exclusively, which works also on network file systems,
+where fcntl/flock locks are unreliable or missing.
+The lock file tells who holds the lock (host, process id, time), to help investigating
+stuck locks. Locks left behind by crashed processes on this host are removed automatically
+(with a warning) - the owners of locks from other hosts can not be checked.
+'''
+
+import os
+import socket
+import sys
+import time
+import uuid
+import warnings
+from typing import Optional, Tuple
+
+from . import fs
+from .timestamp import timestamp
+
+# seconds to wait for a lock held by someone else
+DEFAULT_TIMEOUT = 60
+POLL_INTERVAL = 0.02
+
+
+class LockTimeout(Exception):
+    '''
+    The lock was held by someone else for too long.
+    '''
+
+
+class StaleLockRemoved(UserWarning):
+    '''
+    A lock left behind by a crashed process was removed.
+    '''
+
+
+def is_process_running(pid: int) -> bool:
+    '''
+    Is there a process with pid on this host? - True, when it can not be told.
+    '''
+    if sys.platform == 'win32':
+        # os.kill would terminate the process
+        return True
+    try:
+        os.kill(pid, 0)
+    except ProcessLookupError:
+        return False
+    except (PermissionError, OverflowError):
+        return True
+    return True
+
+
+def parse_owner(content: str) -> Optional[Tuple[str, int]]:
+    '''
+    (host, pid) from the content of a lock file, None if it is not known.
+    '''
+    try:
+        host, pid = content.split()[:2]
+        return host, int(pid)
+    except ValueError:
+        return None
+
+
+class FileLock:
+    '''
+    Context manager holding the lock file at path - not reentrant.
+    '''
+
+    def __init__(self, path, timeout=DEFAULT_TIMEOUT, poll_interval=POLL_INTERVAL):
+        self.path = fs.Path(path)
+        self.timeout = timeout
+        self.poll_interval = poll_interval
+        # identifies the lock file written by this instance
+        self._token: Optional[str] = None
+
+    def acquire(self):
+        deadline = time.monotonic() + self.timeout
+        while True:
+            try:
+                fd = os.open(self.path, os.O_CREAT | os.O_EXCL | os.O_WRONLY, 0o666)
+            except FileExistsError:
+                holder = self.holder()
+                if self.remove_if_stale():
+                    warnings.warn(
+                        f'Removed lock {self.path} left behind by {holder}', StaleLockRemoved)
+                    continue
+                if time.monotonic() >= deadline:
+                    raise LockTimeout(f'{self.path} is locked by {holder}')
+                time.sleep(self.poll_interval)
+                continue
+            self._token = uuid.uuid4().hex
+            with os.fdopen(fd, 'w') as f:
+                f.write(f'{socket.gethostname()} {os.getpid()} {timestamp()} {self._token}\n')
+            return
+
+    def release(self):
+        '''
+        Remove the lock file - only if it is still the one written by acquire.
+        '''
+        token, self._token = self._token, None
+        content = self._read()
+        if token is None or content is None or token not in content.split():
+            # lost the lock (e.g. removed by hand) - the lock file belongs to someone else
+            return
+        self.remove()
+
+    def remove(self):
+        '''
+        Remove the lock file, whoever holds it.
+        '''
+        try:
+            os.remove(self.path)
+        except FileNotFoundError:
+            pass
+
+    def _read(self) -> Optional[str]:
+        try:
+            return self.path.read_text()
+        except OSError:
+            return None
+
+    def holder(self) -> str:
+        '''
+        "host pid time" of the lock holder, as written in the lock file.
+        '''
+        return (self._read() or '').strip() or 'unknown'
+
+    def is_stale(self, content: Optional[str] = None) -> bool:
+        '''
+        Is the lock held by a process of this host, that is not running anymore?
+        '''
+        if content is None:
+            content = self._read()
+        owner = parse_owner(content or '')
+        if owner is None:
+            return False
+        host, pid = owner
+        return host == socket.gethostname() and not is_process_running(pid)
+
+    def remove_if_stale(self) -> bool:
+        '''
+        Remove the lock, if it is stale. Returns whether it was removed.
+
+        The lock is taken over atomically: it is renamed to a unique name and removed only,
+        if the renamed file is the stale lock - others finding the same stale lock
+        could have replaced it with a live one since it was read.
+        '''
+        content = self._read()
+        if content is None or not self.is_stale(content):
+            return False
+        taken_over = self.path.with_name(f'{self.path.name}.{uuid.uuid4().hex}.stale')
+        try:
+            os.rename(self.path, taken_over)
+        except FileNotFoundError:
+            # removed by another contender
+            return False
+        try:
+            is_same_lock = taken_over.read_text() == content
+        except OSError:
+            is_same_lock = False
+        if not is_same_lock:
+            # a live lock - put it back, unless the lock is taken again meanwhile
+            try:
+                os.link(taken_over, self.path)
+            except OSError:
+                # keep it, it is the only trace of the live lock
+                return False
+        os.remove(taken_over)
+        return is_same_lock
+
+    def __enter__(self):
+        self.acquire()
+        return self
+
+    def __exit__(self, *exc_info):
+        self.release()
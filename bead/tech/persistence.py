@@ -2,8 +2,14 @@
 Functions to persist python structures or load them.
 '''
 
+import contextlib
 import io
 import json
+import os
+import stat
+import tempfile
+
+from .fs import IS_WINDOWS, removed_on_error
 
 # json is used for serializing objects for persistence as it is
 # - in the standard library from >=2.6 (including 3.*)
@@ -54,6 +60,60 @@ def file_load(path):
         return load(f)
 
 
-def file_dump(content, path):
-    with open(path, 'w') as f:
-        dump(content, f)
+def file_dump(content, path, end=''):
+    '''
+    Write content to path atomically - followed by end (e.g. a newline).
+
+    The content is written to a uniquely named temporary file next to path, which then
+    replaces it, so after a crash path has either the old or the new content - never a
+    partial one - and concurrent writers do not clobber each other's temporary file.
+    '''
+    path = os.fspath(path)
+    directory = os.path.dirname(path) or os.curdir
+    fd, temp_path = tempfile.mkstemp(
+        dir=directory, prefix=os.path.basename(path) + '.', suffix='.tmp')
+    with removed_on_error(temp_path):
+        with open(fd, 'w') as f:
+            dump(content, f)
+            f.write(end)
+            f.flush()
+            os.fsync(f.fileno())
+        _copy_mode(path, temp_path)
+        os.replace(temp_path, path)
+    _fsync_directory(directory)
+
+
+def _read_umask():
+    umask = os.umask(0)
+    os.umask(umask)
+    return umask
+
+
+# read once: os.umask can only be queried by setting it, which is not thread safe
+_UMASK = _read_umask()
+
+
+def _copy_mode(path, temp_path):
+    # mkstemp creates the file readable by the owner only, keep the mode open() would give
+    try:
+        mode = stat.S_IMODE(os.stat(path).st_mode)
+    except FileNotFoundError:
+        mode = 0o666 & ~_UMASK
+    try:
+        os.chmod(temp_path, mode)
+    except PermissionError:
+        # file systems without permission support (e.g. some network shares)
+        pass
+
+
+def _fsync_directory(directory):
+    # make the rename itself durable - directories can not be opened for fsync on Windows
+    if IS_WINDOWS:
+        return
+    # some file systems (e.g. network shares) do not support it - the file is written anyway
+    with contextlib.suppress(OSError):
+        fd = os.open(directory, os.O_RDONLY)
+        try:
+            os.fsync(fd)
+        finally:
+            os.close(fd)
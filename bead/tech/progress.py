@@ -0,0 +1,34 @@
+'''
+Progress reporting of long running operations.
+
+The operation tells its observer the amount of work up front (bytes and files),
+then reports files as they are started and done and bytes as they are processed.
+Observers decide what to show, e.g. a progress bar on the terminal.
+'''
+
+
+class Progress:
+    '''
+    Observer of an operation - this base class ignores everything.
+
+    Operations using multiple threads call the methods concurrently.
+    '''
+
+    def start(self, total_bytes: int, total_files: int):
+        pass
+
+    def file_started(self, path: str):
+        pass
+
+    def advance(self, byte_count: int):
+        pass
+
+    def file_done(self, path: str):
+        pass
+
+    def finish(self):
+        pass
+
+
+# for operations without an observer
+NO_PROGRESS = Progress()
@@ -1,57 +1,368 @@
 '''
 I am providing the content hash functions.
+
+Hashes are hex digests, tagged with the algorithm, e.g. `blake3:<hex digest>`.
+SHA-512 hashes are not tagged: it was the only algorithm before tagging,
+so untagged hashes (and content ids) of existing archives remain valid.
 '''
 
+from concurrent.futures import ThreadPoolExecutor
+import contextlib
+import enum
 import hashlib
+import io
+import mmap
+import os
+import queue
+from typing import Iterator, List, Sequence, Tuple
+
+from .cancellation import Cancelled, NEVER_CANCELLED
+from .progress import NO_PROGRESS
 
 READ_BLOCK_SIZE = 1024 ** 2
+# bigger blocks can be faster on network file systems
+READ_BLOCK_SIZE_ENV_VAR = 'BEAD_READ_BLOCK_SIZE'
+# hashing files by mapping them into memory - set to 1 to enable
+HASH_MMAP_ENV_VAR = 'BEAD_HASH_MMAP'
+
+HASH_THREADS_ENV_VAR = 'BEAD_HASH_THREADS'
+DEFAULT_HASH_THREADS = min(4, os.cpu_count() or 1)
+# blocks read ahead by a HashedFile - bounds the memory used by reading threads
+READ_AHEAD_BLOCKS = 4
+# seconds between checks for cancellation while waiting for blocks
+_WAIT_INTERVAL = 0.1
+
+
+class HashAlgo(enum.Enum):
+    SHA512 = 'sha512'
+    BLAKE2B = 'blake2b'
+    # needs the blake3 package
+    BLAKE3 = 'blake3'
+
+
+DEFAULT_ALGORITHM = HashAlgo.SHA512
+HASH_ALGORITHMS = tuple(algorithm.value for algorithm in HashAlgo)
+
+
+def _new(algorithm: HashAlgo):
+    if algorithm is HashAlgo.SHA512:
+        return hashlib.sha512()
+    if algorithm is HashAlgo.BLAKE2B:
+        return hashlib.blake2b()
+    try:
+        import blake3
+    except ImportError:
+        raise ValueError('blake3 hashing needs the blake3 package') from None
+    return blake3.blake3()
+
+
+def is_available(algorithm: HashAlgo) -> bool:
+    try:
+        _new(algorithm)
+    except ValueError:
+        return False
+    return True
+
+
+def _tagged(algorithm: HashAlgo, hash) -> str:
+    if algorithm is DEFAULT_ALGORITHM:
+        return str(hash.hexdigest())
+    return f'{algorithm.value}:{hash.hexdigest()}'
+
+
+def algorithm_of(hash: str) -> HashAlgo:
+    '''
+    Algorithm that made the (tagged) hash.
+
+    Raises ValueError for unknown algorithms.
+    '''
+    tag, separator, _digest = hash.partition(':')
+    if not separator:
+        return DEFAULT_ALGORITHM
+    return HashAlgo(tag)
+
+
+def digest_of(hash: str) -> str:
+    '''
+    The hex digest of the (tagged) hash - e.g. to show a prefix of it.
+    '''
+    return hash.rpartition(':')[2]
+
+
+def safe_name(hash: str) -> str:
+    '''
+    The (tagged) hash usable in file names and graphviz ids - the `:` of the tag is replaced.
+    '''
+    return hash.replace(':', '_')
 
 # hashes are created from {length of content}:content;
 # similarity to http://cr.yp.to/proto/netstrings.txt are not accidental:
 # length is hashed with content AND there is a known suffix
 
 
-def _add_prefix(hash, size):
-    hash.update(f'{size}:'.encode('ascii'))
+class Hasher:
+    '''
+    Hash of content fed incrementally - the size of the content must be known in advance.
+
+        hasher = Hasher(size)
+        for block in blocks:
+            hasher.update(block)
+        hash = hasher.hash()
+    '''
+
+    def __init__(self, size: int, algorithm=DEFAULT_ALGORITHM):
+        self.size = size
+        self.algorithm = algorithm
+        self.bytes_hashed = 0
+        self._hash = _new(algorithm)
+        self._hash.update(f'{size}:'.encode('ascii'))
+
+    def update(self, block):
+        self.bytes_hashed += len(block)
+        self._hash.update(block)
 
+    def hash(self) -> str:
+        '''
+        The (tagged) hash of the content.
 
-def _add_suffix(hash, size):
-    hash.update(f';{size}'.encode('ascii'))
+        Raises ValueError, if not exactly size bytes were fed.
+        '''
+        if self.bytes_hashed != self.size:
+            raise ValueError(f'Expected {self.size} bytes, got {self.bytes_hashed}')
+        hash = self._hash.copy()
+        hash.update(f';{self.size}'.encode('ascii'))
+        return _tagged(self.algorithm, hash)
 
 
-def file(file, file_size):
+def read_block_size() -> int:
+    try:
+        return max(1, int(os.environ[READ_BLOCK_SIZE_ENV_VAR]))
+    except (KeyError, ValueError):
+        return READ_BLOCK_SIZE
+
+
+def is_mmap_enabled() -> bool:
+    return os.environ.get(HASH_MMAP_ENV_VAR, '') == '1'
+
+
+def _mapped(file):
+    '''
+    Memory map of a regular file, None if file can not be mapped (e.g. an entry of a zip).
     '''
-    Read file and return sha512 hash for its content.
+    try:
+        return mmap.mmap(file.fileno(), 0, access=mmap.ACCESS_READ)
+    except (AttributeError, io.UnsupportedOperation, OSError, ValueError):
+        return None
 
-    Closes the file.
-    Can process BIG files.
+
+def _blocks(file, block_size):
+    '''
+    Blocks of file - views of its memory map, when enabled and possible.
     '''
+    mapped = _mapped(file) if is_mmap_enabled() else None
+    if mapped is None:
+        while True:
+            block = file.read(block_size)
+            if not block:
+                return
+            yield block
+    with mapped, memoryview(mapped) as view:
+        for offset in range(0, len(view), block_size):
+            with view[offset:offset + block_size] as block:
+                yield block
 
-    hash = hashlib.sha512()
-    _add_prefix(hash, file_size)
 
-    bytes_read = 0
+def file(
+        file, file_size, cancel=NEVER_CANCELLED, progress=NO_PROGRESS,
+        algorithm=DEFAULT_ALGORITHM, block_size=None):
+    '''
+    Read file and return hash for its content.
 
+    Closes the file.
+    Can process BIG files - cancel is checked and progress is reported between blocks.
+    block_size defaults to the BEAD_READ_BLOCK_SIZE environment variable,
+    with BEAD_HASH_MMAP=1 regular files are hashed through a memory map.
+    '''
+    hasher = Hasher(file_size, algorithm)
     with file:
+        for block in _blocks(file, block_size or read_block_size()):
+            cancel.check()
+            hasher.update(block)
+            progress.advance(len(block))
+    return hasher.hash()
+
+
+def hash_threads() -> int:
+    try:
+        return max(1, int(os.environ[HASH_THREADS_ENV_VAR]))
+    except (KeyError, ValueError):
+        return DEFAULT_HASH_THREADS
+
+
+def hash_files_parallel(
+        paths: Sequence, threads=None, cancel=NEVER_CANCELLED,
+        algorithm=DEFAULT_ALGORITHM) -> List[Tuple[object, str]]:
+    '''
+    Hash files by multiple threads, return (path, hash) pairs in the order of paths.
+
+    hashlib releases the GIL while hashing blocks, so threads hash in parallel.
+    The number of threads defaults to the BEAD_HASH_THREADS environment variable.
+    '''
+    paths = list(paths)
+    threads = min(threads or hash_threads(), len(paths))
+    # stops all threads on the first error, or on interrupting the calling thread
+    threads_cancel = cancel.child()
+
+    def hash_file(path):
+        return file(open(path, 'rb'), os.path.getsize(path), threads_cancel, algorithm=algorithm)
+
+    if threads <= 1:
+        return [(path, hash_file(path)) for path in paths]
+    with ThreadPoolExecutor(max_workers=threads) as executor:
+        results = [executor.submit(hash_file, path) for path in paths]
+        try:
+            hashes = [result.result() for result in results]
+        except BaseException:
+            threads_cancel.cancel()
+            raise
+    return list(zip(paths, hashes))
+
+
+def copy(
+        source, target, file_size, cancel=NEVER_CANCELLED, progress=NO_PROGRESS,
+        algorithm=DEFAULT_ALGORITHM):
+    '''
+    Copy file content from source to target and return hash for it.
+
+    The content is read only once, block by block - can process BIG files,
+    cancel is checked and progress is reported between blocks.
+    With algorithm None the content is only copied and None is returned.
+    Raises ValueError, if not exactly file_size bytes were copied.
+    '''
+    hasher = None if algorithm is None else Hasher(file_size, algorithm)
+    block_size = read_block_size()
+    bytes_copied = 0
+    while True:
+        cancel.check()
+        block = source.read(block_size)
+        if not block:
+            break
+        bytes_copied += len(block)
+        if hasher is not None:
+            hasher.update(block)
+        target.write(block)
+        progress.advance(len(block))
+    if bytes_copied != file_size:
+        raise ValueError(f'Expected {file_size} bytes, got {bytes_copied}')
+    return None if hasher is None else hasher.hash()
+
+
+_END_OF_FILE = object()
+
+
+class HashedFile:
+    '''
+    Content of a file, read and hashed block by block by a background thread.
+
+    Iterating gives the blocks, that were hashed - after the last one, hash is set.
+    The reading thread stays at most READ_AHEAD_BLOCKS ahead.
+    '''
+
+    def __init__(self, path, cancel=NEVER_CANCELLED, algorithm=DEFAULT_ALGORITHM):
+        self.path = path
+        self.hash = None
+        self._cancel = cancel
+        self._algorithm = algorithm
+        self._blocks: queue.Queue = queue.Queue(maxsize=READ_AHEAD_BLOCKS)
+
+    def read(self):
+        '''
+        Read and hash the file - run by the background thread.
+        '''
+        try:
+            with open(self.path, 'rb') as file:
+                hasher = Hasher(os.fstat(file.fileno()).st_size, self._algorithm)
+                block_size = read_block_size()
+                while True:
+                    self._cancel.check()
+                    block = file.read(block_size)
+                    if not block:
+                        break
+                    hasher.update(block)
+                    self._put(block)
+            self.hash = hasher.hash()
+            self._put(_END_OF_FILE)
+        except Cancelled:
+            # the consumer is gone
+            pass
+        except BaseException as e:
+            with contextlib.suppress(Cancelled):
+                self._put(e)
+
+    def _put(self, item):
         while True:
-            block = file.read(READ_BLOCK_SIZE)
-            if not block:
-                break
-            bytes_read += len(block)
-            hash.update(block)
+            try:
+                self._blocks.put(item, timeout=_WAIT_INTERVAL)
+                return
+            except queue.Full:
+                self._cancel.check()
+
+    def __iter__(self) -> Iterator[bytes]:
+        while True:
+            try:
+                item = self._blocks.get(timeout=_WAIT_INTERVAL)
+            except queue.Empty:
+                self._cancel.check()
+                continue
+            if item is _END_OF_FILE:
+                return
+            if isinstance(item, BaseException):
+                raise item
+            yield item
+
+
+@contextlib.contextmanager
+def read_files_parallel(
+        paths: Sequence, threads=None, cancel=NEVER_CANCELLED,
+        algorithm=DEFAULT_ALGORITHM) -> Iterator[List[HashedFile]]:
+    '''
+    Read and hash files by multiple threads, give HashedFile-s in the order of paths.
+
+    The files must be consumed in order: threads are assigned to the files in order,
+    and a thread is not freed, until its file is consumed.
+    The hashes are of the blocks given to the consumer, so they match, even if a file
+    is modified meanwhile. The number of threads defaults to BEAD_HASH_THREADS.
+    '''
+    # stops all threads on leaving the with block
+    threads_cancel = cancel.child()
+    files = [HashedFile(path, threads_cancel, algorithm) for path in paths]
+    with ThreadPoolExecutor(max_workers=threads or hash_threads()) as executor:
+        for file in files:
+            executor.submit(file.read)
+        try:
+            yield files
+        finally:
+            threads_cancel.cancel()
+
 
-    assert bytes_read == file_size
+def file_sha256(path, cancel=NEVER_CANCELLED) -> str:
+    '''
+    Plain SHA-256 hex digest of the file at path, as made by sha256sum - not a content hash.
 
-    _add_suffix(hash, file_size)
-    return str(hash.hexdigest())
+    Used as checksum of whole archive files, to detect damage done by copying them.
+    '''
+    hash = hashlib.sha256()
+    with open(path, 'rb') as file:
+        for block in _blocks(file, read_block_size()):
+            cancel.check()
+            hash.update(block)
+    return hash.hexdigest()
 
 
-def bytes(bytes):
+def bytes(bytes, algorithm=DEFAULT_ALGORITHM):
     '''
-    Return sha512 hash for bytes.
+    Return hash for bytes.
     '''
-    hash = hashlib.sha512()
-    _add_prefix(hash, len(bytes))
-    hash.update(bytes)
-    _add_suffix(hash, len(bytes))
-    return str(hash.hexdigest())
+    hasher = Hasher(len(bytes), algorithm)
+    hasher.update(bytes)
+    return hasher.hash()
@@ -0,0 +1,35 @@
+import pytest
+
+from .cancellation import CancellationToken, Cancelled, NEVER_CANCELLED
+
+
+def test_cancelling_parent_cancels_child():
+    parent = CancellationToken()
+    child = parent.child()
+    child.cancel()
+    assert not parent.is_cancelled
+
+    other_child = parent.child()
+    parent.cancel()
+    assert other_child.is_cancelled
+    with pytest.raises(Cancelled):
+        other_child.check()
+
+
+def test_never_cancelled_can_not_be_changed():
+    with pytest.raises(TypeError):
+        NEVER_CANCELLED.cancel()
+    with pytest.raises(TypeError):
+        NEVER_CANCELLED.pause()
+    NEVER_CANCELLED.check()
+    assert not NEVER_CANCELLED.is_cancelled
+
+
+def test_child_of_never_cancelled_can_be_cancelled():
+    child = NEVER_CANCELLED.child()
+    child.cancel()
+
+    assert child.is_cancelled
+    assert not NEVER_CANCELLED.is_cancelled
+    with pytest.raises(Cancelled):
+        child.check()
@@ -0,0 +1,43 @@
+from .checkpoint import Checkpoint
+
+
+def test_items_are_remembered(tmp_path):
+    path = tmp_path / 'dir/checkpoint'
+    checkpoint = Checkpoint(path, 'key')
+    checkpoint.add('a')
+    checkpoint.add('b')
+    checkpoint.close()
+
+    resumed = Checkpoint(path, 'key')
+    assert 'a' in resumed
+    assert 'c' not in resumed
+    resumed.add('c')
+    resumed.close()
+
+    assert 3 == len(Checkpoint(path, 'key'))
+
+
+def test_checkpoint_of_another_operation_is_ignored(tmp_path):
+    path = tmp_path / 'checkpoint'
+    checkpoint = Checkpoint(path, 'key')
+    checkpoint.add('a')
+    checkpoint.close()
+
+    other = Checkpoint(path, 'other-key')
+    assert 'a' not in other
+    other.add('b')
+    other.close()
+
+    assert ['other-key', 'b'] == path.read_text().splitlines()
+
+
+def test_remove(tmp_path):
+    path = tmp_path / 'checkpoint'
+    checkpoint = Checkpoint(path, 'key')
+    checkpoint.add('a')
+    checkpoint.remove()
+
+    assert not path.exists()
+    assert 0 == len(checkpoint)
+    # removing a missing checkpoint is fine
+    checkpoint.remove()
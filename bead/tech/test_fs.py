@@ -1,5 +1,6 @@
 # coding: utf-8
 import os
+import subprocess
 import pytest
 from . import fs as m
 
@@ -65,6 +66,25 @@ def test_make_writable_directory(tmp_path):
     (dir_path / 'file').write_bytes(b'little something')
 
 
+def test_make_readonly_reports_unsupported_permissions(tmp_path, monkeypatch):
+    """Test that failing to change permissions is reported, not raised."""
+    file_path = tmp_path / 'file'
+    file_path.write_bytes(b'')
+
+    def chmod(path, mode):
+        raise PermissionError(1, 'Operation not permitted', str(path))
+    monkeypatch.setattr(os, 'chmod', chmod)
+
+    assert not m.make_readonly(file_path)
+    assert not m.make_writable(file_path)
+
+
+def test_make_readonly_missing_file_fails(tmp_path):
+    """Test that missing files are still errors."""
+    with pytest.raises(FileNotFoundError):
+        m.make_readonly(tmp_path / 'missing')
+
+
 def test_all_subpaths(tmp_path):
     """Test collecting all subpaths from a directory structure."""
     DIRS = ('a', 'b', 'c', 'c/d')
@@ -96,3 +116,84 @@ def test_read_write_file(tmp_path):
     m.write_file(testfile, content)
     read_content = m.read_file(testfile)
     assert content == read_content
+
+
+def test_portable_name_is_nfc_normalized():
+    """Test that decomposed (NFD) names are composed."""
+    assert '\u00e1rv\u00edz' == m.portable_name('a\u0301rvi\u0301z')
+    assert '日本語.csv' == m.portable_name('日本語.csv')
+
+
+def test_portable_name_refuses_non_unicode_names():
+    """Test that undecodable (surrogate escaped) names are refused."""
+    with pytest.raises(ValueError):
+        m.portable_name(os.fsdecode(b'data-\xff.csv'))
+
+
+def test_removed_on_error_keeps_artifact_on_success(tmp_path):
+    """Test that a successfully made artifact is kept."""
+    path = tmp_path / 'artifact'
+    with m.removed_on_error(path):
+        m.write_file(path, 'done')
+    assert path.exists()
+
+
+@pytest.mark.parametrize('error', [ValueError, KeyboardInterrupt])
+def test_removed_on_error_removes_partial_file(tmp_path, error):
+    """Test that a partial file is removed on errors and interrupts."""
+    path = tmp_path / 'partial'
+    with pytest.raises(error):
+        with m.removed_on_error(path):
+            m.write_file(path, 'half')
+            raise error
+    assert not path.exists()
+
+
+def test_removed_on_error_removes_read_only_staging_directory(tmp_path):
+    """Test that a partial directory tree is removed, even if made read-only."""
+    path = tmp_path / 'staging'
+    with pytest.raises(ValueError):
+        with m.removed_on_error(path):
+            m.ensure_directory(path / 'sub')
+            m.write_file(path / 'sub/file', 'half')
+            m.make_readonly(path / 'sub/file')
+            m.make_readonly(path / 'sub')
+            raise ValueError
+    assert not path.exists()
+
+
+def test_removed_on_error_tolerates_missing_artifact(tmp_path):
+    """Test that failing before the artifact is created is not an issue."""
+    with pytest.raises(ValueError):
+        with m.removed_on_error(tmp_path / 'never-made'):
+            raise ValueError
+
+
+@pytest.mark.parametrize(
+    'make, expected_change', [
+        (m.make_readonly, ['/deny', f'{m.EVERYONE_SID}:{m.DENY_WRITE_PERMISSIONS}']),
+        (m.make_writable, ['/remove:d', m.EVERYONE_SID]),
+    ])
+def test_windows_folders_are_protected_by_access_control(
+        tmp_path, monkeypatch, make, expected_change):
+    """Test that on Windows folder protection is changed with icacls."""
+    calls = []
+
+    def run(args, **kwargs):
+        calls.append(args)
+        return subprocess.CompletedProcess(args, 0)
+    monkeypatch.setattr(m, 'IS_WINDOWS', True)
+    monkeypatch.setattr(subprocess, 'run', run)
+
+    assert make(tmp_path)
+    assert [['icacls', os.fspath(tmp_path), *expected_change]] == calls
+
+
+def test_windows_folder_protection_without_icacls(tmp_path, monkeypatch):
+    """Test that missing access control support is reported, not raised."""
+    def run(args, **kwargs):
+        raise FileNotFoundError(args[0])
+    monkeypatch.setattr(m, 'IS_WINDOWS', True)
+    monkeypatch.setattr(subprocess, 'run', run)
+
+    assert not m.make_readonly(tmp_path)
@@ -0,0 +1,20 @@
+from .identifier import unique_prefix_length
+
+
+def test_unique_prefix_length_has_a_minimum():
+    assert unique_prefix_length(['0123456789', 'abcdefghij'], min_length=4) == 4
+    assert unique_prefix_length([], min_length=4) == 4
+
+
+def test_unique_prefix_length_distinguishes_ids():
+    ids = ['abcdef01', 'abcdef02', 'abcd9999']
+    assert unique_prefix_length(ids, min_length=2) == 8
+    assert unique_prefix_length(ids[1:], min_length=2) == 5
+
+
+def test_unique_prefix_length_ignores_duplicates():
+    assert unique_prefix_length(['abcdef', 'abcdef'], min_length=2) == 2
+
+
+def test_unique_prefix_length_is_limited_by_id_length():
+    assert unique_prefix_length(['abc', 'abd'], min_length=8) == 3
@@ -0,0 +1,124 @@
+import os
+import socket
+import subprocess
+import sys
+import warnings
+
+import pytest
+
+from .lock import FileLock, LockTimeout, StaleLockRemoved
+
+
+def dead_pid():
+    process = subprocess.Popen([sys.executable, '-c', 'pass'])
+    process.wait()
+    return process.pid
+
+
+def test_lock_file_exists_while_held(tmp_path):
+    path = tmp_path / 'lock'
+
+    with FileLock(path):
+        assert path.exists()
+        assert str(os.getpid()) in path.read_text()
+
+    assert not path.exists()
+
+
+def test_held_lock_times_out(tmp_path):
+    path = tmp_path / 'lock'
+
+    with FileLock(path):
+        with pytest.raises(LockTimeout) as excinfo:
+            FileLock(path, timeout=0.05).acquire()
+
+    assert str(os.getpid()) in str(excinfo.value)
+
+
+def test_lock_is_released_on_error(tmp_path):
+    path = tmp_path / 'lock'
+
+    with pytest.raises(ValueError):
+        with FileLock(path):
+            raise ValueError
+
+    with FileLock(path, timeout=0):
+        pass
+
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='process ids are not checked on Windows')
+def test_stale_lock_of_crashed_process_is_removed(tmp_path):
+    path = tmp_path / 'lock'
+    path.write_text(f'{socket.gethostname()} {dead_pid()} 20200101T000000000000+0000\n')
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        with FileLock(path, timeout=0):
+            assert str(os.getpid()) in path.read_text()
+
+    [warning] = caught
+    assert issubclass(warning.category, StaleLockRemoved)
+
+
+def test_locks_of_running_or_remote_processes_are_not_stale(tmp_path):
+    path = tmp_path / 'lock'
+    lock = FileLock(path, timeout=0)
+
+    path.write_text(f'{socket.gethostname()} {os.getpid()}\n')
+    assert not lock.is_stale()
+    path.write_text(f'another-{socket.gethostname()} {dead_pid()}\n')
+    assert not lock.is_stale()
+    path.write_text('garbage')
+    assert not lock.remove_if_stale()
+    with pytest.raises(LockTimeout):
+        lock.acquire()
+
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='process ids are not checked on Windows')
+def test_stale_lock_is_taken_over_by_one_contender_only(tmp_path):
+    path = tmp_path / 'lock'
+    stale = f'{socket.gethostname()} {dead_pid()} 20200101T000000000000+0000\n'
+    path.write_text(stale)
+    first, second = FileLock(path, timeout=0), FileLock(path, timeout=0)
+    # the second contender read the stale lock before the first one took it over
+    second._read = lambda: stale
+
+    with warnings.catch_warnings():
+        warnings.simplefilter('ignore', StaleLockRemoved)
+        with first:
+            assert not second.remove_if_stale()
+            assert str(os.getpid()) in path.read_text()
+            with pytest.raises(LockTimeout):
+                FileLock(path, timeout=0).acquire()
+
+    assert [] == list(tmp_path.iterdir())
+
+
+def test_release_keeps_the_lock_of_others(tmp_path):
+    path = tmp_path / 'lock'
+    lock = FileLock(path)
+    lock.acquire()
+    # e.g. the lock was removed by hand and taken by another process
+    path.write_text(f'{socket.gethostname()} {os.getpid()} 20200101T000000000000+0000 other\n')
+
+    lock.release()
+    assert path.exists()
+
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='process ids are not checked on Windows')
+def test_live_lock_is_kept_when_it_can_not_be_put_back(tmp_path, monkeypatch):
+    path = tmp_path / 'lock'
+    stale = f'{socket.gethostname()} {dead_pid()} 20200101T000000000000+0000\n'
+    live = f'{socket.gethostname()} {os.getpid()} 20200101T000000000000+0000\n'
+    path.write_text(live)
+    lock = FileLock(path, timeout=0)
+    # read the stale lock, that was replaced by a live one since
+    lock._read = lambda: stale
+
+    def link(src, dst):
+        raise FileExistsError(dst)
+    monkeypatch.setattr(os, 'link', link)
+
+    assert not lock.remove_if_stale()
+    [kept] = tmp_path.iterdir()
+    assert live == kept.read_text()
@@ -1,4 +1,11 @@
 # coding: utf-8
+import json
+import os
+import stat
+from concurrent.futures import ThreadPoolExecutor
+
+import pytest
+
 from . import persistence as m
 
 
@@ -37,3 +44,84 @@ def test_strings():
 
     # then it equals the original structure
     assert original_structure == structure
+
+
+def test_file_dump_replaces_content(tmp_path):
+    """Test that file_dump overwrites the file without leaving temporary files behind."""
+    file_path = tmp_path / 'file'
+    m.file_dump({'old': 'content'}, file_path)
+
+    m.file_dump(get_structure(), file_path)
+
+    assert get_structure() == m.file_load(file_path)
+    assert ['file'] == [p.name for p in tmp_path.iterdir()]
+
+
+def test_interrupted_file_dump_keeps_old_content(tmp_path, monkeypatch):
+    """Test that a crash while writing leaves the previous content intact."""
+    file_path = tmp_path / 'file'
+    m.file_dump({'old': 'content'}, file_path)
+
+    def crash(content, ostream, **kwargs):
+        ostream.write('{"partial')
+        raise KeyboardInterrupt
+    monkeypatch.setattr(json, 'dump', crash)
+
+    with pytest.raises(KeyboardInterrupt):
+        m.file_dump(get_structure(), file_path)
+
+    monkeypatch.undo()
+    assert {'old': 'content'} == m.file_load(file_path)
+    assert ['file'] == [p.name for p in tmp_path.iterdir()]
+
+
+@pytest.mark.skipif(m.IS_WINDOWS, reason='directories are not synced on Windows')
+def test_file_dump_on_file_system_not_syncing_directories(tmp_path, monkeypatch):
+    """Test that a directory fsync failing (e.g. EINVAL on network shares) is not an error."""
+    fsync = os.fsync
+
+    def fsync_files_only(fd):
+        if stat.S_ISDIR(os.fstat(fd).st_mode):
+            raise OSError(22, 'Invalid argument')
+        fsync(fd)
+    monkeypatch.setattr(os, 'fsync', fsync_files_only)
+
+    m.file_dump({'data': 1}, tmp_path / 'file')
+
+    assert {'data': 1} == m.file_load(tmp_path / 'file')
+
+
+def test_keys_are_written_in_sorted_order(tmp_path):
+    """Test that the output does not depend on the insertion order of keys."""
+    file_path = tmp_path / 'file'
+    m.file_dump({'b': {'y': 1, 'x': 2}, 'a': 0}, file_path)
+    content = file_path.read_text()
+
+    assert m.dumps({'a': 0, 'b': {'x': 2, 'y': 1}}) == content
+    assert content.index('"a"') < content.index('"b"')
+    assert content.index('"x"') < content.index('"y"')
+
+
+def test_concurrent_file_dumps_do_not_interfere(tmp_path):
+    """Test that writers racing for the same path each write a complete file."""
+    file_path = tmp_path / 'file'
+    contents = [{'writer': i, 'data': list(range(1000))} for i in range(8)]
+
+    with ThreadPoolExecutor(max_workers=len(contents)) as executor:
+        list(executor.map(lambda content: m.file_dump(content, file_path), contents))
+
+    assert m.file_load(file_path) in contents
+    assert ['file'] == [p.name for p in tmp_path.iterdir()]
+
+
+@pytest.mark.skipif(os.name != 'posix', reason='file modes are not supported e.g. on Windows')
+def test_file_dump_keeps_file_mode(tmp_path):
+    """Test that new files get the default mode and replaced ones keep theirs."""
+    file_path = tmp_path / 'file'
+    m.file_dump({}, file_path)
+    assert 0o666 & ~m._UMASK == stat.S_IMODE(file_path.stat().st_mode)
+    file_path.chmod(0o640)
+
+    m.file_dump(get_structure(), file_path)
+
+    assert 0o640 == stat.S_IMODE(file_path.stat().st_mode)
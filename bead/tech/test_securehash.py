@@ -1,4 +1,10 @@
+import hashlib
+import io
+
+import pytest
+
 from .. import tech
+from ..tech.cancellation import CancellationToken, Cancelled
 
 securehash = tech.securehash
 write_file = tech.fs.write_file
@@ -23,6 +29,14 @@ def test_file_hash(tmp_path):
     assert length > 32
 
 
+def test_file_sha256_is_a_plain_checksum(tmp_path):
+    """Test that file checksums can be compared with the output of sha256sum."""
+    file_path = tmp_path / 'file'
+    file_path.write_bytes(b'with some content')
+
+    assert hashlib.sha256(b'with some content').hexdigest() == securehash.file_sha256(file_path)
+
+
 def test_bytes_hash():
     """Test hashing bytes."""
     # given some bytes
@@ -54,3 +68,199 @@ def test_bytes_and_file_compatibility(tmp_path):
 
     # then the hashes are the same
     assert bytes_hash == file_hash
+
+
+def test_copy_hashes_the_copied_content():
+    """Test that copy writes the content and returns its hash."""
+    some_bytes = b'some bytes' * 1000
+    target = io.BytesIO()
+
+    copy_hash = securehash.copy(io.BytesIO(some_bytes), target, len(some_bytes))
+
+    assert target.getvalue() == some_bytes
+    assert copy_hash == securehash.bytes(some_bytes)
+
+
+def test_copy_cancelled():
+    """Test that copy stops, when cancelled."""
+    cancel = CancellationToken()
+    cancel.cancel()
+
+    with pytest.raises(Cancelled):
+        securehash.copy(io.BytesIO(b'some bytes'), io.BytesIO(), 10, cancel)
+
+
+def test_default_algorithm_hashes_are_not_tagged():
+    hash = securehash.bytes(b'content')
+
+    assert ':' not in hash
+    assert securehash.HashAlgo.SHA512 is securehash.algorithm_of(hash)
+
+
+def test_other_algorithms_are_tagged():
+    hash = securehash.bytes(b'content', securehash.HashAlgo.BLAKE2B)
+
+    assert hash.startswith('blake2b:')
+    assert securehash.HashAlgo.BLAKE2B is securehash.algorithm_of(hash)
+    assert hash != securehash.bytes(b'content')
+
+
+def test_file_and_copy_hashes_agree_for_other_algorithms():
+    algorithm = securehash.HashAlgo.BLAKE2B
+    content = b'some bytes' * 1000
+    copy_hash = securehash.copy(
+        io.BytesIO(content), io.BytesIO(), len(content), algorithm=algorithm)
+
+    assert copy_hash == securehash.file(io.BytesIO(content), len(content), algorithm=algorithm)
+    assert copy_hash == securehash.bytes(content, algorithm)
+
+
+def test_digest_and_safe_name_of_tagged_hash():
+    hash = securehash.bytes(b'content', securehash.HashAlgo.BLAKE2B)
+    untagged = securehash.bytes(b'content')
+
+    assert hash[len('blake2b:'):] == securehash.digest_of(hash)
+    assert untagged == securehash.digest_of(untagged)
+    assert ':' not in securehash.safe_name(hash)
+    assert untagged == securehash.safe_name(untagged)
+
+
+def test_unknown_algorithm_tag():
+    with pytest.raises(ValueError):
+        securehash.algorithm_of('md5:0123')
+
+
+@pytest.mark.skipif(
+    not securehash.is_available(securehash.HashAlgo.BLAKE3), reason='blake3 is not installed')
+def test_blake3():
+    assert securehash.bytes(b'content', securehash.HashAlgo.BLAKE3).startswith('blake3:')
+
+
+@pytest.mark.parametrize('threads', [1, 3])
+def test_hash_files_parallel(tmp_path, threads):
+    paths = []
+    for i in range(10):
+        path = tmp_path / f'file{i}'
+        path.write_bytes(b'content %d' % i * i)
+        paths.append(path)
+
+    hashes = securehash.hash_files_parallel(paths, threads, algorithm=securehash.HashAlgo.BLAKE2B)
+
+    assert paths == [path for path, _ in hashes]
+    for path, hash in hashes:
+        content = path.read_bytes()
+        assert securehash.bytes(content, securehash.HashAlgo.BLAKE2B) == hash
+
+
+def test_hash_files_parallel_cancelled(tmp_path):
+    paths = [tmp_path / 'a', tmp_path / 'b']
+    for path in paths:
+        path.write_bytes(b'content')
+    cancel = CancellationToken()
+    cancel.cancel()
+
+    with pytest.raises(Cancelled):
+        securehash.hash_files_parallel(paths, 2, cancel)
+
+
+@pytest.mark.parametrize('threads', [1, 3])
+def test_read_files_parallel(tmp_path, monkeypatch, threads):
+    monkeypatch.setenv(securehash.READ_BLOCK_SIZE_ENV_VAR, '3')
+    paths = []
+    for i in range(10):
+        path = tmp_path / f'file{i}'
+        path.write_bytes(b'content %d' % i * i)
+        paths.append(path)
+
+    with securehash.read_files_parallel(paths, threads) as files:
+        contents = [(file.path, b''.join(file), file.hash) for file in files]
+
+    assert paths == [path for path, _, _ in contents]
+    for path, content, hash in contents:
+        assert path.read_bytes() == content
+        assert securehash.bytes(content) == hash
+
+
+def test_read_files_parallel_stops_threads_of_unread_files(tmp_path, monkeypatch):
+    monkeypatch.setenv(securehash.READ_BLOCK_SIZE_ENV_VAR, '1')
+    paths = [tmp_path / f'file{i}' for i in range(4)]
+    for path in paths:
+        path.write_bytes(b'content' * 10)
+
+    with securehash.read_files_parallel(paths, 2) as files:
+        assert b'c' == next(iter(files[0]))
+    # leaving the with block does not hang on the blocked reading threads
+
+
+def test_read_files_parallel_cancelled(tmp_path):
+    path = tmp_path / 'file'
+    path.write_bytes(b'content')
+    cancel = CancellationToken()
+    cancel.cancel()
+
+    with pytest.raises(Cancelled):
+        with securehash.read_files_parallel([path], 2, cancel) as files:
+            list(files[0])
+
+
+def test_copy_without_hashing():
+    target = io.BytesIO()
+
+    assert securehash.copy(io.BytesIO(b'content'), target, 7, algorithm=None) is None
+    assert b'content' == target.getvalue()
+    with pytest.raises(ValueError):
+        securehash.copy(io.BytesIO(b'content'), io.BytesIO(), 8, algorithm=None)
+
+
+def test_hash_threads_from_environment(monkeypatch):
+    monkeypatch.setenv(securehash.HASH_THREADS_ENV_VAR, '7')
+    assert 7 == securehash.hash_threads()
+    monkeypatch.setenv(securehash.HASH_THREADS_ENV_VAR, 'many')
+    assert securehash.DEFAULT_HASH_THREADS == securehash.hash_threads()
+
+
+def test_hasher_fed_incrementally():
+    hasher = securehash.Hasher(10)
+    hasher.update(b'some ')
+    hasher.update(memoryview(b'bytes'))
+
+    assert securehash.bytes(b'some bytes') == hasher.hash()
+
+
+def test_hasher_checks_size():
+    hasher = securehash.Hasher(10, securehash.HashAlgo.BLAKE2B)
+    hasher.update(b'some')
+
+    with pytest.raises(ValueError):
+        hasher.hash()
+
+
+@pytest.mark.parametrize('mmap', ['0', '1'])
+def test_file_hash_with_block_size_and_mmap(tmp_path, monkeypatch, mmap):
+    monkeypatch.setenv(securehash.HASH_MMAP_ENV_VAR, mmap)
+    content = bytes(range(256)) * 10
+    file_path = tmp_path / 'file'
+    file_path.write_bytes(content)
+    progress = []
+
+    class Progress:
+        def advance(self, size):
+            progress.append(size)
+
+    with file_path.open('rb') as f:
+        hash = securehash.file(f, len(content), progress=Progress(), block_size=1000)
+
+    assert securehash.bytes(content) == hash
+    assert [1000, 1000, 560] == progress
+
+
+def test_mmap_falls_back_to_reading_streams(monkeypatch):
+    monkeypatch.setenv(securehash.HASH_MMAP_ENV_VAR, '1')
+    assert securehash.bytes(b'abc') == securehash.file(io.BytesIO(b'abc'), 3)
+
+
+def test_read_block_size_from_environment(monkeypatch):
+    monkeypatch.setenv(securehash.READ_BLOCK_SIZE_ENV_VAR, '65536')
+    assert 65536 == securehash.read_block_size()
+    monkeypatch.setenv(securehash.READ_BLOCK_SIZE_ENV_VAR, 'big')
+    assert securehash.READ_BLOCK_SIZE == securehash.read_block_size()
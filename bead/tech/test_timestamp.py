@@ -3,7 +3,9 @@ from datetime import timedelta, datetime
 from freezegun import freeze_time
 import pytest
 
-from .timestamp import FixedOffset, Local, timestamp
+from .timestamp import FixedOffset, InvalidTimestamp, Local, timestamp, timestamp_after
+from .timestamp import format_timestamp
+from .timestamp import time_from_legacy_timestamp, normalize_timestamp
 from .timestamp import parse_timedelta, parse_iso8601, time_from_timestamp, time_from_user
 
 
@@ -47,16 +49,27 @@ UTC = FixedOffset(0, 'UTC')
         (
             '20121130T235947-0100',
             datetime(2012, 11, 30, 23, 59, 47, tzinfo=FixedOffset(-60, 'UTC-1'))),
-        ('20121120T090000-0100', parse_iso8601('20121120T120000+0200'))
+        ('20121120T090000-0100', parse_iso8601('20121120T120000+0200')),
+        ('2012-11-30T23:59:47Z', datetime(2012, 11, 30, 23, 59, 47, tzinfo=UTC)),
+        ('20121130T235947Z', datetime(2012, 11, 30, 23, 59, 47, tzinfo=UTC)),
+        ('20121130T235947000012Z', datetime(2012, 11, 30, 23, 59, 47, 12, tzinfo=UTC)),
+        (
+            '2012-11-30T23:59:47+01:30',
+            datetime(2012, 11, 30, 23, 59, 47, tzinfo=FixedOffset(90, 'UTC+1:30'))),
+        ('2012-11-30T23:59:47.5Z', datetime(2012, 11, 30, 23, 59, 47, 500000, tzinfo=UTC)),
+        ('2012-11-30T23:59:47.000012Z', datetime(2012, 11, 30, 23, 59, 47, 12, tzinfo=UTC)),
     ]
 )
 def test_parse_iso8601(text, value):
     assert parse_iso8601(text) == value
 
 
-@pytest.mark.parametrize("text", ['1y', '12345', '2012-11-30T23', '20121130T23:59:47-0100'])
+@pytest.mark.parametrize(
+    "text", [
+        '1y', '12345', '2012-11-30T23', '20121130T23:59:47-0100', '', '2',
+        '2012-13-01', '2012-11-30T23:59:47.1234567Z', '2012-11-30T23:59:47X', None])
 def test_parse_invalid_iso8601(text):
-    with pytest.raises(ValueError):
+    with pytest.raises(InvalidTimestamp):
         parse_iso8601(text)
 
 
@@ -68,6 +81,24 @@ def test_time_from_timestamp():
         time_from_timestamp('20000101T000000000000')
 
 
+@pytest.mark.parametrize("text", ['', '2000', '20000102T0304', '20000132T030405000006+0123'])
+def test_time_from_timestamp_rejects_short_or_invalid(text):
+    with pytest.raises(InvalidTimestamp):
+        time_from_timestamp(text)
+
+
+@pytest.mark.parametrize(
+    "text", [
+        '20000102T030405000006+0123', '19991231T235959999999-0100', '20000101T000000000000+0000'])
+def test_format_timestamp_round_trips(text):
+    assert format_timestamp(time_from_timestamp(text)) == text
+
+
+def test_format_timestamp_needs_time_zone():
+    with pytest.raises(ValueError):
+        format_timestamp(datetime(2000, 1, 2))
+
+
 def test_time_from_user():
     assert time_from_user('1234') == datetime(1234, 1, 1, tzinfo=UTC)
     assert time_from_user('21340228') == datetime(2134, 2, 28, tzinfo=UTC)
@@ -88,3 +119,35 @@ def test_timestamp():
         assert (
             time_from_timestamp(timestamp())
             == time_from_timestamp('20191101T010203000004+0500'))
+
+
+def test_timestamp_after():
+    assert timestamp_after('20000102T030405000006+0123') == '20000102T030405000007+0123'
+    assert timestamp_after('19991231T235959999999-0100') == '20000101T000000000000-0100'
+
+
+@pytest.mark.parametrize(
+    "text, value",
+    [
+        ('20000102T030405000006+0123', datetime(2000, 1, 2, 3, 4, 5, 6, FixedOffset(83, ''))),
+        ('20000102T030405000006', datetime(2000, 1, 2, 3, 4, 5, 6, UTC)),
+        ('20000102T030405+0123', datetime(2000, 1, 2, 3, 4, 5, 0, FixedOffset(83, ''))),
+        ('20000102T030405', datetime(2000, 1, 2, 3, 4, 5, 0, UTC)),
+        ('20000102T0304', datetime(2000, 1, 2, 3, 4, 0, 0, UTC)),
+        ('20000102', datetime(2000, 1, 2, tzinfo=UTC)),
+        ('20000102T030405Z', datetime(2000, 1, 2, 3, 4, 5, 0, UTC)),
+        ('20000102T030405000006Z', datetime(2000, 1, 2, 3, 4, 5, 6, UTC)),
+    ])
+def test_time_from_legacy_timestamp(text, value):
+    assert time_from_legacy_timestamp(text) == value
+
+
+def test_time_from_legacy_timestamp_rejects_garbage():
+    with pytest.raises(InvalidTimestamp):
+        time_from_legacy_timestamp('2000-01-02 junk')
+
+
+def test_normalize_timestamp():
+    assert normalize_timestamp('20000102T030405000006+0123') == '20000102T030405000006+0123'
+    assert normalize_timestamp('20000102T030405') == '20000102T030405000000+0000'
+    assert normalize_timestamp('20000102') == '20000102T000000000000+0000'
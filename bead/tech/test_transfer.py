@@ -0,0 +1,107 @@
+import io
+import os
+import threading
+
+import pytest
+
+from .cancellation import CancellationToken, Cancelled
+from . import transfer as m
+
+
+class SlowTarget(io.BytesIO):
+    '''
+    Target, that accepts writes only when allowed.
+    '''
+
+    def __init__(self):
+        super().__init__()
+        self.allowed = threading.Semaphore(0)
+
+    def write(self, block):
+        self.allowed.acquire()
+        return super().write(block)
+
+
+class CountingSource(io.BytesIO):
+
+    def __init__(self, content):
+        super().__init__(content)
+        self.reads = 0
+
+    def read(self, size=-1):
+        self.reads += 1
+        return super().read(size)
+
+
+def test_copy_file(tmp_path):
+    source = tmp_path / 'source'
+    content = os.urandom(10000)
+    source.write_bytes(content)
+    os.utime(source, (1234567890, 1234567890))
+
+    copied = m.copy_file(source, tmp_path / 'target', block_size=1000, buffer_blocks=2)
+
+    assert copied == len(content)
+    assert (tmp_path / 'target').read_bytes() == content
+    assert (tmp_path / 'target').stat().st_mtime == 1234567890
+
+
+def test_reading_does_not_get_ahead_of_slow_writing():
+    source = CountingSource(b'x' * 100)
+    target = SlowTarget()
+    thread = threading.Thread(
+        target=m.copy_stream, args=(source, target),
+        kwargs=dict(block_size=1, buffer_blocks=3), daemon=True)
+    thread.start()
+
+    # let 10 blocks through, then give the reader time to run ahead
+    for _ in range(10):
+        target.allowed.release()
+    thread.join(timeout=0.5)
+    # written + one being written + buffered + one held by the reader
+    assert source.reads <= 10 + 1 + 3 + 1
+
+    for _ in range(100):
+        target.allowed.release()
+    thread.join()
+    assert target.getvalue() == b'x' * 100
+
+
+def test_cancelled_copy_leaves_no_partial_file(tmp_path):
+    source = tmp_path / 'source'
+    source.write_bytes(b'x' * 10000)
+    cancel = CancellationToken()
+    cancel.cancel()
+
+    with pytest.raises(Cancelled):
+        m.copy_file(source, tmp_path / 'target', cancel, block_size=100)
+
+    assert os.listdir(tmp_path) == ['source']
+
+
+def test_paused_copy_continues_on_resume():
+    cancel = CancellationToken()
+    cancel.pause()
+    target = io.BytesIO()
+    thread = threading.Thread(
+        target=m.copy_stream, args=(io.BytesIO(b'x' * 1000), target, cancel),
+        kwargs=dict(block_size=10), daemon=True)
+    thread.start()
+
+    thread.join(timeout=0.2)
+    assert thread.is_alive()
+    assert target.getvalue() == b''
+
+    cancel.resume()
+    thread.join()
+    assert target.getvalue() == b'x' * 1000
+
+
+def test_read_errors_are_raised():
+    class BrokenSource:
+        def read(self, size):
+            raise OSError('broken')
+
+    with pytest.raises(OSError):
+        m.copy_stream(BrokenSource(), io.BytesIO())
+
@@ -80,6 +80,12 @@ Local = LocalTimezone()
 # - implement just what is needed (cons: errors?)
 
 
+class InvalidTimestamp(ValueError):
+    '''
+    A string that could not be parsed as a time.
+    '''
+
+
 _NAMED_REGEXPS = (
     ('{YEAR}',     '(?P<year>DIGIT{4})'),
     ('{MONTH}',    '(?P<month>DIGIT{2})'),
@@ -88,7 +94,13 @@ _NAMED_REGEXPS = (
     ('{MINUTE}',   '(?P<minute>DIGIT{2})'),
     ('{SECOND}',   '(?P<second>DIGIT{2})'),
     ('{MICROSEC}', '(?P<microsec>DIGIT{6})'),
+    # decimal fraction of a second, up to microsecond precision
+    ('{FRACTION}', '(?P<fraction>DIGIT{1,6})'),
     ('{TIMEZONE}', '(?P<tzsign>[-+])(?P<tzhour>DIGIT{2})(?P<tzmin>DIGIT{2})'),
+    # also Z for UTC and the extended +HH:MM format
+    (
+        '{ANY_TIMEZONE}',
+        '(?:(?P<utc>Z)|(?P<tzsign>[-+])(?P<tzhour>DIGIT{2}):?(?P<tzmin>DIGIT{2}))'),
     ('DIGIT',      '[0-9]')
 )
 
@@ -101,7 +113,7 @@ def _compile_parser(template):
     def convert(timeish: str):
         parts = match(timeish)
         if parts:
-            values = parts.groupdict()
+            values = {key: value for key, value in parts.groupdict().items() if value is not None}
 
             # v for value
             def v(key, default):
@@ -110,19 +122,33 @@ def _compile_parser(template):
                 (-1 if values.get('tzsign', '+') == '-' else 1)
                 *
                 (v('tzhour', 0) * 60 + v('tzmin', 0)))
-
-            return datetime(
-                v('year', 0),
-                v('month', 1),
-                v('day', 1),
-                v('hour', 0),
-                v('minute', 0),
-                v('second', 0),
-                v('microsec', 0),
-                FixedOffset(tzoffset, 'TZ' + str(tzoffset)))
+            microsec = v('microsec', values.get('fraction', '0').ljust(6, '0'))
+
+            try:
+                return datetime(
+                    v('year', 0),
+                    v('month', 1),
+                    v('day', 1),
+                    v('hour', 0),
+                    v('minute', 0),
+                    v('second', 0),
+                    microsec,
+                    FixedOffset(tzoffset, 'TZ' + str(tzoffset)))
+            except ValueError:
+                # well formed, but not a valid time, e.g. month 13
+                return None
     return convert
 
 
+def _parse_first(parsers, timeish):
+    if isinstance(timeish, str):
+        for parse in parsers:
+            parsed = parse(timeish)
+            if parsed is not None:
+                return parsed
+    return None
+
+
 _DEFAULT_FULL_TIMESTAMP = '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{SECOND}{MICROSEC}{TIMEZONE}'
 _parse_default_timestamp = _compile_parser(_DEFAULT_FULL_TIMESTAMP)
 
@@ -135,9 +161,10 @@ _ISO8601_PARSERS = [
         '{YEAR}-{MONTH}',
         '{YEAR}{MONTH}{DAY}',
         '{YEAR}-{MONTH}-{DAY}',
-        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{SECOND}{TIMEZONE}',
-        '{YEAR}-{MONTH}-{DAY}T{HOUR}:{MINUTE}:{SECOND}{TIMEZONE}',
-        '{YEAR}-{MONTH}-{DAY}T{HOUR}:{MINUTE}:{SECOND}.{MICROSEC}{TIMEZONE}',
+        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{SECOND}{MICROSEC}{ANY_TIMEZONE}',
+        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{SECOND}{ANY_TIMEZONE}',
+        '{YEAR}-{MONTH}-{DAY}T{HOUR}:{MINUTE}:{SECOND}{ANY_TIMEZONE}',
+        '{YEAR}-{MONTH}-{DAY}T{HOUR}:{MINUTE}:{SECOND}.{FRACTION}{ANY_TIMEZONE}',
     )]
 
 
@@ -145,11 +172,10 @@ def parse_iso8601(timeish):
     '''
         Parse some iso-8601 date/time formats to a datetime with timezone.
     '''
-    for parse in _ISO8601_PARSERS:
-        parsed = parse(timeish)
-        if parsed is not None:
-            return parsed
-    raise ValueError('Time is not in a recognised iso-8601 format', timeish)
+    parsed = _parse_first(_ISO8601_PARSERS, timeish)
+    if parsed is None:
+        raise InvalidTimestamp('Time is not in a recognised iso-8601 format', timeish)
+    return parsed
 
 
 _TIME_UNITS = {
@@ -184,6 +210,15 @@ def parse_timedelta(delta_str):
         delta_str)
 
 
+def format_timestamp(time: datetime):
+    '''
+        The canonical string form of a time zone aware datetime - parsed by time_from_timestamp.
+    '''
+    if time.utcoffset() is None:
+        raise ValueError('A timestamp needs a time zone', time)
+    return time.strftime('%Y%m%dT%H%M%S%f%z')
+
+
 def timestamp():
     '''
         A string representation of this moment.
@@ -194,7 +229,15 @@ def timestamp():
         - when parsed back, can be compared with others
           even from different time zones
     '''
-    return datetime.now(Local).strftime('%Y%m%dT%H%M%S%f%z')
+    return format_timestamp(datetime.now(Local))
+
+
+def timestamp_after(timestamp_str):
+    '''
+        The earliest timestamp later than timestamp_str (in the same time zone).
+    '''
+    next_moment = time_from_timestamp(timestamp_str) + timedelta(microseconds=1)
+    return format_timestamp(next_moment)
 
 
 # a not so forgiving parser
@@ -202,14 +245,49 @@ def time_from_timestamp(timestamp_str):
     '''
         Parse a datetime from a timestamp string - strict!
     '''
-    parsed = _parse_default_timestamp(timestamp_str)
+    parsed = _parse_first([_parse_default_timestamp], timestamp_str)
     if parsed is None:
-        raise ValueError(
+        raise InvalidTimestamp(
             'Not a full, basic timestamp (%s)' % _DEFAULT_FULL_TIMESTAMP,
             timestamp_str)
     return parsed
 
 
+# timestamps produced by earlier tools (or by hand), missing time zone or sub-second precision
+# time zone defaults to UTC
+_LEGACY_TIMESTAMP_PARSERS = [
+    _parse_default_timestamp
+] + [
+    _compile_parser(template) for template in (
+        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{SECOND}{MICROSEC}{ANY_TIMEZONE}',
+        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{SECOND}{MICROSEC}',
+        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{SECOND}{ANY_TIMEZONE}',
+        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{SECOND}',
+        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}{ANY_TIMEZONE}',
+        '{YEAR}{MONTH}{DAY}T{HOUR}{MINUTE}',
+        '{YEAR}{MONTH}{DAY}',
+    )]
+
+
+def time_from_legacy_timestamp(timestamp_str):
+    '''
+        Parse a datetime from a timestamp string, accepting legacy formats.
+
+        Missing time parts are taken as 0, a missing time zone as UTC.
+    '''
+    parsed = _parse_first(_LEGACY_TIMESTAMP_PARSERS, timestamp_str)
+    if parsed is None:
+        raise InvalidTimestamp('Not a recognised timestamp', timestamp_str)
+    return parsed
+
+
+def normalize_timestamp(timestamp_str):
+    '''
+        Convert a possibly legacy timestamp to the full, basic format.
+    '''
+    return format_timestamp(time_from_legacy_timestamp(timestamp_str))
+
+
 # The earliest time, beads could be created (actually it could be 10+ years later)
 EPOCH_STR = '20000101T000000000000+0000'
 assert time_from_timestamp(EPOCH_STR) == datetime(2000, 1, 1, 0, 0, 0, 0, FixedOffset(0, 'epoch'))
@@ -229,5 +307,5 @@ def time_from_user(timeish):
         # fall back to interpreting it as a time-delta added to `now`
         return datetime.now(Local) + parse_timedelta(timeish)
     except ValueError:
-        raise ValueError(
+        raise InvalidTimestamp(
             'Can not interpret string either as time or as delta', timeish)
@@ -0,0 +1,125 @@
+'''
+Streaming copies with flow control.
+
+Reading and writing run in separate threads connected by a bounded buffer:
+a fast source (e.g. a local disk) can not get ahead of a slow target
+(e.g. a network share) by more than the buffer, so memory use is bounded
+regardless of the file size. Copies can be paused and cancelled cleanly
+through a CancellationToken - a cancelled copy leaves no partial file behind.
+'''
+
+import os
+import queue
+import shutil
+import threading
+
+from .cancellation import CancellationToken, NEVER_CANCELLED
+from .fs import Path, removed_on_error
+
+BLOCK_SIZE = 1024 ** 2
+# at most this many blocks are buffered between reading and writing
+BUFFER_BLOCKS = 8
+# how often blocked threads check for cancellation (seconds)
+_POLL_INTERVAL = 0.1
+
+_END = None
+
+
+def _put(buffer: queue.Queue, stop: threading.Event, item) -> bool:
+    # False, if the copy stopped before item could be put
+    while not stop.is_set():
+        try:
+            buffer.put(item, timeout=_POLL_INTERVAL)
+            return True
+        except queue.Full:
+            pass
+    return False
+
+
+def _read(source, block_size, buffer: queue.Queue, stop: threading.Event, read_error: list):
+    try:
+        while not stop.is_set():
+            block = source.read(block_size)
+            if not block:
+                break
+            if not _put(buffer, stop, block):
+                return
+    except BaseException as e:
+        read_error.append(e)
+    _put(buffer, stop, _END)
+
+
+def _write(buffer: queue.Queue, target, cancel: CancellationToken) -> int:
+    copied = 0
+    while True:
+        cancel.check()
+        try:
+            block = buffer.get(timeout=_POLL_INTERVAL)
+        except queue.Empty:
+            continue
+        if block is _END:
+            return copied
+        target.write(block)
+        copied += len(block)
+
+
+def copy_stream(
+        source, target,
+        cancel: CancellationToken = NEVER_CANCELLED,
+        block_size=BLOCK_SIZE, buffer_blocks=BUFFER_BLOCKS) -> int:
+    '''
+    Copy file object source to file object target, return the number of bytes copied.
+
+    Raises Cancelled (or the error of reading) - target is then left incomplete.
+    '''
+    buffer: queue.Queue = queue.Queue(maxsize=buffer_blocks)
+    stop = threading.Event()
+    read_error: list = []
+    reader = threading.Thread(
+        target=_read, args=(source, block_size, buffer, stop, read_error),
+        name='bead-transfer-reader', daemon=True)
+    reader.start()
+    try:
+        copied = _write(buffer, target, cancel)
+    finally:
+        stop.set()
+        reader.join()
+    if read_error:
+        raise read_error[0]
+    return copied
+
+
+class _HashingReader:
+    '''
+    File object reading from file, while feeding what is read to hasher.
+    '''
+
+    def __init__(self, file, hasher):
+        self.file = file
+        self.hasher = hasher
+
+    def read(self, size=-1):
+        block = self.file.read(size)
+        self.hasher.update(block)
+        return block
+
+
+def copy_file(
+        source_path: Path, target_path: Path,
+        cancel: CancellationToken = NEVER_CANCELLED,
+        block_size=BLOCK_SIZE, buffer_blocks=BUFFER_BLOCKS, hasher=None) -> int:
+    '''
+    Copy file with its modification time - atomically: target_path appears only when complete.
+
+    hasher (e.g. hashlib.sha256()) is updated with the content read from source_path.
+    '''
+    target_path = Path(target_path)
+    temp_path = target_path.with_name(f'.{target_path.name}.{os.getpid()}.partial')
+    with removed_on_error(temp_path):
+        with open(source_path, 'rb') as source, open(temp_path, 'wb') as target:
+            if hasher is not None:
+                source = _HashingReader(source, hasher)
+            copied = copy_stream(source, target, cancel, block_size, buffer_blocks)
+        shutil.copystat(source_path, temp_path)
+        os.replace(temp_path, target_path)
+    return copied
@@ -0,0 +1,139 @@
+'''
+Workspace templates - skeletons of new workspaces (e.g. Makefile, src/, README),
+so that the projects of a lab share their structure.
+
+A template is a directory, whose content is copied into new workspaces,
+or one of the BUILTIN_TEMPLATES.
+The directories managed by bead (input/, output/, temp/, .bead-meta/) are not copied.
+'''
+
+import os
+import shutil
+from typing import Dict, Optional, Union
+
+from . import layouts
+from .tech import fs
+
+_README = '''\
+# {name}
+
+    input/   data of other beads, loaded by `bead input` - read-only
+    output/  results, saved with the bead
+    temp/    scratch files, not saved
+'''
+
+_MAKEFILE = '''\
+# `make` recomputes the output from the inputs
+.PHONY: all
+all:
+\tpython src/main.py
+'''
+
+_MAIN = '''\
+"""
+Compute output/ from input/.
+"""
+'''
+
+_R_MAKEFILE = '''\
+# `make` recomputes the output from the inputs
+# `make restore` installs the R packages pinned in renv.lock (see `renv::snapshot()`)
+.PHONY: all restore
+all:
+\tRscript src/main.R
+restore:
+\tRscript -e 'renv::restore()'
+'''
+
+_R_PROFILE = '''\
+if (file.exists("renv/activate.R")) source("renv/activate.R")
+'''
+
+_R_MAIN = '''\
+# Compute output/ from input/.
+'''
+
+_STATA_MAKEFILE = '''\
+# `make` recomputes the output from the inputs, Stata writes its log to the current
+# directory, so it is moved to temp/
+.PHONY: all
+all:
+\tstata -b do src/main.do
+\tmv main.log temp/
+'''
+
+_STATA_MAIN = '''\
+* Compute output/ from input/.
+version 16
+'''
+
+# name -> {path: content}, {name} in the content is replaced by the name of the workspace
+BUILTIN_TEMPLATES: Dict[str, Dict[str, str]] = {
+    'minimal': {'README.md': _README},
+    'make': {'README.md': _README, 'Makefile': _MAKEFILE, 'src/main.py': _MAIN},
+    'r': {
+        'README.md': _README, 'Makefile': _R_MAKEFILE, '.Rprofile': _R_PROFILE,
+        'src/main.R': _R_MAIN},
+    'stata': {'README.md': _README, 'Makefile': _STATA_MAKEFILE, 'src/main.do': _STATA_MAIN},
+}
+
+_BEAD_DIRECTORIES = {
+    layouts.Workspace.INPUT.as_posix(),
+    layouts.Workspace.OUTPUT.as_posix(),
+    layouts.Workspace.TEMP.as_posix(),
+    layouts.Workspace.META.as_posix()}
+
+# a template directory or the name of a built-in template
+Template = Union[fs.Path, str]
+
+
+def resolve(name: str, template_directory: Optional[fs.Path] = None) -> Template:
+    '''
+    The template name refers to - a directory, a template in template_directory or a built-in.
+
+    Raises LookupError for unknown templates.
+    '''
+    if os.path.isdir(name):
+        return fs.Path(name)
+    if template_directory is not None and (fs.Path(template_directory) / name).is_dir():
+        return fs.Path(template_directory) / name
+    if name in BUILTIN_TEMPLATES:
+        return name
+    raise LookupError(name)
+
+
+def available(template_directory: Optional[fs.Path] = None) -> Dict[str, Template]:
+    '''
+    Templates by name - the ones in template_directory hide built-ins of the same name.
+    '''
+    templates: Dict[str, Template] = {name: name for name in BUILTIN_TEMPLATES}
+    if template_directory is not None:
+        try:
+            names = os.listdir(template_directory)
+        except OSError:
+            names = []
+        for name in names:
+            path = fs.Path(template_directory) / name
+            if path.is_dir():
+                templates[name] = path
+    return dict(sorted(templates.items()))
+
+
+def copy(template: Template, directory: fs.Path, name: str):
+    '''
+    Copy the files of template into the workspace directory of the bead name.
+    '''
+    if isinstance(template, str):
+        for path, content in BUILTIN_TEMPLATES[template].items():
+            target = directory / path
+            fs.ensure_directory(target.parent)
+            fs.write_file(target, content.replace('{name}', name))
+        return
+    for entry in sorted(os.listdir(template)):
+        if entry in _BEAD_DIRECTORIES:
+            continue
+        source = fs.Path(template) / entry
+        if source.is_dir():
+            shutil.copytree(source, directory / entry)
+        else:
+            shutil.copy(source, directory / entry)
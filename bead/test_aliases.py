@@ -0,0 +1,37 @@
+import pytest
+
+from . import aliases as m
+
+
+def test_aliases_are_found_in_parent_directories(tmp_path):
+    """Test that the nearest bead names file is used."""
+    (tmp_path / m.ALIASES_FILE).write_text('population = "census-2020"\n')
+    workspace_dir = tmp_path / 'project/workspace'
+    workspace_dir.mkdir(parents=True)
+
+    assert {'population': m.Alias('population', 'census-2020')} == m.aliases_for(workspace_dir)
+
+    (tmp_path / 'project' / m.ALIASES_FILE).write_text('gdp = "worldbank-gdp"\n')
+    assert ['gdp'] == list(m.aliases_for(workspace_dir))
+
+
+def test_alias_tables(tmp_path):
+    """Test aliases restricted to a kind."""
+    path = tmp_path / m.ALIASES_FILE
+    path.write_text('[gdp]\nbead = "worldbank-gdp"\nkind = "gdp-kind"\n')
+
+    assert {'gdp': m.Alias('gdp', 'worldbank-gdp', 'gdp-kind')} == m.load_aliases(path)
+
+
+def test_no_aliases_file(tmp_path):
+    assert {} == m.aliases_for(tmp_path)
+
+
+@pytest.mark.parametrize('content', ['population = ', 'population = 1', '[gdp]\nkind = "k"'])
+def test_invalid_aliases_file(tmp_path, content):
+    """Test that mistakes in the shared file are reported as user errors."""
+    path = tmp_path / m.ALIASES_FILE
+    path.write_text(content)
+
+    with pytest.raises(m.AliasError):
+        m.load_aliases(path)
@@ -1,9 +1,17 @@
+import json
 import os
 import zipfile
 import pytest
 
 from . import archive as m
 from . import layouts
+from . import tech
+from . import ziparchive
+from . import zipopener
+from .exceptions import ContentMismatch, UnsupportedMetaVersion
+from .tech.cancellation import CancellationToken, Cancelled
+from .tech.fs import ensure_directory, write_file
+from .workspace import META_VERSION, Workspace
 
 
 @pytest.fixture
@@ -58,6 +66,32 @@ def test_extract_dir(bead_archive, tmp_path):
     assert content == b'''file1's known content'''
 
 
+def test_extract_dir_with_patterns(bead_archive, tmp_path):
+    """Test that only the files selected by the patterns are extracted."""
+    extracted_dir = tmp_path / 'destination dir'
+    bead = m.Archive(bead_archive)
+
+    assert 2 == bead.extract_dir('path', extracted_dir, patterns=['to/*2', 'file1'])
+
+    assert {'file1', 'to'} == set(os.listdir(extracted_dir))
+    assert ['file2'] == os.listdir(extracted_dir / 'to')
+
+
+@pytest.mark.parametrize(
+    'path, patterns, selected', [
+        ('tables/a.csv', ['tables'], True),
+        ('tables/a.csv', ['tables/'], True),
+        ('tables/a.csv', ['./tables/*.csv'], True),
+        ('tables/2020/a.csv', ['tables/20*'], True),
+        ('tables/a.json', ['tables/*.csv'], False),
+        ('tables2/a.csv', ['tables'], False),
+        ('a.csv', ['*.CSV'], False),
+        ('a.csv', ['b.csv', 'a.csv'], True),
+    ])
+def test_is_selected(path, patterns, selected):
+    assert selected == ziparchive.is_selected(path, patterns)
+
+
 def test_extract_nonexistent_dir(bead_archive, tmp_path):
     """Test extracting a non-existent directory creates an empty directory."""
     # when a nonexistent directory is extracted
@@ -72,6 +106,236 @@ def test_extract_nonexistent_dir(bead_archive, tmp_path):
     assert [] == files
 
 
+@pytest.fixture
+def big_bead_archive(tmp_path_factory):
+    """Create a test bead with a data directory of many files and a valid manifest."""
+    workspace = Workspace(tmp_path_factory.mktemp('big_bead') / 'big_bead')
+    workspace.create('TEST-BIG')
+    for i in range(50):
+        ensure_directory(workspace.directory / f'output/dir{i % 7}')
+        write_file(workspace.directory / f'output/dir{i % 7}/file{i}', f'content {i}' * i)
+    bead_path = tmp_path_factory.mktemp('big_bead_archive') / 'big_bead.zip'
+    workspace.pack(bead_path, '20200913T173910000000+0000', 'big bead')
+    return bead_path
+
+
+@pytest.mark.parametrize('threads', ['1', '4'])
+def test_extract_dir_with_threads(big_bead_archive, tmp_path, monkeypatch, threads):
+    """Test extracting a data directory by multiple threads."""
+    monkeypatch.setenv(ziparchive.EXTRACT_THREADS_ENV_VAR, threads)
+    monkeypatch.setattr(ziparchive, 'PARALLEL_EXTRACT_MIN_SIZE', 0)
+
+    bead = m.Archive(big_bead_archive)
+    bead.unpack_data_to(tmp_path / 'data')
+
+    for i in range(50):
+        assert (tmp_path / f'data/dir{i % 7}/file{i}').read_text() == f'content {i}' * i
+
+
+def test_extract_dir_with_threads_detects_damage(tmp_path, monkeypatch):
+    """Test that errors in extracting threads are reported."""
+    monkeypatch.setenv(ziparchive.EXTRACT_THREADS_ENV_VAR, '4')
+    monkeypatch.setattr(ziparchive, 'PARALLEL_EXTRACT_MIN_SIZE', 0)
+    bead_path = tmp_path / 'damaged.zip'
+    with zipfile.ZipFile(bead_path, 'w') as z:
+        z.writestr(
+            layouts.Archive.BEAD_META,
+            b'''{
+                "meta_version": "aaa947a6-1f7a-11e6-ba3a-0021cc73492e",
+                "kind": "TEST-FAKE",
+                "freeze_time": "20200913T173910000000+0000",
+                "inputs": {}
+            }''')
+        z.writestr('data/file1', b'file1')
+        z.writestr('data/file2', b'file2')
+        z.writestr(
+            layouts.Archive.MANIFEST,
+            json.dumps({
+                'data/file1': tech.securehash.bytes(b'file1'),
+                'data/file2': tech.securehash.bytes(b'not file2')}))
+
+    bead = m.Archive(bead_path)
+    with pytest.raises(ContentMismatch):
+        bead.unpack_data_to(tmp_path / 'data')
+
+
+def test_extract_file_cancelled_leaves_no_partial_file(bead_archive, tmp_path):
+    """Test that a cancelled extraction does not leave a partial file."""
+    cancel = CancellationToken()
+    cancel.cancel()
+    bead = m.Archive(bead_archive)
+
+    with pytest.raises(Cancelled):
+        bead.extract_file('path/to/file1', tmp_path / 'file1', cancel=cancel)
+
+    assert not (tmp_path / 'file1').exists()
+
+
+def test_extract_dir_with_threads_cancelled(big_bead_archive, tmp_path, monkeypatch):
+    """Test that cancellation stops all extracting threads."""
+    monkeypatch.setenv(ziparchive.EXTRACT_THREADS_ENV_VAR, '4')
+    monkeypatch.setattr(ziparchive, 'PARALLEL_EXTRACT_MIN_SIZE', 0)
+    cancel = CancellationToken()
+    cancel.cancel()
+
+    bead = m.Archive(big_bead_archive)
+    with pytest.raises(Cancelled):
+        bead.unpack_data_to(tmp_path / 'data', cancel=cancel)
+
+    assert [p for p in (tmp_path / 'data').glob('**/*') if p.is_file()] == []
+
+
+def test_read_bead_meta(big_bead_archive):
+    """Test reading the bead meta without an Archive."""
+    bead_meta = m.read_bead_meta(big_bead_archive)
+
+    assert 'TEST-BIG' == bead_meta['kind']
+    assert m.Archive(big_bead_archive).meta_version == bead_meta['meta_version']
+
+
+def test_peek_archive_summary(big_bead_archive):
+    """Test that the summary agrees with the Archive."""
+    summary = m.peek_archive_summary(big_bead_archive)
+    archive = m.Archive(big_bead_archive)
+
+    assert archive.name == summary.name
+    assert archive.kind == summary.kind
+    assert archive.content_id == summary.content_id
+    assert archive.freeze_time_str == summary.freeze_time_str
+    assert archive.inputs == summary.inputs
+
+
+def _without_manifest(archive_path, path):
+    with zipfile.ZipFile(archive_path) as source, zipfile.ZipFile(path, 'w') as target:
+        for info in source.infolist():
+            if info.filename != layouts.Archive.MANIFEST:
+                target.writestr(info, source.read(info))
+    return path
+
+
+def test_rebuilt_manifest_of_intact_archive_is_the_original(big_bead_archive):
+    """Test that rehashing the entries gives back the manifest."""
+    bead = ziparchive.ZipArchive(big_bead_archive)
+
+    assert bead.has_intact_manifest
+    assert bead.manifest == bead.rebuild_manifest()
+
+
+def test_repair_missing_manifest(big_bead_archive, tmp_path):
+    """Test that a repaired manifest restores verification and the content id."""
+    content_id = m.Archive(big_bead_archive).content_id
+    broken_path = _without_manifest(big_bead_archive, tmp_path / 'big_bead.zip')
+    broken_bytes = broken_path.read_bytes()
+    broken = ziparchive.ZipArchive(broken_path)
+    assert not broken.has_intact_manifest
+    assert [ziparchive.Damage(layouts.Archive.MANIFEST, ziparchive.MISSING)] == broken.check()
+
+    sidecar = broken.repair_manifest()
+
+    assert ziparchive.manifest_sidecar_path(broken_path) == sidecar
+    assert broken_bytes == broken_path.read_bytes()
+    repaired = ziparchive.ZipArchive(broken_path)
+    assert [] == repaired.check()
+    assert content_id == repaired.content_id
+    assert content_id == m.peek_archive_summary(broken_path).content_id
+
+
+def test_meta_cache_is_written_for_box_archives_only(big_bead_archive):
+    """Test that reading the meta of an archive in a box populates its cache file."""
+    archive = m.Archive(big_bead_archive)
+    archive.content_id
+    assert not archive.cache_path.exists()
+
+    archive = m.Archive(big_bead_archive, box_name='box')
+    archive.content_id
+    cache = m.read_cache(big_bead_archive)
+    assert cache is not None
+    assert archive.content_id == cache[m.CACHE_CONTENT_ID]
+    assert os.path.getsize(big_bead_archive) == cache[m.CACHE_FILE_SIZE]
+
+
+def test_meta_cache_of_changed_archive_is_stale(big_bead_archive):
+    """Test that modifying the zip invalidates its cache."""
+    m.Archive(big_bead_archive, box_name='box').content_id
+    stat = os.stat(big_bead_archive)
+    os.utime(big_bead_archive, ns=(stat.st_atime_ns, stat.st_mtime_ns + 1))
+
+    assert m.read_cache(big_bead_archive) is None
+
+
+def test_legacy_meta_cache_is_stamped_when_read(big_bead_archive):
+    """Test that a cache without size and mtime is trusted only until the zip changes."""
+    m.Archive(big_bead_archive, box_name='box').content_id
+    cache_path = big_bead_archive.with_suffix('.xmeta')
+    legacy_cache = tech.persistence.file_load(cache_path)
+    del legacy_cache[m.CACHE_FILE_SIZE]
+    del legacy_cache[m.CACHE_MTIME]
+    tech.persistence.file_dump(legacy_cache, cache_path)
+    assert m.read_cache(big_bead_archive) is None
+
+    m.Archive(big_bead_archive, box_name='box')
+    assert m.read_cache(big_bead_archive) is not None
+
+    stat = os.stat(big_bead_archive)
+    os.utime(big_bead_archive, ns=(stat.st_atime_ns, stat.st_mtime_ns + 1))
+    assert m.read_cache(big_bead_archive) is None
+
+
+def test_provenance_is_read_from_meta_cache(big_bead_archive, monkeypatch):
+    m.Archive(big_bead_archive, box_name='box').content_id
+
+    def no_zip(*args):
+        raise AssertionError('zip opened')
+    monkeypatch.setattr(m, 'ZipArchive', no_zip)
+    assert m.Archive(big_bead_archive, box_name='box').provenance is None
+
+
+def test_cached_entry_table_skips_parsing_the_zip_directory(
+        big_bead_archive, tmp_path, monkeypatch):
+    """Test that big archives are opened again with their entry table from the cache."""
+    monkeypatch.setattr(m, 'CACHE_ENTRIES_MIN_COUNT', 10)
+    m.Archive(big_bead_archive, box_name='box').content_id
+    assert m.CACHE_ENTRIES not in m.read_cache(big_bead_archive)
+    entries_path = big_bead_archive.with_suffix(m.ENTRIES_SUFFIX)
+    assert len(tech.persistence.file_load(entries_path)[m.CACHE_ENTRIES]) > 50
+    zipopener.close_all()
+    zipopener.stats.clear()
+
+    archive = m.Archive(big_bead_archive, box_name='box')
+    archive.unpack_data_to(tmp_path / 'data')
+
+    assert 1 == zipopener.stats['cached']
+    assert 0 == zipopener.stats['parsed']
+    assert 'content 3' * 3 == (tmp_path / 'data/dir3/file3').read_text()
+    assert [] == archive.check()
+    zipopener.close_all()
+
+    # a changed zip is parsed again
+    stat = os.stat(big_bead_archive)
+    os.utime(big_bead_archive, ns=(stat.st_atime_ns, stat.st_mtime_ns + 1))
+    zipopener.stats.clear()
+    m.Archive(big_bead_archive, box_name='box').ziparchive
+    assert 0 == zipopener.stats['cached']
+    assert 1 == zipopener.stats['parsed']
+
+
+def test_entry_table_is_not_cached_for_small_archives(big_bead_archive):
+    """Test that archives below the threshold are cached without their entries."""
+    m.Archive(big_bead_archive, box_name='box').ziparchive
+
+    assert not big_bead_archive.with_suffix(m.ENTRIES_SUFFIX).exists()
+
+
+@pytest.mark.parametrize('read', [m.read_bead_meta, m.peek_archive_summary])
+def test_reading_meta_of_non_archive_fails(tmp_path, read):
+    """Test that broken files are reported as InvalidArchive."""
+    path = tmp_path / 'broken.zip'
+    path.write_bytes(b'not a zip')
+
+    with pytest.raises(m.InvalidArchive):
+        read(path)
+
+
 def test_content_id(bead_archive):
     """Test that content_id returns a string."""
     # when content_id is checked
@@ -80,3 +344,92 @@ def test_content_id(bead_archive):
 
     # then content_id is a string
     assert isinstance(content_id, str)
+
+
+INPUT_CONTENT_ID = 'a' * 128
+
+
+def _write_foreign_archive(path, meta_version=META_VERSION):
+    '''
+    An archive as written by another implementation of the format:
+    meta and manifest in another key order and JSON formatting.
+    '''
+    code = b'print("hello")\n'
+    bead_meta = json.dumps({
+        'freeze_name': 'foreign',
+        'inputs': {
+            'data': {
+                'kind': 'input-kind',
+                'content_id': INPUT_CONTENT_ID,
+                'freeze_time': '20200101T000000000000+0000'}},
+        'kind': 'foreign-kind',
+        'freeze_time': '20200913T173910123456+0200',
+        'meta_version': meta_version,
+    }).encode('utf-8')
+    manifest = {
+        'meta/bead': tech.securehash.bytes(bead_meta),
+        'code/run.py': tech.securehash.bytes(code),
+    }
+    with zipfile.ZipFile(path, 'w') as z:
+        z.writestr('code/run.py', code)
+        z.writestr(layouts.Archive.BEAD_META, bead_meta)
+        z.writestr(layouts.Archive.MANIFEST, json.dumps(manifest, separators=(',', ':')))
+    return path
+
+
+def test_archive_of_other_implementation_is_readable(tmp_path):
+    path = _write_foreign_archive(tmp_path / 'foreign_20200913T173910123456+0200.zip')
+    archive = m.Archive(path)
+
+    archive.validate()
+    assert 'foreign' == archive.name
+    assert 'foreign-kind' == archive.kind
+    assert '20200913T173910123456+0200' == archive.freeze_time_str
+    [input] = archive.inputs
+    assert ('data', 'input-kind', INPUT_CONTENT_ID) == (input.name, input.kind, input.content_id)
+    with open(path, 'rb') as f:
+        with zipfile.ZipFile(f) as z:
+            manifest_bytes = z.read(layouts.Archive.MANIFEST)
+    assert tech.securehash.bytes(manifest_bytes) == archive.content_id
+
+    summary = m.peek_archive_summary(path)
+    assert archive.content_id == summary.content_id
+    assert archive.inputs == summary.inputs
+    assert 'foreign-kind' == m.read_bead_meta(path)['kind']
+
+
+def test_archive_of_other_implementation_can_be_developed(tmp_path):
+    path = _write_foreign_archive(tmp_path / 'foreign_20200913T173910123456+0200.zip')
+
+    workspace = Workspace.from_archive(m.Archive(path), tmp_path / 'developed')
+
+    assert 'foreign-kind' == workspace.kind
+    assert ['data'] == [input.name for input in workspace.inputs]
+    workspace.pack(tmp_path / 'repacked.zip', '20210101T000000000000+0000', 'comment')
+    repacked = m.Archive(tmp_path / 'repacked.zip')
+    repacked.validate()
+    assert m.Archive(path).inputs == repacked.inputs
+
+
+def test_unknown_meta_version_is_refused(tmp_path):
+    path = _write_foreign_archive(tmp_path / 'future_20200913T173910123456+0200.zip', 'v2')
+
+    with pytest.raises(UnsupportedMetaVersion):
+        m.Archive(path).content_id
+
+
+def test_inputs_by_name(tmp_path):
+    ws = Workspace(tmp_path / 'ws')
+    ws.create('kind')
+    ws.add_input('input1', 'kind1', 'content-id-1', '20200101T000000000000+0000')
+    ws.set_input_bead_name('input1', 'bead-of-input1')
+    ws.pack(tmp_path / 'bead.zip', '20200102T000000000000+0000', comment='')
+
+    archive = m.Archive(tmp_path / 'bead.zip')
+
+    assert archive.has_input('input1')
+    assert not archive.has_input('input2')
+    assert 'content-id-1' == archive.get_input('input1').content_id
+    assert archive.get_input('input2') is None
+    assert 'bead-of-input1' == archive.get_input_bead_name('input1')
+    assert 'input2' == archive.get_input_bead_name('input2')
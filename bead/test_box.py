@@ -1,9 +1,28 @@
+from concurrent.futures import ThreadPoolExecutor
+from datetime import timedelta
+import hashlib
+import os
+import socket
+import subprocess
+import sys
+import threading
+import time
+import warnings
+import zipfile
+
 import pytest
-from .box import Box
-from .tech.fs import write_file, rmtree
+from .archive import CACHE_ARCHIVE_SHA256
+from .box import Box, CollectedGarbage, SyncReport, UnionBox, DEFAULT_LEFTOVER_AGE
+from .box import CONFLICT_ERROR, CONFLICT_NEWEST, CONFLICT_PRIORITY, LOCK_FILE
+from .box import NAME_MATCHING_CI
+from .box_index import BoxIndex, INDEX_FILE
+from .exceptions import AmbiguousBead, BoxError, InvalidArchive, TransferError
+from .tech.fs import write_file, rmtree, PermissionsNotSupported
+from .tech.lock import FileLock
 from .tech.timestamp import time_from_user
 from .workspace import Workspace
 from . import spec as bead_spec
+from . import tech as bead_tech
 
 
 @pytest.fixture
@@ -66,6 +85,48 @@ def test_find_with_uppercase_name(box, timestamp):
     assert 'BEAD3' == best_name
 
 
+def test_names_are_matched_strictly_by_default(box, timestamp):
+    """Test that names differing only in case are not found - whatever the file system."""
+    assert [] == box.find_name_collisions('bead3', 'other-kind')
+    with pytest.raises(LookupError):
+        box.get_context(bead_spec.BEAD_NAME, 'bead3', timestamp)
+
+
+def test_case_insensitive_name_matching(box, timestamp):
+    """Test that boxes configured to ignore case find names in any case."""
+    ci_box = Box('test', box.directory, name_matching=NAME_MATCHING_CI)
+
+    assert 'BEAD3' == ci_box.get_context(bead_spec.BEAD_NAME, 'bead3', timestamp).best.name
+    assert ['BEAD3'] == [b.name for b in ci_box.find_name_collisions('Bead3', 'other-kind')]
+    bead3 = ci_box.get_context(bead_spec.BEAD_NAME, 'BEAD3', timestamp).best
+    assert 'BEAD3' == ci_box.find_bead('bead3', bead3.content_id).name
+
+
+def test_is_case_sensitive_leaves_no_probe_behind(box):
+    before = set(os.listdir(box.directory))
+    assert box.is_case_sensitive() in (True, False)
+    assert before == set(os.listdir(box.directory))
+
+
+def test_find_matching_glob(box):
+    assert ['bead1', 'bead2'] == [b.name for b in box.find_matching('bead?')]
+    assert ['BEAD3'] == [b.name for b in box.find_matching('*3')]
+
+
+def test_find_matching_regex_matches_kinds_too(box):
+    assert ['bead1', 'bead2'] == [b.name for b in box.find_matching(r'test-bead[12]', regex=True)]
+    assert [] == box.find_matching('bead', regex=True)
+
+
+def test_find_matching_invalid_regex(box):
+    with pytest.raises(ValueError):
+        box.find_matching('bead[', regex=True)
+
+
+def test_union_box_find_matching(box):
+    assert ['BEAD3'] == [b.name for b in UnionBox([box]).find_matching('BEAD*')]
+
+
 def test_box_methods_tolerate_junk_in_box(tmp_path_factory):
     """Test that box methods work even with junk files present."""
     temp_dir = tmp_path_factory.mktemp("box_junk")
@@ -86,3 +147,601 @@ def test_box_methods_tolerate_junk_in_box(tmp_path_factory):
 
     bead_names = set(b.name for b in box.all_beads())
     assert set(['bead1', 'bead2', 'BEAD3']) == bead_names
+
+
+def test_check(box):
+    """Test that check validates every archive, including unreadable ones."""
+    write_file(box.directory / 'junk.zip', 'not a zip')
+
+    results = {path.name: damages for path, damages in box.check()}
+
+    assert 4 == len(results)
+    assert results.pop('junk.zip')
+    assert all(damages == [] for damages in results.values())
+
+
+def test_rejected_store_leaves_nothing_behind(box, tmp_path):
+    ws = Workspace(tmp_path / 'rejected')
+    ws.create('kind')
+    staged_paths = []
+
+    def reject(path):
+        staged_paths.append(path)
+        assert path.exists()
+        raise ValueError('rejected')
+
+    with pytest.raises(ValueError):
+        box.store(ws, '20160704T000000000000+0200', validate=reject)
+
+    assert not staged_paths[0].exists()
+    assert [] == list(box.directory.glob('rejected*'))
+
+
+def test_store_does_not_overwrite_archive_with_the_same_freeze_time(box, tmp_path):
+    """Test that a taken freeze time is replaced by a later one."""
+    ws = Workspace(tmp_path / 'bead1')
+    ws.create('test-bead1')
+    write_file(ws.directory / 'output/data', 'new content')
+
+    archive = box.store(ws, '20160704T000000000000+0200')
+
+    assert 'bead1_20160704T000000000001+0200.zip' == archive.archive_path.name
+    assert '20160704T000000000001+0200' == archive.freeze_time_str
+    assert 'test' == archive.box_name
+    assert 2 == len([bead for bead in box.all_beads() if bead.name == 'bead1'])
+
+
+def test_concurrent_stores_of_the_same_name(box, tmp_path):
+    """Stress test: simultaneous saves of the same name all end up in the box and its index."""
+    count = 8
+    barrier = threading.Barrier(count)
+    workspaces = []
+    for i in range(count):
+        ws = Workspace(tmp_path / f'user{i}' / 'shared')
+        ws.create('shared-kind')
+        write_file(ws.directory / 'output/data', f'content of user {i}')
+        workspaces.append(ws)
+
+    def store(ws):
+        barrier.wait()
+        return box.store(ws, '20200101T000000000000+0000').archive_path
+
+    with ThreadPoolExecutor(max_workers=count) as executor:
+        paths = list(executor.map(store, workspaces))
+
+    assert count == len(set(paths))
+    beads = [bead for bead in box.all_beads() if bead.name == 'shared']
+    assert count == len(set(bead.freeze_time_str for bead in beads))
+    indexed = BoxIndex.load(box.directory).entries
+    assert all(path.name in indexed for path in paths)
+    contents = set()
+    for path in paths:
+        with zipfile.ZipFile(path) as z:
+            contents.add(z.read('data/data'))
+    assert count == len(contents)
+    assert not (box.directory / LOCK_FILE).exists()
+    assert [] == list(box.directory.glob('*.staged'))
+
+
+def test_find_name_collisions(box):
+    """Test that beads with the same name, but different kind are reported."""
+    assert [] == box.find_name_collisions('bead1', 'test-bead1')
+    assert [] == box.find_name_collisions('new-bead', 'test-bead1')
+
+    collisions = box.find_name_collisions('bead1', 'another kind')
+    assert ['bead1'] == [bead.name for bead in collisions]
+
+
+def _archive_filename(box, name):
+    [bead] = [bead for bead in box.all_beads() if bead.name == name]
+    return bead.archive_path.name
+
+
+def test_deleted_bead_is_not_found_but_restorable(box):
+    """Test soft delete and restore round trip."""
+    filename = _archive_filename(box, 'bead1')
+
+    box.delete(filename)
+    assert 'bead1' not in set(b.name for b in box.all_beads())
+    assert [filename] == [path.name for _, path in box.deleted_archives()]
+
+    box.restore(filename)
+    assert 'bead1' in set(b.name for b in box.all_beads())
+    assert [] == box.deleted_archives()
+
+
+def test_restored_bead_is_indexed_again(box, monkeypatch):
+    """Test that a restored archive is found through the index, without scanning the box."""
+    [bead1] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    box.delete(bead1.archive_path.name)
+    assert bead1.archive_path.name not in BoxIndex.load(box.directory).entries
+
+    box.restore(bead1.archive_path.name)
+
+    def no_scanning(*args):
+        raise AssertionError('box was scanned')
+    monkeypatch.setattr(Box, '_archives_from', no_scanning)
+    assert bead1.archive_path == box.find_bead('bead1', bead1.content_id).archive_path
+
+
+def test_delete_purges_archives_deleted_before_retention(box):
+    """Test that old deletions are purged for good."""
+    box.delete(_archive_filename(box, 'bead1'))
+    box.delete(_archive_filename(box, 'bead2'), retention=timedelta(seconds=-1))
+
+    assert [] == box.deleted_archives()
+
+
+def test_delete_missing_archive_is_an_error(box):
+    """Test that deleting a non-existing archive fails."""
+    with pytest.raises(BoxError):
+        box.delete('no-such-bead_20160704T000000000000+0200.zip')
+    with pytest.raises(BoxError):
+        box.restore('no-such-bead_20160704T000000000000+0200.zip')
+
+
+def test_remove_bead_removes_archive_cache_and_index_entry(box):
+    """Test that a removed bead leaves nothing behind."""
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    bead.save_cache()
+    assert bead.cache_path.exists()
+
+    assert [bead.archive_path] == box.remove_bead('bead1', bead.content_id)
+
+    assert not bead.archive_path.exists()
+    assert not bead.cache_path.exists()
+    assert bead.archive_path.name not in BoxIndex.load(box.directory).entries
+    assert 'bead1' not in set(b.name for b in box.all_beads())
+    assert 'bead2' in set(b.name for b in box.all_beads())
+
+
+def test_remove_bead_and_gc_wait_for_the_box_lock(box, monkeypatch):
+    """Test that permanent removals do not interleave with other modifications."""
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    monkeypatch.setattr(Box, 'lock', lambda self: FileLock(self.directory / LOCK_FILE, 0))
+
+    with FileLock(box.directory / LOCK_FILE):
+        with pytest.raises(BoxError, match='locked'):
+            box.remove_bead('bead1', bead.content_id)
+        with pytest.raises(BoxError, match='locked'):
+            box.gc()
+
+    assert bead.archive_path.exists()
+    assert bead.archive_path.name in BoxIndex.load(box.directory).entries
+
+
+def test_remove_bead_needs_exact_content_id(box):
+    """Test that content id prefixes do not remove beads."""
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    with pytest.raises(BoxError):
+        box.remove_bead('bead1', bead.content_id[:10])
+    with pytest.raises(BoxError):
+        box.remove_bead('bead2', bead.content_id)
+    assert bead.archive_path.exists()
+
+
+def test_find_by_tag(box, tmp_path):
+    ws = Workspace(tmp_path / 'tagged')
+    ws.create('kind')
+    ws.add_tag('sales')
+    box.store(ws, '20160704T000000000000+0200')
+
+    assert ['tagged'] == [bead.name for bead in box.find_by_tag('sales')]
+    assert ['tagged'] == [bead.name for bead in UnionBox([box]).find_by_tag('sales')]
+    assert [] == box.find_by_tag('finance')
+
+
+def test_read_only_box_refuses_writes(box, tmp_path):
+    """Test that a read-only box can be searched, but not changed."""
+    # as if the archives were copied into the box by hand
+    for path in box.directory.glob('*.xmeta'):
+        os.remove(path)
+    os.remove(box.directory / INDEX_FILE)
+    read_only_box = Box('test', box.directory, read_only=True)
+    before = set(os.listdir(box.directory))
+    [bead] = [bead for bead in read_only_box.all_beads() if bead.name == 'bead1']
+    ws = Workspace(tmp_path / 'ws')
+    ws.create('kind')
+
+    with pytest.raises(BoxError, match='read-only'):
+        read_only_box.store(ws, '20160704T000000000000+0200')
+    with pytest.raises(BoxError, match='read-only'):
+        read_only_box.remove_bead('bead1', bead.content_id)
+    with pytest.raises(BoxError, match='read-only'):
+        read_only_box.gc()
+    with pytest.raises(BoxError, match='read-only'):
+        read_only_box.delete(bead.archive_path.name)
+    with pytest.raises(BoxError, match='read-only'):
+        read_only_box.set_alias('latest', bead.kind)
+    # only aliases defined in the box are removed from it
+    assert not read_only_box.remove_alias('latest')
+
+    assert before == set(os.listdir(box.directory))
+    assert read_only_box.repair(dry_run=True).is_clean
+
+
+def test_gc_removes_orphans(box):
+    """Test that gc cleans up after archives removed by hand."""
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    bead.save_cache()
+    os.remove(bead.archive_path)
+    assert bead.archive_path.name in BoxIndex.load(box.directory).entries
+
+    garbage = box.gc()
+
+    assert [bead.cache_path] == garbage.files
+    assert [bead.archive_path.name] == garbage.index_entries
+    assert not bead.cache_path.exists()
+    assert bead.archive_path.name not in BoxIndex.load(box.directory).entries
+    assert box.gc() == CollectedGarbage([], [])
+
+
+def test_box_scan_uses_meta_caches_instead_of_zips(box, monkeypatch):
+    """Test that archives with up to date meta caches are indexed without opening them."""
+    expected = sorted(bead.content_id for bead in box.all_beads())
+    os.remove(box.directory / INDEX_FILE)
+
+    def no_zip_reading(path):
+        # the workspaces of the beads are also in the box directory
+        assert path.suffix != '.zip', f'{path} was opened'
+        raise InvalidArchive(path=path)
+    monkeypatch.setattr('bead.box.peek_archive_summary', no_zip_reading)
+
+    assert expected == sorted(bead.content_id for bead in box.all_beads())
+
+
+def test_sync_copies_missing_archives(box, tmp_path):
+    mirror = Box('mirror', tmp_path)
+
+    report = mirror.sync_from(box)
+
+    assert sorted(bead.archive_path for bead in box.all_beads()) == report.copied
+    assert sorted(b.content_id for b in box.all_beads()) == sorted(
+        b.content_id for b in mirror.all_beads())
+    for bead in mirror.all_beads():
+        bead.validate()
+    assert mirror.sync_from(box) == SyncReport([], report.copied, [])
+
+
+def test_sync_filters(box, tmp_path):
+    mirror = Box('mirror', tmp_path)
+
+    report = mirror.sync_from(box, [(bead_spec.KIND, 'test-bead2')], dry_run=True)
+    assert ['bead2'] == [path.name.split('_')[0] for path in report.copied]
+    assert [] == list(mirror.all_beads())
+
+    report = mirror.sync_from(box, since=time_from_user('20160704T162800000001+0200'))
+    assert {'BEAD3'} == {bead.name for bead in mirror.all_beads()}
+    report = mirror.sync_from(box, until=time_from_user('20160704T000000000000+0200'))
+    assert {'BEAD3', 'bead1'} == {bead.name for bead in mirror.all_beads()}
+
+
+def test_sync_does_not_overwrite_different_content(box, tmp_path):
+    mirror = Box('mirror', tmp_path)
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    taken = tmp_path / bead.archive_path.name
+    write_file(taken, 'not the same archive')
+
+    report = mirror.sync_from(box, [(bead_spec.BEAD_NAME, 'bead1')])
+
+    assert [bead.archive_path] == report.conflicts
+    assert b'not the same archive' == taken.read_bytes()
+
+
+def test_add_archive_rejects_damaged_copy(box, tmp_path, monkeypatch):
+    mirror = Box('mirror', tmp_path)
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    monkeypatch.setattr(
+        'bead.box.content_id_of', lambda manifest_bytes, archive_path=None: 'damaged')
+
+    with pytest.raises(BoxError) as e:
+        mirror.add_archive(bead)
+    assert not isinstance(e.value, TransferError)
+    assert [] == list(tmp_path.iterdir())
+
+
+def test_store_records_archive_checksum(box):
+    for bead in box.all_beads():
+        checksum = hashlib.sha256(bead.archive_path.read_bytes()).hexdigest()
+        assert checksum == bead.archive_sha256
+        assert checksum == BoxIndex.load(box.directory).get(bead.archive_path).archive_sha256
+
+
+def test_add_archive_rejects_copy_corrupted_in_transfer(box, tmp_path, monkeypatch):
+    mirror = Box('mirror', tmp_path)
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    copy_file = bead_tech.transfer.copy_file
+
+    def corrupting_copy(source, target, cancel, hasher):
+        copy_file(source, target, cancel, hasher=hasher)
+        # trailing garbage is ignored by zip readers - the content id is intact
+        with open(target, 'ab') as f:
+            f.write(b'noise')
+    monkeypatch.setattr(bead_tech.transfer, 'copy_file', corrupting_copy)
+
+    with pytest.raises(TransferError):
+        mirror.add_archive(bead)
+    assert [] == list(tmp_path.iterdir())
+
+    # without a recorded checksum as well
+    bead.cache.pop(CACHE_ARCHIVE_SHA256)
+    assert bead.archive_sha256 is None
+    with pytest.raises(TransferError):
+        mirror.add_archive(bead)
+
+
+def test_add_archive_tells_damaged_original_from_damaged_copy(box, tmp_path):
+    mirror = Box('mirror', tmp_path)
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    # e.g. bit rot in the source box since the archive was stored
+    bead.cache[CACHE_ARCHIVE_SHA256] = '0' * 64
+
+    with pytest.raises(InvalidArchive) as e:
+        mirror.add_archive(bead)
+    assert 'recorded' in str(e.value)
+    assert [] == list(tmp_path.iterdir())
+
+
+def test_add_archive_records_checksum_of_the_copy(box, tmp_path):
+    mirror = Box('mirror', tmp_path)
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+
+    mirror.add_archive(bead)
+
+    [copy] = mirror.all_beads()
+    assert bead.archive_sha256 == copy.archive_sha256
+
+
+def _make_old(path):
+    old = time.time() - 2 * DEFAULT_LEFTOVER_AGE.total_seconds()
+    os.utime(path, (old, old))
+
+
+def test_repair(box):
+    """Test that repair removes the leftovers of crashes and rebuilds the index."""
+    [bead] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    old_staged = box.directory / 'bead3_20200101T000000000000+0000.zip.x.staged'
+    write_file(old_staged, 'partial')
+    _make_old(old_staged)
+    new_staged = box.directory / 'bead3_20200102T000000000000+0000.zip.y.staged'
+    write_file(new_staged, 'in progress')
+    old_temp = box.directory / '.bead-index.json.123.tmp'
+    write_file(old_temp, '{')
+    _make_old(old_temp)
+    empty = box.directory / 'bead4_20200101T000000000000+0000.zip'
+    write_file(empty, '')
+    write_file(empty.with_suffix('.xmeta'), '{}')
+    os.remove(bead.archive_path)
+
+    report = box.repair(dry_run=True)
+    assert [old_temp, old_staged] == report.leftover_files
+    assert [empty] == report.empty_archives
+    # the meta cache of the archive removed by hand is orphaned as well
+    assert sorted([bead.cache_path, empty.with_suffix('.xmeta')]) == report.orphaned_sidecars
+    assert [bead.archive_path.name] == report.index_entries
+    assert old_staged.exists() and empty.exists()
+
+    assert report == box.repair()
+
+    assert not old_staged.exists() and not old_temp.exists()
+    assert new_staged.exists()
+    assert not empty.exists() and not empty.with_suffix('.xmeta').exists()
+    indexed = BoxIndex.load(box.directory).entries
+    assert bead.archive_path.name not in indexed
+    assert {'bead2', 'BEAD3'} <= {entry.name for entry in indexed.values()}
+    assert box.repair().is_clean
+
+
+@pytest.mark.skipif(os.name != 'posix', reason='process ids are not checked on Windows')
+def test_repair_removes_only_stale_lock(box):
+    """Test that repair removes the lock of a crashed process, but not a held one."""
+    lock_path = box.directory / LOCK_FILE
+    with FileLock(lock_path):
+        _make_old(lock_path)
+        assert lock_path not in box.repair(dry_run=True).leftover_files
+    crashed = subprocess.Popen([sys.executable, '-c', 'pass'])
+    crashed.wait()
+    write_file(lock_path, f'{socket.gethostname()} {crashed.pid} 20200101T000000000000+0000\n')
+
+    assert [lock_path] == box.repair(dry_run=True).leftover_files
+    assert [lock_path] == box.repair().leftover_files
+    assert not lock_path.exists()
+
+
+def test_legacy_timestamps_are_found_and_normalized(box, tmp_path):
+    """Test that archives from old tools with short or zone-less timestamps are usable."""
+    ws = Workspace(tmp_path / 'legacy')
+    ws.create('test-legacy')
+    ws.pack(box.directory / 'legacy_20160705.zip', freeze_time='20160705', comment='')
+    ws.pack(
+        box.directory / 'legacy_20160706T120000.zip',
+        freeze_time='20160706T120000', comment='')
+
+    time = time_from_user('20160706T000000+0000')
+    matches = box.get_context(bead_spec.BEAD_NAME, 'legacy', time)
+
+    assert '20160705T000000000000+0000' == matches.prev.freeze_time_str
+    assert '20160706T120000000000+0000' == matches.next.freeze_time_str
+    assert '20160706T120000000000+0000' == matches.best.freeze_time_str
+
+
+@pytest.fixture
+def history(tmp_path_factory):
+    """Create two boxes sharing the versions of a bead."""
+    boxes = [Box(name, tmp_path_factory.mktemp(name)) for name in ('box1', 'box2')]
+    workspaces = tmp_path_factory.mktemp('workspaces')
+    for i, freeze_time in enumerate(('20160701', '20160702', '20160703', '20160704')):
+        ws = Workspace(workspaces / f'{i}' / 'bead')
+        ws.create('kind')
+        boxes[i % 2].store(ws, f'{freeze_time}T000000000000+0000')
+    # the same version might be in more than one box
+    boxes[0].store(ws, '20160704T000000000000+0000')
+    return UnionBox(boxes)
+
+
+@pytest.mark.parametrize(
+    'offset, time, expected_day', [
+        (0, '20160702T110000+0000', '02'),
+        (0, '20160702T230000+0000', '03'),
+        (-1, '2099', '04'),
+        (-2, '2099', '03'),
+        (-4, '2099', '01'),
+        (1, '20160702T000000+0000', '03'),
+        (2, '20160702T000000+0000', '04'),
+        (-1, '20160702T000000+0000', '01'),
+        (-1, '20160702T120000+0000', '02'),
+    ])
+def test_resolve_spec(history, offset, time, expected_day):
+    bead = history.resolve_spec('bead', offset, time_from_user(time))
+    assert bead.freeze_time_str.startswith(f'201607{expected_day}T')
+
+
+@pytest.mark.parametrize(
+    'offset, time', [
+        (-5, '2099'),
+        (1, '20160704T000000+0000'),
+        (-1, '20160701T000000+0000'),
+    ])
+def test_resolve_spec_out_of_range(history, offset, time):
+    with pytest.raises(LookupError):
+        history.resolve_spec('bead', offset, time_from_user(time))
+
+
+def test_resolve_spec_unknown_name(history):
+    with pytest.raises(LookupError):
+        history.resolve_spec('unknown', -1, time_from_user('2099'))
+
+
+@pytest.mark.parametrize(
+    'time, expected_day', [
+        ('20160702T000000+0000', '02'),
+        ('20160702T230000+0000', '02'),
+        ('2099', '04'),
+    ])
+def test_find_as_of(history, time, expected_day):
+    bead = history.find_as_of('bead', time_from_user(time))
+    assert bead.freeze_time_str.startswith(f'201607{expected_day}T')
+
+
+def test_find_as_of_in_a_box(history):
+    box1, box2 = history.boxes
+    time = time_from_user('20160702T230000+0000')
+    assert box1.find_as_of('bead', time).freeze_time_str.startswith('20160701T')
+    assert box2.find_as_of('bead', time).freeze_time_str.startswith('20160702T')
+
+
+def test_find_as_of_before_the_first_version(history):
+    with pytest.raises(LookupError):
+        history.find_as_of('bead', time_from_user('20160630T000000+0000'))
+    with pytest.raises(LookupError):
+        history.find_as_of('unknown', time_from_user('2099'))
+
+
+def _copy_in(union_box, box_name, freeze_time_str='20160704T000000000000+0000'):
+    [bead] = [
+        bead for bead in union_box.all_beads()
+        if bead.box_name == box_name and bead.freeze_time_str == freeze_time_str]
+    return bead
+
+
+@pytest.mark.parametrize('box_order', [(0, 1), (1, 0)])
+def test_conflict_priority_prefers_first_box(history, box_order):
+    boxes = [history.boxes[i] for i in box_order]
+    union_box = UnionBox(boxes, CONFLICT_PRIORITY)
+    expected = _copy_in(union_box, boxes[0].name)
+    newest = time_from_user('2099')
+
+    bead = union_box.get_at(bead_spec.BEAD_NAME, 'bead', newest)
+    assert bead.archive_path == expected.archive_path
+    assert union_box.resolve_spec('bead', -1, newest).archive_path == expected.archive_path
+    assert union_box.find_bead('bead', expected.content_id).archive_path == expected.archive_path
+
+
+@pytest.mark.parametrize('newer_box', ['box1', 'box2'])
+def test_conflict_newest_prefers_most_recent_archive(history, newer_box):
+    for box in history.boxes:
+        mtime = 2_000_000_000 if box.name == newer_box else 1_000_000_000
+        os.utime(_copy_in(history, box.name).archive_path, (mtime, mtime))
+    union_box = UnionBox(history.boxes, CONFLICT_NEWEST)
+    expected = _copy_in(history, newer_box)
+
+    bead = union_box.resolve_spec('bead', -1, time_from_user('2099'))
+    assert bead.archive_path == expected.archive_path
+
+
+def test_conflict_error_refuses_to_choose(history):
+    union_box = UnionBox(history.boxes, CONFLICT_ERROR)
+
+    with pytest.raises(AmbiguousBead) as exc_info:
+        union_box.get_at(bead_spec.BEAD_NAME, 'bead', time_from_user('2099'))
+    assert 'box1' in str(exc_info.value)
+    assert 'box2' in str(exc_info.value)
+    # unique versions are still found
+    bead = union_box.get_at(bead_spec.BEAD_NAME, 'bead', time_from_user('20160701'))
+    assert bead.freeze_time_str.startswith('20160701T')
+
+
+def test_unknown_conflict_policy(history):
+    with pytest.raises(ValueError):
+        UnionBox(history.boxes, 'random')
+
+
+def test_search_with_timeout_returns_partial_results(history):
+    box1, box2 = history.boxes
+    released = threading.Event()
+    box2_beads = box2._beads
+
+    def hanging_beads(conditions):
+        released.wait(10)
+        return box2_beads(conditions)
+    box2._beads = hanging_beads
+    union_box = UnionBox(history.boxes, timeout=0.2)
+    try:
+        result = union_box.search([(bead_spec.BEAD_NAME, 'bead')])
+        assert result.timed_out == ['box2']
+        assert {bead.box_name for bead in result.beads} == {'box1'}
+        # lookups use what was found in time
+        bead = union_box.get_at(bead_spec.BEAD_NAME, 'bead', time_from_user('2099'))
+        assert bead.box_name == 'box1'
+        assert union_box.timed_out == ['box2']
+    finally:
+        released.set()
+
+
+def test_search_with_timeout_finds_all_in_time(history):
+    union_box = UnionBox(history.boxes, timeout=10)
+
+    result = union_box.search([(bead_spec.BEAD_NAME, 'bead')])
+    assert result.timed_out == []
+    assert len(result.beads) == 5
+
+
+def test_shared_box_sets_mode_of_stored_and_deleted_archives(tmp_path_factory):
+    """Test that archives in shared boxes get the configured mode."""
+    box = Box('shared', tmp_path_factory.mktemp('shared'), file_mode=0o640)
+    ws = Workspace(tmp_path_factory.mktemp('workspace') / 'bead')
+    ws.create('kind')
+
+    archive = box.store(ws, '20160704T000000000000+0000').archive_path
+    assert archive.stat().st_mode & 0o777 == 0o640
+
+    deleted = box.delete(archive.name)
+    assert deleted.parent.stat().st_mode & 0o777 == 0o750
+    assert box.deleted_directory.stat().st_mode & 0o777 == 0o750
+
+    os.chmod(deleted, 0o600)
+    assert box.restore(archive.name).stat().st_mode & 0o777 == 0o640
+
+
+def test_shared_box_with_unknown_group_warns(tmp_path_factory):
+    """Test that storing in a box with an unknown group is not an error."""
+    box = Box('shared', tmp_path_factory.mktemp('shared'), group='no-such-group-hopefully')
+    ws = Workspace(tmp_path_factory.mktemp('workspace') / 'bead')
+    ws.create('kind')
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        archive = box.store(ws, '20160704T000000000000+0000')
+
+    assert archive.archive_path.exists()
+    assert any(issubclass(w.category, PermissionsNotSupported) for w in caught)
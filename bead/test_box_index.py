@@ -0,0 +1,213 @@
+import json
+import os
+
+import pytest
+
+from . import archive
+from .box import Box, SCAN_THREADS_ENV_VAR, DEFAULT_SCAN_THREADS, UnionBox, scan_threads
+from .box_index import BoxIndex, INDEX_FILE
+from .tech.fs import write_file
+from .tech.timestamp import parse_iso8601, time_from_timestamp
+from .workspace import Workspace
+from . import spec as bead_spec
+
+LATEST = parse_iso8601('9999-12-31')
+
+
+@pytest.fixture
+def box(tmp_path_factory):
+    box = Box('test', tmp_path_factory.mktemp('box'))
+    workspaces = tmp_path_factory.mktemp('workspaces')
+
+    def add_bead(name, kind, freeze_time):
+        ws = Workspace(workspaces / name)
+        ws.create(kind)
+        box.store(ws, freeze_time)
+
+    add_bead('bead1', 'kind1', '20160704T000000000000+0200')
+    add_bead('bead2', 'kind2', '20160704T162800000000+0200')
+    return box
+
+
+@pytest.fixture
+def unreadable_zips(monkeypatch):
+    def fail(*args, **kwargs):
+        raise AssertionError('archive was opened')
+    monkeypatch.setattr(archive, 'ZipArchive', fail)
+
+
+def test_save_merges_concurrent_changes(box):
+    """Test that saving an index keeps the changes saved by others since loading it."""
+    [bead1] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    [bead2] = [bead for bead in box.all_beads() if bead.name == 'bead2']
+    mine = BoxIndex.load(box.directory)
+    theirs = BoxIndex.load(box.directory)
+
+    theirs.set_verified(bead2, '20200101T000000000000+0000')
+    assert theirs.save()
+    mine.set_verified(bead1, '20200102T000000000000+0000')
+    assert mine.save()
+
+    entries = BoxIndex.load(box.directory).entries
+    assert '20200101T000000000000+0000' == entries[bead2.archive_path.name].verified_at
+    assert '20200102T000000000000+0000' == entries[bead1.archive_path.name].verified_at
+
+
+def test_store_indexes_the_new_archive(box):
+    index = BoxIndex.load(box.directory)
+
+    assert {'bead1', 'bead2'} == {entry.name for entry in index.entries.values()}
+    [bead1] = [entry for entry in index.entries.values() if entry.name == 'bead1']
+    assert 'kind1' == bead1.kind
+    assert '20160704T000000000000+0200' == bead1.freeze_time_str
+
+
+def test_queries_are_answered_from_the_index(box, unreadable_zips):
+    [bead] = box._beads([(bead_spec.KIND, 'kind2')])
+
+    assert 'bead2' == bead.name
+    assert 'kind2' == bead.kind
+    assert BoxIndex.load(box.directory).get(bead.archive_path).content_id == bead.content_id
+
+
+def test_exact_content_id_is_found_without_scanning(box, monkeypatch):
+    [bead1] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+
+    def no_scanning(*args):
+        raise AssertionError('box was scanned')
+    monkeypatch.setattr(Box, '_archives_from', no_scanning)
+
+    assert bead1.archive_path == box.find_bead('bead1', bead1.content_id).archive_path
+    assert [] == BoxIndex.load(box.directory).with_content_id('unknown')
+    monkeypatch.undo()
+    assert box.find_bead('bead2', bead1.content_id) is None
+    assert bead1.archive_path == box.find_bead('bead1', bead1.content_id[:10]).archive_path
+
+
+def test_missing_index_is_rebuilt(box):
+    os.remove(box.directory / INDEX_FILE)
+
+    assert {'bead1', 'bead2'} == {bead.name for bead in box.all_beads()}
+    assert 2 == len(BoxIndex.load(box.directory).entries)
+
+
+def test_archive_checksum_survives_rebuilding_the_index(box):
+    [bead1] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    checksum = BoxIndex.load(box.directory).get(bead1.archive_path).archive_sha256
+    assert checksum is not None
+    os.remove(box.directory / INDEX_FILE)
+
+    assert {'bead1', 'bead2'} == {bead.name for bead in box.all_beads()}
+    assert checksum == BoxIndex.load(box.directory).get(bead1.archive_path).archive_sha256
+
+
+def test_malformed_index_is_ignored_and_rebuilt(box):
+    write_file(box.directory / INDEX_FILE, '{junk')
+
+    assert {'bead1', 'bead2'} == {bead.name for bead in box.all_beads()}
+    assert 2 == len(BoxIndex.load(box.directory).entries)
+
+
+def test_modified_archive_is_reindexed(box):
+    [bead1] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    stat = bead1.archive_path.stat()
+    os.utime(bead1.archive_path, ns=(stat.st_atime_ns, stat.st_mtime_ns + 10**9))
+
+    index = BoxIndex.load(box.directory)
+    assert index.get(bead1.archive_path) is None
+    assert {'bead1', 'bead2'} == {bead.name for bead in box.all_beads()}
+    assert BoxIndex.load(box.directory).get(bead1.archive_path) is not None
+
+
+def test_verification_is_remembered_until_the_archive_changes(box):
+    [bead1] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    verified_at = '20240101T120000000000+0000'
+    before = time_from_timestamp('20240101T000000000000+0000')
+    after = time_from_timestamp('20240102T000000000000+0000')
+    assert not box.is_verified_since(bead1.archive_path, before)
+
+    box.set_verified(bead1, verified_at)
+
+    assert box.is_verified_since(bead1.archive_path, before)
+    assert not box.is_verified_since(bead1.archive_path, after)
+    stat = bead1.archive_path.stat()
+    os.utime(bead1.archive_path, ns=(stat.st_atime_ns, stat.st_mtime_ns + 10**9))
+    assert not box.is_verified_since(bead1.archive_path, before)
+
+
+def test_check_skips_recently_verified_archives(box):
+    before = time_from_timestamp('20000101T000000000000+0000')
+    assert all(damages == [] for _, damages in box.check(verified_since=before))
+
+    assert all(damages is None for _, damages in box.check(verified_since=before))
+    # forced
+    assert all(damages == [] for _, damages in box.check())
+
+
+def test_deleted_archive_is_removed_from_index(box):
+    [bead1] = [bead for bead in box.all_beads() if bead.name == 'bead1']
+    box.delete(bead1.archive_path.name)
+
+    assert bead1.archive_path.name not in BoxIndex.load(box.directory).entries
+
+
+@pytest.mark.parametrize('threads', ['1', '4'])
+def test_scanning_with_threads(box, monkeypatch, threads):
+    monkeypatch.setenv(SCAN_THREADS_ENV_VAR, threads)
+    os.remove(box.directory / INDEX_FILE)
+
+    beads = {bead.name: bead for bead in box.all_beads()}
+
+    assert {'bead1', 'bead2'} == set(beads)
+    assert 'kind1' == beads['bead1'].kind
+    assert 2 == len(BoxIndex.load(box.directory).entries)
+
+
+def test_scan_threads(monkeypatch):
+    monkeypatch.setenv(SCAN_THREADS_ENV_VAR, '3')
+    assert 3 == scan_threads()
+    monkeypatch.setenv(SCAN_THREADS_ENV_VAR, '0')
+    assert 1 == scan_threads()
+    monkeypatch.setenv(SCAN_THREADS_ENV_VAR, 'many')
+    assert DEFAULT_SCAN_THREADS == scan_threads()
+
+
+def test_aliases_resolve_to_the_newest_bead_of_their_kind(box, tmp_path):
+    box.set_alias('latest', 'kind1')
+    assert {'latest': 'kind1'} == box.aliases()
+    ws = Workspace(tmp_path / 'renamed')
+    ws.create('kind1')
+    box.store(ws, '20170101T000000000000+0200')
+
+    assert 'renamed' == box.resolve_alias('latest').name
+    assert 'renamed' == UnionBox([box]).resolve_reference('latest', LATEST).name
+
+
+def test_aliases_survive_incompatible_index(box):
+    box.set_alias('latest', 'kind1')
+    index = BoxIndex.load(box.directory)
+    content = json.loads(index.path.read_text())
+    content['version'] = 'incompatible'
+    index.path.write_text(json.dumps(content))
+
+    list(box.all_beads())
+
+    assert {'latest': 'kind1'} == box.aliases()
+
+
+def test_concurrent_alias_changes_are_merged(box):
+    mine = BoxIndex.load(box.directory)
+    box.set_alias('theirs', 'kind2')
+    mine.set_alias('mine', 'kind1')
+    mine.save()
+
+    assert {'mine': 'kind1', 'theirs': 'kind2'} == box.aliases()
+
+
+def test_removed_and_unknown_aliases_do_not_resolve(box):
+    box.set_alias('latest', 'kind1')
+    assert box.remove_alias('latest')
+    assert not box.remove_alias('latest')
+
+    with pytest.raises(LookupError):
+        box.resolve_alias('latest')
@@ -0,0 +1,290 @@
+import os
+import warnings
+
+import pytest
+
+from . import hooks
+from . import meta
+from . import notebooks
+from . import save_policy
+from .box import Box
+from .context import BeadContext, DryRun, DEVELOP, LOAD, SAVE, UNLOAD
+from .exceptions import AmbiguousReference, BoxError, UserError
+from .tech.fs import ensure_directory, write_file
+from .workspace import Workspace
+
+TS1 = '20200101T000000000000+0000'
+TS2 = '20200102T000000000000+0000'
+
+
+@pytest.fixture
+def box(tmp_path):
+    directory = tmp_path / 'box'
+    directory.mkdir()
+    return Box('main', directory)
+
+
+@pytest.fixture
+def ctx(box):
+    return BeadContext([box])
+
+
+def make_workspace(directory, kind='kind', output='data'):
+    workspace = Workspace(directory)
+    workspace.create(kind)
+    write_file(workspace.directory / 'output/data', output)
+    return workspace
+
+
+def test_save_stores_in_the_only_box(ctx, box, tmp_path):
+    archive = ctx.save(make_workspace(tmp_path / 'src'), freeze_time=TS1)
+
+    assert 'main' == archive.box_name
+    assert [archive.content_id] == [bead.content_id for bead in box.all_beads()]
+
+
+def test_save_needs_box_name_with_more_boxes(box, tmp_path):
+    other = Box('other', tmp_path)
+    ctx = BeadContext([box, other])
+
+    with pytest.raises(BoxError):
+        ctx.save(make_workspace(tmp_path / 'src'))
+    with pytest.raises(BoxError):
+        ctx.save(make_workspace(tmp_path / 'src2'), 'unknown')
+    assert 'main' == ctx.save(make_workspace(tmp_path / 'src3'), 'main').box_name
+
+
+def test_save_applies_settings(box, tmp_path):
+    ctx = BeadContext([box], notebook_outputs=notebooks.STRIP, validators=['false'])
+
+    with pytest.raises(UserError):
+        ctx.save(make_workspace(tmp_path / 'src'))
+    assert [] == list(box.all_beads())
+
+
+def test_save_checks_name_collision(box, tmp_path):
+    BeadContext([box]).save(make_workspace(tmp_path / 'src', kind='kind1'), freeze_time=TS1)
+    ctx = BeadContext([box], name_collision_policy=save_policy.NAME_COLLISION_REFUSE)
+
+    with pytest.raises(save_policy.SaveRefused):
+        ctx.save(make_workspace(tmp_path / 'other' / 'src', kind='kind2'), freeze_time=TS2)
+    assert 1 == len(list(box.all_beads()))
+
+
+def test_save_checks_save_policies(box, tmp_path):
+    ctx = BeadContext([box], save_policies={save_policy.REQUIRED_FILES: ['README.md']})
+    workspace = make_workspace(tmp_path / 'src')
+
+    with pytest.raises(save_policy.SaveRefused):
+        ctx.save(workspace, freeze_time=TS1)
+    assert [] == list(box.all_beads())
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        assert ctx.save(workspace, freeze_time=TS1, override=True) is not None
+    assert [save_policy.SaveWarning] == [w.category for w in caught]
+
+
+def test_save_after_newest_version_when_clock_is_behind(ctx, tmp_path):
+    future = '29991231T235959999999+0000'
+    workspace = make_workspace(tmp_path / 'src')
+    ctx.save(workspace, freeze_time=future)
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        archive = ctx.save(workspace)
+    assert [save_policy.SaveWarning] == [w.category for w in caught]
+    assert '30000101T000000000000+0000' == archive.freeze_time_str
+
+
+def test_save_with_provenance(ctx, tmp_path):
+    workspace = make_workspace(tmp_path / 'src')
+
+    archive = ctx.save(
+        workspace, freeze_time=TS1, provenance=workspace.provenance('runner', 'plan'))
+
+    assert meta.Provenance('runner', 'plan', {}) == archive.provenance
+
+
+def test_develop_by_name(ctx, tmp_path):
+    saved = ctx.save(make_workspace(tmp_path / 'src'), freeze_time=TS1)
+
+    workspace = ctx.develop('src', tmp_path / 'developed', extract_output=True)
+
+    assert saved.kind == workspace.kind
+    assert 'data' == (workspace.directory / 'output/data').read_text()
+    with pytest.raises(UserError):
+        ctx.develop('src', tmp_path / 'developed')
+    with pytest.raises(LookupError):
+        ctx.develop('unknown', tmp_path / 'unknown')
+
+
+def test_develop_by_content_id_prefix_or_kind(box, tmp_path):
+    ctx = BeadContext([box], fuzzy_references=True)
+    first = ctx.save(make_workspace(tmp_path / 'first', kind='kind1'), freeze_time=TS1)
+    second = ctx.save(make_workspace(tmp_path / 'second', kind='kind2'), freeze_time=TS1)
+
+    by_prefix = ctx.develop(first.content_id[:12], tmp_path / 'by-prefix')
+    by_kind = ctx.develop('kind2', tmp_path / 'by-kind')
+
+    assert first.kind == by_prefix.kind
+    assert second.kind == by_kind.kind
+
+
+def test_develop_by_kind_of_more_names_is_ambiguous(box, tmp_path):
+    ctx = BeadContext([box], fuzzy_references=True)
+    ctx.save(make_workspace(tmp_path / 'first'), freeze_time=TS1)
+    ctx.save(make_workspace(tmp_path / 'second'), freeze_time=TS1)
+
+    with pytest.raises(AmbiguousReference):
+        ctx.develop('kind', tmp_path / 'developed')
+
+
+def test_references_are_only_names_by_default(ctx, tmp_path):
+    archive = ctx.save(make_workspace(tmp_path / 'src'), freeze_time=TS1)
+
+    with pytest.raises(LookupError):
+        ctx.resolve(archive.content_id[:12])
+    with pytest.raises(LookupError):
+        ctx.resolve('kind')
+    assert archive.content_id == ctx.resolve('src').content_id
+
+
+def test_develop_loading_inputs(ctx, tmp_path):
+    source = ctx.save(make_workspace(tmp_path / 'src', output='source data'), freeze_time=TS1)
+    consumer = make_workspace(tmp_path / 'consumer', kind='consumer')
+    consumer.load('source', source)
+    consumer.set_input_bead_name('source', 'src')
+    consumer.load('lost', source)
+    ctx.save(consumer, freeze_time=TS2)
+    lost = consumer.get_input('lost')
+
+    workspace = ctx.develop('consumer', tmp_path / 'developed', load_inputs=True)
+
+    assert workspace.is_loaded('source')
+    assert 'source data' == (workspace.directory / 'input/source/data').read_text()
+    assert not workspace.is_loaded('lost')
+    assert lost == workspace.get_input('lost')
+    assert ['lost'] == ctx.load_inputs(workspace)
+
+
+def test_update_input_to_newest_version(ctx, tmp_path):
+    source = make_workspace(tmp_path / 'src', output='old')
+    old = ctx.save(source, freeze_time=TS1)
+    consumer = make_workspace(tmp_path / 'consumer', kind='consumer')
+    consumer.load('source', old)
+    consumer.set_input_bead_name('source', 'src')
+    write_file(source.directory / 'output/data', 'new')
+    new = ctx.save(source, freeze_time=TS2)
+
+    assert new.content_id == ctx.update_input(consumer, 'source').content_id
+
+    assert new.content_id == consumer.get_input('source').content_id
+    assert 'new' == (consumer.directory / 'input/source/data').read_text()
+    assert 'src' == consumer.get_input_bead_name('source')
+
+
+def test_update_keeps_partial_load(ctx, tmp_path):
+    source = make_workspace(tmp_path / 'src', output='old')
+    write_file(source.directory / 'output/other', 'old')
+    old = ctx.save(source, freeze_time=TS1)
+    consumer = make_workspace(tmp_path / 'consumer', kind='consumer')
+    consumer.load('source', old, patterns=['data'])
+    consumer.set_input_bead_name('source', 'src')
+    write_file(source.directory / 'output/data', 'new')
+    ctx.save(source, freeze_time=TS2)
+
+    ctx.update_input(consumer, 'source')
+
+    assert 'new' == (consumer.directory / 'input/source/data').read_text()
+    assert not (consumer.directory / 'input/source/other').exists()
+    assert ('data',) == consumer.loaded_patterns('source')
+
+
+def test_update_unknown_input(ctx, tmp_path):
+    with pytest.raises(UserError):
+        ctx.update_input(make_workspace(tmp_path / 'ws'), 'missing')
+
+
+def test_dry_run_save_changes_nothing(box, tmp_path):
+    dry_run = DryRun()
+    ctx = BeadContext([box], dry_run=dry_run)
+
+    assert ctx.save(make_workspace(tmp_path / 'src'), freeze_time=TS1) is None
+
+    assert [] == list(box.all_beads())
+    [action] = dry_run.actions
+    assert SAVE == action.operation
+    assert box.directory / f'src_{TS1}.zip' == action.path
+    assert 'main' in str(action)
+    with pytest.raises(UserError):
+        ctx.save(Workspace(tmp_path / 'not-a-workspace'))
+
+
+def test_dry_run_develop_with_inputs(ctx, box, tmp_path):
+    source = ctx.save(make_workspace(tmp_path / 'src'), freeze_time=TS1)
+    consumer = make_workspace(tmp_path / 'consumer', kind='consumer')
+    consumer.load('source', source)
+    consumer.set_input_bead_name('source', 'src')
+    consumer.load('lost', source)
+    ctx.save(consumer, freeze_time=TS2)
+    dry_run = DryRun()
+    preview = BeadContext([box], dry_run=dry_run)
+
+    assert preview.develop('consumer', tmp_path / 'developed', load_inputs=True) is None
+
+    assert not (tmp_path / 'developed').exists()
+    assert [(DEVELOP, tmp_path / 'developed'), (LOAD, tmp_path / 'developed/input/source')] == [
+        (action.operation, action.path) for action in dry_run.actions]
+
+
+def test_dry_run_update_input(ctx, box, tmp_path):
+    source = make_workspace(tmp_path / 'src', output='old')
+    old = ctx.save(source, freeze_time=TS1)
+    consumer = make_workspace(tmp_path / 'consumer', kind='consumer')
+    consumer.load('source', old)
+    consumer.set_input_bead_name('source', 'src')
+    write_file(source.directory / 'output/data', 'new')
+    new = ctx.save(source, freeze_time=TS2)
+    dry_run = DryRun()
+
+    archive = BeadContext([box], dry_run=dry_run).update_input(consumer, 'source')
+
+    assert new.content_id == archive.content_id
+    assert old.content_id == consumer.get_input('source').content_id
+    assert [UNLOAD, LOAD] == [action.operation for action in dry_run.actions]
+
+
+@pytest.mark.skipif(os.name != 'posix', reason='hooks are shell scripts here')
+def test_save_runs_trusted_hooks(box, tmp_path):
+    workspace = make_workspace(tmp_path / 'src')
+    pre_save = hooks.hook_path(workspace, hooks.PRE_SAVE)
+    ensure_directory(pre_save.parent)
+    write_file(pre_save, '#!/bin/sh\nexit 1\n')
+    pre_save.chmod(0o755)
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        assert BeadContext([box]).save(workspace) is not None
+    assert [hooks.UntrustedHook] == [w.category for w in caught]
+
+    def trusting_context():
+        return BeadContext(
+            [box], trusted_hooks={hooks.trust_key(workspace): hooks.fingerprint(workspace)})
+    ctx = trusting_context()
+    with pytest.raises(hooks.HookError):
+        ctx.save(workspace)
+    assert 1 == len(list(box.all_beads()))
+    assert ctx.save(workspace, run_hooks=False) is not None
+
+    write_file(pre_save, '#!/bin/sh\necho regenerated > output/data\n')
+    post_save = hooks.hook_path(workspace, hooks.POST_SAVE)
+    write_file(post_save, '#!/bin/sh\nexit 1\n')
+    post_save.chmod(0o755)
+    ctx = trusting_context()
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        archive = ctx.save(workspace)
+    assert [hooks.HookFailed] == [w.category for w in caught]
+    assert archive.content_id in {bead.content_id for bead in box.all_beads()}
+    assert 'regenerated\n' == (workspace.directory / 'output/data').read_text()
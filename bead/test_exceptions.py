@@ -0,0 +1,38 @@
+import pytest
+
+from . import exceptions as m
+
+
+def test_message_with_context():
+    """Test that the context of the error is appended to the message."""
+    e = m.BoxError('Box "main": no such archive', operation='delete', path='/box/a.zip')
+
+    assert 'Box "main": no such archive (operation: delete, path: /box/a.zip)' == str(e)
+
+
+def test_message_without_context():
+    """Test that plain errors are unchanged."""
+    assert 'damaged' == str(m.InvalidArchive('damaged'))
+
+
+def test_missing_message_defaults_to_description():
+    """Test that errors with context only still have a readable message."""
+    e = m.InvalidArchive(path='a.zip', bead_name='a')
+
+    assert 'Not a valid bead archive (path: a.zip, bead: a)' == str(e)
+
+
+@pytest.mark.parametrize(
+    'error, exit_code', [
+        (m.BeadError, 1),
+        (m.UserError, 2),
+        (m.BoxError, 2),
+        (m.TransferError, 4),
+        (m.InvalidArchive, 3),
+        (m.ContentMismatch, 3),
+        (m.SignatureError, 1),
+        (m.InternalError, 70),
+    ])
+def test_exit_codes(error, exit_code):
+    """Test that scripts can tell misuse, damaged data and bugs apart."""
+    assert exit_code == error().exit_code
@@ -0,0 +1,62 @@
+import os
+import tarfile
+
+import pytest
+
+from .archive import Archive
+from .export import export
+from .tech.cancellation import CancellationToken, Cancelled
+from .tech.fs import ensure_directory, write_file
+from .tech.timestamp import timestamp
+from .workspace import Workspace
+
+
+@pytest.fixture
+def archive(tmp_path_factory):
+    tmp_path = tmp_path_factory.mktemp('export')
+    workspace = Workspace(tmp_path / 'bead')
+    workspace.create('kind')
+    ensure_directory(workspace.directory / 'output/results')
+    write_file(workspace.directory / 'output/results/table.csv', 'a,b\n1,2\n')
+    write_file(workspace.directory / 'script.py', 'print(42)')
+    archive_path = tmp_path / 'bead_20150923T010203012345+0200.zip'
+    workspace.pack(archive_path, timestamp(), 'comment')
+    return Archive(archive_path)
+
+
+def files_under(directory):
+    return sorted(
+        os.path.relpath(os.path.join(root, name), directory).replace(os.sep, '/')
+        for root, _dirs, names in os.walk(directory)
+        for name in names)
+
+
+def test_export_data_to_directory(archive, tmp_path):
+    export(archive, tmp_path / 'exported')
+
+    assert ['results/table.csv'] == files_under(tmp_path / 'exported')
+    assert 'a,b\n1,2\n' == (tmp_path / 'exported/results/table.csv').read_text()
+
+
+def test_export_with_code(archive, tmp_path):
+    export(archive, tmp_path / 'exported', include_code=True)
+
+    assert ['code/script.py', 'data/results/table.csv'] == files_under(tmp_path / 'exported')
+
+
+def test_export_to_tarball(archive, tmp_path):
+    tarball = export(archive, tmp_path / 'results.tar.gz')
+
+    with tarfile.open(tarball) as tar:
+        names = sorted(member.name for member in tar.getmembers() if member.isfile())
+    assert ['results/results/table.csv'] == names
+    assert ['results.tar.gz'] == os.listdir(tmp_path)
+
+
+def test_cancelled_export_leaves_nothing_behind(archive, tmp_path):
+    cancel = CancellationToken()
+    cancel.cancel()
+
+    with pytest.raises(Cancelled):
+        export(archive, tmp_path / 'results.tgz', cancel=cancel)
+    assert [] == os.listdir(tmp_path)
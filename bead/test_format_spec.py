@@ -0,0 +1,133 @@
+import json
+import re
+import zipfile
+
+import pytest
+
+from . import format_spec as m
+from . import layouts
+from .archive import Archive
+from .tech import persistence, securehash
+from .tech.timestamp import time_from_timestamp
+from .workspace import Workspace
+
+
+@pytest.mark.parametrize('vector', m.TEST_VECTORS['file_hash'])
+def test_file_hash_vectors(vector):
+    algorithm = securehash.HashAlgo(vector.get('algorithm', 'sha512'))
+
+    assert securehash.bytes(vector['content'].encode('utf-8'), algorithm) == vector['hash']
+    assert re.fullmatch(m.HASH_GRAMMAR, vector['hash'])
+
+
+@pytest.mark.parametrize('vector', m.TEST_VECTORS['content_id'])
+def test_content_id_vectors(vector, tmp_path):
+    archive_path = tmp_path / 'bead_20200913T173910000000+0000.zip'
+    bead_meta = {
+        'meta_version': m.FORMAT_VERSION,
+        'kind': 'kind',
+        'freeze_time': '20200913T173910000000+0000',
+        'freeze_name': 'bead',
+        'inputs': {}}
+    with zipfile.ZipFile(archive_path, 'w') as zf:
+        zf.writestr(layouts.Archive.BEAD_META, persistence.dumps(bead_meta))
+        zf.writestr(layouts.Archive.MANIFEST, persistence.dumps(vector['manifest']))
+
+    assert Archive(archive_path).content_id == vector['content_id']
+    assert re.fullmatch(m.HASH_GRAMMAR, vector['content_id'])
+
+
+@pytest.mark.parametrize(
+    'hash, valid', [
+        ('a' * 128, True),
+        ('blake2b:' + 'a' * 128, True),
+        ('blake3:' + 'a' * 64, True),
+        ('blake3:' + 'a' * 128, False),
+        ('blake2b:' + 'a' * 64, False),
+        ('sha512:' + 'a' * 128, False),
+        ('md5:' + 'a' * 32, False),
+    ])
+def test_hash_grammar(hash, valid):
+    assert valid == bool(re.fullmatch(m.HASH_GRAMMAR, hash))
+
+
+def test_timestamp_vectors():
+    for valid in m.TEST_VECTORS['timestamp']['valid']:
+        assert re.fullmatch(m.TIMESTAMP_GRAMMAR, valid)
+        time_from_timestamp(valid)
+    for invalid in m.TEST_VECTORS['timestamp']['invalid']:
+        assert not re.fullmatch(m.TIMESTAMP_GRAMMAR, invalid)
+        with pytest.raises(ValueError):
+            time_from_timestamp(invalid)
+
+
+@pytest.fixture
+def archive_path(tmp_path):
+    workspace = Workspace(tmp_path / 'bead')
+    workspace.create('kind')
+    (workspace.directory / 'output/data').write_text('data')
+    path = tmp_path / 'bead.zip'
+    workspace.pack(path, '20200913T173910000000+0000', 'comment')
+    return path
+
+
+def test_archives_conform(archive_path):
+    assert m.conformance_errors(archive_path) == []
+
+
+def test_archives_with_tagged_hashes_conform(tmp_path):
+    input_workspace = Workspace(tmp_path / 'input')
+    input_workspace.create('input-kind')
+    input_path = tmp_path / 'input.zip'
+    input_workspace.pack(
+        input_path, '20200913T173910000000+0000', 'comment',
+        hash_algorithm=securehash.HashAlgo.BLAKE2B)
+    workspace = Workspace(tmp_path / 'bead')
+    workspace.create('kind')
+    workspace.load('input', Archive(input_path))
+    (workspace.directory / 'output/data').write_text('data')
+    path = tmp_path / 'bead.zip'
+    workspace.pack(
+        path, '20200913T173910000000+0000', 'comment',
+        hash_algorithm=securehash.HashAlgo.BLAKE2B)
+
+    archive = Archive(path)
+    archive.validate()
+    assert archive.content_id.startswith('blake2b:')
+    assert archive.inputs[0].content_id.startswith('blake2b:')
+    assert m.conformance_errors(path) == []
+
+
+def test_archives_with_annotations_conform(tmp_path):
+    workspace = Workspace(tmp_path / 'bead')
+    workspace.create('kind')
+    workspace.set_description('description')
+    workspace.add_tag('tag')
+    path = tmp_path / 'bead.zip'
+    workspace.pack(path, '20200913T173910000000+0000', 'comment')
+
+    assert m.conformance_errors(path) == []
+    assert ['$.tags[0]: expected string'] == m.schema_errors({'tags': [1]}, {
+        'type': 'object', 'properties': {'tags': m.META_SCHEMA['properties']['tags']}})
+
+
+def test_file_missing_from_manifest_is_reported(archive_path):
+    with zipfile.ZipFile(archive_path, 'a') as zf:
+        zf.writestr('data/smuggled', 'extra')
+
+    assert m.conformance_errors(archive_path) == ['data/smuggled: not in manifest']
+
+
+def test_schema_errors():
+    bead_meta = {'meta_version': 'unknown', 'kind': 'kind', 'freeze_time': '2020', 'inputs': []}
+
+    errors = m.schema_errors(bead_meta, m.META_SCHEMA)
+
+    assert "$: missing freeze_name" in errors
+    assert any(error.startswith('$.meta_version:') for error in errors)
+    assert any(error.startswith('$.freeze_time:') for error in errors)
+    assert '$.inputs: expected object' in errors
+
+
+def test_specification_is_json():
+    assert json.loads(json.dumps(m.specification()))['format_version'] == m.FORMAT_VERSION
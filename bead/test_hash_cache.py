@@ -0,0 +1,87 @@
+import os
+
+import pytest
+
+from .archive import Archive
+from .hash_cache import HashCache
+from .tech.fs import ensure_directory, write_file
+from .tech.securehash import HashAlgo
+from .tech.timestamp import timestamp
+from .workspace import Workspace
+from . import layouts
+
+
+@pytest.fixture
+def workspace(tmp_path):
+    ws = Workspace(tmp_path / 'workspace')
+    ws.create('KIND')
+    write_file(ws.directory / 'output/data', 'data')
+    write_file(ws.directory / 'code', 'code')
+    return ws
+
+
+def age(path, seconds=3600):
+    stat = os.stat(path)
+    os.utime(path, ns=(stat.st_atime_ns, stat.st_mtime_ns - seconds * 10 ** 9))
+
+
+def rewrite_keeping_size_and_mtime(path, content):
+    stat = os.stat(path)
+    write_file(path, content)
+    os.utime(path, ns=(stat.st_atime_ns, stat.st_mtime_ns))
+
+
+def data_hash(path):
+    return Archive(path).ziparchive.manifest[f'{layouts.Archive.DATA}/data']
+
+
+def test_unchanged_files_are_not_hashed_again(workspace, tmp_path):
+    age(workspace.directory / 'output/data')
+    workspace.pack(tmp_path / 'first.zip', timestamp(), 'comment')
+    # a change not visible in size and modification time is not noticed with the cache
+    rewrite_keeping_size_and_mtime(workspace.directory / 'output/data', 'DATA')
+
+    workspace.pack(tmp_path / 'cached.zip', timestamp(), 'comment')
+    workspace.pack(tmp_path / 'hashed.zip', timestamp(), 'comment', use_hash_cache=False)
+
+    assert data_hash(tmp_path / 'first.zip') == data_hash(tmp_path / 'cached.zip')
+    assert data_hash(tmp_path / 'first.zip') != data_hash(tmp_path / 'hashed.zip')
+    Archive(tmp_path / 'hashed.zip').validate()
+
+
+def test_recently_modified_files_are_not_cached(workspace, tmp_path):
+    age(workspace.directory / 'output/data')
+    workspace.pack(tmp_path / 'bead.zip', timestamp(), 'comment')
+
+    cache = HashCache.load(workspace)
+    assert cache.get(workspace.directory / 'output/data', HashAlgo.SHA512) is not None
+    assert cache.get(workspace.directory / 'code', HashAlgo.SHA512) is None
+
+
+def test_hashes_of_other_algorithms_are_not_used(workspace, tmp_path):
+    age(workspace.directory / 'output/data')
+    workspace.pack(tmp_path / 'bead.zip', timestamp(), 'comment')
+
+    cache = HashCache.load(workspace)
+    assert cache.get(workspace.directory / 'output/data', HashAlgo.BLAKE2B) is None
+
+
+def test_removed_files_are_forgotten(workspace, tmp_path):
+    ensure_directory(workspace.directory / 'output/old')
+    write_file(workspace.directory / 'output/old/file', 'old')
+    age(workspace.directory / 'output/old/file')
+    workspace.pack(tmp_path / 'first.zip', timestamp(), 'comment')
+    os.remove(workspace.directory / 'output/old/file')
+
+    workspace.pack(tmp_path / 'second.zip', timestamp(), 'comment')
+
+    cache = (workspace.directory / layouts.Workspace.HASH_CACHE).read_text()
+    assert 'output/old/file' not in cache
+
+
+def test_unreadable_cache_is_ignored(workspace, tmp_path):
+    write_file(workspace.directory / layouts.Workspace.HASH_CACHE, 'not json')
+
+    workspace.pack(tmp_path / 'bead.zip', timestamp(), 'comment')
+
+    Archive(tmp_path / 'bead.zip').validate()
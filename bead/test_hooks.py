@@ -0,0 +1,75 @@
+import os
+import warnings
+
+import pytest
+
+from . import hooks
+from .tech.fs import ensure_directory, write_file
+from .workspace import Workspace
+
+pytestmark = pytest.mark.skipif(os.name != 'posix', reason='hooks are shell scripts here')
+
+
+@pytest.fixture
+def workspace(tmp_path):
+    workspace = Workspace(tmp_path / 'ws')
+    workspace.create('kind')
+    return workspace
+
+
+def add_hook(workspace, hook, script):
+    path = hooks.hook_path(workspace, hook)
+    ensure_directory(path.parent)
+    write_file(path, '#!/bin/sh\n' + script)
+    path.chmod(0o755)
+    return path
+
+
+def test_missing_hook_is_not_run(workspace):
+    hooks.run(workspace, hooks.PRE_SAVE)
+
+
+def test_hook_runs_in_workspace_with_bead_variables(workspace, tmp_path):
+    add_hook(workspace, hooks.POST_SAVE, 'echo "$BEAD_NAME $1 $BEAD_ARCHIVE" > output/hooked\n')
+
+    hooks.run(workspace, hooks.POST_SAVE, tmp_path / 'ws.zip', hooks.fingerprint(workspace))
+
+    archive = tmp_path / 'ws.zip'
+    assert f'ws {archive} {archive}\n' == (workspace.directory / 'output/hooked').read_text()
+
+
+def test_failing_hook(workspace):
+    add_hook(workspace, hooks.PRE_SAVE, 'exit 3\n')
+
+    with pytest.raises(hooks.HookError) as exc_info:
+        hooks.run(workspace, hooks.PRE_SAVE, trusted=hooks.fingerprint(workspace))
+    assert 'exit status 3' in str(exc_info.value)
+
+
+def test_not_executable_hook(workspace):
+    add_hook(workspace, hooks.PRE_SAVE, 'exit 0\n').chmod(0o644)
+
+    with pytest.raises(hooks.HookError):
+        hooks.run(workspace, hooks.PRE_SAVE, trusted=hooks.fingerprint(workspace))
+
+
+def test_untrusted_hook_is_not_run(workspace):
+    add_hook(workspace, hooks.PRE_SAVE, 'touch output/hooked\n')
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        hooks.run(workspace, hooks.PRE_SAVE)
+    assert [hooks.UntrustedHook] == [w.category for w in caught]
+    assert not (workspace.directory / 'output/hooked').exists()
+
+
+def test_hook_changed_since_trusted_is_not_run(workspace):
+    add_hook(workspace, hooks.PRE_SAVE, 'exit 0\n')
+    trusted = hooks.fingerprint(workspace)
+    add_hook(workspace, hooks.PRE_SAVE, 'touch output/hooked\n')
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        hooks.run(workspace, hooks.PRE_SAVE, trusted=trusted)
+    assert [hooks.UntrustedHook] == [w.category for w in caught]
+    assert not (workspace.directory / 'output/hooked').exists()
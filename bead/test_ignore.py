@@ -0,0 +1,44 @@
+import pytest
+
+from . import ignore as m
+
+
+@pytest.mark.parametrize(
+    'pattern, path, is_dir, ignored', [
+        ('*.log', 'run.log', False, True),
+        ('*.log', 'logs/run.log', False, True),
+        ('*.log', 'run.log.txt', False, False),
+        ('cache/', 'cache', True, True),
+        ('cache/', 'cache', False, False),
+        ('cache/', 'src/cache', True, True),
+        ('/cache', 'src/cache', True, False),
+        ('src/*.tmp', 'src/a.tmp', False, True),
+        ('src/*.tmp', 'other/src/a.tmp', False, False),
+        ('**/build', 'a/b/build', True, True),
+        ('docs/**', 'docs/a/b.md', False, True),
+        ('a/**/z', 'a/z', False, True),
+        ('a/**/z', 'a/b/c/z', False, True),
+        ('file?.txt', 'file1.txt', False, True),
+        ('file?.txt', 'file10.txt', False, False),
+        ('*.py[co]', 'x.pyc', False, True),
+        ('*.py[!co]', 'x.pyc', False, False),
+        ('# comment', '# comment', False, False),
+    ])
+def test_patterns(pattern, path, is_dir, ignored):
+    assert ignored == m.IgnoreRules([pattern]).is_ignored(path, is_dir)
+
+
+def test_last_matching_rule_decides():
+    rules = m.IgnoreRules(['*.csv', '!keep.csv'])
+    assert rules.is_ignored('data.csv', False)
+    assert not rules.is_ignored('keep.csv', False)
+
+
+def test_defaults_can_be_overridden(tmp_path):
+    assert m.load(tmp_path).is_ignored('.git', True)
+    assert m.load(tmp_path).is_ignored('src/__pycache__', True)
+
+    (tmp_path / m.IGNORE_FILE).write_text('# keep the repository\n!.git/\n*.bak\n')
+    rules = m.load(tmp_path)
+    assert not rules.is_ignored('.git', True)
+    assert rules.is_ignored('old.bak', False)
@@ -0,0 +1,45 @@
+import json
+
+from . import notebooks as m
+
+
+NOTEBOOK = {
+    'cells': [
+        {'cell_type': 'markdown', 'metadata': {}, 'source': ['# Title']},
+        {
+            'cell_type': 'code',
+            'execution_count': 3,
+            'metadata': {},
+            'outputs': [{'output_type': 'stream', 'name': 'stdout', 'text': ['42\n']}],
+            'source': ['print(42)'],
+        },
+    ],
+    'metadata': {},
+    'nbformat': 4,
+    'nbformat_minor': 5,
+}
+
+
+def test_strip_outputs():
+    stripped = json.loads(m.strip_outputs(json.dumps(NOTEBOOK).encode('utf-8')))
+
+    code_cell = stripped['cells'][1]
+    assert [] == code_cell['outputs']
+    assert code_cell['execution_count'] is None
+    assert ['print(42)'] == code_cell['source']
+    assert NOTEBOOK['cells'][0] == stripped['cells'][0]
+
+
+def test_strip_outputs_is_stable():
+    stripped = m.strip_outputs(json.dumps(NOTEBOOK).encode('utf-8'))
+    assert stripped == m.strip_outputs(stripped)
+
+
+def test_non_notebook_content_is_unchanged():
+    for content in (b'not json', b'{"no": "cells"}', b'\xff\xfe'):
+        assert content == m.strip_outputs(content)
+
+
+def test_is_notebook():
+    assert m.is_notebook('code/analysis.ipynb')
+    assert not m.is_notebook('code/analysis.py')
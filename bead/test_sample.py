@@ -0,0 +1,79 @@
+import json
+
+from . import sample as m
+from .archive import Archive
+from .tech.fs import ensure_directory, write_file
+from .workspace import Workspace
+
+TS = '20200101T000000000000+0000'
+
+
+def make_archive(tmp_path, files):
+    workspace = Workspace(tmp_path / 'workspace')
+    workspace.create('kind')
+    for path, content in files.items():
+        ensure_directory((workspace.directory / 'output' / path).parent)
+        write_file(workspace.directory / 'output' / path, content)
+    workspace.pack(tmp_path / 'bead.zip', TS, 'comment')
+    return Archive(tmp_path / 'bead.zip')
+
+
+def csv_content(rows):
+    return 'id,value\n' + ''.join(f'{i},"v,{i}"\n' for i in range(rows))
+
+
+def test_choose_rows_keeps_order():
+    import random
+    chosen = m.choose_rows(range(100), 10, random.Random(1))
+    assert 10 == len(chosen)
+    assert sorted(chosen) == chosen
+
+
+def test_choose_rows_keeps_all_when_few():
+    import random
+    assert [0, 1, 2] == m.choose_rows(range(3), 10, random.Random(1))
+
+
+def test_sample_csv_keeps_header(tmp_path):
+    archive = make_archive(tmp_path, {'table.csv': csv_content(100)})
+
+    result = m.sample(archive, 10, tmp_path / 'out')
+
+    assert [('table.csv', 10)] == result.sampled
+    lines = (tmp_path / 'out' / 'table.csv').read_text().splitlines()
+    assert 'id,value' == lines[0]
+    assert 11 == len(lines)
+    assert all(',"v,' in line for line in lines[1:])
+
+
+def test_sample_is_deterministic(tmp_path):
+    archive = make_archive(tmp_path, {'table.csv': csv_content(1000), 'sub/rows.jsonl': ''.join(
+        json.dumps({'i': i}) + '\n' for i in range(1000))})
+
+    m.sample(archive, 20, tmp_path / 'out1')
+    m.sample(Archive(archive.archive_path), 20, tmp_path / 'out2')
+
+    for path in ('table.csv', 'sub/rows.jsonl'):
+        assert (tmp_path / 'out1' / path).read_text() == (tmp_path / 'out2' / path).read_text()
+    assert 20 == len((tmp_path / 'out1/sub/rows.jsonl').read_text().splitlines())
+
+
+def test_non_tabular_files_are_skipped(tmp_path):
+    archive = make_archive(tmp_path, {'figure.png': 'binary', 'a.tsv': 'x\ty\n1\t2\n'})
+
+    result = m.sample(archive, 10, tmp_path / 'out')
+
+    assert [('a.tsv', 1)] == result.sampled
+    assert ['figure.png'] == result.skipped
+    assert not (tmp_path / 'out' / 'figure.png').exists()
+
+
+def test_max_files(tmp_path):
+    files = {f'{i}.csv': csv_content(5) for i in range(5)}
+    archive = make_archive(tmp_path, files)
+
+    result = m.sample(archive, 10, tmp_path / 'out', max_files=2)
+
+    assert 2 == len(result.sampled)
+    assert 3 == len(result.skipped)
+    assert result == m.sample(archive, 10, tmp_path / 'out2', max_files=2)
@@ -0,0 +1,88 @@
+import pytest
+
+from .tech.fs import write_file
+from .workspace import Workspace
+from . import save_policy as m
+
+
+@pytest.fixture
+def workspace(tmp_path):
+    workspace = Workspace(tmp_path / 'bead')
+    workspace.create('kind')
+    return workspace
+
+
+def messages(violations):
+    return [str(violation) for violation in violations]
+
+
+def test_no_policies(workspace):
+    assert [] == m.check(workspace, {})
+
+
+def test_non_empty_output(workspace):
+    policies = {m.NON_EMPTY_OUTPUT: True}
+    assert ['non-empty-output: output is empty'] == messages(m.check(workspace, policies))
+
+    write_file(workspace.directory / 'output/result.csv', 'a,b')
+    assert [] == m.check(workspace, policies)
+
+
+def test_max_file_size(workspace):
+    write_file(workspace.directory / 'code.py', 'x' * 100)
+    write_file(workspace.directory / 'output/data', 'x' * 10)
+    # temp is not saved
+    write_file(workspace.directory / 'temp/big', 'x' * 1000)
+
+    # ignored files are not saved either
+    write_file(workspace.directory / '.beadignore', 'big.log\n')
+    write_file(workspace.directory / 'big.log', 'x' * 1000)
+
+    [violation] = m.check(workspace, {m.MAX_FILE_SIZE: '50B'})
+    assert 'code.py is 100 bytes' in violation.message
+    assert [] == m.check(workspace, {m.MAX_FILE_SIZE: 100})
+
+
+def test_required_files(workspace):
+    policies = {m.REQUIRED_FILES: ['README.md', 'LICENSE']}
+    write_file(workspace.directory / 'README.md', '# bead')
+
+    assert ['required-files: LICENSE is missing'] == messages(m.check(workspace, policies))
+
+
+def test_inputs_resolve(workspace):
+    workspace.add_input('missing', 'kind', 'content-id', '20160704T000000000000+0000')
+
+    class NoBoxes:
+        def find_bead(self, name, content_id):
+            return None
+
+    [violation] = m.check(workspace, {m.INPUTS_RESOLVE: True}, NoBoxes())
+    assert 'missing' in violation.message
+
+
+def test_disabled_policy_is_not_checked(workspace):
+    assert [] == m.check(workspace, {m.NON_EMPTY_OUTPUT: False, m.MAX_FILE_SIZE: None})
+
+
+def test_unknown_policy(workspace):
+    with pytest.raises(m.PolicyError):
+        m.check(workspace, {'no-bugs': True})
+
+
+@pytest.mark.parametrize(
+    'size, expected', [
+        (10, 10),
+        ('10', 10),
+        ('10GB', 10 * 1000 ** 3),
+        ('1.5 KiB', 1536),
+        ('2mb', 2 * 1000 ** 2),
+    ])
+def test_parse_size(size, expected):
+    assert expected == m.parse_size(size)
+
+
+@pytest.mark.parametrize('size', ['', 'ten', '10 XB', True])
+def test_parse_invalid_size(size):
+    with pytest.raises(m.PolicyError):
+        m.parse_size(size)
@@ -0,0 +1,91 @@
+import shutil
+import subprocess
+
+import pytest
+
+from .archive import Archive
+from .box import Box
+from .exceptions import SignatureError
+from .workspace import Workspace
+from . import signing as m
+
+pytestmark = pytest.mark.skipif(
+    shutil.which(m.SSH_KEYGEN) is None, reason='OpenSSH is not available')
+
+
+def make_key(directory, name):
+    key = directory / name
+    subprocess.run(
+        [m.SSH_KEYGEN, '-t', 'ed25519', '-N', '', '-q', '-C', name, '-f', key], check=True)
+    return key
+
+
+@pytest.fixture
+def keys(tmp_path_factory):
+    directory = tmp_path_factory.mktemp('keys')
+    return make_key(directory, 'alice'), make_key(directory, 'mallory')
+
+
+@pytest.fixture
+def box(tmp_path_factory):
+    return Box('signed', tmp_path_factory.mktemp('box'))
+
+
+@pytest.fixture
+def workspace(tmp_path_factory):
+    workspace = Workspace(tmp_path_factory.mktemp('workspace') / 'bead')
+    workspace.create('kind')
+    return workspace
+
+
+def test_signed_archive_is_verified(box, workspace, keys):
+    alice, _ = keys
+    path = box.store(workspace, '20200101T000000000000+0000', signing_key=alice).archive_path
+
+    archive = Archive(path)
+    assert archive.is_signed
+    archive.verify_signature(alice.with_suffix('.pub'))
+
+
+def test_signature_of_another_key_is_refused(box, workspace, keys):
+    alice, mallory = keys
+    path = box.store(workspace, '20200101T000000000000+0000', signing_key=mallory).archive_path
+
+    with pytest.raises(SignatureError):
+        Archive(path).verify_signature(alice.with_suffix('.pub'))
+
+
+def test_modified_archive_is_refused(box, workspace, keys):
+    alice, _ = keys
+    path = box.store(workspace, '20200101T000000000000+0000', signing_key=alice).archive_path
+    with open(path, 'ab') as f:
+        f.write(b'tampered')
+
+    with pytest.raises(SignatureError):
+        m.verify(path, alice.with_suffix('.pub'))
+
+
+def test_unsigned_archive_is_refused(box, workspace, keys):
+    alice, _ = keys
+    path = box.store(workspace, '20200101T000000000000+0000').archive_path
+
+    assert not Archive(path).is_signed
+    with pytest.raises(SignatureError):
+        Archive(path).verify_signature(alice.with_suffix('.pub'))
+
+
+def test_failed_signing_does_not_store_archive(box, workspace, tmp_path):
+    with pytest.raises(SignatureError):
+        box.store(workspace, '20200101T000000000000+0000', signing_key=tmp_path / 'missing')
+    assert not list(box.directory.glob('*.zip'))
+
+
+def test_signature_is_deleted_and_restored_with_archive(box, workspace, keys):
+    alice, _ = keys
+    path = box.store(workspace, '20200101T000000000000+0000', signing_key=alice).archive_path
+
+    box.delete(path.name)
+    assert not m.signature_path(path).exists()
+
+    box.restore(path.name)
+    Archive(path).verify_signature(alice.with_suffix('.pub'))
@@ -0,0 +1,64 @@
+import os
+
+import pytest
+
+from . import templates
+from .tech.fs import ensure_directory, write_file
+from .workspace import Workspace
+
+
+@pytest.fixture
+def template(tmp_path):
+    template = tmp_path / 'template'
+    ensure_directory(template / 'src')
+    write_file(template / 'src/main.py', 'print(42)')
+    write_file(template / 'Makefile', 'all:')
+    # managed by bead - not copied
+    ensure_directory(template / 'output')
+    write_file(template / 'output/result', 'stale')
+    return template
+
+
+def test_create_from_template_directory(template, tmp_path):
+    workspace = Workspace(tmp_path / 'workspace')
+    workspace.create_from_template('kind', template)
+
+    assert workspace.is_valid
+    assert 'print(42)' == (workspace.directory / 'src/main.py').read_text()
+    assert (workspace.directory / 'Makefile').is_file()
+    assert [] == os.listdir(workspace.directory / 'output')
+
+
+def test_create_from_builtin_template(tmp_path):
+    workspace = Workspace(tmp_path / 'workspace')
+    workspace.create_from_template('kind', 'make')
+
+    assert {'Makefile', 'README.md', 'src'} <= set(os.listdir(workspace.directory))
+    assert '{name}' not in (workspace.directory / 'README.md').read_text()
+
+
+@pytest.mark.parametrize('name, main', [('r', 'src/main.R'), ('stata', 'src/main.do')])
+def test_create_from_language_template(name, main, tmp_path):
+    workspace = Workspace(tmp_path / 'workspace')
+    workspace.create_from_template('kind', name)
+
+    assert workspace.is_valid
+    assert (workspace.directory / main).is_file()
+    assert main in (workspace.directory / 'Makefile').read_text()
+
+
+def test_resolve(template, tmp_path):
+    assert template == templates.resolve(os.fspath(template))
+    assert template == templates.resolve('template', tmp_path)
+    assert 'minimal' == templates.resolve('minimal', tmp_path)
+    with pytest.raises(LookupError):
+        templates.resolve('unknown', tmp_path)
+
+
+def test_template_directory_hides_builtins(tmp_path):
+    ensure_directory(tmp_path / 'minimal')
+
+    available = templates.available(tmp_path)
+
+    assert tmp_path / 'minimal' == available['minimal']
+    assert 'make' == available['make']
@@ -0,0 +1,51 @@
+import sys
+
+import pytest
+
+from .workspace import Workspace
+from . import validators as m
+
+
+@pytest.fixture
+def archive_path(tmp_path):
+    workspace = Workspace(tmp_path / 'bead')
+    workspace.create('kind')
+    (workspace.directory / 'code.py').write_text('print(1)')
+    path = tmp_path / 'bead.zip'
+    workspace.pack(path, '20160704T000000000000+0000', 'comment')
+    return path
+
+
+def python(code):
+    return [sys.executable, '-c', code]
+
+
+def test_no_validators(archive_path):
+    m.run([], archive_path)
+
+
+def test_archive_path_is_passed(archive_path):
+    m.run([python('import sys, zipfile; zipfile.ZipFile(sys.argv[1])')], archive_path)
+
+
+def test_manifest_is_passed_on_stdin(archive_path):
+    check = 'import json, sys; assert "code/code.py" in json.load(sys.stdin)'
+    m.run([{'command': python(check), 'stdin': m.STDIN_MANIFEST}], archive_path)
+
+
+def test_failing_validator_rejects(archive_path):
+    reject = 'import sys; print("PII found", file=sys.stderr); sys.exit(1)'
+    with pytest.raises(m.ValidatorError) as exc_info:
+        m.run([python(reject)], archive_path)
+    assert 'PII found' in str(exc_info.value)
+
+
+def test_missing_validator_command(archive_path):
+    with pytest.raises(m.ValidatorError):
+        m.run(['surely-not-an-existing-validator-command'], archive_path)
+
+
+@pytest.mark.parametrize('validator', ['', {'stdin': 'path'}, {'command': 'x', 'stdin': 'env'}])
+def test_invalid_configuration(archive_path, validator):
+    with pytest.raises(m.ValidatorError):
+        m.run([validator], archive_path)
@@ -1,15 +1,29 @@
-from bead.exceptions import InvalidArchive
+from bead.exceptions import InvalidArchive, ContentMismatch, UnsupportedHashAlgorithm
+from bead.exceptions import UserError, WorkspaceLocked
 from . import workspace as m
 
+import json
 import os
+import stat
+import threading
+import time
+import warnings
 import zipfile
 import pytest
 
 from .archive import Archive
+from .box import Box
 from . import layouts
+from . import notebooks
 from . import tech
+from . import ziparchive
+from .tech.cancellation import CancellationToken, Cancelled
+from .tech.checkpoint import Checkpoint
+from .tech.lock import FileLock
+from .tech.progress import Progress
 
 write_file = tech.fs.write_file
+read_file = tech.fs.read_file
 ensure_directory = tech.fs.ensure_directory
 temp_dir = tech.fs.temp_dir
 timestamp = tech.timestamp.timestamp
@@ -146,6 +160,29 @@ def test_pack_not_saved_content(packed_archive):
     does_not_contain(layouts.Workspace.TEMP / 'README')
 
 
+def test_pack_skips_ignored_files(pack_workspace, tmp_path):
+    """Test that default and .beadignore patterns are left out of the archive."""
+    directory = pack_workspace.directory
+    write_file(directory / '.beadignore', '*.log\n!keep.log\n')
+    ensure_directory(directory / '__pycache__')
+    write_file(directory / '__pycache__/source1.pyc', 'bytecode')
+    write_file(directory / 'debug.log', 'debug')
+    write_file(directory / 'keep.log', 'keep')
+    write_file(directory / 'output/run.log', 'run')
+    archive_path = tmp_path / 'bead.zip'
+
+    pack_workspace.pack(archive_path, timestamp(), BEAD_COMMENT)
+
+    with zipfile.ZipFile(archive_path) as z:
+        names = set(z.namelist())
+    assert 'code/.beadignore' in names
+    assert 'code/keep.log' in names
+    assert 'code/debug.log' not in names
+    assert 'code/__pycache__/source1.pyc' not in names
+    assert 'data/run.log' not in names
+    Archive(archive_path).validate()
+
+
 def test_pack_archive_has_comment(packed_archive):
     """Test that the archive has the expected comment."""
     with zipfile.ZipFile(packed_archive) as z:
@@ -153,6 +190,286 @@ def test_pack_archive_has_comment(packed_archive):
         assert BEAD_COMMENT == comment
 
 
+def test_pack_strips_notebook_outputs_in_code(pack_workspace, tmp_path):
+    notebook = json.dumps({
+        'cells': [{
+            'cell_type': 'code', 'execution_count': 1, 'metadata': {},
+            'outputs': [{'output_type': 'stream', 'name': 'stdout', 'text': ['result']}],
+            'source': ['print("result")']}],
+        'metadata': {}, 'nbformat': 4, 'nbformat_minor': 5})
+    write_file(pack_workspace.directory / 'analysis.ipynb', notebook)
+    write_file(pack_workspace.directory / 'output/report.ipynb', notebook)
+    zipfile_path = tmp_path / 'bead.zip'
+
+    pack_workspace.pack(
+        zipfile_path, timestamp(), BEAD_COMMENT, notebook_outputs=notebooks.STRIP)
+
+    archive = Archive(zipfile_path)
+    archive.validate()
+    with zipfile.ZipFile(zipfile_path) as z:
+        assert [] == json.loads(z.read('code/analysis.ipynb'))['cells'][0]['outputs']
+        # outputs are results, they are kept
+        assert notebook == z.read('data/report.ipynb').decode('utf-8')
+
+
+def test_pack_with_other_hash_algorithm(pack_workspace, tmp_path):
+    zipfile_path = tmp_path / 'bead.zip'
+
+    pack_workspace.pack(
+        zipfile_path, timestamp(), BEAD_COMMENT,
+        hash_algorithm=tech.securehash.HashAlgo.BLAKE2B)
+
+    archive = Archive(zipfile_path)
+    archive.validate()
+    assert archive.content_id.startswith('blake2b:')
+    assert all(hash.startswith('blake2b:') for hash in archive.ziparchive.manifest.values())
+    workspace = m.Workspace.from_archive(archive, tmp_path / 'developed', extract_output=True)
+    assert workspace.is_valid
+
+
+def test_output_manifest_matches_archive_manifest(pack_workspace, packed_archive):
+    manifest = Archive(packed_archive).ziparchive.manifest
+
+    assert {'data/output1': manifest['data/output1']} == pack_workspace.output_manifest()
+
+
+def test_output_dirty_detection(pack_workspace, packed_archive):
+    archive = Archive(packed_archive)
+    output_dir = pack_workspace.directory / layouts.Workspace.OUTPUT
+    assert not pack_workspace.is_output_dirty(archive)
+
+    # rewritten, but the same
+    write_file(output_dir / 'output1', OUTPUT1)
+    assert not pack_workspace.is_output_dirty(archive)
+
+    write_file(output_dir / 'output1', b'changed')
+    assert pack_workspace.is_output_dirty(archive)
+
+    write_file(output_dir / 'output1', OUTPUT1)
+    write_file(output_dir / 'output2', OUTPUT1)
+    assert pack_workspace.is_output_dirty(archive)
+
+    os.remove(output_dir / 'output2')
+    os.remove(output_dir / 'output1')
+    assert pack_workspace.is_output_dirty(archive)
+
+
+def test_output_changed_with_old_modification_time_is_dirty(pack_workspace, packed_archive):
+    archive = Archive(packed_archive)
+    output1 = pack_workspace.directory / layouts.Workspace.OUTPUT / 'output1'
+    # e.g. copied with `cp -p`
+    write_file(output1, OUTPUT1[:-1] + b'!')
+    os.utime(output1, (1e9, 1e9))
+
+    assert pack_workspace.is_output_dirty(archive)
+
+
+def test_dirty_detection(pack_workspace, packed_archive):
+    archive = Archive(packed_archive)
+    assert not pack_workspace.is_dirty(archive)
+
+    write_file(pack_workspace.directory / 'source1', 'changed code')
+    assert pack_workspace.is_dirty(archive)
+
+    write_file(pack_workspace.directory / 'source1', SOURCE1)
+    assert not pack_workspace.is_dirty(archive)
+    pack_workspace.add_input('new-input', 'kind', 'content-id', timestamp())
+    assert pack_workspace.is_dirty(archive)
+
+
+def test_code_diff_against_developed_archive(pack_workspace, tmp_path):
+    box = Box('box', tmp_path / 'box')
+    ensure_directory(box.directory)
+    archive = box.store(pack_workspace, timestamp())
+    workspace = m.Workspace.from_archive(archive, tmp_path / 'developed')
+    assert archive.content_id == workspace.origin_content_id
+
+    diff = workspace.code_diff([box])
+    assert not diff.is_changed
+
+    write_file(workspace.directory / 'source1', b'changed')
+    os.remove(workspace.directory / 'subdir/source2')
+    write_file(workspace.directory / 'subdir/source3', SOURCE2)
+    write_file(workspace.directory / layouts.Workspace.OUTPUT / 'output2', OUTPUT1)
+    diff = workspace.code_diff([box])
+    assert (('subdir/source3',), ('source1',), ('subdir/source2',)) == (
+        diff.added, diff.modified, diff.deleted)
+    assert diff == workspace.status([box]).code_changes
+
+
+def test_description_tags_and_author_are_saved(pack_workspace, tmp_path):
+    pack_workspace.set_description('monthly sales')
+    pack_workspace.set_author('Alice')
+    pack_workspace.add_tag('sales')
+    pack_workspace.add_tag('finance')
+    pack_workspace.add_tag('sales')
+    assert ('finance', 'sales') == pack_workspace.tags
+    with pytest.raises(ValueError):
+        pack_workspace.add_tag('two words')
+
+    zipfile_path = tmp_path / 'bead.zip'
+    pack_workspace.pack(zipfile_path, timestamp(), BEAD_COMMENT)
+
+    archive = Archive(zipfile_path)
+    archive.validate()
+    assert ('monthly sales', 'Alice', ('finance', 'sales')) == (
+        archive.description, archive.author, archive.tags)
+
+
+def test_annotations_are_absent_unless_set(pack_workspace, packed_archive):
+    pack_workspace.add_tag('sales')
+    assert pack_workspace.remove_tag('sales')
+    assert not pack_workspace.remove_tag('sales')
+    pack_workspace.set_description('')
+
+    bead_meta = Archive(packed_archive).ziparchive.meta
+    assert not set(bead_meta) & {'description', 'tags', 'author'}
+    assert not set(pack_workspace.meta) & {'description', 'tags', 'author'}
+
+
+def test_data_directory_is_stored_as_output(tmp_path):
+    ensure_directory(tmp_path / 'dataset/.git')
+    write_file(tmp_path / 'dataset/.git/data', 'kept')
+    write_file(tmp_path / 'dataset/table.csv', 'a,b\n')
+    box = Box('box', tmp_path / 'box')
+    ensure_directory(box.directory)
+
+    data = m.DataDirectory(tmp_path / 'dataset', 'dataset', A_KIND, description='prices')
+    archive = box.store(data, timestamp())
+
+    archive.validate()
+    assert ('dataset', A_KIND, 'prices', ()) == (
+        archive.name, archive.kind, archive.description, archive.inputs)
+    manifest = archive.ziparchive.manifest
+    assert {'data/.git/data', 'data/table.csv'} == {
+        zip_path for zip_path in manifest if not zip_path.startswith('meta/')}
+
+
+def test_code_diff_without_developed_archive(pack_workspace, tmp_path):
+    assert pack_workspace.origin_content_id is None
+    assert pack_workspace.code_diff([]) is None
+
+
+def test_output_dirty_detection_uses_the_hash_algorithm_of_archive(pack_workspace, tmp_path):
+    zipfile_path = tmp_path / 'bead.zip'
+    pack_workspace.pack(
+        zipfile_path, timestamp(), BEAD_COMMENT,
+        hash_algorithm=tech.securehash.HashAlgo.BLAKE2B)
+    write_file(pack_workspace.directory / layouts.Workspace.OUTPUT / 'output1', OUTPUT1)
+
+    assert not pack_workspace.is_output_dirty(Archive(zipfile_path))
+
+
+def test_unknown_hash_algorithm_is_reported(pack_workspace, tmp_path):
+    zipfile_path = tmp_path / 'bead.zip'
+    pack_workspace.pack(zipfile_path, timestamp(), BEAD_COMMENT)
+    with zipfile.ZipFile(zipfile_path) as z:
+        entries = {name: z.read(name) for name in z.namelist()}
+    manifest = json.loads(entries[layouts.Archive.MANIFEST])
+    manifest = {name: 'future:' + hash for name, hash in manifest.items()}
+    entries[layouts.Archive.MANIFEST] = json.dumps(manifest).encode('utf-8')
+    rewritten = tmp_path / 'future.zip'
+    with zipfile.ZipFile(rewritten, 'w') as z:
+        for name, content in entries.items():
+            z.writestr(name, content)
+
+    with pytest.raises(UnsupportedHashAlgorithm):
+        Archive(rewritten).content_id
+    with pytest.raises(UnsupportedHashAlgorithm):
+        Archive(rewritten).validate()
+
+
+def test_pack_orders_entries_by_name(tmp_path, monkeypatch):
+    """Test that archive entries do not depend on the directory listing order."""
+    workspace = m.Workspace(tmp_path / 'workspace')
+    workspace.create(A_KIND)
+    for name in ('b', 'c', 'a'):
+        write_file(workspace.directory / layouts.Workspace.OUTPUT / name, name)
+    listdir = os.listdir
+    monkeypatch.setattr(os, 'listdir', lambda path: list(reversed(sorted(listdir(path)))))
+    zipfile_path = tmp_path / 'bead.zip'
+
+    workspace.pack(zipfile_path, timestamp(), BEAD_COMMENT)
+
+    with zipfile.ZipFile(zipfile_path) as z:
+        data_entries = [name for name in z.namelist() if name.startswith('data/')]
+    assert ['data/a', 'data/b', 'data/c'] == data_entries
+
+
+def test_pack_cancelled_leaves_no_archive(pack_workspace, tmp_path):
+    """Test that a cancelled pack removes the partial archive."""
+    cancel = CancellationToken()
+    cancel.cancel()
+    zipfile_path = tmp_path / 'bead.zip'
+
+    with pytest.raises(Cancelled):
+        pack_workspace.pack(zipfile_path, timestamp(), BEAD_COMMENT, cancel=cancel)
+
+    assert not zipfile_path.exists()
+
+
+def test_pack_interrupted_leaves_no_archive(pack_workspace, tmp_path, monkeypatch):
+    """Test that Ctrl-C during pack removes the partial archive."""
+    def interrupt(*args, **kwargs):
+        raise KeyboardInterrupt
+    monkeypatch.setattr(tech.securehash, 'copy', interrupt)
+    zipfile_path = tmp_path / 'bead.zip'
+
+    with pytest.raises(KeyboardInterrupt):
+        pack_workspace.pack(zipfile_path, timestamp(), BEAD_COMMENT)
+
+    assert not zipfile_path.exists()
+
+
+class RecordingProgress(Progress):
+    def __init__(self):
+        self.events = []
+        self.bytes_done = 0
+
+    def start(self, total_bytes, total_files):
+        self.events.append(('start', total_bytes, total_files))
+
+    def file_started(self, path):
+        self.events.append(('file', path))
+
+    def advance(self, byte_count):
+        self.bytes_done += byte_count
+
+    def file_done(self, path):
+        self.events.append(('done', path))
+
+    def finish(self):
+        self.events.append(('finish',))
+
+
+def test_pack_reports_progress(pack_workspace, tmp_path):
+    """Test that packing reports the files and bytes saved."""
+    progress = RecordingProgress()
+    pack_workspace.pack(tmp_path / 'bead.zip', timestamp(), BEAD_COMMENT, progress=progress)
+
+    total_bytes = len(OUTPUT1) + len(SOURCE1) + len(SOURCE2)
+    assert [
+        ('start', total_bytes, 3),
+        ('file', 'data/output1'), ('done', 'data/output1'),
+        ('file', 'code/source1'), ('done', 'code/source1'),
+        ('file', 'code/subdir/source2'), ('done', 'code/subdir/source2'),
+        ('finish',),
+    ] == progress.events
+    assert total_bytes == progress.bytes_done
+
+
+def test_from_archive_cancelled_leaves_no_workspace(packed_archive, tmp_path):
+    """Test that a cancelled develop removes the partial workspace."""
+    cancel = CancellationToken()
+    cancel.cancel()
+    directory = tmp_path / 'develop'
+
+    with pytest.raises(Cancelled):
+        m.Workspace.from_archive(Archive(packed_archive), directory, cancel=cancel)
+
+    assert not directory.exists()
+
+
 def test_pack_stability_directory_name_data_and_timestamp_determines_content_ids(tmp_path_factory):
     """Test that content IDs are stable based on directory name, data, and timestamp."""
     TS = '20150910T093724802366+0200'
@@ -175,6 +492,80 @@ def test_pack_stability_directory_name_data_and_timestamp_determines_content_ids
     assert bead1.content_id == bead2.content_id
 
 
+def test_pack_of_same_content_is_byte_identical(tmp_path_factory):
+    """Test that file times and permissions do not leak into archives."""
+    TS = '20150910T093724802366+0200'
+
+    def make_archive(mtime, mode):
+        temp_dir = tmp_path_factory.mktemp("bead_bytes")
+        ws = m.Workspace(temp_dir / 'workspace')
+        ws.create(A_KIND)
+        write_file(ws.directory / 'source1', 'code to produce output')
+        write_file(ws.directory / 'output/output1', TS)
+        for path in (ws.directory / 'source1', ws.directory / 'output/output1'):
+            os.chmod(path, mode)
+            os.utime(path, (mtime, mtime))
+        ws.pack(temp_dir / 'bead.zip', TS, comment='')
+        return (temp_dir / 'bead.zip').read_bytes()
+
+    assert make_archive(1e9, 0o600) == make_archive(1.5e9, 0o664)
+
+
+def test_pack_with_parallel_hashing_is_identical(tmp_path_factory, monkeypatch):
+    """Test that hashing by threads makes the same archive as hashing while writing."""
+    TS = '20150910T093724802366+0200'
+
+    def make_archive(threads):
+        monkeypatch.setenv(tech.securehash.HASH_THREADS_ENV_VAR, str(threads))
+        temp_dir = tmp_path_factory.mktemp("bead_parallel")
+        ws = m.Workspace(temp_dir / 'workspace')
+        ws.create(A_KIND)
+        write_file(ws.directory / 'source1', 'code to produce output')
+        for i in range(20):
+            write_file(ws.directory / f'output/output{i}', f'{i}' * i)
+        ws.pack(temp_dir / 'bead.zip', TS, comment='')
+        return (temp_dir / 'bead.zip').read_bytes()
+
+    monkeypatch.setattr(m, 'PARALLEL_HASH_MIN_SIZE', 0)
+    assert make_archive(1) == make_archive(4)
+
+
+def test_pack_with_parallel_hashing_reads_files_once(pack_workspace, tmp_path, monkeypatch):
+    """Test that the hashed content is the archived content - it is not read again."""
+    for i in range(5):
+        write_file(pack_workspace.directory / f'output/output{i}', f'{i}' * 1000)
+    monkeypatch.setenv(tech.securehash.HASH_THREADS_ENV_VAR, '4')
+    monkeypatch.setattr(m, 'PARALLEL_HASH_MIN_SIZE', 0)
+    opened = []
+    real_open = open
+
+    def recording_open(file, *args, **kwargs):
+        opened.append(str(file))
+        return real_open(file, *args, **kwargs)
+    monkeypatch.setattr('builtins.open', recording_open)
+
+    pack_workspace.pack(tmp_path / 'bead.zip', timestamp(), BEAD_COMMENT)
+
+    monkeypatch.undo()
+    outputs = [path for path in opened if os.path.basename(path).startswith('output')]
+    assert sorted(outputs) == sorted(set(outputs))
+    assert 5 <= len(outputs)
+    assert [] == Archive(tmp_path / 'bead.zip').check()
+
+
+def test_pack_keeps_executable_bit(pack_workspace, tmp_path):
+    script = pack_workspace.directory / 'run.sh'
+    write_file(script, '#!/bin/sh')
+    os.chmod(script, 0o700)
+    zipfile_path = tmp_path / 'bead.zip'
+
+    pack_workspace.pack(zipfile_path, timestamp(), BEAD_COMMENT)
+
+    with zipfile.ZipFile(zipfile_path) as z:
+        assert m.ZIP_EXECUTABLE_MODE == (z.getinfo('code/run.sh').external_attr >> 16) & 0o777
+        assert m.ZIP_FILE_MODE == (z.getinfo('meta/bead').external_attr >> 16) & 0o777
+
+
 def make_bead(path, filespecs, tmp_path_factory):
     """Helper function to create a bead with specified files."""
     temp_dir = tmp_path_factory.mktemp("make_bead")
@@ -218,6 +609,29 @@ def test_load_makes_bead_files_available_under_input(load_workspace, tmp_path_fa
     assert content == b'data for bead1'
 
 
+def test_loaded_files_are_newer_than_the_zip_entries(load_workspace, tmp_path_factory):
+    """Test that make-style pipelines see freshly loaded inputs as new."""
+    _load_a_bead(load_workspace, 'bead1', tmp_path_factory)
+
+    mtime = (load_workspace.directory / 'input/bead1/output1').stat().st_mtime
+    assert mtime > time.mktime(m.ZIP_ENTRY_DATE_TIME + (0, 0, -1)) + 24 * 3600
+
+
+def test_load_reports_progress(load_workspace, tmp_path_factory):
+    """Test that loading reports the extracted data."""
+    archive_path = tmp_path_factory.mktemp('beads') / 'bead.zip'
+    make_bead(
+        archive_path, {'output/data1': b'12345', 'output/data2': b'678'}, tmp_path_factory)
+    progress = RecordingProgress()
+
+    load_workspace.load('bead', Archive(archive_path), progress=progress)
+
+    assert ('start', 8, 2) == progress.events[0]
+    assert ('finish',) == progress.events[-1]
+    assert {'data/data1', 'data/data2'} == {e[1] for e in progress.events if e[0] == 'done'}
+    assert 8 == progress.bytes_done
+
+
 def test_load_loaded_inputs_are_read_only(load_workspace, tmp_path_factory):
     """Test that loaded input files are read-only."""
     _load_a_bead(load_workspace, 'bead1', tmp_path_factory)
@@ -232,6 +646,23 @@ def test_load_loaded_inputs_are_read_only(load_workspace, tmp_path_factory):
             (root / 'new-file').write_bytes(b'')
 
 
+def test_load_writes_input_marker_on_request(load_workspace, tmp_path_factory):
+    """Test that input markers tell which bead is loaded, once enabled."""
+    _load_a_bead(load_workspace, 'before', tmp_path_factory)
+    load_workspace.set_input_markers(True)
+    _load_a_bead(load_workspace, 'after', tmp_path_factory)
+
+    input_dir = load_workspace.directory / layouts.Workspace.INPUT
+    assert not (input_dir / 'before' / layouts.Workspace.INPUT_MARKER).exists()
+    marker = json.loads((input_dir / 'after' / layouts.Workspace.INPUT_MARKER).read_text())
+    input = load_workspace.get_input('after')
+    assert 'after' == marker['bead_name']
+    assert input.kind == marker['kind']
+    assert input.content_id == marker['content_id']
+    assert input.freeze_time_str == marker['freeze_time']
+    assert marker['loaded_at']
+
+
 def test_load_adds_input_to_bead_meta(load_workspace, tmp_path_factory):
     """Test that loading adds input info to bead meta."""
     _load_a_bead(load_workspace, 'bead1', tmp_path_factory)
@@ -249,6 +680,27 @@ def test_load_loading_more_than_one_bead(load_workspace, tmp_path_factory):
     assert load_workspace.has_input('bead2')
 
 
+def _refuse_chmod(path, mode):
+    raise PermissionError(1, 'Operation not permitted', str(path))
+
+
+def test_load_and_unload_work_without_permission_support(
+        load_workspace, tmp_path_factory, monkeypatch):
+    """Test that load/unload work on file systems not supporting permissions."""
+    monkeypatch.setattr(os, 'chmod', _refuse_chmod)
+
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter('always')
+        _load_a_bead(load_workspace, 'bead1', tmp_path_factory)
+        assert (load_workspace.directory / 'input/bead1/output1').exists()
+
+        load_workspace.unload('bead1')
+        assert not (load_workspace.directory / 'input/bead1').exists()
+
+    assert caught
+    assert all(issubclass(w.category, tech.fs.PermissionsNotSupported) for w in caught)
+
+
 @pytest.fixture
 def input_nick():
     """Provide a test input nickname."""
@@ -301,6 +753,116 @@ def test_input_map_independent_update(workspace_with_input, input_nick):
     assert f'{input_nick2}222' == workspace_with_input.get_input_bead_name(input_nick2)
 
 
+def test_input_map_entry_wins_over_alias(workspace_with_input, input_nick):
+    (workspace_with_input.directory / 'bead-names.toml').write_text(
+        f'{input_nick} = "shared"\n')
+    assert 'shared' == workspace_with_input.get_input_bead_name(input_nick)
+    assert 'shared' == workspace_with_input.input_alias(input_nick).bead_name
+
+    workspace_with_input.set_input_bead_name(input_nick, 'own')
+    assert 'own' == workspace_with_input.get_input_bead_name(input_nick)
+    assert workspace_with_input.input_alias(input_nick) is None
+
+    # following the alias again
+    workspace_with_input.set_input_bead_name(input_nick, 'shared')
+    assert input_nick not in workspace_with_input.input_map
+    assert 'shared' == workspace_with_input.input_alias(input_nick).bead_name
+
+
+def test_rename_input_keeps_bead_name_of_alias(load_workspace, tmp_path_factory):
+    _load_a_bead(load_workspace, 'bead1', tmp_path_factory)
+    (load_workspace.directory / 'bead-names.toml').write_text('bead1 = "shared"\n')
+
+    load_workspace.rename_input('bead1', 'renamed')
+
+    assert 'shared' == load_workspace.get_input_bead_name('renamed')
+
+
+def test_rename_loaded_input(load_workspace, tmp_path_factory):
+    """Test that renaming an input keeps its data, spec and bead name."""
+    _load_a_bead(load_workspace, 'bead1', tmp_path_factory)
+    spec = load_workspace.get_input('bead1')
+
+    load_workspace.rename_input('bead1', 'renamed')
+
+    assert not load_workspace.has_input('bead1')
+    assert not (load_workspace.directory / 'input/bead1').exists()
+    assert load_workspace.is_loaded('renamed')
+    assert 'data for bead1' == read_file(load_workspace.directory / 'input/renamed/output1')
+    assert spec.content_id == load_workspace.get_input('renamed').content_id
+    assert 'bead1' == load_workspace.get_input_bead_name('renamed')
+
+
+@pytest.fixture
+def tables_bead(tmp_path_factory):
+    workspace = m.Workspace(tmp_path_factory.mktemp('tables') / 'workspace')
+    workspace.create(A_KIND)
+    for directory in ('tables', 'raw'):
+        tech.fs.ensure_directory(workspace.directory / 'output' / directory)
+    write_file(workspace.directory / 'output/tables/a.csv', 'a')
+    write_file(workspace.directory / 'output/tables/b.json', 'b')
+    write_file(workspace.directory / 'output/raw/c.csv', 'c')
+    path = workspace.directory.parent / 'tables.zip'
+    workspace.pack(path, timestamp(), 'no comment')
+    return Archive(path)
+
+
+def test_load_partially(load_workspace, tables_bead):
+    """Test that only the selected data files are loaded, and the selection is remembered."""
+    load_workspace.load('tables', tables_bead, patterns=['tables/*.csv'])
+
+    assert ['a.csv'] == os.listdir(load_workspace.directory / 'input/tables/tables')
+    assert not (load_workspace.directory / 'input/tables/raw').exists()
+    assert ('tables/*.csv',) == load_workspace.loaded_patterns('tables')
+    [input] = load_workspace.status([]).inputs
+    assert ('tables/*.csv',) == input.loaded_patterns
+
+    load_workspace.unload('tables')
+    assert load_workspace.loaded_patterns('tables') is None
+    load_workspace.load('tables', tables_bead)
+    assert load_workspace.loaded_patterns('tables') is None
+    assert (load_workspace.directory / 'input/tables/raw/c.csv').exists()
+
+
+def test_load_partially_without_matching_files_is_error(load_workspace, tables_bead):
+    with pytest.raises(UserError):
+        load_workspace.load('tables', tables_bead, patterns=['missing'])
+
+    assert not load_workspace.is_loaded('tables')
+    assert not load_workspace.has_input('tables')
+
+
+def test_rename_keeps_partial_load(load_workspace, tables_bead):
+    load_workspace.load('tables', tables_bead, patterns=['raw'])
+
+    load_workspace.rename_input('tables', 'renamed')
+
+    assert ('raw',) == load_workspace.loaded_patterns('renamed')
+    assert {'renamed': ['raw']} == load_workspace.partial_inputs
+
+
+def test_rename_input_keeps_mapping(workspace_with_input, input_nick):
+    """Test that renaming a not loaded input keeps its input map entry."""
+    workspace_with_input.set_input_bead_name(input_nick, 'mapped')
+
+    workspace_with_input.rename_input(input_nick, 'renamed')
+
+    assert 'mapped' == workspace_with_input.get_input_bead_name('renamed')
+    assert not workspace_with_input.is_loaded('renamed')
+    assert input_nick not in workspace_with_input.input_map
+
+
+def test_rename_input_to_existing_input_fails(workspace_with_input, input_nick):
+    """Test that inputs are not overwritten by renaming."""
+    add_input(workspace_with_input, 'other')
+
+    with pytest.raises(UserError):
+        workspace_with_input.rename_input(input_nick, 'other')
+    with pytest.raises(UserError):
+        workspace_with_input.rename_input(input_nick, 'a/b')
+    assert workspace_with_input.has_input(input_nick)
+
+
 def unzip(archive_path, directory):
     """Helper function to unzip an archive."""
     ensure_directory(directory)
@@ -409,3 +971,274 @@ def test_changing_a_file_makes_the_bead_invalid(unzipped_archive_path, tmp_path)
 
     with pytest.raises(InvalidArchive):
         Archive(modified_archive_path).validate()
+
+
+def test_check_lists_all_damaged_files(unzipped_archive_path, tmp_path):
+    """Test that check reports every damaged file, not just the first one."""
+    os.remove(unzipped_archive_path / layouts.Archive.CODE / 'code1')
+    write_file(unzipped_archive_path / layouts.Archive.DATA / 'data1', b'HACKED')
+    write_file(unzipped_archive_path / layouts.Archive.DATA / 'extra', b'extra')
+    modified_archive_path = tmp_path / 'modified_archive.zip'
+    zip_up(unzipped_archive_path, modified_archive_path)
+
+    damages = Archive(modified_archive_path).check()
+
+    assert {
+        (f'{layouts.Archive.CODE}/code1', ziparchive.MISSING),
+        (f'{layouts.Archive.DATA}/data1', ziparchive.CORRUPT),
+        (f'{layouts.Archive.DATA}/extra', ziparchive.NOT_IN_MANIFEST),
+    } == {(damage.zip_path, damage.problem) for damage in damages}
+    with pytest.raises(InvalidArchive) as exc_info:
+        Archive(modified_archive_path).validate()
+    assert f'{layouts.Archive.CODE}/code1: {ziparchive.MISSING}' in str(exc_info.value)
+
+
+def test_check_skips_and_records_checkpointed_files(unzipped_archive_path, tmp_path):
+    """Test that a resumed check skips files verified before."""
+    write_file(unzipped_archive_path / layouts.Archive.DATA / 'data1', b'HACKED')
+    modified_archive_path = tmp_path / 'modified_archive.zip'
+    zip_up(unzipped_archive_path, modified_archive_path)
+    checkpoint = Checkpoint(tmp_path / 'checkpoint', 'key')
+
+    damages = Archive(modified_archive_path).check(checkpoint=checkpoint)
+    assert [f'{layouts.Archive.DATA}/data1'] == [damage.zip_path for damage in damages]
+    # only the good file is recorded
+    assert f'{layouts.Archive.CODE}/code1' in checkpoint
+    assert f'{layouts.Archive.DATA}/data1' not in checkpoint
+
+    checkpoint.add(f'{layouts.Archive.DATA}/data1')
+    assert [] == Archive(modified_archive_path).check(checkpoint=checkpoint)
+
+
+def test_check_detects_bad_crc(validation_workspace, validation_timestamp, tmp_path):
+    """Test that a bit flip in a stored file is found by the zip CRC check."""
+    write_file(validation_workspace.directory / 'output/data1', b'data1-content')
+    archive_path = tmp_path / 'bead.zip'
+    validation_workspace.pack(archive_path, validation_timestamp, 'comment', compression='stored')
+    content = archive_path.read_bytes()
+    offset = content.index(b'data1-content')
+    archive_path.write_bytes(content[:offset] + b'X' + content[offset + 1:])
+
+    [damage] = Archive(archive_path).check()
+
+    assert f'{layouts.Archive.DATA}/data1' == damage.zip_path
+    assert ziparchive.UNREADABLE == damage.problem
+
+
+def test_from_archive_restores_code_meta_and_input_map(pack_workspace, tmp_path_factory):
+    """Test that a workspace developed from an archive is restored fully."""
+    pack_workspace.add_input('input1', A_KIND, 'content_id', timestamp())
+    pack_workspace.set_input_bead_name('input1', 'bead-for-input1')
+    archive_path = tmp_path_factory.mktemp('archive') / 'bead.zip'
+    pack_workspace.pack(archive_path, timestamp(), BEAD_COMMENT)
+
+    directory = tmp_path_factory.mktemp('develop') / 'developed'
+    ws = m.Workspace.from_archive(Archive(archive_path), directory)
+
+    assert ws.is_valid
+    assert A_KIND == ws.kind
+    assert SOURCE1 == (directory / 'source1').read_bytes()
+    assert SOURCE2 == (directory / 'subdir/source2').read_bytes()
+    assert ws.has_input('input1')
+    assert not ws.is_loaded('input1')
+    assert 'bead-for-input1' == ws.get_input_bead_name('input1')
+    assert not (directory / layouts.Workspace.OUTPUT / 'output1').exists()
+    assert not (directory / layouts.Workspace.INPUT).stat().st_mode & stat.S_IWRITE
+
+
+def test_from_archive_vcs_friendly(pack_workspace, tmp_path_factory):
+    """Test that vcs friendly workspaces have no volatile meta values."""
+    pack_workspace.add_input('input1', A_KIND, 'content_id', timestamp())
+    pack_workspace.set_input_bead_name('input1', 'bead-for-input1')
+    archive_path = tmp_path_factory.mktemp('archive') / 'bead.zip'
+    pack_workspace.pack(archive_path, timestamp(), BEAD_COMMENT)
+
+    directory = tmp_path_factory.mktemp('develop') / 'developed'
+    ws = m.Workspace.from_archive(Archive(archive_path), directory, vcs_friendly=True)
+
+    assert ws.vcs_friendly
+    assert {'inputs', 'kind', 'origin', 'vcs_friendly'} == set(ws.meta)
+    assert 'bead-for-input1' == ws.get_input_bead_name('input1')
+    for meta_file in (layouts.Workspace.BEAD_META, layouts.Workspace.INPUT_MAP):
+        assert (directory / meta_file).read_text().endswith('}\n')
+
+
+def test_vcs_friendly_mode_can_be_turned_off(workspace_dir):
+    """Test that files are written in the original format, when not vcs friendly."""
+    ws = m.Workspace(workspace_dir)
+    ws.create(A_KIND, vcs_friendly=True)
+    assert ws.vcs_friendly
+
+    ws.set_vcs_friendly(False)
+
+    assert not ws.vcs_friendly
+    assert (workspace_dir / layouts.Workspace.BEAD_META).read_text().endswith('}')
+
+
+def test_input_markers_can_be_turned_off(workspace_dir):
+    """Test that the input markers setting is kept in the workspace meta."""
+    ws = m.Workspace(workspace_dir)
+    ws.create(A_KIND, input_markers=True)
+    assert ws.input_markers
+
+    ws.set_input_markers(False)
+
+    assert not ws.input_markers
+
+
+def test_from_archive_extract_output(packed_archive, tmp_path_factory):
+    """Test that output is restored when requested."""
+    directory = tmp_path_factory.mktemp('develop') / 'developed'
+    m.Workspace.from_archive(Archive(packed_archive), directory, extract_output=True)
+
+    assert OUTPUT1 == (directory / layouts.Workspace.OUTPUT / 'output1').read_bytes()
+
+
+def test_load_verifies_data_against_manifest(
+        load_workspace, unzipped_archive_path, tmp_path):
+    """Test that data files not matching the manifest are refused on load."""
+    write_file(unzipped_archive_path / layouts.Archive.DATA / 'data1', b'HACKED')
+    modified_archive_path = tmp_path / 'modified_archive.zip'
+    zip_up(unzipped_archive_path, modified_archive_path)
+
+    with pytest.raises(ContentMismatch):
+        load_workspace.load('hacked', Archive(modified_archive_path))
+
+    assert not load_workspace.has_input('hacked')
+    assert not load_workspace.is_loaded('hacked')
+
+
+def test_validate_input_archive(load_workspace, archive_path, archive_with_two_files_path):
+    """Test that only the recorded input bead is accepted for an input."""
+    load_workspace.load('input', Archive(archive_path))
+
+    load_workspace.validate_input_archive('input', Archive(archive_path))
+    with pytest.raises(ContentMismatch):
+        load_workspace.validate_input_archive('input', Archive(archive_with_two_files_path))
+
+
+NON_ASCII_FILES = {
+    'árvíztűrő tükörfúrógép.txt': 'magyar',
+    '日本語/データ.csv': 'nihongo',
+    'ελληνικά.dat': 'ellinika',
+}
+
+
+def test_non_ascii_names_survive_pack_and_extract(tmp_path_factory):
+    """Test non-ASCII workspace, input and file names through pack/extract."""
+    workspaces = tmp_path_factory.mktemp('workspaces')
+    workspace = m.Workspace(workspaces / 'ősi-ábécé')
+    workspace.create(A_KIND)
+    for name, content in NON_ASCII_FILES.items():
+        ensure_directory((workspace.directory / 'output' / name).parent)
+        write_file(workspace.directory / 'output' / name, content)
+    write_file(workspace.directory / 'kód.py', 'code')
+    workspace.add_input('bemenet-ő', 'kind', 'content-id', timestamp())
+    archive_path = workspaces / 'ősi-ábécé_20150923T010203012345+0200.zip'
+    workspace.pack(archive_path, timestamp(), 'comment')
+
+    archive = Archive(archive_path)
+    archive.validate()
+    assert 'ősi-ábécé' == archive.name
+    assert ['bemenet-ő'] == [input.name for input in archive.inputs]
+
+    extracted = m.Workspace.from_archive(
+        archive, tmp_path_factory.mktemp('extract') / archive.name, extract_output=True)
+    assert 'code' == tech.fs.read_file(extracted.directory / 'kód.py')
+    for name, content in NON_ASCII_FILES.items():
+        assert content == tech.fs.read_file(extracted.directory / 'output' / name)
+
+
+def test_decomposed_names_are_stored_composed(workspace_dir, tmp_path):
+    """Test that NFD file names (e.g. from macOS) are stored as NFC in the archive."""
+    workspace = m.Workspace(workspace_dir)
+    workspace.create(A_KIND)
+    write_file(workspace.directory / 'output' / 'a\u0301.txt', 'data')
+    archive_path = tmp_path / 'bead_20150923T010203012345+0200.zip'
+    workspace.pack(archive_path, timestamp(), 'comment')
+
+    with zipfile.ZipFile(archive_path) as z:
+        assert 'data/\u00e1.txt' in z.namelist()
+
+
+@pytest.mark.skipif(os.name != 'posix', reason='needs byte file names')
+def test_non_utf8_file_name_is_refused(workspace_dir, tmp_path):
+    """Test that names not representable in the archive fail packing cleanly."""
+    workspace = m.Workspace(workspace_dir)
+    workspace.create(A_KIND)
+    try:
+        write_file(os.fsencode(workspace.directory / 'output') + b'/data-\xff', 'data')
+    except OSError:
+        pytest.skip('file system does not support non UTF-8 names')
+    archive_path = tmp_path / 'bead_20150923T010203012345+0200.zip'
+
+    with pytest.raises(ValueError):
+        workspace.pack(archive_path, timestamp(), 'comment')
+    assert not archive_path.exists()
+
+
+def test_status_of_unsaved_workspace(workspace_dir):
+    """Test status of a workspace without saved versions."""
+    workspace = m.Workspace(workspace_dir)
+    workspace.create(A_KIND)
+    workspace.add_input('input1', 'kind1', 'content1', timestamp())
+
+    status = workspace.status(boxes=[])
+
+    assert 'new_workspace' == status.name
+    assert A_KIND == status.kind
+    assert status.last_saved_freeze_time_str is None
+    assert status.has_output_changes is None
+    [input] = status.inputs
+    assert ('input1', 'input1', False, None) == (
+        input.name, input.bead_name, input.is_loaded, input.newer_freeze_time_str)
+
+
+def test_modification_of_a_locked_workspace_fails(workspace_dir):
+    workspace = m.Workspace(workspace_dir)
+    workspace.create(A_KIND)
+
+    with FileLock(workspace.directory / '.bead-meta/lock'):
+        with pytest.raises(WorkspaceLocked):
+            workspace.add_input('input1', 'kind1', 'content1', timestamp())
+    assert not workspace.has_input('input1')
+
+
+def test_modification_waits_for_the_lock(workspace_dir):
+    workspace = m.Workspace(workspace_dir)
+    workspace.create(A_KIND)
+    other_process = FileLock(workspace.directory / '.bead-meta/lock')
+    other_process.acquire()
+    threading.Timer(0.2, other_process.release).start()
+
+    with workspace.locked(timeout=10):
+        workspace.add_input('input1', 'kind1', 'content1', timestamp())
+        workspace.set_input_bead_name('input1', 'bead1')
+
+    assert 'bead1' == workspace.get_input_bead_name('input1')
+    assert not (workspace.directory / '.bead-meta/lock').exists()
+
+
+def test_pack_of_a_locked_workspace_fails(workspace_dir, tmp_path):
+    workspace = m.Workspace(workspace_dir)
+    workspace.create(A_KIND)
+
+    with FileLock(workspace.directory / '.bead-meta/lock'):
+        with pytest.raises(WorkspaceLocked):
+            workspace.pack(tmp_path / 'bead.zip', timestamp(), BEAD_COMMENT)
+    assert not (tmp_path / 'bead.zip').exists()
+
+
+def test_lock_timeout_is_per_workspace(workspace_dir):
+    workspace = m.Workspace(workspace_dir, lock_timeout=10)
+
+    assert 10 == workspace.lock_timeout
+    assert 0 == m.Workspace(workspace_dir).lock_timeout
+
+
+def test_unused_workspace_locks_are_not_kept(workspace_dir):
+    workspace = m.Workspace(workspace_dir)
+    workspace.create(A_KIND)
+    workspace.add_input('input1', 'kind1', 'content1', timestamp())
+
+    assert workspace.directory not in m._workspace_locks
@@ -0,0 +1,65 @@
+'''
+External validators - commands checking archives before they are stored in a box.
+
+Schema checks, PII scanners and the like are configured as commands:
+
+    "schema-check --strict"                         # gets the staged archive path
+    {"command": "pii-scan", "stdin": "manifest"}    # gets the manifest JSON on stdin
+
+Commands are strings (split like a shell would) or argument lists.
+A command exiting with non-zero status rejects the archive.
+'''
+
+import shlex
+import subprocess
+from typing import Sequence
+
+from .exceptions import UserError
+from .ziparchive import ZipArchive
+from .tech import persistence
+
+# what validators get
+STDIN_PATH = 'path'          # the staged archive path as last argument
+STDIN_MANIFEST = 'manifest'  # the manifest (file name -> hash) as JSON on stdin
+STDIN_MODES = (STDIN_PATH, STDIN_MANIFEST)
+
+
+class ValidatorError(UserError):
+    """An external validator rejected the archive"""
+
+
+def _parse(validator):
+    if isinstance(validator, dict):
+        command, stdin = validator.get('command'), validator.get('stdin', STDIN_PATH)
+    else:
+        command, stdin = validator, STDIN_PATH
+    if isinstance(command, str):
+        command = shlex.split(command)
+    if not command or stdin not in STDIN_MODES:
+        raise ValidatorError(f'Invalid validator configuration: {validator}')
+    return list(command), stdin
+
+
+def run(validators: Sequence, archive_path):
+    '''
+    Run all validators on the archive, raise ValidatorError on the first rejection.
+    '''
+    for validator in validators:
+        command, stdin = _parse(validator)
+        if stdin == STDIN_MANIFEST:
+            input = persistence.dumps(ZipArchive(archive_path).manifest)
+        else:
+            command.append(str(archive_path))
+            input = None
+        try:
+            result = subprocess.run(command, input=input, capture_output=True, text=True)
+        except OSError as e:
+            raise ValidatorError(
+                f'Validator "{command[0]}" can not be run: {e}',
+                operation='validate', path=archive_path)
+        if result.returncode != 0:
+            output = (result.stderr or result.stdout).strip()
+            raise ValidatorError(
+                f'Validator "{command[0]}" rejected the archive'
+                + (f': {output}' if output else f' (exit status {result.returncode})'),
+                operation='validate', path=archive_path)
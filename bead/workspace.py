@@ -2,13 +2,34 @@
 Proto-Beads & their filesystem layout
 '''
 
+import contextlib
+import functools
 import os
+import stat
+import threading
+import warnings
+import weakref
 import zipfile
+from typing import Dict, List, Optional, Sequence, Tuple
 
+import attr
+
+from . import aliases
+from . import hash_cache
+from . import ignore
 from . import layouts
 from . import meta
+from . import notebooks
+from . import templates
 from . import tech
+from . import spec as bead_spec
+from .aliases import Alias
 from .bead import Bead
+from .box import Box, UnionBox
+from .exceptions import ContentMismatch, UserError, WorkspaceLocked
+from .tech.cancellation import NEVER_CANCELLED
+from .tech.progress import NO_PROGRESS
+from .ziparchive import hash_algorithm as manifest_hash_algorithm
 
 # technology modules
 persistence = tech.persistence
@@ -18,14 +39,195 @@ fs = tech.fs
 
 # generated with `uuidgen -t`
 META_VERSION = 'aaa947a6-1f7a-11e6-ba3a-0021cc73492e'
+# meta versions of archives this implementation can process
+KNOWN_META_VERSIONS = (META_VERSION,)
+# values of the developed archive, that change with every save - noise under version control
+VCS_VOLATILE_META_KEYS = (meta.META_VERSION, meta.FREEZE_TIME, meta.FREEZE_NAME)
+
+# later than any freeze time
+_LATEST = tech.timestamp.parse_iso8601('9999-12-31')
+
+# below this total size hashing threads would just add overhead
+# (the number of threads is set by the BEAD_HASH_THREADS environment variable)
+PARALLEL_HASH_MIN_SIZE = 64 * 1024 ** 2
+
+# compression methods of new archives by name
+ZIP_COMPRESSIONS = {
+    'stored': zipfile.ZIP_STORED,
+    'deflated': zipfile.ZIP_DEFLATED,
+}
+# zstd compresses research data far better, but it needs Python 3.14+ (also for reading!)
+# and not all zip tools can extract it - it is offered only where zipfile supports it
+if hasattr(zipfile, 'ZIP_ZSTANDARD'):
+    ZIP_COMPRESSIONS['zstd'] = zipfile.ZIP_ZSTANDARD
+ZIP_COMPRESSION_ALIASES = {'off': 'stored'}
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class InputStatus:
+    name: str
+    bead_name: str
+    kind: str
+    content_id: str
+    freeze_time_str: str
+    is_loaded: bool
+    # patterns selecting the loaded data files, None when all of them are loaded
+    loaded_patterns: Optional[Tuple[str, ...]]
+    # freeze time of the newest bead with the same name and kind, when it is newer
+    newer_freeze_time_str: Optional[str]
+
+    def as_dict(self):
+        return attr.asdict(self)
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class CodeDiff:
+    '''
+    Code files changed since a saved version - paths relative to the workspace, sorted.
+    '''
+    added: Tuple[str, ...]
+    modified: Tuple[str, ...]
+    deleted: Tuple[str, ...]
+
+    @property
+    def is_changed(self):
+        return bool(self.added or self.modified or self.deleted)
+
+    def as_dict(self):
+        return attr.asdict(self)
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class WorkspaceStatus:
+    name: str
+    kind: str
+    directory: str
+    inputs: Tuple[InputStatus, ...]
+    # freeze time of the newest saved version, None if it was never saved
+    last_saved_freeze_time_str: Optional[str]
+    # output files modified since the last save, None if it was never saved
+    has_output_changes: Optional[bool]
+    # code changes since the version the workspace was developed from,
+    # None if it is not known or not found in the boxes
+    code_changes: Optional[CodeDiff]
+
+    def as_dict(self):
+        return attr.asdict(self)
+
+
+def _write_input_marker(input_dir, bead):
+    persistence.file_dump(
+        {
+            meta.MARKER_BEAD_NAME: bead.name,
+            meta.MARKER_KIND: bead.kind,
+            meta.MARKER_CONTENT_ID: bead.content_id,
+            meta.MARKER_FREEZE_TIME: bead.freeze_time_str,
+            meta.MARKER_LOADED_AT: tech.timestamp.timestamp(),
+        },
+        input_dir / layouts.Workspace.INPUT_MARKER, end='\n')
+
+
+def _file_end(vcs_friendly):
+    return '\n' if vcs_friendly else ''
+
+
+class _WorkspaceLock:
+    '''
+    Lock of a workspace directory: a lock file against other processes,
+    a reentrant lock against other threads - so that nested modifications
+    (e.g. load -> add_input -> meta) take it only once.
+    '''
+
+    def __init__(self, directory):
+        self.directory = directory
+        self.file_lock = tech.lock.FileLock(directory / layouts.Workspace.LOCK)
+        self.thread_lock = threading.RLock()
+        self.depth = 0
+
+    def _locked_error(self, holder):
+        return WorkspaceLocked(
+            f'Workspace {self.directory} is being modified by {holder}', path=self.directory)
+
+    @contextlib.contextmanager
+    def held(self, timeout):
+        if not self.thread_lock.acquire(timeout=timeout):
+            raise self._locked_error('another thread')
+        try:
+            if self.depth == 0:
+                self.file_lock.timeout = timeout
+                try:
+                    self.file_lock.acquire()
+                except tech.lock.LockTimeout as e:
+                    raise self._locked_error(self.file_lock.holder()) from e
+            self.depth += 1
+            try:
+                yield
+            finally:
+                self.depth -= 1
+                if self.depth == 0:
+                    self.file_lock.release()
+        finally:
+            self.thread_lock.release()
+
+
+# a lock is kept only while it is in use - that is, held or waited for
+_workspace_locks: 'weakref.WeakValueDictionary[fs.Path, _WorkspaceLock]' = (
+    weakref.WeakValueDictionary())
+_workspace_locks_guard = threading.Lock()
+
+
+def _workspace_lock(directory) -> _WorkspaceLock:
+    with _workspace_locks_guard:
+        lock = _workspace_locks.get(directory)
+        if lock is None:
+            lock = _workspace_locks[directory] = _WorkspaceLock(directory)
+        return lock
+
+
+def _modifying(method):
+    '''
+    Decorator for Workspace methods modifying the workspace - they hold its lock.
+    '''
+    @functools.wraps(method)
+    def locked_method(self, *args, **kwargs):
+        with self.locked():
+            return method(self, *args, **kwargs)
+    return locked_method
+
+
+def _newest(boxes, bead_name, kind):
+    try:
+        context = UnionBox(boxes).get_context(bead_spec.BEAD_NAME, bead_name, _LATEST)
+    except LookupError:
+        return None
+    newest = context.best
+    if newest.kind != kind:
+        return None
+    return newest
 
 
 class Workspace(Bead):
 
     directory: fs.Path
 
-    def __init__(self, directory):
+    # seconds to wait for the lock of a workspace modified by another process
+    lock_timeout: float
+
+    def __init__(self, directory, lock_timeout: float = 0):
         self.directory = fs.Path(directory).resolve()
+        self.lock_timeout = lock_timeout
+
+    def locked(self, timeout: Optional[float] = None):
+        '''
+        Context manager holding the lock of the workspace - taken by all modifications.
+
+        Raises WorkspaceLocked, if it is held by another process (or thread)
+        for longer than timeout seconds (lock_timeout by default).
+        Nesting is allowed.
+        '''
+        if timeout is None:
+            timeout = self.lock_timeout
+        return _workspace_lock(self.directory).held(timeout)
 
     @property
     def is_valid(self):
@@ -46,8 +248,112 @@ class Workspace(Bead):
         return persistence.file_load(self._meta_filename)
 
     @meta.setter
-    def meta(self, meta):
-        persistence.file_dump(meta, self._meta_filename)
+    @_modifying
+    def meta(self, bead_meta):
+        vcs_friendly = bead_meta.get(meta.VCS_FRIENDLY, False)
+        if vcs_friendly:
+            bead_meta = {
+                key: value
+                for key, value in bead_meta.items()
+                if key not in VCS_VOLATILE_META_KEYS}
+        persistence.file_dump(bead_meta, self._meta_filename, end=_file_end(vcs_friendly))
+
+    @property
+    def vcs_friendly(self) -> bool:
+        '''
+        Are .bead-meta files kept friendly for version control?
+
+        Volatile values of the archive the workspace was developed from are dropped,
+        and files end in a newline - only real changes show up in diffs.
+        '''
+        return self.meta.get(meta.VCS_FRIENDLY, False)
+
+    @_modifying
+    def set_vcs_friendly(self, vcs_friendly: bool):
+        bead_meta = self.meta
+        if vcs_friendly:
+            bead_meta[meta.VCS_FRIENDLY] = True
+        else:
+            bead_meta.pop(meta.VCS_FRIENDLY, None)
+        self.meta = bead_meta
+        # rewrite with the new formatting
+        self.input_map = self.input_map
+
+    @property
+    def input_markers(self) -> bool:
+        '''
+        Are markers written into loaded input directories?
+
+        The marker (layouts.Workspace.INPUT_MARKER) tells the name, content id and freeze time
+        of the loaded bead and when it was loaded - for scripts and people browsing input/.
+        '''
+        return self.meta.get(meta.INPUT_MARKERS, False)
+
+    @_modifying
+    def set_input_markers(self, input_markers: bool):
+        '''
+        Write markers on loading inputs from now on - or stop writing them.
+
+        Inputs already loaded are left as they are.
+        '''
+        bead_meta = self.meta
+        if input_markers:
+            bead_meta[meta.INPUT_MARKERS] = True
+        else:
+            bead_meta.pop(meta.INPUT_MARKERS, None)
+        self.meta = bead_meta
+
+    @property
+    def description(self) -> Optional[str]:
+        return self.meta.get(meta.DESCRIPTION)
+
+    @_modifying
+    def set_description(self, description: Optional[str]):
+        '''
+        Describe the bead in the archives saved from now on - None or '' removes the description.
+        '''
+        self._set_annotation(meta.DESCRIPTION, description or None)
+
+    @property
+    def tags(self) -> Tuple[str, ...]:
+        return meta.parse_tags(self.meta)
+
+    @_modifying
+    def add_tag(self, tag: str):
+        '''
+        Tag the archives saved from now on, e.g. for finding them by Box.find_by_tag.
+
+        Raises ValueError for empty tags and tags with white space.
+        '''
+        if not meta.is_valid_tag(tag):
+            raise ValueError(f'Invalid tag: {tag!r}')
+        self._set_annotation(meta.TAGS, sorted(set(self.tags) | {tag}))
+
+    @_modifying
+    def remove_tag(self, tag: str) -> bool:
+        '''
+        Stop tagging saved archives with tag - returns False, if the workspace has no such tag.
+        '''
+        if tag not in self.tags:
+            return False
+        self._set_annotation(meta.TAGS, [t for t in self.tags if t != tag] or None)
+        return True
+
+    @property
+    def author(self) -> Optional[str]:
+        return self.meta.get(meta.AUTHOR)
+
+    @_modifying
+    def set_author(self, author: Optional[str]):
+        self._set_annotation(meta.AUTHOR, author or None)
+
+    def _set_annotation(self, key, value):
+        bead_meta = self.meta
+        if value is None:
+            bead_meta.pop(key, None)
+        else:
+            bead_meta[key] = value
+        self.meta = bead_meta
 
     # Bead properties
     @property
@@ -56,12 +362,19 @@ class Workspace(Bead):
 
     @property
     def name(self):
-        return self.directory.name
+        return fs.portable_name(self.directory.name)
 
     @property
     def inputs(self):
         return tuple(meta.parse_inputs(self.meta))
 
+    @property
+    def origin_content_id(self) -> Optional[str]:
+        '''
+        Content id of the archive the workspace was developed from - None for new workspaces.
+        '''
+        return self.meta.get(meta.ORIGIN)
+
     # faked Bead properties
     @property
     def content_id(self):
@@ -79,7 +392,7 @@ class Workspace(Bead):
         return '<UNSAVED>'
 
     # workspace constructors
-    def create(self, kind):
+    def create(self, kind, vcs_friendly=False, input_markers=False):
         '''
         Set up an empty project structure.
 
@@ -93,33 +406,108 @@ class Workspace(Bead):
         bead_meta = {
             meta.KIND: kind,
             meta.INPUTS: {}}
-        fs.write_file(
-            dir / layouts.Workspace.BEAD_META,
-            persistence.dumps(bead_meta))
+        if vcs_friendly:
+            bead_meta[meta.VCS_FRIENDLY] = True
+        if input_markers:
+            bead_meta[meta.INPUT_MARKERS] = True
+        self.meta = bead_meta
 
         assert self.is_valid
 
+    def create_from_template(
+            self, kind, template: templates.Template, vcs_friendly=False, input_markers=False):
+        '''
+        Set up a new project with the files of template (see the templates module).
+
+        On failure the partial workspace is removed.
+        '''
+        assert not self.directory.exists()
+        with fs.removed_on_error(self.directory):
+            self.create(kind, vcs_friendly=vcs_friendly, input_markers=input_markers)
+            templates.copy(template, self.directory, self.name)
+
+    @classmethod
+    def from_archive(
+            cls, archive, directory, extract_output=False, cancel=NEVER_CANCELLED,
+            vcs_friendly=False, progress=NO_PROGRESS, input_markers=False):
+        '''
+        Create a new workspace for further development of archive.
+
+        Code, metadata and input map are restored, input/ is made read only.
+        Output data is extracted only on request, as it is normally not needed.
+        On failure or cancellation the partial workspace is removed.
+        '''
+        workspace = cls(directory)
+        assert not workspace.directory.exists()
+        with fs.removed_on_error(workspace.directory):
+            archive.unpack_to(workspace, cancel, progress)
+            if vcs_friendly:
+                workspace.set_vcs_friendly(True)
+            if input_markers:
+                workspace.set_input_markers(True)
+            if extract_output:
+                archive.unpack_data_to(
+                    workspace.directory / layouts.Workspace.OUTPUT, cancel, progress)
+            bead_meta = workspace.meta
+            bead_meta[meta.ORIGIN] = archive.content_id
+            workspace.meta = bead_meta
+        assert workspace.is_valid
+        return workspace
+
+    def _make_readonly(self, paths):
+        '''
+        Protect paths against accidental modification - as far as the file system allows.
+        '''
+        failed = [path for path in paths if not fs.make_readonly(path)]
+        if failed:
+            warnings.warn(
+                f'Could not make {len(failed)} path(s) read-only under {self.directory}'
+                + ' (not supported by the file system?), input data is not protected',
+                fs.PermissionsNotSupported)
+
     def create_directories(self):
         dir = self.directory
         fs.ensure_directory(dir)
         fs.ensure_directory(dir / layouts.Workspace.INPUT)
-        fs.make_readonly(dir / layouts.Workspace.INPUT)
+        self._make_readonly([dir / layouts.Workspace.INPUT])
         fs.ensure_directory(dir / layouts.Workspace.OUTPUT)
         fs.ensure_directory(dir / layouts.Workspace.TEMP)
         fs.ensure_directory(dir / layouts.Workspace.META)
 
-    def pack(self, zipfilename: fs.Path, freeze_time, comment: str):
+    @_modifying
+    def pack(
+            self, zipfilename: fs.Path, freeze_time, comment: str, compression=None,
+            cancel=NEVER_CANCELLED, progress=NO_PROGRESS, notebook_outputs=notebooks.KEEP,
+            hash_algorithm=securehash.DEFAULT_ALGORITHM, provenance=None, use_hash_cache=True):
         '''
         Create archive from workspace.
+
+        The workspace is locked meanwhile, so that the archive is not a mix of
+        the files before and after a concurrent load or update.
+
+        compression is one of ZIP_COMPRESSIONS ('deflated' by default),
+        the BEAD_ZIP_COMPRESSION environment variable takes precedence over it.
+        notebook_outputs tells what to do with the cell outputs of Jupyter notebooks in code.
+        hash_algorithm makes the manifest hashes and the content id (tagged with it).
+        provenance (a meta.Provenance) marks the archive as saved by a pipeline runner.
+        With use_hash_cache files unchanged since the last pack are not hashed again
+        (see the hash_cache module).
+        The partial archive is removed on errors, cancellation and interrupts (Ctrl-C).
         '''
         zipfilename = fs.Path(zipfilename)
         assert not zipfilename.exists()
-        try:
-            _ZipCreator().create(zipfilename, self, freeze_time, comment)
-        except (RuntimeError, Exception):
-            if zipfilename.exists():
-                zipfilename.unlink()
-            raise
+        cache = hash_cache.HashCache.load(self) if use_hash_cache else None
+        with fs.removed_on_error(zipfilename):
+            _ZipCreator(
+                cancel, progress, notebook_outputs, hash_algorithm, provenance, cache,
+            ).create(zipfilename, self, freeze_time, comment, compression)
+
+    def provenance(self, runner: str, plan_hash: str) -> 'meta.Provenance':
+        '''
+        Provenance for saving the workspace after runner executed the plan with its inputs.
+        '''
+        return meta.Provenance(
+            runner, plan_hash, {input.name: input.content_id for input in self.inputs})
 
     def has_input(self, input_nick):
         '''
@@ -132,6 +520,46 @@ class Workspace(Bead):
     def is_loaded(self, input_nick):
         return (self.directory / layouts.Workspace.INPUT / input_nick).is_dir()
 
+    @property
+    def _partial_inputs_filename(self):
+        return self.directory / layouts.Workspace.PARTIAL_INPUTS
+
+    @property
+    def partial_inputs(self) -> Dict[str, List[str]]:
+        '''
+        Patterns of the inputs, that are loaded only partially - by input nick.
+
+        Local to the workspace (like the loaded data), it is not saved in archives.
+        '''
+        try:
+            return persistence.file_load(self._partial_inputs_filename)
+        except (OSError, persistence.ReadError):
+            return {}
+
+    @_modifying
+    def _set_loaded_patterns(self, input_nick, patterns: Optional[Sequence[str]]):
+        partial_inputs = self.partial_inputs
+        if patterns is None:
+            if partial_inputs.pop(input_nick, None) is None:
+                return
+        else:
+            partial_inputs[input_nick] = list(patterns)
+        if partial_inputs:
+            persistence.file_dump(partial_inputs, self._partial_inputs_filename)
+        else:
+            with contextlib.suppress(FileNotFoundError):
+                os.remove(self._partial_inputs_filename)
+
+    def loaded_patterns(self, input_nick) -> Optional[Tuple[str, ...]]:
+        '''
+        Patterns selecting the data files of the loaded input - None, if all of them are loaded.
+        '''
+        if not self.is_loaded(input_nick):
+            return None
+        patterns = self.partial_inputs.get(input_nick)
+        return None if patterns is None else tuple(patterns)
+
+    @_modifying
     def add_input(self, input_nick, kind, content_id, freeze_time_str):
         m = self.meta
         m[meta.INPUTS][input_nick] = {
@@ -140,6 +568,7 @@ class Workspace(Bead):
             meta.INPUT_FREEZE_TIME: freeze_time_str}
         self.meta = m
 
+    @_modifying
     def delete_input(self, input_nick):
         assert self.has_input(input_nick)
         if self.is_loaded(input_nick):
@@ -148,6 +577,52 @@ class Workspace(Bead):
         del m[meta.INPUTS][input_nick]
         self.meta = m
 
+    @_modifying
+    def rename_input(self, old_nick, new_nick):
+        '''
+        Rename input old_nick to new_nick - keeping its data and the bead name it is updated from.
+
+        On failure the input is left as it was.
+        '''
+        assert self.has_input(old_nick)
+        if not meta.InputName.is_wellformed(new_nick):
+            raise UserError(
+                f'Invalid input name: {new_nick}', operation='rename', path=self.directory)
+        if self.has_input(new_nick):
+            raise UserError(
+                f'Input {new_nick} already exists', operation='rename', path=self.directory)
+        old_meta, old_input_map = self.meta, self.input_map
+        new_meta, new_input_map = self.meta, self.input_map
+        new_meta[meta.INPUTS][new_nick] = new_meta[meta.INPUTS].pop(old_nick)
+        # the bead name defaults to the input name (or its alias) - it must not change with it
+        new_input_map.pop(old_nick, None)
+        new_input_map[new_nick] = self.get_input_bead_name(old_nick)
+
+        patterns = self.loaded_patterns(old_nick)
+        loaded = self.is_loaded(old_nick)
+        if loaded:
+            self._rename_input_directory(old_nick, new_nick)
+        try:
+            self.meta = new_meta
+            self.input_map = new_input_map
+            if patterns is not None:
+                self._set_loaded_patterns(old_nick, None)
+                self._set_loaded_patterns(new_nick, patterns)
+        except BaseException:
+            self.meta = old_meta
+            self.input_map = old_input_map
+            if loaded:
+                self._rename_input_directory(new_nick, old_nick)
+            raise
+
+    def _rename_input_directory(self, old_nick, new_nick):
+        input_dir = self.directory / layouts.Workspace.INPUT
+        fs.make_writable(input_dir)
+        try:
+            os.rename(input_dir / old_nick, input_dir / new_nick)
+        finally:
+            self._make_readonly([input_dir])
+
     @property
     def _input_map_filename(self):
         return self.directory / layouts.Workspace.INPUT_MAP
@@ -163,40 +638,109 @@ class Workspace(Bead):
             return {}
 
     @input_map.setter
+    @_modifying
     def input_map(self, input_map):
-        persistence.file_dump(input_map, self._input_map_filename)
+        persistence.file_dump(
+            input_map, self._input_map_filename, end=_file_end(self.vcs_friendly))
+
+    @property
+    def aliases(self):
+        '''
+        Team shared input names in effect (see the aliases module).
+        '''
+        return aliases.aliases_for(self.directory)
+
+    def input_alias(self, input_nick) -> Optional[Alias]:
+        '''
+        The shared alias input_nick is updated from - None, if there is none,
+        or the input map sets another bead name explicitly.
+        '''
+        if input_nick in self.input_map:
+            return None
+        return self.aliases.get(input_nick)
 
     def get_input_bead_name(self, input_nick):
         '''
         Returns the name on which update works.
+
+        An explicit input map entry takes precedence over shared aliases.
         '''
-        return self.input_map.get(input_nick, input_nick)
+        input_map = self.input_map
+        if input_nick in input_map:
+            return input_map[input_nick]
+        alias = self.aliases.get(input_nick)
+        if alias is not None:
+            return alias.bead_name
+        return input_nick
 
+    @_modifying
     def set_input_bead_name(self, input_nick, bead_name):
         '''
         Sets the name to be used for updates in the future.
+
+        The name of the shared alias of input_nick is not recorded,
+        so that the input follows, when the alias is repointed.
         '''
         input_map = self.input_map
-        input_map[input_nick] = bead_name
+        alias = self.aliases.get(input_nick)
+        if alias is not None and alias.bead_name == bead_name:
+            input_map.pop(input_nick, None)
+        else:
+            input_map[input_nick] = bead_name
         self.input_map = input_map
 
-    def load(self, input_nick, bead):
+    def validate_input_archive(self, input_nick, bead):
+        '''
+        Verify, that bead is the very same bead, that is recorded as input_nick.
+
+        Raises ContentMismatch if it is a different bead.
+        '''
+        input = self.get_input(input_nick)
+        assert input is not None
+        if input.kind != bead.kind:
+            raise ContentMismatch(
+                f'Input {input_nick}: bead is of kind {bead.kind}, expected {input.kind}',
+                operation='load', bead_name=bead.name)
+        if input.content_id != bead.content_id:
+            raise ContentMismatch(
+                f'Input {input_nick}: bead has content_id {bead.content_id},'
+                f' expected {input.content_id}',
+                operation='load', bead_name=bead.name)
+
+    @_modifying
+    def load(
+            self, input_nick, bead, cancel=NEVER_CANCELLED, progress=NO_PROGRESS,
+            patterns: Optional[Sequence[str]] = None):
         '''
         Make output data files in bead available under input directory
+
+        With patterns only the data files selected by them are loaded (see
+        ziparchive.is_selected) - e.g. one table of a huge bead, the patterns are remembered.
+        Data files are verified against the bead's manifest while extracted,
+        a damaged bead leaves neither data nor input definition behind.
+        With input_markers a marker file describing bead is also written.
         '''
         input_dir = self.directory / layouts.Workspace.INPUT
         fs.make_writable(input_dir)
         try:
+            destination_dir = input_dir / input_nick
+            with fs.removed_on_error(destination_dir):
+                loaded = bead.unpack_data_to(destination_dir, cancel, progress, patterns)
+                if patterns is not None and not loaded:
+                    raise UserError(
+                        f'Input {input_nick}: no data files match {" ".join(patterns)}',
+                        operation='load', bead_name=bead.name)
+                if self.input_markers:
+                    _write_input_marker(destination_dir, bead)
+            self._make_readonly(fs.all_subpaths(destination_dir))
+            self._set_loaded_patterns(input_nick, patterns)
             self.add_input(
                 input_nick,
                 bead.kind, bead.content_id, bead.freeze_time_str)
-            destination_dir = input_dir / input_nick
-            bead.unpack_data_to(destination_dir)
-            for f in fs.all_subpaths(destination_dir):
-                fs.make_readonly(f)
         finally:
-            fs.make_readonly(input_dir)
+            self._make_readonly([input_dir])
 
+    @_modifying
     def unload(self, input_nick):
         '''
         Remove files for given input
@@ -207,7 +751,153 @@ class Workspace(Bead):
         try:
             fs.rmtree(input_dir / input_nick)
         finally:
-            fs.make_readonly(input_dir)
+            self._make_readonly([input_dir])
+        self._set_loaded_patterns(input_nick, None)
+
+    def last_saved(self, boxes: Sequence[Box]):
+        '''
+        The newest version of the workspace in boxes - None, if it was never saved there.
+        '''
+        return _newest(boxes, self.name, self.kind)
+
+    def status(self, boxes: Sequence[Box]) -> WorkspaceStatus:
+        '''
+        Summary of the workspace, its inputs and their versions in boxes.
+
+        Output changes are detected by content, see is_output_dirty.
+        '''
+        def input_status(input):
+            bead_name = self.get_input_bead_name(input.name)
+            newest = _newest(boxes, bead_name, input.kind)
+            newer_freeze_time_str = None
+            if newest is not None and newest.freeze_time > input.freeze_time:
+                newer_freeze_time_str = newest.freeze_time_str
+            return InputStatus(
+                name=input.name,
+                bead_name=bead_name,
+                kind=input.kind,
+                content_id=input.content_id,
+                freeze_time_str=input.freeze_time_str,
+                is_loaded=self.is_loaded(input.name),
+                loaded_patterns=self.loaded_patterns(input.name),
+                newer_freeze_time_str=newer_freeze_time_str)
+
+        last_saved = self.last_saved(boxes)
+        if last_saved is None:
+            last_saved_freeze_time_str = None
+            has_output_changes = None
+        else:
+            last_saved_freeze_time_str = last_saved.freeze_time_str
+            has_output_changes = self.is_output_dirty(last_saved)
+        return WorkspaceStatus(
+            name=self.name,
+            kind=self.kind,
+            directory=self.directory.as_posix(),
+            inputs=tuple(input_status(input) for input in sorted(self.inputs)),
+            last_saved_freeze_time_str=last_saved_freeze_time_str,
+            has_output_changes=has_output_changes,
+            code_changes=self.code_diff(boxes))
+
+    def origin(self, boxes: Sequence[Box]):
+        '''
+        The archive the workspace was developed from - None, if it is not known or not found.
+        '''
+        content_id = self.origin_content_id
+        if content_id is None:
+            return None
+        conditions = [(bead_spec.KIND, self.kind), (bead_spec.CONTENT_ID, content_id)]
+        for archive in UnionBox(boxes).search(conditions).beads:
+            # content ids are matched by prefix in searches
+            if archive.content_id == content_id:
+                return archive
+        return None
+
+    def code_diff(self, boxes: Sequence[Box], cancel=NEVER_CANCELLED) -> Optional[CodeDiff]:
+        '''
+        Code files added, modified and deleted since the archive the workspace was developed from.
+
+        Files are compared by their hash in the manifest of the archive,
+        None is returned, when the archive is not known or not found in boxes.
+        A notebook saved with stripped outputs is modified, if it has outputs in the workspace.
+        '''
+        archive = self.origin(boxes)
+        if archive is None:
+            return None
+        return self.code_diff_against(archive, cancel)
+
+    def code_diff_against(self, archive, cancel=NEVER_CANCELLED) -> CodeDiff:
+        '''
+        Code files added, modified and deleted since archive - see code_diff.
+        '''
+        prefix = f'{layouts.Archive.CODE}/'
+        manifest = archive.ziparchive.manifest
+        saved = {
+            zip_path: hash for zip_path, hash in manifest.items() if zip_path.startswith(prefix)}
+        algorithm = manifest_hash_algorithm(
+            manifest[layouts.Archive.BEAD_META], archive.archive_filename)
+        # unchanged files are not hashed again - the cache is only read here
+        cache = hash_cache.HashCache.load(self)
+        added, modified = [], []
+        current = set()
+        for path, zip_path in code_files(self.directory, ignore.load(self.directory)):
+            current.add(zip_path)
+            if zip_path not in saved:
+                added.append(zip_path)
+                continue
+            hash = cache.get(path, algorithm) or securehash.file(
+                open(path, 'rb'), os.path.getsize(path), cancel, algorithm=algorithm)
+            if hash != saved[zip_path]:
+                modified.append(zip_path)
+        deleted = set(saved) - current
+
+        def relative(zip_paths):
+            return tuple(sorted(zip_path[len(prefix):] for zip_path in zip_paths))
+        return CodeDiff(relative(added), relative(modified), relative(deleted))
+
+    def output_manifest(
+            self, hash_algorithm=securehash.DEFAULT_ALGORITHM,
+            cancel=NEVER_CANCELLED) -> Dict[str, str]:
+        '''
+        Hashes of the files under output/, keyed by their path in an archive - as in its manifest.
+
+        Ignored files are left out, as they would be left out of a saved archive.
+        '''
+        return {
+            zip_path: securehash.file(
+                open(path, 'rb'), os.path.getsize(path), cancel, algorithm=hash_algorithm)
+            for path, zip_path in self._output_files()}
+
+    def is_output_dirty(self, archive, cancel=NEVER_CANCELLED) -> bool:
+        '''
+        Is the output different from the output saved in archive (e.g. the last saved version)?
+
+        Files are compared by content - a rerun producing the same output is not a change,
+        while changed content is found even with an old modification time (e.g. `cp -p`).
+        '''
+        prefix = f'{layouts.Archive.DATA}/'
+        manifest = archive.ziparchive.manifest
+        saved = {
+            zip_path: hash for zip_path, hash in manifest.items() if zip_path.startswith(prefix)}
+        output_files = list(self._output_files())
+        if {zip_path for _, zip_path in output_files} != set(saved):
+            return True
+        algorithm = manifest_hash_algorithm(
+            manifest[layouts.Archive.BEAD_META], archive.archive_filename)
+        return self.output_manifest(algorithm, cancel) != saved
+
+    def is_dirty(self, archive, cancel=NEVER_CANCELLED) -> bool:
+        '''
+        Would saving the workspace make a different bead than archive - in inputs, code or output?
+        '''
+        def input_versions(bead):
+            return {(input.name, input.content_id) for input in bead.inputs}
+        return (
+            input_versions(self) != input_versions(archive)
+            or self.code_diff_against(archive, cancel).is_changed
+            or self.is_output_dirty(archive, cancel))
+
+    def _output_files(self):
+        return data_files(self.directory, ignore.load(self.directory))
 
     def __repr__(self):
         # default values are printed as repr of the value
@@ -233,85 +923,298 @@ class Workspace(Bead):
             ws = cls(parent)
         return ws
 
+    @classmethod
+    def find_all(cls, directory) -> List['Workspace']:
+        '''
+        Valid workspaces in directory and under it, sorted by directory.
+
+        Workspaces are not searched for further workspaces.
+        '''
+        workspaces = []
+        for dirpath, dirnames, _filenames in os.walk(directory):
+            workspace = cls(dirpath)
+            if workspace.is_valid:
+                workspaces.append(workspace)
+                dirnames.clear()
+        return sorted(workspaces, key=lambda workspace: workspace.directory)
+
+
+class DataDirectory:
+    '''
+    A plain directory of files to save as the output of a new bead - e.g. a third party dataset.
+
+    It is stored in a box (Box.store) like a workspace, without making a workspace of it:
+    the archive has all the files as data, no code and no inputs.
+    '''
+
+    def __init__(self, directory, name: str, kind: str, description: Optional[str] = None):
+        self.directory = fs.Path(directory)
+        self.name = name
+        self.kind = kind
+        self.inputs = ()
+        self.input_map: Dict[str, str] = {}
+        self.meta = {meta.KIND: kind, meta.INPUTS: {}}
+        if description:
+            self.meta[meta.DESCRIPTION] = description
+
+    @property
+    def is_valid(self):
+        return self.directory.is_dir()
+
+    def pack(
+            self, zipfilename: fs.Path, freeze_time, comment: str, compression=None,
+            cancel=NEVER_CANCELLED, progress=NO_PROGRESS, notebook_outputs=notebooks.KEEP,
+            hash_algorithm=securehash.DEFAULT_ALGORITHM, provenance=None, use_hash_cache=True):
+        '''
+        Create archive from the directory - as Workspace.pack.
+
+        There is no code, whose notebooks could be stripped (notebook_outputs),
+        and the directory is not written to, so there is no hash cache (use_hash_cache).
+        '''
+        zipfilename = fs.Path(zipfilename)
+        assert not zipfilename.exists()
+        with fs.removed_on_error(zipfilename):
+            _DataDirectoryZipCreator(
+                cancel, progress, notebooks.KEEP, hash_algorithm, provenance,
+            ).create(zipfilename, self, freeze_time, comment, compression)
+
+    def __repr__(self):
+        return self.directory.as_posix()
+
+
+def zip_compression_method(name):
+    '''
+    zipfile compression constant for a compression name, unknown names mean deflated.
+
+    Raises ValueError for compressions not supported by this Python.
+    '''
+    name = ZIP_COMPRESSION_ALIASES.get(name, name)
+    if name not in ZIP_COMPRESSIONS:
+        return zipfile.ZIP_DEFLATED
+    method = ZIP_COMPRESSIONS[name]
+    if method is None:
+        raise ValueError(f'{name} compression is not supported by this Python version')
+    return method
+
+
+# archives of the same content are byte for byte identical:
+# entries have a fixed time stamp and only the executable bit of their mode is kept
+ZIP_ENTRY_DATE_TIME = (1980, 1, 1, 0, 0, 0)
+ZIP_FILE_MODE = 0o644
+ZIP_EXECUTABLE_MODE = 0o755
+_ZIP_CREATE_SYSTEM_UNIX = 3
+
+
+def files_under(path, zip_path, relative_path, ignore_rules: ignore.IgnoreRules):
+    '''
+    (path, zip_path) of files to add for path - except the ignored ones.
+
+    relative_path is the path relative to the workspace directory, to match ignore rules.
+    '''
+    is_dir = os.path.isdir(path)
+    if ignore_rules.is_ignored(relative_path, is_dir):
+        return
+    if is_dir:
+        # sorted for reproducible archives - listdir order depends on the file system
+        for f in sorted(os.listdir(path)):
+            yield from files_under(
+                path / f, f'{zip_path}/{fs.portable_name(f)}', f'{relative_path}/{f}',
+                ignore_rules)
+    else:
+        assert os.path.isfile(path), '%s is neither a file nor a directory' % path
+        yield path, zip_path
+
+
+def code_files(directory, ignore_rules: ignore.IgnoreRules):
+    '''
+    (path, zip_path) of the code files of the workspace in directory.
+    '''
+    def is_code(f):
+        return f not in {
+            layouts.Workspace.INPUT.as_posix(),
+            layouts.Workspace.OUTPUT.as_posix(),
+            layouts.Workspace.META.as_posix(),
+            layouts.Workspace.TEMP.as_posix()}
+
+    for f in sorted(os.listdir(directory)):
+        if is_code(f):
+            yield from files_under(
+                directory / f, f'{layouts.Archive.CODE}/{fs.portable_name(f)}', f, ignore_rules)
+
+
+def data_files(directory, ignore_rules: ignore.IgnoreRules):
+    '''
+    (path, zip_path) of the output files of the workspace in directory.
+    '''
+    return files_under(
+        directory / layouts.Workspace.OUTPUT, layouts.Archive.DATA,
+        layouts.Workspace.OUTPUT.as_posix(), ignore_rules)
+
 
 class _ZipCreator:
-    def __init__(self):
+    def __init__(
+            self, cancel=NEVER_CANCELLED, progress=NO_PROGRESS, notebook_outputs=notebooks.KEEP,
+            hash_algorithm=securehash.DEFAULT_ALGORITHM, provenance=None, hash_cache=None):
         self.hashes = {}
         self.zipfile = None
+        self.cancel = cancel
+        self.progress = progress
+        self.notebook_outputs = notebook_outputs
+        self.hash_algorithm = hash_algorithm
+        self.provenance = provenance
+        # a hash_cache.HashCache or None
+        self.hash_cache = hash_cache
+        self.ignore_rules = ignore.IgnoreRules(())
 
     def add_hash(self, path, hash):
         assert path not in self.hashes
         self.hashes[path] = hash
 
-    def add_file(self, path, zip_path: str):
+    def zipinfo(self, zip_path: str, executable=False) -> zipfile.ZipInfo:
         assert self.zipfile
-        self.zipfile.write(path, zip_path)
-        self.add_hash(
-            zip_path,
-            securehash.file(open(path, 'rb'), os.path.getsize(path)))
-
-    def add_path(self, path, zip_path):
-        if os.path.isdir(path):
-            self.add_directory(path, zip_path)
+        zipinfo = zipfile.ZipInfo(zip_path, ZIP_ENTRY_DATE_TIME)
+        zipinfo.compress_type = self.zipfile.compression
+        zipinfo.create_system = _ZIP_CREATE_SYSTEM_UNIX
+        mode = ZIP_EXECUTABLE_MODE if executable else ZIP_FILE_MODE
+        zipinfo.external_attr = (stat.S_IFREG | mode) << 16
+        return zipinfo
+
+    def cached_hash(self, path, zip_path: str) -> Optional[str]:
+        if self.hash_cache is None or self.is_stripped_notebook(zip_path):
+            return None
+        return self.hash_cache.get(path, self.hash_algorithm)
+
+    def add_computed_hash(self, path, zip_path: str, hash):
+        self.add_hash(zip_path, hash)
+        if self.hash_cache is not None and not self.is_stripped_notebook(zip_path):
+            self.hash_cache.add(path, hash)
+
+    def add_files(self, files):
+        '''
+        Add (path, zip_path) files - read and hashed by parallel threads, when they are big enough.
+
+        Files with a hash in the hash cache are not hashed.
+        '''
+        cached_hashes = {}
+        for path, zip_path in files:
+            hash = self.cached_hash(path, zip_path)
+            if hash is not None:
+                cached_hashes[zip_path] = hash
+        uncached_size = sum(
+            os.path.getsize(path) for path, zip_path in files if zip_path not in cached_hashes)
+        if securehash.hash_threads() <= 1 or uncached_size < PARALLEL_HASH_MIN_SIZE:
+            for path, zip_path in files:
+                self.add_file(path, zip_path, cached_hash=cached_hashes.get(zip_path))
+            return
+        # stripped notebooks are hashed after stripping, by add_file
+        to_read = [
+            (path, zip_path) for path, zip_path in files
+            if not self.is_stripped_notebook(zip_path) and zip_path not in cached_hashes]
+        # the reading threads hash the blocks, that are written to the archive
+        with securehash.read_files_parallel(
+                [path for path, _ in to_read], cancel=self.cancel,
+                algorithm=self.hash_algorithm) as hashed_files:
+            hashed_files_by_zip_path = {
+                zip_path: hashed_file
+                for (_, zip_path), hashed_file in zip(to_read, hashed_files)}
+            for path, zip_path in files:
+                self.add_file(
+                    path, zip_path, cached_hash=cached_hashes.get(zip_path),
+                    hashed_file=hashed_files_by_zip_path.get(zip_path))
+
+    def add_file(self, path, zip_path: str, cached_hash=None, hashed_file=None):
+        '''
+        Add file content to the archive - and its hash to the manifest.
+
+        A cached_hash is added to the manifest without hashing the content,
+        the content and hash of a securehash.HashedFile are read by another thread.
+        '''
+        zipinfo = self.zipinfo(zip_path, executable=bool(os.stat(path).st_mode & stat.S_IXUSR))
+        zipinfo.file_size = os.path.getsize(path)
+        self.progress.file_started(zip_path)
+        if self.is_stripped_notebook(zip_path):
+            self.add_stripped_notebook(path, zipinfo)
+            return
+        if hashed_file is not None:
+            with self.zipfile.open(zipinfo, 'w') as target:
+                for block in hashed_file:
+                    self.cancel.check()
+                    target.write(block)
+                    self.progress.advance(len(block))
+            self.add_computed_hash(path, zip_path, hashed_file.hash)
+        elif cached_hash is not None:
+            with open(path, 'rb') as source:
+                with self.zipfile.open(zipinfo, 'w') as target:
+                    securehash.copy(
+                        source, target, zipinfo.file_size, self.cancel, self.progress,
+                        algorithm=None)
+            self.add_hash(zip_path, cached_hash)
         else:
-            assert os.path.isfile(path), '%s is neither a file nor a directory' % path
-            self.add_file(path, zip_path)
+            with open(path, 'rb') as source:
+                with self.zipfile.open(zipinfo, 'w') as target:
+                    # the content is streamed and hashed in one pass
+                    hash = securehash.copy(
+                        source, target, zipinfo.file_size, self.cancel, self.progress,
+                        self.hash_algorithm)
+            self.add_computed_hash(path, zip_path, hash)
+        self.progress.file_done(zip_path)
+
+    def is_stripped_notebook(self, zip_path: str):
+        return (
+            self.notebook_outputs == notebooks.STRIP
+            and zip_path.startswith(f'{layouts.Archive.CODE}/')
+            and notebooks.is_notebook(zip_path))
+
+    def add_stripped_notebook(self, path, zipinfo: zipfile.ZipInfo):
+        self.cancel.check()
+        with open(path, 'rb') as f:
+            content = notebooks.strip_outputs(f.read())
+        self.zipfile.writestr(zipinfo, content)
+        self.add_hash(zipinfo.filename, securehash.bytes(content, self.hash_algorithm))
+        self.progress.advance(zipinfo.file_size)
+        self.progress.file_done(zipinfo.filename)
+
+    def load_ignore_rules(self, workspace) -> ignore.IgnoreRules:
+        return ignore.load(workspace.directory)
 
-    def add_directory(self, path, zip_path: str):
-        for f in os.listdir(path):
-            self.add_path(path / f, f'{zip_path}/{f}')
+    def files_under(self, path, zip_path, relative_path):
+        return files_under(path, zip_path, relative_path, self.ignore_rules)
 
     def add_string_content(self, zip_path: str, string):
-        assert self.zipfile
         bytes = string.encode('utf-8')
-        self.zipfile.writestr(zip_path, bytes)
-        self.add_hash(zip_path, securehash.bytes(bytes))
+        self.zipfile.writestr(self.zipinfo(zip_path), bytes)
+        self.add_hash(zip_path, securehash.bytes(bytes, self.hash_algorithm))
 
-    def create(self, zip_file_name: tech.fs.Path, workspace, timestamp, comment: str):
+    def create(
+            self, zip_file_name: tech.fs.Path, workspace, timestamp, comment: str,
+            compression=None):
         assert workspace.is_valid
-        user_compression_preference = os.environ.get('BEAD_ZIP_COMPRESSION')
-        compression = {
-            'off': zipfile.ZIP_STORED,
-            'stored': zipfile.ZIP_STORED,
-            # these are not universally supported compression methods
-            # 'lzma': zipfile.ZIP_LZMA,
-            # 'bz2': zipfile.ZIP_BZIP2,
-            'deflated': zipfile.ZIP_DEFLATED,
-        }.get(user_compression_preference, zipfile.ZIP_DEFLATED)
+        user_compression_preference = os.environ.get('BEAD_ZIP_COMPRESSION', compression)
+        zip_compression = zip_compression_method(user_compression_preference)
         try:
             with zipfile.ZipFile(
                 zip_file_name,
                 mode='w',
-                compression=compression,
+                compression=zip_compression,
                 allowZip64=True,
             ) as self.zipfile:
                 self.zipfile.comment = comment.encode('utf-8')
-                self.add_data(workspace)
-                self.add_code(workspace)
+                self.ignore_rules = self.load_ignore_rules(workspace)
+                files = [*self.data_files(workspace), *self.code_files(workspace)]
+                self.progress.start(sum(os.path.getsize(path) for path, _ in files), len(files))
+                self.add_files(files)
                 self.add_meta(workspace, timestamp)
+            if self.hash_cache is not None:
+                self.hash_cache.save()
         finally:
             self.zipfile = None
+            self.progress.finish()
 
-    def add_code(self, workspace):
-        source_directory = workspace.directory
-
-        def is_code(f):
-            return f not in {
-                layouts.Workspace.INPUT.as_posix(),
-                layouts.Workspace.OUTPUT.as_posix(),
-                layouts.Workspace.META.as_posix(),
-                layouts.Workspace.TEMP.as_posix()}
+    def code_files(self, workspace):
+        return code_files(workspace.directory, self.ignore_rules)
 
-        for f in sorted(os.listdir(source_directory)):
-            if is_code(f):
-                self.add_path(
-                    source_directory / f,
-                    f'{layouts.Archive.CODE}/{f}')
-
-    def add_data(self, workspace):
-        self.add_directory(
-            workspace.directory / layouts.Workspace.OUTPUT,
-            layouts.Archive.DATA)
+    def data_files(self, workspace):
+        return data_files(workspace.directory, self.ignore_rules)
 
     def add_meta(self, workspace, timestamp):
         bead_meta = {
@@ -325,7 +1228,29 @@ class _ZipCreator:
                     meta.INPUT_FREEZE_TIME: input.freeze_time_str}
                 for input in workspace.inputs},
             meta.FREEZE_NAME: workspace.name}
+        workspace_meta = workspace.meta
+        bead_meta.update(
+            (key, workspace_meta[key])
+            for key in meta.ANNOTATION_KEYS
+            if key in workspace_meta)
+        if self.provenance is not None:
+            bead_meta[meta.PROVENANCE] = self.provenance.as_meta()
 
         self.add_string_content(layouts.Archive.BEAD_META, persistence.dumps(bead_meta))
         self.add_string_content(layouts.Archive.MANIFEST, persistence.dumps(self.hashes))
-        persistence.zip_dump(workspace.input_map, self.zipfile, layouts.Archive.INPUT_MAP)
+        persistence.zip_dump(
+            workspace.input_map, self.zipfile, self.zipinfo(layouts.Archive.INPUT_MAP))
+
+
+class _DataDirectoryZipCreator(_ZipCreator):
+    # all files are data: none is left out, as the directory is not a workspace
+    def load_ignore_rules(self, data_directory) -> ignore.IgnoreRules:
+        return ignore.IgnoreRules(())
+
+    def code_files(self, data_directory):
+        return iter(())
+
+    def data_files(self, data_directory):
+        return self.files_under(
+            data_directory.directory, layouts.Archive.DATA,
+            layouts.Workspace.OUTPUT.as_posix())
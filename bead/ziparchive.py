@@ -1,9 +1,17 @@
+from concurrent.futures import ThreadPoolExecutor
 from copy import deepcopy
+import fnmatch
 import os
-import shutil
+from typing import List, Optional, Sequence
+import zlib
+
+import attr
 
 from .bead import UnpackableBead
-from .exceptions import InvalidArchive
+from .tech.cancellation import Cancelled, NEVER_CANCELLED
+from .tech.progress import NO_PROGRESS
+from .exceptions import InvalidArchive, ContentMismatch
+from .exceptions import UnsupportedHashAlgorithm, UnsupportedMetaVersion
 from . import tech
 from . import layouts
 from . import meta
@@ -15,6 +23,70 @@ securehash = tech.securehash
 persistence = tech.persistence
 
 
+# big data directories are extracted by this many threads
+EXTRACT_THREADS_ENV_VAR = 'BEAD_EXTRACT_THREADS'
+DEFAULT_EXTRACT_THREADS = min(4, os.cpu_count() or 1)
+# below this total size threads would just add overhead
+PARALLEL_EXTRACT_MIN_SIZE = 64 * 1024 ** 2
+
+
+def extract_threads() -> int:
+    try:
+        return max(1, int(os.environ[EXTRACT_THREADS_ENV_VAR]))
+    except (KeyError, ValueError):
+        return DEFAULT_EXTRACT_THREADS
+
+
+def normalize_pattern(pattern: str) -> str:
+    return pattern.strip().strip('/').removeprefix('./')
+
+
+def is_selected(path: str, patterns: Sequence[str]) -> bool:
+    '''
+    Is path (relative to the extracted directory) matched by any of the shell patterns?
+
+    A pattern matching a directory selects everything under it, e.g. `tables` or `raw/2020*`.
+    '''
+    parts = path.split('/')
+    prefixes = ['/'.join(parts[:i]) for i in range(1, len(parts) + 1)]
+    return any(
+        fnmatch.fnmatchcase(prefix, normalize_pattern(pattern))
+        for pattern in patterns
+        for prefix in prefixes)
+
+
+def hash_algorithm(hash: str, archive_path=None) -> securehash.HashAlgo:
+    '''
+    Algorithm of a manifest hash - raise UnsupportedHashAlgorithm if it can not be used.
+    '''
+    try:
+        algorithm = securehash.algorithm_of(hash)
+    except ValueError:
+        raise UnsupportedHashAlgorithm(
+            f'Unknown hash algorithm in {hash[:20]}...', path=archive_path) from None
+    if not securehash.is_available(algorithm):
+        raise UnsupportedHashAlgorithm(
+            f'{algorithm.value} hashing is not available (is the package installed?)',
+            path=archive_path)
+    return algorithm
+
+
+def content_id_of(manifest_bytes, archive_path=None) -> str:
+    '''
+    Content id - hash of the manifest, with the algorithm of the archive.
+
+    The algorithm of the archive is the one hashing its meta data.
+    Manifests without (readable) meta data hash are hashed with the default algorithm.
+    '''
+    try:
+        meta_hash = persistence.loads(manifest_bytes.decode('utf-8'))[layouts.Archive.BEAD_META]
+    except (UnicodeDecodeError, persistence.ReadError, KeyError, TypeError):
+        algorithm = securehash.DEFAULT_ALGORITHM
+    else:
+        algorithm = hash_algorithm(meta_hash, archive_path)
+    return securehash.bytes(manifest_bytes, algorithm)
+
+
 META_KEYS = (
     meta.META_VERSION,
     meta.KIND,
@@ -24,22 +96,79 @@ META_KEYS = (
 )
 
 
+# a manifest rebuilt by repair_manifest is stored next to the archive (ARCHIVE.manifest),
+# the archive itself is never changed
+MANIFEST_SIDECAR_SUFFIX = '.manifest'
+
+
+def manifest_sidecar_path(archive_path) -> tech.fs.Path:
+    return tech.fs.Path(archive_path).with_suffix(MANIFEST_SIDECAR_SUFFIX)
+
+
+# missing entry or bad CRC
+_UNREADABLE_ENTRY_ERRORS = (KeyError, zipopener.BadZipFile, zlib.error)
+
+
+def _is_manifest(manifest_bytes) -> bool:
+    try:
+        return isinstance(persistence.loads(manifest_bytes.decode('utf-8')), dict)
+    except (persistence.ReadError, UnicodeDecodeError):
+        return False
+
+
+def read_manifest_bytes(zf, archive_path) -> bytes:
+    '''
+    The manifest in zip file zf of archive_path - or the repaired one next to it,
+    if that is missing or broken (see ZipArchive.repair_manifest).
+    '''
+    sidecar = manifest_sidecar_path(archive_path)
+    try:
+        manifest_bytes = zf.read(layouts.Archive.MANIFEST)
+    except _UNREADABLE_ENTRY_ERRORS:
+        if sidecar.exists():
+            return sidecar.read_bytes()
+        raise
+    if not _is_manifest(manifest_bytes) and sidecar.exists():
+        return sidecar.read_bytes()
+    return manifest_bytes
+
+
+# problems with archive entries found by validation
+MISSING = 'missing'
+CORRUPT = 'content does not match the manifest'
+UNREADABLE = 'can not be read (bad CRC or compressed data)'
+NOT_IN_MANIFEST = 'not in the manifest'
+
+
+@attr.s(frozen=True, auto_attribs=True)
+class Damage:
+    zip_path: str
+    problem: str
+
+    def __str__(self):
+        return f'{self.zip_path}: {self.problem}'
+
+
 class ZipArchive(UnpackableBead):
 
-    def __init__(self, filename, box_name=''):
+    def __init__(self, filename, box_name='', entries=None):
+        '''
+        entries: the cached entry table of the zip file, see zipopener.entry_table()
+        '''
         self.archive_filename = filename
         self.box_name = box_name
+        self._entries = entries
         self._meta = self._load_meta()
         self._content_id = None
 
     @property
     def zipfile(self):
         try:
-            return zipopener.open(self.archive_filename)
+            return zipopener.open(self.archive_filename, self._entries)
         except (zipopener.BadZipFile, OSError, IOError):
-            raise InvalidArchive(self.archive_filename)
+            raise InvalidArchive(path=self.archive_filename)
 
-    def validate(self):
+    def validate(self, cancel=NEVER_CANCELLED, progress=NO_PROGRESS, checkpoint=None):
         '''
         verify, that
         - all files under code, data, meta are present in the manifest
@@ -51,24 +180,42 @@ class ZipArchive(UnpackableBead):
             - has freeze time
             - has freezed name
             - has inputs (even if empty)
-        '''
-        if not all(self._checks()):
-            raise InvalidArchive
 
-    def _checks(self):
-        yield self._has_well_formed_meta()
-        yield self._bead_creation_time_is_in_the_past()
-        yield self._extra_file() is None
-        yield self._file_with_different_content_id() is None
+        InvalidArchive lists the damaged entries, see check()
+        '''
+        damages = self.check(cancel, progress, checkpoint)
+        if damages:
+            raise InvalidArchive(
+                '; '.join(str(damage) for damage in damages),
+                operation='validate', path=self.archive_filename)
+
+    def check(
+            self, cancel=NEVER_CANCELLED, progress=NO_PROGRESS, checkpoint=None) -> List[Damage]:
+        '''
+        All problems found by validate(), an empty list for a valid archive.
 
-    def _has_well_formed_meta(self):
-        meta = self.meta
-        return all(key in meta for key in META_KEYS)
+        Every file in the manifest is read fully, checking both its hash and its zip CRC.
+        Files in checkpoint (a tech.checkpoint.Checkpoint) were verified before,
+        they are skipped and the newly verified ones are added to it.
+        '''
+        damages = []
+        missing_keys = [key for key in META_KEYS if key not in self.meta]
+        if missing_keys:
+            damages.append(
+                Damage(layouts.Archive.BEAD_META, f'missing keys: {", ".join(missing_keys)}'))
+        elif not self._bead_creation_time_is_in_the_past():
+            damages.append(Damage(layouts.Archive.BEAD_META, 'freeze time is in the future'))
+        try:
+            manifest = self.manifest
+        except (*_UNREADABLE_ENTRY_ERRORS, persistence.ReadError, UnicodeDecodeError):
+            return damages + [Damage(layouts.Archive.MANIFEST, MISSING)]
+        damages.extend(self._entries_not_in_manifest(manifest))
+        damages.extend(self._damaged_entries(manifest, cancel, progress, checkpoint))
+        return damages
 
     def _bead_creation_time_is_in_the_past(self):
-        read_time = timestamp.time_from_timestamp
-        now = read_time(timestamp.timestamp())
-        freeze_time = read_time(self.meta[meta.FREEZE_TIME])
+        now = timestamp.time_from_timestamp(timestamp.timestamp())
+        freeze_time = timestamp.time_from_legacy_timestamp(self.meta[meta.FREEZE_TIME])
         # we could be strict, but unfortunately on windows the resolution
         # of datetime.now is low yielding the same value for multiple calls
         # so we need that = in the <= to get the tests pass
@@ -76,32 +223,121 @@ class ZipArchive(UnpackableBead):
         #                 2010/04/08/precision-and-accuracy-of-datetime/
         return freeze_time <= now
 
-    def _extra_file(self):
+    def _entries_not_in_manifest(self, manifest):
         data_dir_prefix = layouts.Archive.DATA + '/'
         code_dir_prefix = layouts.Archive.CODE + '/'
-        manifest = self.manifest
-        # check that there are no extra files
         for name in self.zipfile.namelist():
             is_data = name.startswith(data_dir_prefix)
             is_code = name.startswith(code_dir_prefix)
-            if is_data or is_code:
-                if name not in manifest:
-                    # unexpected extra file!
-                    return name
-
-    def _file_with_different_content_id(self):
-        for name, hash in self.manifest.items():
+            if (is_data or is_code) and name not in manifest:
+                yield Damage(name, NOT_IN_MANIFEST)
+
+    def _damaged_entries(self, manifest, cancel, progress, checkpoint):
+        damages = []
+        entries = []
+        for name, hash in sorted(manifest.items()):
+            if checkpoint is not None and name in checkpoint:
+                continue
             try:
-                info = self.zipfile.getinfo(name)
+                entries.append((self.zipfile.getinfo(name), hash))
             except KeyError:
-                return name
-            archived_hash = securehash.file(self.zipfile.open(info), info.file_size)
-            if hash != archived_hash:
-                return name
+                damages.append(Damage(name, MISSING))
+        progress.start(sum(info.file_size for info, _ in entries), len(entries))
+        try:
+            for info, hash in entries:
+                damage = self._check_entry(info, hash, cancel, progress)
+                if damage is not None:
+                    damages.append(damage)
+                elif checkpoint is not None:
+                    checkpoint.add(info.filename)
+        finally:
+            progress.finish()
+        return damages
+
+    def _check_entry(self, info, hash, cancel, progress):
+        algorithm = hash_algorithm(hash, self.archive_filename)
+        progress.file_started(info.filename)
+        try:
+            with self.zipfile.open(info) as f:
+                archived_hash = securehash.file(
+                    f, info.file_size, cancel, progress, algorithm)
+        except (zipopener.BadZipFile, zlib.error, EOFError):
+            # bad CRC or broken compressed stream
+            return Damage(info.filename, UNREADABLE)
+        finally:
+            progress.file_done(info.filename)
+        if hash != archived_hash:
+            return Damage(info.filename, CORRUPT)
+        return None
+
+    @property
+    def entry_table(self):
+        return zipopener.entry_table(self.zipfile)
+
+    @property
+    def has_cached_entries(self) -> bool:
+        return self._entries is not None
 
     @property
     def manifest(self):
-        return self.zip_load(layouts.Archive.MANIFEST)
+        return persistence.loads(self._manifest_bytes().decode('utf-8'))
+
+    @property
+    def has_intact_manifest(self):
+        '''
+        Is the manifest in the archive readable?
+        '''
+        try:
+            return _is_manifest(self.zipfile.read(layouts.Archive.MANIFEST))
+        except _UNREADABLE_ENTRY_ERRORS:
+            return False
+
+    def _manifest_bytes(self):
+        return read_manifest_bytes(self.zipfile, self.archive_filename)
+
+    def rebuild_manifest(
+            self, algorithm=securehash.DEFAULT_ALGORITHM, cancel=NEVER_CANCELLED,
+            progress=NO_PROGRESS):
+        '''
+        Manifest made by hashing the code, data and meta data entries again.
+
+        It is the original manifest, if the entries are intact and algorithm is the original one
+        (archives made before hash algorithms could be chosen use the default one).
+        '''
+        prefixes = (layouts.Archive.CODE + '/', layouts.Archive.DATA + '/')
+        entries = [
+            info for info in self.zipfile.infolist()
+            if (info.filename.startswith(prefixes) and not info.is_dir())
+            or info.filename == layouts.Archive.BEAD_META]
+        manifest = {}
+        progress.start(sum(info.file_size for info in entries), len(entries))
+        try:
+            for info in entries:
+                progress.file_started(info.filename)
+                with self.zipfile.open(info) as f:
+                    manifest[info.filename] = securehash.file(
+                        f, info.file_size, cancel, progress, algorithm)
+                progress.file_done(info.filename)
+        finally:
+            progress.finish()
+        return manifest
+
+    def repair_manifest(
+            self, algorithm=securehash.DEFAULT_ALGORITHM, cancel=NEVER_CANCELLED,
+            progress=NO_PROGRESS) -> tech.fs.Path:
+        '''
+        Store a rebuilt manifest next to the archive, return its path.
+
+        It is used instead of a missing or unreadable manifest in the archive,
+        restoring verification and the content id - the archive itself is not changed.
+        Data damaged along with the manifest is not detected: compare the content id
+        with a known one (e.g. of an input) to make sure.
+        '''
+        manifest = self.rebuild_manifest(algorithm, cancel, progress)
+        path = manifest_sidecar_path(self.archive_filename)
+        persistence.file_dump(manifest, path)
+        self._content_id = None
+        return path
 
     @property
     def content_id(self):
@@ -110,12 +346,14 @@ class ZipArchive(UnpackableBead):
         return self._content_id
 
     def calculate_content_id(self):
-        # there is currently only one meta version
-        # and it must match the one defined in the workspace module
-        assert self._meta[meta.META_VERSION] == 'aaa947a6-1f7a-11e6-ba3a-0021cc73492e'
-        zipinfo = self.zipfile.getinfo(layouts.Archive.MANIFEST)
-        with self.zipfile.open(zipinfo) as f:
-            return securehash.file(f, zipinfo.file_size)
+        # the content id algorithm depends on the meta version
+        # (imported here, as the workspace module imports this one)
+        from .workspace import KNOWN_META_VERSIONS
+        meta_version = self._meta.get(meta.META_VERSION)
+        if meta_version not in KNOWN_META_VERSIONS:
+            raise UnsupportedMetaVersion(
+                f'Unknown meta version {meta_version}', path=self.archive_filename)
+        return content_id_of(self._manifest_bytes(), self.archive_filename)
 
     @property
     def meta_version(self):
@@ -127,7 +365,12 @@ class ZipArchive(UnpackableBead):
 
     @property
     def freeze_time_str(self):
-        return self._meta[meta.FREEZE_TIME]
+        # archives made by earlier tools might have shorter timestamps
+        return timestamp.normalize_timestamp(self._meta[meta.FREEZE_TIME])
+
+    @property
+    def tags(self):
+        return meta.parse_tags(self._meta)
 
     @property
     def meta(self):
@@ -154,11 +397,16 @@ class ZipArchive(UnpackableBead):
         try:
             return self.zip_load(layouts.Archive.BEAD_META)
         except:
-            raise InvalidArchive(self.archive_filename)
+            raise InvalidArchive(path=self.archive_filename)
 
-    def extract_file(self, zip_path: str, fs_path: tech.fs.Path):
+    def extract_file(
+            self, zip_path: str, fs_path: tech.fs.Path, expected_hash=None,
+            cancel=NEVER_CANCELLED, progress=NO_PROGRESS):
         '''
             Extract zip_path from zipfile to fs_path.
+
+            When expected_hash is given, the extracted file is verified against it.
+            An interrupted extraction leaves no partial file behind.
         '''
         fs_path = tech.fs.Path(os.path.normpath(fs_path.as_posix()))
 
@@ -166,31 +414,119 @@ class ZipArchive(UnpackableBead):
         if upperdirs:
             tech.fs.ensure_directory(tech.fs.Path(upperdirs))
 
-        with self.zipfile.open(zip_path) as source:
-            with open(fs_path, 'wb') as target:
-                shutil.copyfileobj(source, target)
-
-    def extract_dir(self, zip_dir: str, fs_dir: tech.fs.Path):
+        zipinfo = self.zipfile.getinfo(zip_path)
+        algorithm = securehash.DEFAULT_ALGORITHM
+        if expected_hash is not None:
+            algorithm = hash_algorithm(expected_hash, self.archive_filename)
+        progress.file_started(zip_path)
+        # the content is streamed and hashed in one pass
+        with tech.fs.removed_on_error(fs_path):
+            with self.zipfile.open(zipinfo) as source:
+                with open(fs_path, 'wb') as target:
+                    extracted_hash = securehash.copy(
+                        source, target, zipinfo.file_size, cancel, progress, algorithm)
+
+        if expected_hash is not None and extracted_hash != expected_hash:
+            raise ContentMismatch(
+                f'{zip_path}: extracted file does not match manifest',
+                operation='extract', path=self.archive_filename)
+        progress.file_done(zip_path)
+
+    def _extract_files_in_thread(self, files, cancel, progress):
+        try:
+            for zip_path, fs_path, expected_hash in files:
+                self.extract_file(zip_path, fs_path, expected_hash, cancel, progress)
+        except BaseException:
+            # no point in continuing with the other threads
+            cancel.cancel()
+            raise
+        finally:
+            # the zip file was opened by (and for) this thread
+            zipopener.close_all()
+
+    def extract_dir(
+            self, zip_dir: str, fs_dir: tech.fs.Path, verify=False, cancel=NEVER_CANCELLED,
+            progress=NO_PROGRESS, patterns: Optional[Sequence[str]] = None) -> int:
         '''
-            Extract all files from zipfile under zip_dir to fs_dir.
+            Extract all files from zipfile under zip_dir to fs_dir, return their number.
+
+            With patterns only the files selected by them are extracted, see is_selected.
+            With verify, every file is checked against the manifest while extracted.
+            Big directories are extracted by multiple threads, each reading
+            the archive through its own handle.
+            Files already extracted are not removed on errors or cancellation.
         '''
 
         tech.fs.ensure_directory(fs_dir)
 
         zip_dir_prefix = zip_dir + '/'
         zip_dir_prefix_len = len(zip_dir_prefix)
+        manifest = self.manifest if verify else {}
 
-        for zip_path in self.zipfile.namelist():
+        files = []
+        total_size = 0
+        for zipinfo in self.zipfile.infolist():
+            zip_path = zipinfo.filename
             if not zip_path.startswith(zip_dir_prefix):
                 continue
-            fs_path = fs_dir / zip_path[zip_dir_prefix_len:]
-            self.extract_file(zip_path, fs_path)
-
-    def unpack_code_to(self, fs_dir):
-        self.extract_dir(layouts.Archive.CODE, fs_dir)
-
-    def unpack_data_to(self, fs_dir):
-        self.extract_dir(layouts.Archive.DATA, fs_dir)
+            relative_path = zip_path[zip_dir_prefix_len:]
+            if patterns is not None and not is_selected(relative_path, patterns):
+                continue
+            fs_path = fs_dir / relative_path
+            if verify and zip_path not in manifest:
+                raise ContentMismatch(
+                    f'{zip_path}: file is not in manifest',
+                    operation='extract', path=self.archive_filename)
+            files.append((zipinfo.file_size, zip_path, fs_path, manifest.get(zip_path)))
+            total_size += zipinfo.file_size
+
+        progress.start(total_size, len(files))
+        try:
+            self._extract_files(files, total_size, cancel, progress)
+        finally:
+            progress.finish()
+        return len(files)
+
+    def _extract_files(self, files, total_size, cancel, progress):
+        threads = min(extract_threads(), len(files))
+        if threads <= 1 or total_size < PARALLEL_EXTRACT_MIN_SIZE:
+            for _size, zip_path, fs_path, expected_hash in files:
+                self.extract_file(zip_path, fs_path, expected_hash, cancel, progress)
+            return
+
+        # balance the work: biggest files first, each to the least loaded thread
+        work = [[] for _ in range(threads)]
+        load = [0] * threads
+        for size, zip_path, fs_path, expected_hash in sorted(files, key=lambda f: -f[0]):
+            thread = load.index(min(load))
+            work[thread].append((zip_path, fs_path, expected_hash))
+            load[thread] += size
+        # stops all threads on the first error, or on interrupting the main thread
+        threads_cancel = cancel.child()
+        with ThreadPoolExecutor(max_workers=threads) as executor:
+            results = [
+                executor.submit(self._extract_files_in_thread, w, threads_cancel, progress)
+                for w in work]
+            try:
+                errors = [result.exception() for result in results]
+            except BaseException:
+                threads_cancel.cancel()
+                raise
+        # re-raise errors of the threads - the cause, not the cancellation of the others
+        errors = sorted(
+            (e for e in errors if e is not None), key=lambda e: isinstance(e, Cancelled))
+        if errors:
+            raise errors[0]
+
+    def unpack_code_to(self, fs_dir, cancel=NEVER_CANCELLED, progress=NO_PROGRESS):
+        self.extract_dir(
+            layouts.Archive.CODE, fs_dir, verify=True, cancel=cancel, progress=progress)
+
+    def unpack_data_to(
+            self, fs_dir, cancel=NEVER_CANCELLED, progress=NO_PROGRESS, patterns=None) -> int:
+        return self.extract_dir(
+            layouts.Archive.DATA, fs_dir, verify=True, cancel=cancel, progress=progress,
+            patterns=patterns)
 
     def unpack_meta_to(self, workspace):
         workspace.meta = self.meta
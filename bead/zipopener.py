@@ -7,15 +7,181 @@ For this reason this module provides a small LRU cache of open (for reading) zip
 
 Actually having this module made the tests (which use only small files)
 run ~4% faster (5.14 -> 4.94 = 0.2s faster).
+
+Every thread has its own cache, as open zip files are not safe to share:
+one thread could close a file, while another is still reading it.
+Threads other than the main thread should call close_all() when done.
+
+Most of the opening time is parsing the central directory, so the parsed entry
+table can be cached (see entry_table()) and given to open() - an unchanged zip file
+is then opened without parsing it again: ZipFile sees an empty central directory
+in place of the real one and the entries are opened by their ZipInfo.
+How many central directories were parsed or taken from cached tables and the time spent
+on opening is counted in stats.
 """
 
 import atexit
-from typing import Dict, Tuple
-from zipfile import BadZipFile, ZipFile
+import builtins
+from collections import Counter
+import io
+import os
+import struct
+import threading
+import time
+from typing import Dict, List, Optional, Tuple
+from zipfile import BadZipFile, ZipFile, ZipInfo
 
 from tracelog import TRACELOG
 
-__all__ = ('BadZipFile', 'open', 'close_all')
+__all__ = ('BadZipFile', 'open', 'close_all', 'entry_table', 'stats')
+
+# 'parsed' and 'cached' central directories, 'seconds' spent opening zip files
+stats: Counter = Counter()
+_stats_lock = threading.Lock()
+
+# [filename, header_offset, compress_size, file_size, compress_type, CRC, flag_bits, date_time]
+Entry = list
+
+
+def entry_table(zf: ZipFile) -> List[Entry]:
+    '''
+    The parsed central directory of zf - as plain data, e.g. for JSON.
+    '''
+    return [
+        [info.filename, info.header_offset, info.compress_size, info.file_size,
+         info.compress_type, info.CRC, info.flag_bits, list(info.date_time)]
+        for info in zf.infolist()]
+
+
+def _zipinfo(entry: Entry) -> ZipInfo:
+    filename, header_offset, compress_size, file_size, compress_type, crc, flag_bits, date_time = (
+        entry)
+    info = ZipInfo(filename, tuple(date_time))
+    info.header_offset = header_offset
+    info.compress_size = compress_size
+    info.file_size = file_size
+    info.compress_type = compress_type
+    info.CRC = crc
+    info.flag_bits = flag_bits
+    return info
+
+
+def _empty_central_directory(offset: int) -> bytes:
+    '''
+    Zip64 end of central directory records of an empty central directory at offset.
+    '''
+    zip64_end = struct.pack('<4sQ2H2L4Q', b'PK\x06\x06', 44, 45, 45, 0, 0, 0, 0, 0, offset)
+    locator = struct.pack('<4sLQL', b'PK\x06\x07', 0, offset, 1)
+    end = struct.pack(
+        '<4s4H2LH', b'PK\x05\x06', 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFFFFFF, 0xFFFFFFFF, 0)
+    return zip64_end + locator + end
+
+
+class _WithEmptyCentralDirectory(io.RawIOBase):
+    '''
+    Read-only view of a zip file followed by an empty central directory.
+
+    ZipFile finds no entries in it, but can open the entries by their ZipInfo.
+    '''
+
+    def __init__(self, filename):
+        super().__init__()
+        self._file = builtins.open(filename, 'rb')
+        self._size = os.fstat(self._file.fileno()).st_size
+        self._end = _empty_central_directory(self._size)
+        self._position = 0
+
+    def readable(self):
+        return True
+
+    def seekable(self):
+        return True
+
+    def tell(self):
+        return self._position
+
+    def seek(self, offset, whence=io.SEEK_SET):
+        base = {
+            io.SEEK_SET: 0, io.SEEK_CUR: self._position, io.SEEK_END: self._size + len(self._end)}
+        position = base[whence] + offset
+        if position < 0:
+            raise OSError(f'Invalid position {position}')
+        self._position = position
+        return position
+
+    def readinto(self, buffer):
+        if self._position < self._size:
+            self._file.seek(self._position)
+            count = self._file.readinto(memoryview(buffer)[:self._size - self._position])
+        else:
+            start = self._position - self._size
+            data = self._end[start:start + len(buffer)]
+            count = len(data)
+            buffer[:count] = data
+        self._position += count
+        return count
+
+    def close(self):
+        self._file.close()
+        super().close()
+
+
+class _CachedDirectoryZipFile:
+    '''
+    A zip file opened for reading with an already known entry table.
+
+    Provides the part of ZipFile used for reading.
+    '''
+
+    def __init__(self, filename, entries: List[Entry]):
+        self._view = _WithEmptyCentralDirectory(filename)
+        try:
+            self._zipfile = ZipFile(self._view)
+        except BaseException:
+            self._view.close()
+            raise
+        self._infos = [_zipinfo(entry) for entry in entries]
+        self._name_to_info = {info.filename: info for info in self._infos}
+
+    def infolist(self) -> List[ZipInfo]:
+        return list(self._infos)
+
+    def namelist(self) -> List[str]:
+        return [info.filename for info in self._infos]
+
+    def getinfo(self, name) -> ZipInfo:
+        try:
+            return self._name_to_info[name]
+        except KeyError:
+            raise KeyError(f'There is no item named {name!r} in the archive')
+
+    def open(self, name, mode='r'):
+        assert mode == 'r', 'cached zip files are read only'
+        info = name if isinstance(name, ZipInfo) else self.getinfo(name)
+        return self._zipfile.open(info)
+
+    def read(self, name) -> bytes:
+        with self.open(name) as f:
+            return f.read()
+
+    def close(self):
+        self._zipfile.close()
+        self._view.close()
+
+
+def _open_zip(filename, entries: Optional[List[Entry]]):
+    start = time.perf_counter()
+    if entries is None:
+        zf = ZipFile(filename)
+        kind = 'parsed'
+    else:
+        zf = _CachedDirectoryZipFile(filename, entries)
+        kind = 'cached'
+    with _stats_lock:
+        stats[kind] += 1
+        stats['seconds'] += time.perf_counter() - start
+    return zf
+
 
 FileName = str
 LogicalTime = int
@@ -28,11 +194,11 @@ class OpenZipLRUCache:
         self.access_times: Dict[FileName, LogicalTime] = {}
         self.access_count: LogicalTime = 0
 
-    def open(self, filename):
+    def open(self, filename, entries: Optional[List[Entry]] = None):
         if filename not in self.open_zip_files:
             if len(self.open_zip_files) == self.max_size:
                 self.close(self.least_recently_used_filename)
-            self.open_zip_files[filename] = ZipFile(filename)
+            self.open_zip_files[filename] = _open_zip(filename, entries)
 
         self.access(filename)
         return self.open_zip_files[filename]
@@ -63,14 +229,30 @@ class OpenZipLRUCache:
             self.close(filename)
 
 
-_cache = OpenZipLRUCache()
+_caches = threading.local()
+
+
+def _cache() -> OpenZipLRUCache:
+    try:
+        return _caches.cache
+    except AttributeError:
+        _caches.cache = OpenZipLRUCache()
+        return _caches.cache
+
+
+def open(filename, entries: Optional[List[Entry]] = None):
+    '''
+    Open zip file for reading - with its entry table, if it is known and up to date.
+    '''
+    return _cache().open(filename, entries)
+
 
-open = _cache.open
-close_all = _cache.close_all
+def close_all():
+    _cache().close_all()
 
 
 def _cleanup():
-    TRACELOG(vars(_cache))
+    TRACELOG(vars(_cache()))
     close_all()
 
 
@@ -0,0 +1,119 @@
+'''
+Aliases of boxes - stable names resolving to the newest bead of a kind, e.g. population-latest.
+
+Aliases can be used wherever beads are referenced, e.g. `bead develop population-latest`
+or `bead input add population population-latest`.
+'''
+
+from bead.exceptions import BoxError
+from bead import spec as bead_spec
+from .box import get_box
+from .cmdparse import Command
+from .common import OPTIONAL_ENV, REPORT_FORMAT, TEXT, TIME_LATEST, die, warning
+from .report import Report, emit
+
+
+def _kind_of(box, reference):
+    '''
+    Kind of the newest bead named reference in box - or reference, if it is a kind there.
+    '''
+    for check_type in (bead_spec.BEAD_NAME, bead_spec.KIND):
+        try:
+            return box.get_context(check_type, reference, TIME_LATEST).best.kind
+        except LookupError:
+            pass
+    return None
+
+
+class CmdSet(Command):
+    '''
+    Define an alias resolving to the newest bead of a kind.
+    '''
+
+    def declare(self, arg):
+        arg('alias')
+        arg('bead', help='bead name or kind - the alias resolves to the newest bead of its kind')
+        arg('--box', dest='box_name', default=None,
+            help='box to define the alias in (default: the first writable box having the bead)')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        if args.box_name:
+            boxes = [get_box(env, args.box_name)]
+        else:
+            boxes = [box for box in env.get_boxes() if not box.read_only]
+        for box in boxes:
+            kind = _kind_of(box, args.bead)
+            if kind is not None:
+                break
+        else:
+            die(f'Bead not found: {args.bead}', suggestions=[f'bead search {args.bead}'])
+        try:
+            box.set_alias(args.alias, kind)
+        except BoxError as e:
+            die(str(e))
+        newest = box.resolve_alias(args.alias)
+        print(
+            f'Alias {args.alias} in box {box.name} resolves to the newest bead of its kind,'
+            + f' now {newest.name} {newest.freeze_time_str}')
+
+
+class CmdList(Command):
+    '''
+    List aliases and the beads they resolve to.
+    '''
+
+    def declare(self, arg):
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        rows = []
+        for box in args.get_env().get_boxes():
+            for alias, kind in sorted(box.aliases().items()):
+                try:
+                    bead = box.resolve_alias(alias)
+                except LookupError:
+                    bead = None
+                rows.append(dict(
+                    alias=alias, box=box.name, kind=kind,
+                    name=None if bead is None else bead.name,
+                    freeze_time=None if bead is None else bead.freeze_time_str))
+        if not rows and args.output_format == TEXT:
+            print('No aliases are defined')
+            return
+        emit(
+            Report('Aliases', ('alias', 'box', 'name', 'freeze_time', 'kind'), rows),
+            args.output_format)
+
+
+class CmdRm(Command):
+    '''
+    Forget an alias.
+    '''
+
+    def declare(self, arg):
+        arg('alias')
+        arg('--box', dest='box_name', default=None,
+            help='box to remove the alias from (default: all writable boxes)')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        if args.box_name:
+            boxes = [get_box(env, args.box_name)]
+        else:
+            boxes = []
+            for box in env.get_boxes():
+                if not box.read_only:
+                    boxes.append(box)
+                elif args.alias in box.aliases():
+                    warning(f'Alias {args.alias} is kept in read-only box {box.name}')
+        try:
+            removed_from = [box.name for box in boxes if box.remove_alias(args.alias)]
+        except BoxError as e:
+            die(str(e))
+        if not removed_from:
+            die(f'Unknown alias: {args.alias}', suggestions=['bead alias list'])
+        print(f'Removed alias {args.alias} from box {", ".join(removed_from)}')
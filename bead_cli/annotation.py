@@ -0,0 +1,102 @@
+'''
+Description, tags and author of beads - set in the workspace, saved with every new version.
+'''
+
+from .cmdparse import Command
+from .common import OPTIONAL_ENV, OPTIONAL_WORKSPACE, REPORT_FORMAT, TEXT
+from .common import assert_valid_workspace, automated_by, die, warn_timed_out
+from .report import Report, emit
+
+
+class CmdDescribe(Command):
+    '''
+    Show or set the description and author of the bead.
+
+    They are saved with the versions saved from now on.
+    '''
+
+    def declare(self, arg):
+        arg('description', nargs='?', default=None,
+            help='description of the bead, an empty string removes it')
+        arg('--author', default=None, help='author of the bead, an empty string removes it')
+        arg(OPTIONAL_WORKSPACE)
+
+    def run(self, args):
+        workspace = args.workspace
+        assert_valid_workspace(workspace)
+        if args.description is not None:
+            workspace.set_description(args.description)
+        if args.author is not None:
+            workspace.set_author(args.author)
+        print(f'Description: {workspace.description or "-"}')
+        print(f'Author: {workspace.author or "-"}')
+        print(f'Tags: {" ".join(workspace.tags) or "-"}')
+
+
+class CmdAdd(Command):
+    '''
+    Tag the versions of the bead saved from now on.
+    '''
+
+    def declare(self, arg):
+        arg('tags', metavar='TAG', nargs='+')
+        arg(OPTIONAL_WORKSPACE)
+
+    def run(self, args):
+        workspace = args.workspace
+        assert_valid_workspace(workspace)
+        for tag in args.tags:
+            try:
+                workspace.add_tag(tag)
+            except ValueError as e:
+                die(str(e))
+        print(f'Tags: {" ".join(workspace.tags)}')
+
+
+class CmdRm(Command):
+    '''
+    Stop tagging the versions of the bead saved from now on.
+    '''
+
+    def declare(self, arg):
+        arg('tags', metavar='TAG', nargs='+')
+        arg(OPTIONAL_WORKSPACE)
+
+    def run(self, args):
+        workspace = args.workspace
+        assert_valid_workspace(workspace)
+        for tag in args.tags:
+            if not workspace.remove_tag(tag):
+                die(f'The bead is not tagged {tag}')
+        print(f'Tags: {" ".join(workspace.tags) or "-"}')
+
+
+class CmdFind(Command):
+    '''
+    Find beads in all boxes by their tag.
+    '''
+
+    def declare(self, arg):
+        arg('tag')
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        unionbox = args.get_env().get_union_box()
+        try:
+            beads = unionbox.find_by_tag(args.tag)
+        finally:
+            warn_timed_out(unionbox)
+        if not beads and args.output_format == TEXT:
+            print(f'No beads are tagged {args.tag}')
+            return
+        rows = [
+            dict(
+                name=bead.name, freeze_time=bead.freeze_time_str, box=bead.box_name,
+                kind=bead.kind, tags=' '.join(bead.tags), automated_by=automated_by(bead))
+            for bead in beads]
+        emit(
+            Report(
+                f'Beads tagged {args.tag}',
+                ('name', 'freeze_time', 'box', 'kind', 'tags', 'automated_by'), rows),
+            args.output_format)
@@ -4,6 +4,9 @@ BEAD_REF  = '''
     bead to load data from
     - either an archive file name or a bead name
 '''
+BEAD_REFERENCE = '''
+    bead - an archive file name, a bead name, a prefix of its content id or its kind
+'''
 INPUT_NICK = (
     'name of input,'
     + ' its workspace relative location is "input/%(metavar)s"')
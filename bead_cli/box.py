@@ -1,7 +1,20 @@
+from datetime import timedelta
+import os
+
 from bead import tech
 from bead.archive import Archive
+from bead.box import DEFAULT_LEFTOVER_AGE, DEFAULT_RETENTION, DEFAULT_VERIFICATION_MAX_AGE
+from bead.box import NAME_MATCHING_MODES, NAME_MATCHING_STRICT
+from bead.exceptions import BoxError, InvalidArchive, TransferError
+from bead import spec as bead_spec
+from bead.tech.timestamp import time_from_user
+from bead.ziparchive import ZipArchive
 from .cmdparse import Command
-from .common import OPTIONAL_ENV, die
+from .common import OPTIONAL_ENV, REPORT_FORMAT, TEXT, die, die_with, warn_timed_out, warning
+from .common import automated_by, short_content_ids
+from .environment import BOX_CONFLICT_POLICIES, HASH_ALGORITHMS, ZIP_COMPRESSIONS
+from .progress import progress_bar
+from .report import Report, emit
 from .web import rewire
 
 
@@ -13,6 +26,18 @@ class CmdAdd(Command):
     def declare(self, arg):
         arg('name')
         arg('directory', type=tech.fs.Path)
+        arg('--group', default=None,
+            help='group owning saved archives - for boxes shared by a team')
+        arg('--mode', dest='file_mode', type=file_mode, default=None,
+            help='octal mode of saved archives, e.g. 0664 for group writable files')
+        arg('--compression', choices=ZIP_COMPRESSIONS, default=None,
+            help='compression of saved archives (default: as configured in the environment)')
+        arg('--name-matching', dest='name_matching', choices=NAME_MATCHING_MODES,
+            default=NAME_MATCHING_STRICT,
+            help='match bead names exactly (strict) or ignoring case (ci)'
+            + ' - on all platforms, whatever the file system does (default: strict)')
+        arg('--read-only', dest='read_only', default=False, action='store_true',
+            help='never write to the box - e.g. a shared institutional box')
         arg(OPTIONAL_ENV)
 
     def run(self, args):
@@ -28,12 +53,30 @@ class CmdAdd(Command):
             return
         location = directory.resolve()
         try:
-            env.add_box(name, location)
+            env.add_box(
+                name, location,
+                file_mode=args.file_mode, group=args.group, compression=args.compression,
+                name_matching=args.name_matching, read_only=args.read_only)
             env.save()
             print(f'Will remember box {name}')
         except ValueError as e:
             print('ERROR:', *e.args)
             print('Check the parameters: both name and directory must be unique!')
+            return
+        box = env.get_box(name)
+        # new files in the box directory should inherit the group
+        box.share(location)
+        if box.is_case_sensitive() is False and not box.ignores_case:
+            print(
+                'Note: the box is on a case-insensitive file system,'
+                + ' bead names are still matched exactly (see --name-matching)')
+
+
+def file_mode(mode_str):
+    mode = int(mode_str, 8)
+    if not 0 <= mode <= 0o777:
+        raise ValueError(mode_str)
+    return mode
 
 
 class CmdList(Command):
@@ -42,13 +85,22 @@ class CmdList(Command):
     '''
 
     def declare(self, arg):
+        arg(REPORT_FORMAT)
         arg(OPTIONAL_ENV)
 
     def run(self, args):
         boxes = args.get_env().get_boxes()
+        if args.output_format != TEXT:
+            emit(
+                Report(
+                    'Boxes',
+                    (
+                        'name', 'location', 'compression', 'file_mode', 'group',
+                        'name_matching', 'read_only'),
+                    [box.as_dict() for box in boxes]),
+                args.output_format)
+            return
 
-        def print_box(box):
-            print(f'{box.name}: {box.location}')
         if boxes:
             print('Boxes:')
             print('-------------')
@@ -58,6 +110,67 @@ class CmdList(Command):
             print('There are no defined boxes')
 
 
+def print_box(box):
+    settings = []
+    if box.group is not None:
+        settings.append(f'group {box.group}')
+    if box.file_mode is not None:
+        settings.append(f'mode {box.file_mode:04o}')
+    if box.compression is not None:
+        settings.append(f'{box.compression} compression')
+    if box.ignores_case:
+        settings.append('names ignoring case')
+    if box.read_only:
+        settings.append('read-only')
+    if settings:
+        print(f'{box.name}: {box.location} ({", ".join(settings)})')
+    else:
+        print(f'{box.name}: {box.location}')
+
+
+class CmdSearch(Command):
+    '''
+    Find beads in all boxes by a pattern matching their names or kinds.
+
+    The pattern is a glob (e.g. 'sales-*'), or with --regex a regular expression,
+    matching the whole name or kind.
+    '''
+
+    def declare(self, arg):
+        arg('pattern')
+        arg('--regex', default=False, action='store_true',
+            help='the pattern is a regular expression')
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        unionbox = args.get_env().get_union_box()
+        try:
+            beads = unionbox.find_matching(args.pattern, args.regex)
+        except ValueError as e:
+            die(str(e))
+        finally:
+            warn_timed_out(unionbox)
+        if not beads and args.output_format == TEXT:
+            print(f'No beads match {args.pattern}')
+            return
+        content_ids = {bead.content_id: bead.content_id for bead in beads}
+        if args.output_format == TEXT:
+            # machine readable formats have the full content ids
+            content_ids = short_content_ids(content_ids)
+        rows = [
+            dict(
+                name=bead.name, freeze_time=bead.freeze_time_str, box=bead.box_name,
+                kind=bead.kind, content_id=content_ids[bead.content_id],
+                automated_by=automated_by(bead))
+            for bead in beads]
+        emit(
+            Report(
+                f'Beads matching {args.pattern}',
+                ('name', 'freeze_time', 'box', 'kind', 'content_id', 'automated_by'), rows),
+            args.output_format)
+
+
 class CmdForget(Command):
     '''
     Remove the named box from the boxes known by the tool.
@@ -79,6 +192,79 @@ class CmdForget(Command):
             print(f'WARNING: no box defined with "{name}"')
 
 
+class CmdReadOnly(Command):
+    '''
+    Show or set whether a box is read-only.
+
+    Beads in read-only boxes can be found and loaded,
+    but saving, deleting or cleaning up the box fails.
+    '''
+
+    def declare(self, arg):
+        arg('name')
+        arg('--on', dest='read_only', action='store_true', default=None,
+            help='make the box read-only')
+        arg('--off', dest='read_only', action='store_false', default=None,
+            help='allow writing to the box')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        box = get_box(env, args.name)
+        if args.read_only is not None:
+            env.set_box_read_only(box.name, args.read_only)
+            env.save()
+            box = get_box(env, args.name)
+        print(f'Box {box.name} is {"read-only" if box.read_only else "writable"}')
+
+
+class CmdConflicts(Command):
+    '''
+    Show or set which copy to use, when the same bead is in multiple boxes.
+
+    priority: the copy in the box defined first (see `box list`)
+    newest: the most recently written archive
+    error: refuse to choose
+    '''
+
+    def declare(self, arg):
+        arg('policy', nargs='?', choices=BOX_CONFLICT_POLICIES, default=None)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        if args.policy is None:
+            print(f'Box conflict policy: {env.get_box_conflict_policy()}')
+            return
+        env.set_box_conflict_policy(args.policy)
+        env.save()
+        print(f'Box conflict policy is set to {args.policy}')
+
+
+class CmdSearchTimeout(Command):
+    '''
+    Show or set how long to wait for boxes when searching beads.
+
+    Boxes not answering in time (e.g. unreachable network drives) are skipped with a warning.
+    '''
+
+    def declare(self, arg):
+        arg('seconds', type=float, nargs='?', default=None)
+        arg('--off', dest='off', action='store_true', help='wait for all boxes')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        if args.off or args.seconds is not None:
+            try:
+                env.set_search_timeout(None if args.off else args.seconds)
+            except ValueError as e:
+                die(str(e))
+            env.save()
+        timeout = env.get_search_timeout()
+        print(f'Search timeout: {"off" if timeout is None else f"{timeout:g} seconds"}')
+
+
 class CmdXmeta(Command):
     '''
     eXport eXtended meta attributes to a file next to zip archive.
@@ -88,10 +274,39 @@ class CmdXmeta(Command):
 
     def run(self, args):
         archive = Archive(args.zip_archive_filename)
-        archive.save_cache()
+        # copies of the archive made along with its .xmeta can be verified by it
+        archive.record_archive_sha256()
         print(f'Saved {archive.cache_path}')
 
 
+class CmdRepairManifest(Command):
+    '''
+    Rebuild the missing or broken manifest of an archive by hashing its content again.
+
+    The manifest is written next to the archive (ARCHIVE.manifest) and used instead of
+    the broken one - the archive itself is not changed.
+    '''
+    def declare(self, arg):
+        arg('zip_archive_filename')
+        arg('--hash', dest='hash_algorithm', choices=HASH_ALGORITHMS,
+            default=tech.securehash.DEFAULT_ALGORITHM.value,
+            help='algorithm the archive was hashed with')
+
+    def run(self, args):
+        try:
+            archive = ZipArchive(args.zip_archive_filename)
+            if archive.has_intact_manifest:
+                print(f'The manifest of {args.zip_archive_filename} is intact, nothing to repair')
+                return
+            path = archive.repair_manifest(
+                tech.securehash.HashAlgo(args.hash_algorithm), progress=progress_bar())
+        except InvalidArchive as e:
+            die(f'Can not repair {args.zip_archive_filename}: {e}', e.exit_code)
+        print(f'Saved {path}')
+        print(f'Content id: {archive.content_id}')
+        print('Compare it with a known content id (e.g. of an input) to trust the data')
+
+
 class CmdRewire(Command):
     '''
     Remap inputs.
@@ -102,16 +317,281 @@ class CmdRewire(Command):
         arg(OPTIONAL_ENV)
 
     def run(self, args):
-        env = args.get_env()
         name = args.name
-        for box in env.get_boxes():
-            if box.name == name:
-                break
-        else:
-            die(f'Unknown box {name}')
+        box = get_box(args.get_env(), name)
         rewire_options = tech.persistence.file_load(args.rewire_options_json)
         rewire_specs = rewire_options.get(name, [])
         # This could be painfully slow, if there are many beads and their metadata
         # is not exported/cached with xmeta
         for bead in box.all_beads():
             rewire.apply(bead, rewire_specs)
+
+
+def get_box(env, name):
+    box = env.get_box(name)
+    if box is None:
+        die(
+            f'Unknown box {name}', error='UnknownBox', context=dict(box=name),
+            suggestions=['bead box list'])
+    return box
+
+
+class CmdDelete(Command):
+    '''
+    Delete archives from a box.
+
+    Deleted archives are kept aside for a while and can be restored.
+    '''
+    def declare(self, arg):
+        arg('name')
+        arg('archives', metavar='ARCHIVE', nargs='+', help='archive file name in the box')
+        arg('--retention-days', dest='retention_days', type=int,
+            default=DEFAULT_RETENTION.days,
+            help='purge archives deleted more than this many days ago')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        box = get_box(args.get_env(), args.name)
+        retention = timedelta(days=args.retention_days)
+        for archive in args.archives:
+            try:
+                box.delete(archive, retention)
+            except BoxError as e:
+                die(f'{e}')
+            print(f'Deleted {archive} (restore with "bead box restore {box.name} {archive}")')
+
+
+class CmdCheck(Command):
+    '''
+    Validate every archive in a box, reporting damaged files.
+
+    Unchanged archives verified recently are skipped, unless forced.
+    '''
+    def declare(self, arg):
+        arg('name')
+        arg('--force', default=False, action='store_true',
+            help='verify also archives verified recently')
+        arg('--max-age-days', dest='max_age_days', type=int,
+            default=DEFAULT_VERIFICATION_MAX_AGE.days,
+            help='skip archives verified less than this many days ago (default: %(default)s)')
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        box = get_box(args.get_env(), args.name)
+        verified_since = None
+        if not args.force:
+            now = tech.timestamp.time_from_timestamp(tech.timestamp.timestamp())
+            verified_since = now - timedelta(days=args.max_age_days)
+        # text is reported while checking, other formats only at the end
+        streaming = args.output_format == TEXT
+        rows = []
+        damaged = 0
+        for path, damages in box.check(verified_since=verified_since):
+            if damages is None:
+                status = 'OK (verified recently)'
+            elif damages:
+                damaged += 1
+                status = 'DAMAGED'
+            else:
+                status = 'OK'
+            if streaming:
+                print(f'{path.name}: {status}')
+                for damage in damages or ():
+                    print(f'  {damage}')
+            else:
+                rows.append(dict(
+                    archive=path.name, status=status,
+                    problems='; '.join(str(damage) for damage in damages or ())))
+        if not streaming:
+            emit(
+                Report(f'Archives in box {box.name}', ('archive', 'status', 'problems'), rows),
+                args.output_format)
+        if damaged:
+            die(f'{damaged} damaged archive(s) in box {box.name}', InvalidArchive.exit_code)
+        if streaming:
+            print(f'All archives in box {box.name} are valid')
+
+
+class CmdDu(Command):
+    '''
+    Disk usage of a box by bead name: number of versions, their total size and the newest one.
+    '''
+
+    def declare(self, arg):
+        arg('name')
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        box = get_box(args.get_env(), args.name)
+        beads_by_name = {}
+        for bead in box.all_beads():
+            beads_by_name.setdefault(bead.name, []).append(bead)
+        rows = []
+        for name, beads in sorted(beads_by_name.items()):
+            rows.append(dict(
+                name=name,
+                versions=len(beads),
+                bytes=sum(bead.archive_path.stat().st_size for bead in beads),
+                newest=max(beads, key=lambda bead: bead.freeze_time).freeze_time_str))
+        emit(
+            Report(f'Disk usage of box {box.name}', ('name', 'versions', 'bytes', 'newest'), rows),
+            args.output_format)
+
+
+class CmdRestore(Command):
+    '''
+    Restore deleted archives in a box, list restorable archives if none is given.
+    '''
+    def declare(self, arg):
+        arg('name')
+        arg('archives', metavar='ARCHIVE', nargs='*', help='deleted archive file name')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        box = get_box(args.get_env(), args.name)
+        if not args.archives:
+            deleted_archives = box.deleted_archives()
+            if not deleted_archives:
+                print(f'There are no deleted archives in box {box.name}')
+            for deletion_time, path in deleted_archives:
+                print(f'{path.name}  (deleted at {deletion_time})')
+            return
+        for archive in args.archives:
+            try:
+                box.restore(archive)
+            except BoxError as e:
+                die(f'{e}')
+            print(f'Restored {archive}')
+
+
+class CmdNuke(Command):
+    '''
+    Permanently remove archives from a box - with their cache and index entries.
+
+    Unlike delete, nuked archives can not be restored.
+    '''
+    def declare(self, arg):
+        arg('name')
+        arg('archives', metavar='ARCHIVE', nargs='+', help='archive file name in the box')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        box = get_box(args.get_env(), args.name)
+        for archive_filename in args.archives:
+            path = box.directory / os.path.basename(archive_filename)
+            if not path.is_file():
+                die(f'Box "{box.name}": no such archive: {path.name}')
+            try:
+                archive = Archive(path, box.name)
+                removed = box.remove_bead(archive.name, archive.content_id)
+            except InvalidArchive:
+                die(f'Not a valid archive: {path}')
+            except BoxError as e:
+                die(f'{e}')
+            for path in removed:
+                print(f'Removed {path.name}')
+
+
+class CmdGc(Command):
+    '''
+    Remove leftovers of archives gone from a box: orphaned cache files and index entries.
+    '''
+    def declare(self, arg):
+        arg('name')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        box = get_box(args.get_env(), args.name)
+        garbage = box.gc()
+        for path in garbage.files:
+            print(f'Removed {path.name}')
+        for filename in garbage.index_entries:
+            print(f'Removed index entry of {filename}')
+        if not garbage.files and not garbage.index_entries:
+            print(f'Box {box.name} is clean')
+
+
+class CmdSync(Command):
+    '''
+    Copy archives missing from a box from another box - e.g. to mirror boxes.
+
+    Copies are verified by their checksum and content id. Existing archives are never overwritten.
+    '''
+    def declare(self, arg):
+        arg('source', metavar='SOURCE_BOX')
+        arg('target', metavar='TARGET_BOX')
+        arg('--name', dest='bead_names', action='append', default=[], metavar='BEAD_NAME',
+            help='copy only these beads (can be repeated)')
+        arg('--kind', default=None, help='copy only beads of this kind')
+        arg('--since', type=time_from_user, default=None, metavar='TIME',
+            help='copy only versions frozen at or after TIME')
+        arg('--until', type=time_from_user, default=None, metavar='TIME',
+            help='copy only versions frozen at or before TIME')
+        arg('--dry-run', default=False, action='store_true',
+            help='only show what would be copied')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        source = get_box(env, args.source)
+        target = get_box(env, args.target)
+        if source.directory.resolve() == target.directory.resolve():
+            die(f'Boxes {source.name} and {target.name} are the same directory')
+        conditions = []
+        if args.kind is not None:
+            conditions.append((bead_spec.KIND, args.kind))
+        condition_sets = [
+            conditions + [(bead_spec.BEAD_NAME, name)] for name in args.bead_names
+        ] or [conditions]
+        action = 'Would copy' if args.dry_run else 'Copied'
+        copied = conflicts = 0
+        for conditions in condition_sets:
+            try:
+                report = target.sync_from(
+                    source, conditions, args.since, args.until, args.dry_run)
+            except (BoxError, InvalidArchive, TransferError) as e:
+                die_with(e)
+            for path in report.copied:
+                print(f'{action} {path.name}')
+            for path in report.conflicts:
+                warning(f'{path.name} exists in box {target.name} with different content')
+            copied += len(report.copied)
+            conflicts += len(report.conflicts)
+        print(f'{action} {copied} archive(s) from {source.name} to {target.name}')
+        if conflicts:
+            die(f'{conflicts} archive(s) could not be copied')
+
+
+class CmdRepair(Command):
+    '''
+    Clean up a box after interrupted saves and crashes, and rebuild its index.
+
+    Removes leftover staged archives and temporary files, empty archives,
+    orphaned cache files and index entries of missing archives.
+    '''
+    def declare(self, arg):
+        arg('name')
+        arg('--dry-run', default=False, action='store_true',
+            help='only report the problems, do not change anything')
+        arg('--min-age-hours', dest='min_age_hours', type=float,
+            default=DEFAULT_LEFTOVER_AGE.total_seconds() / 3600,
+            help='temporary files younger than this might belong to saves in progress'
+            + ' (default: %(default)s)')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        box = get_box(args.get_env(), args.name)
+        report = box.repair(args.dry_run, timedelta(hours=args.min_age_hours))
+        action = 'Would remove' if args.dry_run else 'Removed'
+        for path in report.leftover_files:
+            print(f'{action} leftover of an interrupted save: {path.name}')
+        for path in report.empty_archives:
+            print(f'{action} empty archive: {path.name}')
+        for path in report.orphaned_sidecars:
+            print(f'{action} orphaned file: {path.name}')
+        for filename in report.index_entries:
+            print(f'{action} index entry of missing archive: {filename}')
+        if report.is_clean:
+            print(f'Box {box.name} needs no repair')
@@ -69,7 +69,8 @@ class Parser:
 
     argparser: argparse.ArgumentParser
 
-    def __init__(self, argparser: argparse.ArgumentParser, defaults: dict) -> None:
+    def __init__(
+            self, argparser: argparse.ArgumentParser, defaults: dict, name: str = '') -> None:
         '''
         Wrap an `argparse.ArgumentParser`.
 
@@ -77,6 +78,8 @@ class Parser:
         '''
         self.argparser = argparser
         self.defaults = defaults
+        # full name of the command group, e.g. 'input' - empty for the top level parser
+        self.name = name
 
         # This is ugly :(
         # subparsers should be an `argparse` implementation detail, but is not
@@ -120,9 +123,10 @@ class Parser:
         '''
         Declare one or more arguments.
 
-        Same as `argparse.ArgumentParser.add_argument` with an extension:
+        Same as `argparse.ArgumentParser.add_argument` with extensions:
         when the first and only parameter is a function, it is called with
-        the parser to do some non-trivial work, like adding an argument group.
+        the parser to do some non-trivial work, like adding an argument group;
+        `completer` is the function providing shell completion candidates for the argument.
 
         The argument help is fixed up to show the default value.
         '''
@@ -131,11 +135,14 @@ class Parser:
             args[0](self)
         else:
             arg_kwargs = dict(kwargs)
+            completer = arg_kwargs.pop('completer', None)
             if 'default' in kwargs:
                 # extend help with default
                 arg_kwargs['help'] = (
                     f"{kwargs.get('help', '')} (default: {kwargs['default']!r})")
-            self.argparser.add_argument(*args, **arg_kwargs)
+            action = self.argparser.add_argument(*args, **arg_kwargs)
+            if completer is not None:
+                action.completer = completer
 
     def command(self, name: str, commandish: Command | type, title: str) -> None:
         '''
@@ -153,7 +160,9 @@ class Parser:
             formatter_class=command.FORMATTER_CLASS
         )
         command.declare(self.__class__(parser, self.defaults).arg)
-        parser.set_defaults(_cmdparse__run=command.run)
+        parser.set_defaults(
+            _cmdparse__run=command.run,
+            _cmdparse__name=f'{self.name} {name}'.strip())
 
     def commands(self, *commands_sequence: tuple[str, Command | type, str]) -> None:
         '''
@@ -172,11 +181,16 @@ class Parser:
         '''
         parser = self._subparsers.add_parser(
             name, help=title + '...', description=help)
-        return self.__class__(parser, self.defaults)
+        return self.__class__(parser, self.defaults, f'{self.name} {name}'.strip())
 
-    def dispatch(self, argv: Sequence[str]) -> int:
+    def dispatch(self, argv: Sequence[str], wrap_run=None, check=None) -> int:
         '''
         Parse `argv` and dispatch to the appropriate command.
+
+        `wrap_run`, if given, is called with the full name of the command
+        (e.g. 'input add') and a function running it, and should return its result.
+
+        `check`, if given, is called with the parsed arguments before running the command.
         '''
         def print_help(args):
             print(
@@ -198,7 +212,12 @@ class Parser:
             # this is worked around here
             return -1
         run = getattr(args, '_cmdparse__run', print_help)
-        return run(args) or 0
+        if check is not None:
+            check(args)
+        if wrap_run is None:
+            return run(args) or 0
+        name = getattr(args, '_cmdparse__name', '')
+        return wrap_run(name, lambda: run(args) or 0)
 
     def autocomplete(self):
         """Enable shell autocomplete"""
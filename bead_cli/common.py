@@ -1,17 +1,23 @@
+import argparse
 import os
+import subprocess
 import sys
-from typing import NoReturn
+from typing import Dict, Iterable, NoReturn, Optional
 
-from bead.exceptions import InvalidArchive
+from bead import tech
+from bead.exceptions import BeadError, InvalidArchive
 from bead.workspace import Workspace
 from bead import spec as bead_spec
 from bead.archive import Archive
-from bead import box as bead_box
+from bead.tech import securehash
 from bead.tech.fs import Path
+from bead.tech.identifier import unique_prefix_length
+from bead.tech.progress import NO_PROGRESS
 from bead.tech.timestamp import time_from_user, parse_iso8601
 from . import arg_help
 from . import arg_metavar
-from .environment import Environment
+from .environment import Environment, PROMPT_ASK, PROMPT_MODES, PROMPT_NO_INPUT, PROMPT_YES
+from .complete import bead_name_completer
 
 
 TIME_LATEST = parse_iso8601('9999-12-31')
@@ -19,11 +25,35 @@ TIME_LATEST = parse_iso8601('9999-12-31')
 ERROR_EXIT = 1
 
 
-def die(msg) -> NoReturn:
+def die(msg, exit_code=ERROR_EXIT, error=None, context=None, suggestions=()) -> NoReturn:
+    '''
+    Report an error and exit - as a JSON object in JSON output mode.
+
+    error is the type of the error, context describes what it happened to (e.g. path),
+    suggestions are commands or actions, that might fix it.
+    '''
+    if _error_format == JSON:
+        report = dict(
+            code=exit_code,
+            error=error or 'Error',
+            message=msg,
+            context={key: str(value) for key, value in (context or {}).items()},
+            suggestions=list(suggestions))
+        sys.stderr.write(tech.persistence.dumps(dict(error=report)))
+        sys.stderr.write('\n')
+        sys.exit(exit_code)
     sys.stderr.write('ERROR: ')
     sys.stderr.write(msg)
     sys.stderr.write('\n')
-    sys.exit(ERROR_EXIT)
+    for suggestion in suggestions:
+        sys.stderr.write(f'Try: {suggestion}\n')
+    sys.exit(exit_code)
+
+
+def die_with(error: BeadError, suggestions=()) -> NoReturn:
+    # the context is reported separately in JSON
+    message = error.message if _error_format == JSON else str(error)
+    die(message, error.exit_code, type(error).__name__, error.context, suggestions)
 
 
 def warning(msg):
@@ -37,6 +67,194 @@ def info(msg):
     sys.stderr.write('\n')
 
 
+_prompt_mode = PROMPT_ASK
+
+
+def set_prompt_mode(mode):
+    '''
+    ask: ask the user, when running interactively
+    yes: answer confirmations with yes, other questions with their default, without asking
+    no-input: answer every question with its default, without asking
+    '''
+    global _prompt_mode
+    if mode not in PROMPT_MODES:
+        raise ValueError(f'Unknown prompt mode {mode}')
+    _prompt_mode = mode
+
+
+def is_interactive() -> bool:
+    if _prompt_mode != PROMPT_ASK:
+        return False
+    return sys.stdin is not None and sys.stdin.isatty()
+
+
+def confirm(question, default: bool) -> bool:
+    '''
+    Ask a yes/no question, answer with default when not running interactively.
+    '''
+    if _prompt_mode == PROMPT_YES:
+        return True
+    if not is_interactive():
+        return default
+    choices = '[Y/n]' if default else '[y/N]'
+    answer = input(f'{question} {choices} ').strip().lower()
+    if not answer:
+        return default
+    return answer in ('y', 'yes')
+
+
+def ask(question, default: str) -> str:
+    '''
+    Ask for a value, answer with default when not running interactively.
+    '''
+    if not is_interactive():
+        return default
+    answer = input(f'{question} [{default}] ').strip()
+    return answer or default
+
+
+class _SetPromptMode(argparse.Action):
+    def __call__(self, parser, namespace, values, option_string=None):
+        set_prompt_mode(self.const)
+
+
+def GLOBAL_PROMPT_MODE(parser):
+    '''
+    Define `--yes` and `--no-input` for all commands (given before the command)
+    '''
+    parser.argparser.add_argument(
+        '--yes', '-y', action=_SetPromptMode, nargs=0, const=PROMPT_YES,
+        help='answer yes to confirmations, do not ask anything')
+    parser.argparser.add_argument(
+        '--no-input', action=_SetPromptMode, nargs=0, const=PROMPT_NO_INPUT,
+        help='never ask, use the default answers')
+
+
+def GLOBAL_LOCK_WAIT(parser):
+    '''
+    Define `--wait` for all commands (given before the command)
+    '''
+    parser.argparser.add_argument(
+        '--wait', metavar='SECONDS', type=float, dest='lock_wait', default=0,
+        help='wait this long for workspaces being modified by another bead process,'
+        + ' instead of failing')
+
+
+def open_in_file_manager(path):
+    '''
+    Show path in the system's file manager, without waiting for it to exit.
+    '''
+    if sys.platform == 'win32':
+        os.startfile(path)
+    elif sys.platform == 'darwin':
+        subprocess.Popen(['open', os.fspath(path)])
+    else:
+        subprocess.Popen(['xdg-open', os.fspath(path)])
+
+
+# output formats - JSON is for scripts and pipeline orchestration tools
+TEXT = 'text'
+JSON = 'json'
+OUTPUT_FORMATS = (TEXT, JSON)
+# reports (see report.py) can be rendered also as CSV and as HTML pages for sharing
+CSV = 'csv'
+HTML = 'html'
+REPORT_FORMATS = (TEXT, JSON, CSV, HTML)
+
+
+# errors are reported in the output format, so that wrappers can present them
+_error_format = TEXT
+
+
+def set_error_format(output_format):
+    global _error_format
+    _error_format = output_format
+
+
+class _SetOutputFormat(argparse.Action):
+    def __call__(self, parser, namespace, values, option_string=None):
+        output_format = self.const if self.nargs == 0 else values
+        setattr(namespace, self.dest, output_format)
+        set_error_format(output_format)
+
+
+def GLOBAL_OUTPUT_FORMAT(parser):
+    '''
+    Define `--format` for all commands (given before the command)
+    '''
+    parser.arg(
+        '--format', dest='output_format', choices=REPORT_FORMATS, default=TEXT,
+        action=_SetOutputFormat,
+        help='output format of the commands supporting it (JSON also for errors)')
+
+
+def _support_output_formats(parser, output_formats):
+    parser.argparser.set_defaults(supported_output_formats=output_formats)
+
+
+def OUTPUT_FORMAT(parser):
+    '''
+    Define `--format` for a command supporting JSON output - overriding the global one
+    '''
+    # not parser.arg: a default here would override the global option
+    parser.argparser.add_argument(
+        '--format', dest='output_format', choices=OUTPUT_FORMATS, default=argparse.SUPPRESS,
+        action=_SetOutputFormat,
+        help='output format (default: the global --format, text)')
+    _support_output_formats(parser, OUTPUT_FORMATS)
+
+
+def REPORT_FORMAT(parser):
+    '''
+    Define `--format` for a command with report output - overriding the global one
+    '''
+    parser.argparser.add_argument(
+        '--format', dest='output_format', choices=REPORT_FORMATS, default=argparse.SUPPRESS,
+        action=_SetOutputFormat,
+        help='output format (default: the global --format, text)')
+    _support_output_formats(parser, REPORT_FORMATS)
+
+
+def JSON_FLAG(parser):
+    '''
+    Define `--json` as a shorthand for `--format json`
+    '''
+    parser.argparser.add_argument(
+        '--json', dest='output_format', action=_SetOutputFormat, nargs=0, const=JSON,
+        default=argparse.SUPPRESS, help='same as --format json')
+
+
+def JSON_OUTPUT(parser):
+    '''
+    Declare, that the command prints only JSON - so it accepts the global `--format json`
+    '''
+    _support_output_formats(parser, (TEXT, JSON))
+
+
+def check_output_format(args):
+    '''
+    Refuse a global `--format` the command does not support - instead of ignoring it.
+    '''
+    supported = getattr(args, 'supported_output_formats', (TEXT,))
+    if args.output_format not in supported:
+        die(
+            f'This command does not support --format {args.output_format}'
+            + f' (supported: {", ".join(supported)})')
+
+
+def apply_lock_wait(args):
+    '''
+    Let the workspace of the command wait `--wait` seconds for other bead processes modifying it.
+    '''
+    workspace = getattr(args, 'workspace', None)
+    if isinstance(workspace, Workspace):
+        workspace.lock_timeout = args.lock_wait
+
+
+def print_json(value):
+    print(tech.persistence.dumps(value))
+
+
 def OPTIONAL_WORKSPACE(parser):
     '''
     Define `workspace` as option, defaulting to current directory
@@ -49,7 +267,10 @@ def OPTIONAL_WORKSPACE(parser):
 
 def assert_valid_workspace(workspace):
     if not workspace.is_valid:
-        die(f'{workspace.directory} is not a valid workspace')
+        die(
+            f'{workspace.directory} is not a valid workspace', error='InvalidWorkspace',
+            context=dict(path=workspace.directory),
+            suggestions=['cd into a workspace', 'bead new NAME', 'bead develop BEAD'])
 
 
 class get_env:
@@ -111,6 +332,9 @@ def BEAD_TIME(parser):
 def BEAD_OFFSET(parser):
     parser.arg('-N', '--next', dest='bead_offset', action='store_const', const=1, default=0)
     parser.arg('-P', '--prev', '--previous', dest='bead_offset', action='store_const', const=-1)
+    parser.arg(
+        '--newest', dest='bead_newest', type=int, metavar='N', default=0,
+        help='use the Nth newest version (1: newest, 2: the one before it, ...)')
 
 
 def arg_bead_ref_base(nargs, default):
@@ -120,7 +344,7 @@ def arg_bead_ref_base(nargs, default):
     def declare(parser):
         parser.arg(
             'bead_ref_base', metavar=arg_metavar.BEAD_REF, help=arg_help.BEAD_REF,
-            nargs=nargs, type=str, default=default)
+            nargs=nargs, type=str, default=default, completer=bead_name_completer)
     return declare
 
 
@@ -131,21 +355,74 @@ def BEAD_REF_BASE_defaulting_to(name):
 BEAD_REF_BASE = arg_bead_ref_base(nargs=None, default=None)
 
 
+def BEAD_REFERENCE(parser):
+    '''
+    Declare bead_ref_base argument - accepting also content id prefixes and kinds
+    '''
+    parser.arg(
+        'bead_ref_base', metavar=arg_metavar.BEAD_REF, help=arg_help.BEAD_REFERENCE,
+        type=str, completer=bead_name_completer)
+
+
 def resolve_bead(env, bead_ref_base, time):
     # prefer exact file name over box search
     if os.path.isfile(bead_ref_base):
         return Archive(bead_ref_base)
 
     # not a file - try box search
-    unionbox = bead_box.UnionBox(env.get_boxes())
+    unionbox = env.get_union_box()
+
+    try:
+        return unionbox.get_at(bead_spec.BEAD_NAME, bead_ref_base, time)
+    finally:
+        warn_timed_out(unionbox)
+
+
+def resolve_reference(env, reference, time):
+    '''
+    Like resolve_bead, but reference can be also a content id prefix or a kind.
+    '''
+    if os.path.isfile(reference):
+        return Archive(reference)
+
+    unionbox = env.get_union_box()
+    try:
+        return unionbox.resolve_reference(reference, time)
+    finally:
+        warn_timed_out(unionbox)
+
+
+def automated_by(bead) -> Optional[str]:
+    '''
+    Identity of the pipeline runner, that saved the bead - None for manually saved beads.
+    '''
+    try:
+        provenance = bead.provenance
+    except InvalidArchive:
+        return None
+    return None if provenance is None else provenance.runner
+
+
+def short_content_ids(content_ids: Iterable[str]) -> Dict[str, str]:
+    '''
+    Shortest prefixes of the content ids telling them apart - by content id, for listings.
+    '''
+    digests = {content_id: securehash.digest_of(content_id) for content_id in content_ids}
+    length = unique_prefix_length(digests.values())
+    return {content_id: digest[:length] for content_id, digest in digests.items()}
+
 
-    return unionbox.get_at(bead_spec.BEAD_NAME, bead_ref_base, time)
+def warn_timed_out(unionbox):
+    for box_name in unionbox.timed_out:
+        warning(f'Box {box_name} did not answer in time, its beads were not searched')
 
 
-def verify_with_feedback(archive: Archive):
-    print(f'Verifying archive {archive.archive_filename} ...', end='', flush=True)
+def verify_with_feedback(archive: Archive, progress=NO_PROGRESS, checkpoint=None):
+    # a progress bar would overwrite the rest of the line
+    end = '' if progress is NO_PROGRESS else '\n'
+    print(f'Verifying archive {archive.archive_filename} ...', end=end, flush=True)
     try:
-        archive.validate()
+        archive.validate(progress=progress, checkpoint=checkpoint)
         print(' OK', flush=True)
     except InvalidArchive:
         print(' DAMAGED!', flush=True)
@@ -0,0 +1,66 @@
+'''
+Completion candidates for shell completion scripts and editor plugins.
+
+Candidates come from the box indices only - no archive is opened,
+so completion stays fast even for huge boxes, at the price of missing
+archives not yet indexed.
+'''
+
+from typing import Iterable
+
+from .cmdparse import Command
+from . import common
+
+NAMES = 'names'
+KINDS = 'kinds'
+BOXES = 'boxes'
+CANDIDATE_TYPES = (NAMES, KINDS, BOXES)
+
+
+def candidates(env, candidate_type, prefix='') -> Iterable[str]:
+    '''
+    Sorted, distinct candidates of candidate_type starting with prefix.
+    '''
+    boxes = env.get_boxes()
+    if candidate_type == BOXES:
+        values = {box.name for box in boxes}
+    else:
+        values = set()
+        for box in boxes:
+            try:
+                entries = box.indexed_entries()
+            except OSError:
+                # unavailable boxes have no candidates
+                continue
+            for entry in entries:
+                values.add(entry.name if candidate_type == NAMES else entry.kind)
+    return sorted(value for value in values if value.startswith(prefix))
+
+
+def bead_name_completer(prefix, parsed_args, **kwargs):
+    '''
+    argcomplete completer for bead names.
+    '''
+    get_env = getattr(parsed_args, 'get_env', None)
+    if get_env is None:
+        return []
+    try:
+        return candidates(get_env(), NAMES, prefix)
+    except Exception:
+        # completion must never break the shell
+        return []
+
+
+class CmdComplete(Command):
+    '''
+    Print completion candidates, one per line (for shell completion scripts and editors).
+    '''
+
+    def declare(self, arg):
+        arg('candidate_type', choices=CANDIDATE_TYPES)
+        arg('prefix', nargs='?', default='')
+        arg(common.OPTIONAL_ENV)
+
+    def run(self, args):
+        for candidate in candidates(args.get_env(), args.candidate_type, args.prefix):
+            print(candidate)
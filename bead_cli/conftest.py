@@ -7,7 +7,6 @@ from tracelog import TRACELOG
 from bead.workspace import Workspace
 from bead import layouts
 from bead import tech
-from bead.archive import Archive
 from .test_robot import Robot
 
 
@@ -97,7 +96,7 @@ def _new_bead(robot, beads, box, bead_name, inputs=None, tmp_path_factory=None):
     _add_inputs(robot, inputs)
     with robot.environment:
         TRACELOG('store', robot.cwd, TS1, 'to', box.location)
-        beads[bead_name] = Archive(box.store(Workspace('.'), TS1))
+        beads[bead_name] = box.store(Workspace('.'), TS1)
     robot.cd('..')
     robot.cli('zap', bead_name)
     return bead_name
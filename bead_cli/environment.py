@@ -2,22 +2,128 @@
 User specific environment
 '''
 
+import hashlib
 import os
+from typing import List, Optional
 
-from bead.box import Box
-from bead.tech import persistence
+import attr
+
+from bead import hooks
+from bead import notebooks
+from bead import workspace
+from bead.box import Box, UnionBox, CONFLICT_POLICIES, CONFLICT_PRIORITY
+from bead.box import NAME_MATCHING_MODES, NAME_MATCHING_STRICT
+from bead.context import BeadContext
+from bead.save_policy import NAME_COLLISION_POLICIES, NAME_COLLISION_WARN
+from bead.tech import persistence, securehash
+from bead.tech.checkpoint import Checkpoint
 from bead.tech.fs import Path
 
 ENV_BOXES = 'boxes'
 BOX_NAME = 'name'
 BOX_LOCATION = 'directory'
+# for boxes shared by a group: group ownership and (octal) mode of stored files
+BOX_GROUP = 'group'
+BOX_FILE_MODE = 'file-mode'
+# compression of archives saved to the box, overrides ENV_ZIP_COMPRESSION
+BOX_COMPRESSION = 'compression'
+# how bead names are matched in the box, one of NAME_MATCHING_MODES (default: strict)
+BOX_NAME_MATCHING = 'name-matching'
+# true for boxes, that must not be written to (e.g. shared institutional boxes)
+BOX_READ_ONLY = 'read-only'
+
+# what to do, when saving a bead under a name already used by another kind, see bead.save_policy
+ENV_NAME_COLLISION = 'name-collision'
+
+# which copy to use, when the same bead is in multiple boxes - boxes are in priority order
+ENV_BOX_CONFLICT = 'box-conflict'
+BOX_CONFLICT_POLICIES = CONFLICT_POLICIES
+
+# seconds to wait for boxes when searching beads - slower boxes are skipped with a warning
+ENV_SEARCH_TIMEOUT = 'search-timeout'
+
+# compression of new archives, the BEAD_ZIP_COMPRESSION environment variable overrides it
+ENV_ZIP_COMPRESSION = 'zip-compression'
+ZIP_COMPRESSIONS = tuple(sorted(workspace.ZIP_COMPRESSIONS))
+DEFAULT_ZIP_COMPRESSION = 'deflated'
+
+# lab data standards checked on save, see bead.save_policy
+ENV_SAVE_POLICIES = 'save-policies'
+
+# external commands checking archives before they are stored, see bead.validators
+ENV_VALIDATORS = 'validators'
+
+# cell outputs of Jupyter notebooks in code are kept or stripped on save, see bead.notebooks
+ENV_NOTEBOOK_OUTPUTS = 'notebook-outputs'
+NOTEBOOK_OUTPUT_MODES = notebooks.NOTEBOOK_OUTPUT_MODES
+
+# algorithm hashing new archives, see bead.tech.securehash
+ENV_HASH_ALGORITHM = 'hash-algorithm'
+HASH_ALGORITHMS = securehash.HASH_ALGORITHMS
+
+# directory of workspace templates (e.g. shared by a lab), see bead.templates
+ENV_TEMPLATE_DIRECTORY = 'template-directory'
+
+# checkpoints of interrupted verifications, under the configuration directory
+VERIFY_CHECKPOINTS_DIR = 'verify-checkpoints'
+
+# file to append local usage statistics to, not recorded when missing
+ENV_USAGE_LOG = 'usage-log'
+
+# how questions of commands are answered, the --yes and --no-input options override it
+# unattended runs (schedulers, CI) must not hang waiting for an answer
+ENV_PROMPTS = 'prompts'
+PROMPT_ASK = 'ask'
+PROMPT_YES = 'yes'
+PROMPT_NO_INPUT = 'no-input'
+PROMPT_MODES = (PROMPT_ASK, PROMPT_YES, PROMPT_NO_INPUT)
+
+# known workspaces - recorded on new, develop and save, only when enabled (present)
+ENV_WORKSPACES = 'workspaces'
+WORKSPACE_NAME = 'name'
+WORKSPACE_LOCATION = 'directory'
+WORKSPACE_KIND = 'kind'
+WORKSPACE_LAST_FREEZE = 'last-freeze'
+
+# hooks the user allowed to run: workspace directory -> fingerprint, see bead.hooks
+ENV_TRUSTED_HOOKS = 'trusted-hooks'
+
+# settings, that belong to this machine - not exported, kept on import
+LOCAL_SETTINGS = (ENV_USAGE_LOG, ENV_WORKSPACES, ENV_TRUSTED_HOOKS)
+
+# file format of exported settings (see Environment.export_settings)
+EXPORT_FORMAT = 'format'
+EXPORT_FORMAT_NAME = 'bead-environment'
+EXPORT_VERSION = 'version'
+EXPORT_VERSION_NUMBER = 1
+EXPORT_SETTINGS = 'settings'
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class KnownWorkspace:
+    name: str
+    directory: Path
+    kind: str
+    # freeze time of its last save, None if it was not saved since it is known
+    last_freeze_time_str: Optional[str]
+
+    def as_dict(self):
+        return dict(
+            name=self.name,
+            directory=os.fspath(self.directory),
+            kind=self.kind,
+            last_freeze_time_str=self.last_freeze_time_str)
 
 
 class Environment:
     """
     I am responsible for storing/retrieving user specific data.
 
-    Currently includes just the list of boxes and their definitions.
+    Currently includes the list of boxes and their definitions,
+    the policy for name collisions on save, for beads found in multiple boxes,
+    the save policies and external validators, the compression of new archives,
+    how to answer questions, the directory of workspace templates, the trusted hooks,
+    the location of the opt-in usage log and the opt-in registry of known workspaces.
     """
 
     def __init__(self, filename: Path):
@@ -30,30 +136,147 @@ class Environment:
     def from_dir(cls, directory):
         return cls(Path(os.path.join(directory, 'env.json')))
 
+    @property
+    def exists(self):
+        return os.path.exists(self.filename)
+
     def load(self):
         with open(self.filename, 'r') as f:
             self._content = persistence.load(f)
 
     def save(self):
-        with open(self.filename, 'w') as f:
-            persistence.dump(self._content, f)
+        persistence.file_dump(self._content, self.filename)
+
+    def export_settings(self) -> dict:
+        '''
+        Settings for moving to another machine or sharing a lab configuration.
+
+        Machine specific settings (LOCAL_SETTINGS) are left out.
+        '''
+        return {
+            EXPORT_FORMAT: EXPORT_FORMAT_NAME,
+            EXPORT_VERSION: EXPORT_VERSION_NUMBER,
+            EXPORT_SETTINGS: {
+                key: value
+                for key, value in self._content.items()
+                if key not in LOCAL_SETTINGS}}
+
+    def import_settings(self, exported, replace=False):
+        '''
+        Take over settings made by export_settings.
+
+        Boxes are merged by name - the imported definition wins, other settings are overwritten.
+        With replace all (not machine specific) settings are replaced.
+        Raises ValueError for invalid exports, leaving the environment unchanged.
+        '''
+        if (
+            not isinstance(exported, dict)
+            or exported.get(EXPORT_FORMAT) != EXPORT_FORMAT_NAME
+            or not isinstance(exported.get(EXPORT_SETTINGS), dict)
+        ):
+            raise ValueError('Not an exported bead environment')
+        if exported.get(EXPORT_VERSION) != EXPORT_VERSION_NUMBER:
+            raise ValueError(f'Unsupported export version {exported.get(EXPORT_VERSION)}')
+        settings = exported[EXPORT_SETTINGS]
+        if replace:
+            content = {
+                key: value for key, value in self._content.items() if key in LOCAL_SETTINGS}
+        else:
+            content = dict(self._content)
+        box_specs = {spec.get(BOX_NAME): spec for spec in content.get(ENV_BOXES, ())}
+        for spec in settings.get(ENV_BOXES, ()):
+            if not (
+                isinstance(spec, dict)
+                and isinstance(spec.get(BOX_NAME), str)
+                and isinstance(spec.get(BOX_LOCATION), str)
+            ):
+                raise ValueError(f'Invalid box definition: {spec}')
+            box_specs[spec[BOX_NAME]] = spec
+        content.update(
+            (key, value) for key, value in settings.items() if key not in LOCAL_SETTINGS)
+        content[ENV_BOXES] = list(box_specs.values())
+
+        previous = self._content
+        self._content = content
+        try:
+            self._check_settings()
+        except (ValueError, TypeError) as e:
+            self._content = previous
+            raise ValueError(f'Invalid settings: {e}') from None
+
+    def _check_settings(self):
+        # the setters refuse invalid values
+        setters = {
+            ENV_NAME_COLLISION: self.set_name_collision_policy,
+            ENV_BOX_CONFLICT: self.set_box_conflict_policy,
+            ENV_SEARCH_TIMEOUT: self.set_search_timeout,
+            ENV_ZIP_COMPRESSION: self.set_zip_compression,
+            ENV_NOTEBOOK_OUTPUTS: self.set_notebook_outputs,
+            ENV_HASH_ALGORITHM: self.set_hash_algorithm,
+            ENV_PROMPTS: self.set_prompt_mode,
+        }
+        for key, setter in setters.items():
+            if key in self._content:
+                setter(self._content[key])
+        # parses the file modes
+        for box in self.get_boxes():
+            if box.name_matching not in NAME_MATCHING_MODES:
+                raise ValueError(f'Unknown name matching {box.name_matching}')
+            if not isinstance(box.read_only, bool):
+                raise ValueError(f'Invalid read-only flag of box {box.name}: {box.read_only}')
+
+    def verify_checkpoint(self, archive_path) -> Checkpoint:
+        '''
+        Checkpoint of verifying the archive file at archive_path.
+
+        It is ignored, when the file was replaced or modified since (size, modification time).
+        '''
+        archive_path = os.path.realpath(archive_path)
+        stat = os.stat(archive_path)
+        file_name = hashlib.sha256(os.fsencode(archive_path)).hexdigest()
+        return Checkpoint(
+            Path(self.filename).parent / VERIFY_CHECKPOINTS_DIR / file_name,
+            f'{archive_path} {stat.st_size} {stat.st_mtime_ns}')
 
     def get_boxes(self):
         def box(box_spec):
+            file_mode = box_spec.get(BOX_FILE_MODE)
             return Box(
                 box_spec.get(BOX_NAME),
-                Path(box_spec.get(BOX_LOCATION)))
+                Path(box_spec.get(BOX_LOCATION)),
+                file_mode=None if file_mode is None else int(file_mode, 8),
+                group=box_spec.get(BOX_GROUP),
+                compression=box_spec.get(BOX_COMPRESSION),
+                name_matching=box_spec.get(BOX_NAME_MATCHING, NAME_MATCHING_STRICT),
+                read_only=box_spec.get(BOX_READ_ONLY, False))
         return [box(spec) for spec in self._content.get(ENV_BOXES, ())]
 
     def set_boxes(self, boxes):
-        self._content[ENV_BOXES] = [
-            {
+        def box_spec(box):
+            spec = {
                 BOX_NAME: box.name,
                 BOX_LOCATION: box.location.as_posix()
             }
-            for box in boxes]
+            if box.group is not None:
+                spec[BOX_GROUP] = box.group
+            if box.file_mode is not None:
+                spec[BOX_FILE_MODE] = f'{box.file_mode:04o}'
+            if box.compression is not None:
+                spec[BOX_COMPRESSION] = box.compression
+            if box.name_matching != NAME_MATCHING_STRICT:
+                spec[BOX_NAME_MATCHING] = box.name_matching
+            if box.read_only:
+                spec[BOX_READ_ONLY] = True
+            return spec
+        self._content[ENV_BOXES] = [box_spec(box) for box in boxes]
 
-    def add_box(self, name, directory: Path):
+    def add_box(
+            self, name, directory: Path, file_mode=None, group=None, compression=None,
+            name_matching=NAME_MATCHING_STRICT, read_only=False):
+        if compression is not None and compression not in ZIP_COMPRESSIONS:
+            raise ValueError(f'Unknown zip compression {compression}')
+        if name_matching not in NAME_MATCHING_MODES:
+            raise ValueError(f'Unknown name matching {name_matching}')
         boxes = self.get_boxes()
         # check unique box
         for box in boxes:
@@ -63,7 +286,21 @@ class Environment:
                 raise ValueError(
                     f'Box with location {box.location} already exists')
 
-        self.set_boxes(boxes + [Box(name, directory)])
+        self.set_boxes(
+            boxes
+            + [Box(
+                name, directory, file_mode=file_mode, group=group, compression=compression,
+                name_matching=name_matching, read_only=read_only)])
+
+    def set_box_read_only(self, name, read_only: bool):
+        boxes = self.get_boxes()
+        for box in boxes:
+            if box.name == name:
+                box.read_only = read_only
+                break
+        else:
+            raise ValueError(f'Unknown box {name}')
+        self.set_boxes(boxes)
 
     def forget_box(self, name):
         self.set_boxes(
@@ -81,3 +318,186 @@ class Environment:
 
     def is_known_box(self, name):
         return self.get_box(name) is not None
+
+    def get_name_collision_policy(self):
+        return self._content.get(ENV_NAME_COLLISION, NAME_COLLISION_WARN)
+
+    def set_name_collision_policy(self, policy):
+        if policy not in NAME_COLLISION_POLICIES:
+            raise ValueError(f'Unknown name collision policy {policy}')
+        self._content[ENV_NAME_COLLISION] = policy
+
+    def get_box_conflict_policy(self):
+        return self._content.get(ENV_BOX_CONFLICT, CONFLICT_PRIORITY)
+
+    def set_box_conflict_policy(self, policy):
+        if policy not in BOX_CONFLICT_POLICIES:
+            raise ValueError(f'Unknown box conflict policy {policy}')
+        self._content[ENV_BOX_CONFLICT] = policy
+
+    def get_search_timeout(self) -> Optional[float]:
+        return self._content.get(ENV_SEARCH_TIMEOUT)
+
+    def set_search_timeout(self, seconds: Optional[float]):
+        if seconds is None:
+            self._content.pop(ENV_SEARCH_TIMEOUT, None)
+            return
+        if seconds <= 0:
+            raise ValueError(f'Search timeout must be positive, not {seconds}')
+        self._content[ENV_SEARCH_TIMEOUT] = seconds
+
+    def get_union_box(self):
+        '''
+        All boxes, searched with the configured conflict policy and time budget.
+        '''
+        return UnionBox(
+            self.get_boxes(), self.get_box_conflict_policy(), self.get_search_timeout())
+
+    def get_bead_context(
+            self, dry_run=None, notebook_outputs=None, hash_algorithm=None,
+            name_collision_policy=None):
+        '''
+        BeadContext with the boxes and settings of this environment.
+
+        With dry_run (a bead.context.DryRun) it only records what it would do.
+        notebook_outputs, hash_algorithm and name_collision_policy override
+        the settings of the environment.
+        '''
+        return BeadContext(
+            self.get_boxes(), self.get_box_conflict_policy(),
+            compression=self.get_zip_compression(), validators=self.get_validators(),
+            notebook_outputs=notebook_outputs or self.get_notebook_outputs(),
+            hash_algorithm=hash_algorithm or self.get_hash_algorithm(),
+            search_timeout=self.get_search_timeout(), dry_run=dry_run,
+            trusted_hooks=self.get_trusted_hooks(), save_policies=self.get_save_policies(),
+            name_collision_policy=name_collision_policy or self.get_name_collision_policy())
+
+    def get_trusted_hooks(self):
+        return self._content.get(ENV_TRUSTED_HOOKS, {})
+
+    def trust_hooks(self, workspace, fingerprint):
+        '''
+        Allow the hooks of workspace with fingerprint (see bead.hooks.fingerprint) to run.
+        '''
+        trusted_hooks = dict(self.get_trusted_hooks())
+        key = hooks.trust_key(workspace)
+        if fingerprint:
+            trusted_hooks[key] = dict(fingerprint)
+        else:
+            trusted_hooks.pop(key, None)
+        self._content[ENV_TRUSTED_HOOKS] = trusted_hooks
+
+    def get_save_policies(self):
+        return self._content.get(ENV_SAVE_POLICIES, {})
+
+    def set_save_policies(self, policies):
+        self._content[ENV_SAVE_POLICIES] = dict(policies)
+
+    def get_validators(self):
+        return self._content.get(ENV_VALIDATORS, [])
+
+    def set_validators(self, validators):
+        self._content[ENV_VALIDATORS] = list(validators)
+
+    def get_notebook_outputs(self):
+        return self._content.get(ENV_NOTEBOOK_OUTPUTS, notebooks.KEEP)
+
+    def set_notebook_outputs(self, mode):
+        if mode not in NOTEBOOK_OUTPUT_MODES:
+            raise ValueError(f'Unknown notebook output mode {mode}')
+        self._content[ENV_NOTEBOOK_OUTPUTS] = mode
+
+    def get_hash_algorithm(self) -> securehash.HashAlgo:
+        return securehash.HashAlgo(
+            self._content.get(ENV_HASH_ALGORITHM, securehash.DEFAULT_ALGORITHM.value))
+
+    def set_hash_algorithm(self, algorithm):
+        if algorithm not in HASH_ALGORITHMS:
+            raise ValueError(f'Unknown hash algorithm {algorithm}')
+        self._content[ENV_HASH_ALGORITHM] = algorithm
+
+    def get_zip_compression(self):
+        return self._content.get(ENV_ZIP_COMPRESSION, DEFAULT_ZIP_COMPRESSION)
+
+    def set_zip_compression(self, compression):
+        if compression not in ZIP_COMPRESSIONS:
+            raise ValueError(f'Unknown zip compression {compression}')
+        self._content[ENV_ZIP_COMPRESSION] = compression
+
+    def get_prompt_mode(self):
+        return self._content.get(ENV_PROMPTS, PROMPT_ASK)
+
+    def set_prompt_mode(self, mode):
+        if mode not in PROMPT_MODES:
+            raise ValueError(f'Unknown prompt mode {mode}')
+        self._content[ENV_PROMPTS] = mode
+
+    def get_template_directory(self) -> Optional[Path]:
+        template_directory = self._content.get(ENV_TEMPLATE_DIRECTORY)
+        if template_directory is None:
+            return None
+        return Path(template_directory)
+
+    def set_template_directory(self, path):
+        if path is None:
+            self._content.pop(ENV_TEMPLATE_DIRECTORY, None)
+        else:
+            self._content[ENV_TEMPLATE_DIRECTORY] = Path(path).as_posix()
+
+    def get_usage_log(self):
+        usage_log = self._content.get(ENV_USAGE_LOG)
+        if usage_log is None:
+            return None
+        return Path(usage_log)
+
+    def set_usage_log(self, path):
+        if path is None:
+            self._content.pop(ENV_USAGE_LOG, None)
+        else:
+            self._content[ENV_USAGE_LOG] = Path(path).as_posix()
+
+    @property
+    def is_workspace_registry_enabled(self) -> bool:
+        return ENV_WORKSPACES in self._content
+
+    def set_workspace_registry_enabled(self, enabled: bool):
+        if not enabled:
+            self._content.pop(ENV_WORKSPACES, None)
+        elif not self.is_workspace_registry_enabled:
+            self._content[ENV_WORKSPACES] = []
+
+    def get_known_workspaces(self) -> List[KnownWorkspace]:
+        return [
+            KnownWorkspace(
+                spec[WORKSPACE_NAME], Path(spec[WORKSPACE_LOCATION]), spec[WORKSPACE_KIND],
+                spec.get(WORKSPACE_LAST_FREEZE))
+            for spec in self._content.get(ENV_WORKSPACES, ())]
+
+    def set_known_workspaces(self, workspaces):
+        def workspace_spec(workspace):
+            spec = {
+                WORKSPACE_NAME: workspace.name,
+                WORKSPACE_LOCATION: workspace.directory.as_posix(),
+                WORKSPACE_KIND: workspace.kind,
+            }
+            if workspace.last_freeze_time_str is not None:
+                spec[WORKSPACE_LAST_FREEZE] = workspace.last_freeze_time_str
+            return spec
+        self._content[ENV_WORKSPACES] = [workspace_spec(workspace) for workspace in workspaces]
+
+    def register_workspace(self, workspace, freeze_time_str=None):
+        '''
+        Record workspace (a bead.workspace.Workspace), if the registry is enabled.
+
+        The last freeze time is kept, unless a new one is given.
+        '''
+        if not self.is_workspace_registry_enabled:
+            return
+        known = self.get_known_workspaces()
+        previous = [w for w in known if w.directory == workspace.directory]
+        if freeze_time_str is None and previous:
+            freeze_time_str = previous[0].last_freeze_time_str
+        self.set_known_workspaces(
+            [w for w in known if w.directory != workspace.directory]
+            + [KnownWorkspace(
+                workspace.name, workspace.directory, workspace.kind, freeze_time_str)])
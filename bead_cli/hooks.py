@@ -0,0 +1,50 @@
+'''
+Hooks of workspaces - run on save only, when the user trusts them.
+'''
+
+from bead import hooks
+from .cmdparse import Command
+from .common import OPTIONAL_ENV, OPTIONAL_WORKSPACE, assert_valid_workspace, die
+
+
+class CmdTrust(Command):
+    '''
+    Allow the hooks of the workspace to run on save - as they are now.
+
+    Hooks come with the workspace (e.g. in a cloned repository), so they are not run,
+    until the user checks and trusts them. Changed hooks need to be trusted again.
+    '''
+
+    def declare(self, arg):
+        arg(OPTIONAL_WORKSPACE)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        workspace = args.workspace
+        assert_valid_workspace(workspace)
+        fingerprint = hooks.fingerprint(workspace)
+        if not fingerprint:
+            die(f'{workspace.directory} has no hooks')
+        env = args.get_env()
+        env.trust_hooks(workspace, fingerprint)
+        env.save()
+        for hook in fingerprint:
+            print(f'Trusted {hooks.find(workspace, hook)}')
+
+
+class CmdUntrust(Command):
+    '''
+    Stop running the hooks of the workspace on save.
+    '''
+
+    def declare(self, arg):
+        arg(OPTIONAL_WORKSPACE)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        workspace = args.workspace
+        assert_valid_workspace(workspace)
+        env = args.get_env()
+        env.trust_hooks(workspace, {})
+        env.save()
+        print(f'The hooks of {workspace.directory} are not run from now on')
@@ -1,4 +1,5 @@
-from bead.exceptions import InvalidArchive
+from bead import aliases
+from bead.exceptions import InvalidArchive, ContentMismatch, UserError
 import os.path
 
 from .cmdparse import Command
@@ -6,15 +7,17 @@ from .cmdparse import Command
 from . import arg_metavar
 from . import arg_help
 from .common import (
-    OPTIONAL_WORKSPACE, OPTIONAL_ENV,
+    OPTIONAL_WORKSPACE, OPTIONAL_ENV, REPORT_FORMAT, TEXT,
     DefaultArgSentinel, assert_valid_workspace,
     verify_with_feedback,
-    die, warning
+    die, die_with, warning, short_content_ids
 )
 from .common import BEAD_REF_BASE_defaulting_to, BEAD_OFFSET, BEAD_TIME, resolve_bead, TIME_LATEST
-from bead.box import UnionBox
+from .progress import progress_bar
+from .report import Report, emit
+from .usage import count_bytes
+from bead.tech.timestamp import time_from_user
 from bead.meta import BeadName
-import bead.spec as bead_spec
 from bead.workspace import Workspace
 
 # input_nick
@@ -39,6 +42,16 @@ def INPUT_NICK(parser):
         metavar=arg_metavar.INPUT_NICK, help=arg_help.INPUT_NICK)
 
 
+def ONLY_PATTERNS(parser):
+    '''
+    Declare `patterns` as optional, repeatable --only option
+    '''
+    parser.arg(
+        '--only', dest='patterns', action='append', default=None, metavar='PATTERN',
+        help='load only the data files matching the shell PATTERN'
+        + ' - e.g. a subdirectory like tables or tables/*.csv (can be repeated)')
+
+
 # bead_ref
 SAME_BEAD_NEWEST_VERSION = DefaultArgSentinel('same bead, newest version')
 USE_INPUT_NICK = DefaultArgSentinel(f'use {arg_metavar.INPUT_NICK}')
@@ -53,6 +66,7 @@ class CmdAdd(Command):
         arg(INPUT_NICK)
         arg(BEAD_REF_BASE_defaulting_to(USE_INPUT_NICK))
         arg(BEAD_TIME)
+        arg(ONLY_PATTERNS)
         arg(OPTIONAL_WORKSPACE)
         arg(OPTIONAL_ENV)
 
@@ -65,15 +79,57 @@ class CmdAdd(Command):
         if os.path.dirname(input_nick):
             die(f'Invalid input name: {input_nick}')
 
+        alias = None
         if bead_ref_base is USE_INPUT_NICK:
-            bead_ref_base = input_nick
+            alias = workspace.input_alias(input_nick)
+            bead_ref_base = workspace.get_input_bead_name(input_nick)
 
         try:
             bead = resolve_bead(env, bead_ref_base, args.bead_time)
         except LookupError:
             die(f'Not a known bead name: {bead_ref_base}')
+        check_alias_kind(alias, bead)
+
+        _check_load_with_feedback(workspace, args.input_nick, bead, args.patterns)
+
 
-        _check_load_with_feedback(workspace, args.input_nick, bead)
+class CmdList(Command):
+    '''
+    List the inputs of the workspace with their versions.
+    '''
+
+    def declare(self, arg):
+        arg(OPTIONAL_WORKSPACE)
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        workspace = args.workspace
+        assert_valid_workspace(workspace)
+        inputs = workspace.status(args.get_env().get_boxes()).inputs
+        if args.output_format != TEXT:
+            columns = (
+                'name', 'bead_name', 'kind', 'content_id', 'freeze_time_str', 'is_loaded',
+                'loaded_patterns', 'newer_freeze_time_str')
+            emit(
+                Report(
+                    f'Inputs of {workspace.name}', columns,
+                    [input.as_dict() for input in inputs]),
+                args.output_format)
+            return
+        if not inputs:
+            print('No inputs defined')
+        content_ids = short_content_ids(input.content_id for input in inputs)
+        for input in inputs:
+            notes = [] if input.is_loaded else ['NOT LOADED']
+            if input.loaded_patterns is not None:
+                notes.append(f'partially loaded: {" ".join(input.loaded_patterns)}')
+            if input.newer_freeze_time_str:
+                notes.append(f'newer: {input.newer_freeze_time_str}')
+            suffix = f' [{", ".join(notes)}]' if notes else ''
+            print(
+                f'{input.name}: {input.bead_name} # {input.freeze_time_str}'
+                + f' {content_ids[input.content_id]}{suffix}')
 
 
 class CmdMap(Command):
@@ -123,6 +179,24 @@ class CmdDelete(Command):
             die(f'Input {input_nick} does not exist')
 
 
+class CmdRename(Command):
+    '''
+    Rename an input, keeping its data and the bead it is loaded/updated from.
+    '''
+
+    def declare(self, arg):
+        arg(INPUT_NICK)
+        arg('new_input_nick', metavar='NEW-' + arg_metavar.INPUT_NICK, help='new name of input')
+        arg(OPTIONAL_WORKSPACE)
+
+    def run(self, args):
+        workspace = get_workspace(args)
+        if not workspace.has_input(args.input_nick):
+            die(f'Input {args.input_nick} does not exist')
+        workspace.rename_input(args.input_nick, args.new_input_nick)
+        print(f'Input {args.input_nick} is renamed to {args.new_input_nick}.')
+
+
 class CmdUpdate(Command):
     '''
     Update input[s] to newest version or defined bead.
@@ -133,6 +207,9 @@ class CmdUpdate(Command):
         arg(BEAD_REF_BASE_defaulting_to(SAME_BEAD_NEWEST_VERSION))
         arg(BEAD_TIME)
         arg(BEAD_OFFSET)
+        arg('--as-of', dest='as_of', type=time_from_user, default=None, metavar='TIME',
+            help='use the newest version frozen at or before TIME'
+            + ' - e.g. to reproduce the inputs of a result made at TIME')
         arg(OPTIONAL_WORKSPACE)
         arg(OPTIONAL_ENV)
 
@@ -147,16 +224,18 @@ class CmdUpdate(Command):
             die('Too many arguments')
         if args.bead_offset:
             die("--next, --prev can not be specified when updating all inputs")
+        as_of = _as_of(args)
+        offset, time = _newest_offset_and_time(args)
         workspace = get_workspace(args)
         env = args.get_env()
-        unionbox = UnionBox(env.get_boxes())
+        unionbox = env.get_union_box()
         for input in workspace.inputs:
             bead_name = workspace.get_input_bead_name(input.name)
             try:
-                bead = unionbox.get_at(
-                    check_type=bead_spec.BEAD_NAME,
-                    check_param=bead_name,
-                    time=args.bead_time)
+                if as_of is None:
+                    bead = unionbox.resolve_spec(bead_name, offset, time)
+                else:
+                    bead = unionbox.find_as_of(bead_name, as_of)
             except LookupError:
                 if workspace.is_loaded(input.name):
                     print(
@@ -165,6 +244,11 @@ class CmdUpdate(Command):
                 else:
                     warning(f'Could not find bead for "{input.name}" with name "{bead_name}"')
             else:
+                try:
+                    aliases.check_kind(workspace.input_alias(input.name), bead)
+                except aliases.AliasError as e:
+                    warning(f'Skipping update of "{input.name}": {e.message}')
+                    continue
                 _update_input(workspace, input, bead)
         print('All inputs are up to date.')
 
@@ -177,30 +261,32 @@ class CmdUpdate(Command):
         if input is None:
             die(f'Workspace does not have input "{input_nick}"'
                 ' - did you want to add it as a new one?')
+        as_of = _as_of(args)
         if bead_ref_base is SAME_BEAD_NEWEST_VERSION:
             bead_name = workspace.get_input_bead_name(input.name)
 
-            if args.bead_offset and args.bead_time is not TIME_LATEST:
-                die('You can give either --prev/--next or --time, not both')
+            if args.bead_offset:
+                if args.bead_time is not TIME_LATEST or args.bead_newest:
+                    die('You can give only one of --prev/--next, --newest or --time')
+                # --prev/--next: relative to the loaded version
+                offset, time = args.bead_offset, input.freeze_time
+            else:
+                offset, time = _newest_offset_and_time(args)
 
-            boxes = env.get_boxes()
+            unionbox = env.get_union_box()
             try:
-                if args.bead_offset:
-                    # handle --prev --next
-                    context = _get_context(boxes, bead_name, input.freeze_time)
-                    if args.bead_offset == 1:
-                        bead = context.next
-                    else:
-                        bead = context.prev
+                if as_of is None:
+                    bead = unionbox.resolve_spec(bead_name, offset, time)
                 else:
-                    # --time
-                    bead = _get_context(boxes, bead_name, args.bead_time).best
+                    bead = unionbox.find_as_of(bead_name, as_of)
             except LookupError:
                 die(f'Could not find bead for "{input.name}" with name "{bead_name}"')
+            check_alias_kind(workspace.input_alias(input.name), bead)
         else:
             # path or new bead by name - same as input add, develop
-            if args.bead_offset:
-                die('--prev/--next is not supported when an input is replaced with another bead')
+            if args.bead_offset or args.bead_newest or as_of is not None:
+                die('--prev/--next/--newest/--as-of is not supported'
+                    ' when an input is replaced with another bead')
             bead = resolve_bead(env, bead_ref_base, args.bead_time)
         if bead:
             _update_input(workspace, input, bead)
@@ -208,12 +294,35 @@ class CmdUpdate(Command):
             die('Can not find matching bead')
 
 
-def _get_context(boxes, bead_name, time):
-    unionbox = UnionBox(boxes)
-    return unionbox.get_context(
-        check_type=bead_spec.BEAD_NAME,
-        check_param=bead_name,
-        time=time)
+def check_alias_kind(alias, bead):
+    try:
+        aliases.check_kind(alias, bead)
+    except aliases.AliasError as e:
+        die_with(e)
+
+
+def _as_of(args):
+    '''
+    Time of --as-of - None, if not given.
+    '''
+    if args.as_of is None:
+        return None
+    if args.bead_offset or args.bead_newest or args.bead_time is not TIME_LATEST:
+        die('You can give only one of --prev/--next, --newest, --time or --as-of')
+    return args.as_of
+
+
+def _newest_offset_and_time(args):
+    '''
+    (offset, time) for UnionBox.resolve_spec() from --newest and --time
+    '''
+    if not args.bead_newest:
+        return 0, args.bead_time
+    if args.bead_time is not TIME_LATEST:
+        die('You can give either --newest or --time, not both')
+    if args.bead_newest < 1:
+        die('--newest must be at least 1')
+    return -args.bead_newest, TIME_LATEST
 
 
 def _update_input(workspace, input, bead):
@@ -226,7 +335,9 @@ def _update_input(workspace, input, bead):
     else:
         if input.kind != bead.kind:
             warning(f'Updating input "{input.name}" with a bead of different kind')
-        _check_load_with_feedback(workspace, input.name, bead)
+        # partially loaded inputs are updated with the same selection
+        _check_load_with_feedback(
+            workspace, input.name, bead, workspace.loaded_patterns(input.name))
 
 
 class CmdLoad(Command):
@@ -236,6 +347,7 @@ class CmdLoad(Command):
 
     def declare(self, arg):
         arg(OPTIONAL_INPUT_NICK)
+        arg(ONLY_PATTERNS)
         arg(OPTIONAL_WORKSPACE)
         arg(OPTIONAL_ENV)
 
@@ -244,38 +356,62 @@ class CmdLoad(Command):
         workspace = get_workspace(args)
         env = args.get_env()
         if input_nick is ALL_INPUTS:
-            inputs = workspace.inputs
-            if inputs:
-                for input in inputs:
-                    _load(env, workspace, input)
+            if args.patterns is not None:
+                die('--only can be given only when loading a single input')
+            if workspace.inputs:
+                load_all(env, workspace)
             else:
                 warning('No inputs defined to load.')
         else:
             if not workspace.has_input(input_nick):
                 die(f'No input with name {input_nick}')
-            _load(env, workspace, workspace.get_input(input_nick))
+            _load(env, workspace, workspace.get_input(input_nick), args.patterns)
 
 
-def _load(env, workspace, input):
+def load_all(env, workspace):
+    for input in workspace.inputs:
+        # partially loaded inputs are kept as they are
+        _load(env, workspace, input, workspace.loaded_patterns(input.name))
+
+
+def _load(env, workspace, input, patterns=None):
+    '''
+    Load input - all data files or only the ones matching patterns.
+
+    A loaded input is reloaded, if it was loaded with other patterns.
+    '''
     assert input is not None
-    if not workspace.is_loaded(input.name):
+    if not _is_loaded_as(workspace, input.name, patterns):
         name = workspace.get_input_bead_name(input.name)
         content_id = input.content_id
-        bead = None
-        for box in env.get_boxes():
-            bead = box.find_bead(name, content_id)
-            if bead:
-                break
+        bead = env.get_union_box().find_bead(name, content_id)
         if bead is None:
             warning(
                 f'Could not find archive named "{name}" for input "{input.name}" - not loaded!')
             return
-        _check_load_with_feedback(workspace, input.name, bead)
+        try:
+            workspace.validate_input_archive(input.name, bead)
+        except ContentMismatch as e:
+            warning(f'Archive found for "{input.name}" is not the expected bead - not loaded! {e}')
+            return
+        _check_load_with_feedback(workspace, input.name, bead, patterns)
     else:
         print(f'"{input.name}" is already loaded - skipping')
 
 
-def _check_load_with_feedback(workspace: Workspace, input_nick, bead):
+def _is_loaded_as(workspace, input_nick, patterns):
+    if not workspace.is_loaded(input_nick):
+        return False
+    loaded_patterns = workspace.loaded_patterns(input_nick)
+    if patterns is None or loaded_patterns is None:
+        return patterns is None and loaded_patterns is None
+    return set(patterns) == set(loaded_patterns)
+
+
+def _check_load_with_feedback(workspace: Workspace, input_nick, bead, patterns=None):
+    '''
+    Load bead as input_nick - only the data files matching patterns, if given.
+    '''
     try:
         verify_with_feedback(bead)
     except InvalidArchive:
@@ -285,8 +421,18 @@ def _check_load_with_feedback(workspace: Workspace, input_nick, bead):
         if workspace.is_loaded(input_nick):
             print(f'Removing current data from {input_nick}')
             workspace.unload(input_nick)
-        print(f'Loading new data to {input_nick} ...', end='', flush=True)
-        workspace.load(input_nick, bead)
+        if patterns is None:
+            print(f'Loading new data to {input_nick} ...', end='', flush=True)
+        else:
+            print(
+                f'Loading new data matching {" ".join(patterns)} to {input_nick} ...',
+                end='', flush=True)
+        try:
+            workspace.load(input_nick, bead, progress=progress_bar(), patterns=patterns)
+        except UserError as e:
+            print('')
+            die(str(e))
+        count_bytes('loaded', os.path.getsize(bead.archive_filename))
         print(' Done')
 
 
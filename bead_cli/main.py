@@ -1,9 +1,11 @@
 # PYTHON_ARGCOMPLETE_OK
 import os
+import shlex
 import subprocess
 import sys
 import textwrap
 import traceback
+import warnings
 import importlib.metadata
 
 from collections.abc import Sequence
@@ -11,12 +13,25 @@ from collections.abc import Sequence
 import appdirs
 from .cmdparse import Parser, Command
 
-from bead.tech.fs import Path
+from bead.exceptions import AmbiguousBead, BeadError, InternalError, WorkspaceLocked
+from bead.tech.fs import Path, PermissionsNotSupported
 from bead.tech.timestamp import timestamp
-from .common import warning
+from .common import GLOBAL_LOCK_WAIT, GLOBAL_OUTPUT_FORMAT, GLOBAL_PROMPT_MODE, TEXT
+from .common import check_output_format, die_with, warning
+from .common import apply_lock_wait, set_error_format, set_prompt_mode
+from .environment import Environment
 from . import workspace
 from . import input
 from . import box
+from . import complete
+from . import setup_wizard
+from . import self_check
+from . import registry
+from . import usage
+from . import alias
+from . import annotation
+from . import hooks
+from . import template
 from .web import commands as web
 
 
@@ -96,37 +111,107 @@ class CmdVersion(Command):
 
 def make_argument_parser(defaults):
     parser = Parser.new(defaults)
+    parser.arg(GLOBAL_OUTPUT_FORMAT)
+    parser.arg(GLOBAL_PROMPT_MODE)
+    parser.arg(GLOBAL_LOCK_WAIT)
     (parser
         .commands(
             ('new', workspace.CmdNew, 'Create and initialize new workspace directory with a new bead.'),
             ('develop', workspace.CmdDevelop, 'Create workspace from specified bead.'),
             ('save', workspace.CmdSave, 'Save workspace in a box.'),
             ('status', workspace.CmdStatus, 'Show workspace information.'),
+            ('describe', annotation.CmdDescribe, 'Show or set description and author.'),
             ('web', web.CmdWeb, 'Manage/visualize the big picture - connections between beads.'),
+            ('open', workspace.CmdOpen, 'Show workspace output, input or bead in file manager.'),
+            ('search', box.CmdSearch, 'Find beads by a glob or regex pattern of names or kinds.'),
+            ('verify', workspace.CmdVerify, 'Check integrity and signature of a bead.'),
             ('zap', workspace.CmdZap, 'Delete workspace.'),
+            ('unlock', workspace.CmdUnlock, 'Remove a lock left behind by a crashed process.'),
+            ('sample', workspace.CmdSample, 'Extract a sample of the rows of tabular output.'),
+            ('export', workspace.CmdExport, 'Export data of a bead to a directory or tarball.'),
+            ('import', workspace.CmdImport, 'Save a directory of files as a new bead.'),
+            ('ide-info', workspace.CmdIdeInfo, 'Describe workspace for editors/IDEs as JSON.'),
             ('xmeta', box.CmdXmeta, 'eXport eXtended meta attributes to a file next to zip archive.'),
+            ('repair-manifest', box.CmdRepairManifest, 'Rebuild a broken archive manifest.'),
+            ('setup', setup_wizard.CmdSetup, 'Configure default box and archive compression.'),
+            ('prompts', setup_wizard.CmdPrompts, 'Show or set how questions are answered.'),
             ('version', CmdVersion, 'Show program version.'),
+            ('self-check', self_check.CmdSelfCheck, 'Check for a newer release.'),
+            ('usage-log', usage.CmdUsageLog, 'Configure local usage statistics.'),
+            ('workspaces', registry.CmdWorkspaces, 'List known workspaces.'),
+            ('goto', registry.CmdGoto, 'Print the directory of a known workspace.'),
+            ('_complete', complete.CmdComplete, 'Completion candidates for scripts (internal).'),
         ))
 
     (parser
         .group('input', 'Manage data loaded from other beads')
         .commands(
             ('add', input.CmdAdd, 'Define dependency and load its data.'),
+            ('list', input.CmdList, 'Show inputs and their versions.'),
             ('delete', input.CmdDelete, 'Forget all about an input.'),
             ('rm', input.CmdDelete, 'Forget all about an input. (alias for delete)'),
+            ('rename', input.CmdRename, 'Rename an input, keeping its data and bead name.'),
             ('map', input.CmdMap, 'Change the name of the bead from which the input is loaded/updated.'),
             ('update', input.CmdUpdate, 'Update input[s] to newest version or defined bead.'),
             ('load', input.CmdLoad, 'Load data from already defined dependency.'),
             ('unload', input.CmdUnload, 'Unload input data.'),
         ))
 
+    (parser
+        .group('env', 'Move the configuration to another machine')
+        .commands(
+            ('export', setup_wizard.CmdExportEnv, 'Write the configuration to a file.'),
+            ('import', setup_wizard.CmdImportEnv, 'Take over an exported configuration.'),
+        ))
+
+    (parser
+        .group('alias', 'Stable names for the newest bead of a kind')
+        .commands(
+            ('set', alias.CmdSet, 'Define an alias in a box.'),
+            ('list', alias.CmdList, 'Show aliases and the beads they resolve to.'),
+            ('rm', alias.CmdRm, 'Forget an alias.'),
+        ))
+
+    (parser
+        .group('template', 'Skeletons of new workspaces')
+        .commands(
+            ('list', template.CmdList, 'Show the available templates.'),
+            ('directory', template.CmdDirectory, 'Show or set the directory of templates.'),
+        ))
+
+    (parser
+        .group('hooks', 'Hooks run on save - only trusted ones')
+        .commands(
+            ('trust', hooks.CmdTrust, 'Allow the current hooks of the workspace to run.'),
+            ('untrust', hooks.CmdUntrust, 'Stop running the hooks of the workspace.'),
+        ))
+
+    (parser
+        .group('tag', 'Tags of beads, for finding them')
+        .commands(
+            ('add', annotation.CmdAdd, 'Tag the versions saved from now on.'),
+            ('rm', annotation.CmdRm, 'Remove tags of the workspace.'),
+            ('find', annotation.CmdFind, 'Find beads by tag.'),
+        ))
+
     (parser
         .group('box', 'Manage bead boxes')
         .commands(
             ('add', box.CmdAdd, 'Define a box.'),
             ('list', box.CmdList, 'Show known boxes.'),
             ('forget', box.CmdForget, 'Forget a known box.'),
+            ('read-only', box.CmdReadOnly, 'Show or set whether a box is read-only.'),
+            ('conflicts', box.CmdConflicts, 'Show or set which box wins for beads in many boxes.'),
+            ('search-timeout', box.CmdSearchTimeout, 'Show or set how long to wait for boxes.'),
             ('rewire', box.CmdRewire, 'Remap inputs.'),
+            ('delete', box.CmdDelete, 'Delete archives (they remain restorable for a while).'),
+            ('restore', box.CmdRestore, 'Restore deleted archives.'),
+            ('check', box.CmdCheck, 'Validate all archives in a box.'),
+            ('du', box.CmdDu, 'Show disk usage of a box by bead name.'),
+            ('nuke', box.CmdNuke, 'Permanently remove archives.'),
+            ('gc', box.CmdGc, 'Remove orphaned cache files and index entries.'),
+            ('repair', box.CmdRepair, 'Clean up after interrupted saves, rebuild the index.'),
+            ('sync', box.CmdSync, 'Copy archives missing from a box from another box.'),
         ))
 
     parser.autocomplete()
@@ -135,9 +220,45 @@ def make_argument_parser(defaults):
 
 
 def run(config_dir: str, argv: Sequence[str]):
+    # the --yes and --no-input options override it, when parsed
+    set_prompt_mode(Environment.from_dir(config_dir).get_prompt_mode())
+    # --format json switches to JSON errors, when parsed
+    set_error_format(TEXT)
+    setup_wizard.offer_first_run_setup(config_dir, argv)
     parser_defaults = dict(config_dir=Path(config_dir))
     parser = make_argument_parser(parser_defaults)
-    return parser.dispatch(argv)
+    with warnings.catch_warnings():
+        warnings.simplefilter('once', PermissionsNotSupported)
+        warnings.showwarning = _show_warning
+        try:
+            return parser.dispatch(
+                argv, usage.wrap_run_recording_usage(config_dir), check=_check_args)
+        except InternalError:
+            raise
+        except AmbiguousBead as e:
+            die_with(e, suggestions=['bead box conflicts priority'])
+        except WorkspaceLocked as e:
+            wait = ['--wait', str(LOCK_WAIT_SUGGESTED)]
+            die_with(e, suggestions=[shlex.join(['bead', *wait, *argv])])
+        except BeadError as e:
+            # errors not handled by the commands are still expected ones
+            die_with(e)
+
+
+def _check_args(args):
+    check_output_format(args)
+    apply_lock_wait(args)
+
+
+def _show_warning(message, category, filename, lineno, file=None, line=None):
+    warning(str(message))
+
+
+# seconds to wait for locked workspaces, when suggested
+LOCK_WAIT_SUGGESTED = 60
+
+# overrides the user's configuration directory - e.g. for sandboxes in automated tests
+CONFIG_DIR_ENV_VAR = 'BEAD_CONFIG_DIR'
 
 
 FAILURE_TEMPLATE = """\
@@ -156,7 +277,7 @@ Please use the full error report file when creating the issue.
 def main(run=run):
     if git.dirty:
         warning('test build, DO NOT USE for production!!!')
-    config_dir = appdirs.user_config_dir(
+    config_dir = os.environ.get(CONFIG_DIR_ENV_VAR) or appdirs.user_config_dir(
         'bead_cli-6a4d9d98-8e64-4a2a-b6c2-8a753ea61daf')
     try:
         retval = run(config_dir, sys.argv[1:])
@@ -200,7 +321,8 @@ def main(run=run):
             ),
             file=sys.stderr
         )
-        retval = -1
+        # scripts can tell bugs from misuse (UserError.exit_code) or failures
+        retval = InternalError.exit_code
     sys.exit(retval)
 
 
@@ -0,0 +1,97 @@
+'''
+Progress bar on the terminal for long operations (saving, loading, developing beads).
+'''
+
+import shutil
+import sys
+import threading
+import time
+
+from bead.tech.progress import Progress, NO_PROGRESS
+
+BAR_WIDTH = 20
+# seconds between redraws - big operations report many small blocks
+REDRAW_INTERVAL = 0.1
+
+
+def format_bytes(byte_count):
+    size = float(byte_count)
+    for unit in ('B', 'KB', 'MB', 'GB'):
+        if size < 1000:
+            return f'{size:.0f} {unit}' if unit == 'B' else f'{size:.1f} {unit}'
+        size /= 1000
+    return f'{size:.1f} TB'
+
+
+class ProgressBar(Progress):
+    '''
+    Single line progress bar, redrawn in place.
+    '''
+
+    def __init__(self, stream):
+        self.stream = stream
+        self.lock = threading.Lock()
+        self.total_bytes = 0
+        self.total_files = 0
+        self.bytes_done = 0
+        self.files_done = 0
+        self.current_file = ''
+        self.last_draw = 0.0
+
+    def start(self, total_bytes, total_files):
+        with self.lock:
+            self.total_bytes = total_bytes
+            self.total_files = total_files
+            self.bytes_done = 0
+            self.files_done = 0
+            self._draw()
+
+    def file_started(self, path):
+        with self.lock:
+            self.current_file = path
+            self._draw_throttled()
+
+    def advance(self, byte_count):
+        with self.lock:
+            self.bytes_done += byte_count
+            self._draw_throttled()
+
+    def file_done(self, path):
+        with self.lock:
+            self.files_done += 1
+            self._draw_throttled()
+
+    def finish(self):
+        with self.lock:
+            if self.total_files:
+                self.current_file = ''
+                self._draw()
+                self.stream.write('\n')
+                self.stream.flush()
+
+    def _draw_throttled(self):
+        if time.monotonic() - self.last_draw >= REDRAW_INTERVAL:
+            self._draw()
+
+    def _draw(self):
+        if not self.total_files:
+            return
+        self.last_draw = time.monotonic()
+        ratio = self.bytes_done / self.total_bytes if self.total_bytes else 1
+        filled = int(BAR_WIDTH * ratio)
+        line = (
+            f'{ratio:4.0%} [{"#" * filled}{"-" * (BAR_WIDTH - filled)}]'
+            + f' {format_bytes(self.bytes_done)} / {format_bytes(self.total_bytes)}'
+            + f'  {self.files_done}/{self.total_files} files  {self.current_file}')
+        width = shutil.get_terminal_size().columns - 1
+        self.stream.write('\r' + line[:width].ljust(width))
+        self.stream.flush()
+
+
+def progress_bar() -> Progress:
+    '''
+    A progress bar on stderr - when it is a terminal, otherwise no progress is shown.
+    '''
+    if sys.stderr is not None and sys.stderr.isatty():
+        return ProgressBar(sys.stderr)
+    return NO_PROGRESS
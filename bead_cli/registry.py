@@ -0,0 +1,88 @@
+'''
+Opt-in registry of known workspaces - to find them in large project trees.
+
+Workspaces are recorded with their kind and the freeze time of their last save,
+when they are created, developed or saved. `bead goto NAME` prints the directory
+of a known workspace, e.g. for `cd $(bead goto NAME)`.
+'''
+
+from .cmdparse import Command
+from .common import OPTIONAL_ENV, REPORT_FORMAT, TEXT, die
+from .report import Report, emit
+
+
+def register(env, workspace, freeze_time_str=None):
+    '''
+    Record workspace in the registry of env - if it is enabled.
+    '''
+    if env.is_workspace_registry_enabled:
+        env.register_workspace(workspace, freeze_time_str)
+        env.save()
+
+
+class CmdWorkspaces(Command):
+    '''
+    List known workspaces, enable or disable recording them.
+
+    Workspaces are recorded on new, develop and save - once enabled.
+    '''
+
+    def declare(self, arg):
+        arg('--enable', default=False, action='store_true',
+            help='start recording workspaces')
+        arg('--disable', default=False, action='store_true',
+            help='stop recording workspaces and forget the known ones')
+        arg('--prune', default=False, action='store_true',
+            help='forget workspaces, that do not exist anymore')
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        if args.enable and args.disable:
+            die('Either --enable or --disable can be given, not both')
+        if args.enable or args.disable:
+            env.set_workspace_registry_enabled(args.enable)
+            env.save()
+            print(f'Workspaces are {"" if args.enable else "not "}recorded')
+            return
+        if not env.is_workspace_registry_enabled:
+            print('Workspaces are not recorded, see --enable')
+            return
+        workspaces = env.get_known_workspaces()
+        if args.prune:
+            existing = [w for w in workspaces if w.directory.is_dir()]
+            env.set_known_workspaces(existing)
+            env.save()
+            print(f'Forgot {len(workspaces) - len(existing)} workspace(s)')
+            return
+        if not workspaces and args.output_format == TEXT:
+            print('No workspaces are known yet')
+            return
+        emit(
+            Report(
+                'Known workspaces', ('name', 'directory', 'kind', 'last_freeze_time_str'),
+                [w.as_dict() for w in sorted(workspaces, key=lambda w: w.name)]),
+            args.output_format)
+
+
+class CmdGoto(Command):
+    '''
+    Print the directory of a known workspace - e.g. for `cd $(bead goto NAME)`.
+    '''
+
+    def declare(self, arg):
+        arg('name', help='name of the workspace')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        matches = [w for w in env.get_known_workspaces() if w.name == args.name]
+        if not matches:
+            die(
+                f'Unknown workspace: {args.name}', error='UnknownWorkspace',
+                context=dict(name=args.name), suggestions=['bead workspaces'])
+        if len(matches) > 1:
+            directories = '\n'.join(f'  {w.directory}' for w in matches)
+            die(f'More workspaces are named {args.name}:\n{directories}')
+        print(matches[0].directory)
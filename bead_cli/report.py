@@ -0,0 +1,104 @@
+'''
+Tabular reports of commands, rendered by output sinks.
+
+A command builds a Report - a title, columns and rows - and the sink of the output
+format renders it: a table on the terminal, JSON or CSV for scripts,
+or a standalone HTML page for sharing, e.g. with colleagues not using the command line.
+'''
+
+import csv
+import html
+import sys
+from typing import Dict, List, Sequence
+
+import attr
+
+from bead import tech
+from .common import TEXT, JSON, CSV, HTML
+
+
+@attr.s(auto_attribs=True, frozen=True)
+class Report:
+    title: str
+    columns: Sequence[str]
+    # values by column name, a missing value is empty
+    rows: List[Dict[str, object]]
+
+
+def _text(value) -> str:
+    return '' if value is None else str(value)
+
+
+class TableSink:
+    '''
+    Aligned columns for the terminal.
+    '''
+
+    def emit(self, report: Report, file):
+        cells = [list(report.columns)] + [
+            [_text(row.get(column)) for column in report.columns] for row in report.rows]
+        widths = [max(len(line[i]) for line in cells) for i in range(len(report.columns))]
+        for i, line in enumerate(cells):
+            print('  '.join(cell.ljust(width) for cell, width in zip(line, widths)).rstrip(),
+                  file=file)
+            if i == 0:
+                print('  '.join('-' * width for width in widths), file=file)
+
+
+class JsonSink:
+    def emit(self, report: Report, file):
+        print(tech.persistence.dumps(report.rows), file=file)
+
+
+class CsvSink:
+    def emit(self, report: Report, file):
+        writer = csv.DictWriter(
+            file, report.columns, extrasaction='ignore', lineterminator='\n')
+        writer.writeheader()
+        writer.writerows(report.rows)
+
+
+class HtmlSink:
+    '''
+    A standalone HTML page - to be opened in a browser or sent by mail.
+    '''
+
+    def emit(self, report: Report, file):
+        title = html.escape(report.title)
+        header = ''.join(f'<th>{html.escape(column)}</th>' for column in report.columns)
+        rows = '\n'.join(
+            '<tr>' + ''.join(
+                f'<td>{html.escape(_text(row.get(column)))}</td>' for column in report.columns)
+            + '</tr>'
+            for row in report.rows)
+        print(HTML_TEMPLATE.format(title=title, header=header, rows=rows), file=file)
+
+
+HTML_TEMPLATE = '''\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}
+th {{ background: #eee; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>
+<tr>{header}</tr>
+{rows}
+</table>
+</body>
+</html>'''
+
+
+SINKS = {TEXT: TableSink, JSON: JsonSink, CSV: CsvSink, HTML: HtmlSink}
+
+
+def emit(report: Report, output_format, file=None):
+    SINKS[output_format]().emit(report, file or sys.stdout)
@@ -0,0 +1,102 @@
+'''
+Throw-away environment for testing automation built on top of bead.
+
+A sandbox has its own configuration, boxes and working directory - all in a
+temporary directory, which is removed on exit - so pipelines wrapping bead can
+test their logic without touching real storage:
+
+    with Sandbox(boxes=['main']) as sandbox:
+        sandbox.cli('new', 'analysis')
+        ...
+
+External bead processes can be pointed to the sandbox with `sandbox.environ`.
+'''
+
+import contextlib
+import os
+import tempfile
+from typing import Sequence
+
+import bead.zipopener
+from bead.tech.fs import Path, ensure_directory, rmtree
+from .environment import Environment
+from .main import CONFIG_DIR_ENV_VAR, run
+
+
+@contextlib.contextmanager
+def _chdir(directory):
+    cwd = os.getcwd()
+    try:
+        os.chdir(directory)
+        yield
+    finally:
+        os.chdir(cwd)
+
+
+class Sandbox:
+    '''
+    Isolated environment with the given (empty) boxes.
+    '''
+
+    def __init__(self, boxes: Sequence[str] = ('main',), compression=None):
+        self.box_names = tuple(boxes)
+        self.compression = compression
+        self.directory = None
+
+    def __enter__(self):
+        self.directory = Path(tempfile.mkdtemp(prefix='bead-sandbox-'))
+        ensure_directory(self.config_dir)
+        ensure_directory(self.cwd)
+        env = self.environment
+        for name in self.box_names:
+            box_directory = self.directory / 'boxes' / name
+            ensure_directory(box_directory)
+            env.add_box(name, box_directory)
+        if self.compression is not None:
+            env.set_zip_compression(self.compression)
+        env.save()
+        return self
+
+    def __exit__(self, *_exc):
+        # open zip files would prevent removing them on Windows
+        bead.zipopener.close_all()
+        rmtree(self.directory, ignore_errors=True)
+        self.directory = None
+
+    @property
+    def config_dir(self) -> Path:
+        return self.directory / 'config'
+
+    @property
+    def cwd(self) -> Path:
+        '''
+        Default working directory of commands.
+        '''
+        return self.directory / 'work'
+
+    @property
+    def environment(self) -> Environment:
+        return Environment.from_dir(self.config_dir)
+
+    @property
+    def environ(self):
+        '''
+        Environment variables, that make bead processes use the sandbox.
+        '''
+        return dict(os.environ, **{CONFIG_DIR_ENV_VAR: self.config_dir.as_posix()})
+
+    def box(self, name):
+        return self.environment.get_box(name)
+
+    def cli(self, *args, cwd=None) -> int:
+        '''
+        Run a bead command in the sandbox, return its exit code.
+        '''
+        str_args = [(arg if isinstance(arg, str) else Path(arg).as_posix()) for arg in args]
+        with _chdir(cwd or self.cwd):
+            try:
+                return run(self.config_dir.as_posix(), str_args)
+            except SystemExit as e:
+                return e.code if isinstance(e.code, int) else 1
+            finally:
+                bead.zipopener.close_all()
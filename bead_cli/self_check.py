@@ -0,0 +1,117 @@
+'''
+Check for (and optionally install) a newer release of the tool.
+
+Nothing is queried, unless explicitly asked for with `bead self-check`.
+'''
+
+import importlib.metadata
+import json
+import os
+import re
+import subprocess
+import sys
+import urllib.request
+
+from .cmdparse import Command
+from .common import DefaultArgSentinel, die, info
+
+PACKAGE = 'bead'
+PYPI_RELEASE_URL = 'https://pypi.org/pypi/bead/json'
+# labs distributing releases by themselves can point to their own metadata
+RELEASE_URL_ENV_VAR = 'BEAD_RELEASE_URL'
+TIMEOUT_SECONDS = 10
+
+USE_RELEASE_URL = DefaultArgSentinel(
+    f'${RELEASE_URL_ENV_VAR} if defined, otherwise {PYPI_RELEASE_URL}')
+
+
+def fetch_release_metadata(url):
+    with urllib.request.urlopen(url, timeout=TIMEOUT_SECONDS) as response:
+        return json.load(response)
+
+
+def latest_version(release_metadata):
+    '''
+    Version from either PyPI's JSON API format or a plain {"version": ...} document.
+    '''
+    try:
+        return release_metadata['info']['version']
+    except (KeyError, TypeError):
+        return release_metadata['version']
+
+
+def parse_version(version):
+    '''
+    Comparable form of a version string like "0.8.2" or "1.0rc1".
+    '''
+    return tuple(int(part) for part in re.findall(r'\d+', version.split('+')[0]))
+
+
+def installed_version():
+    '''
+    Version of the installed package or None for development checkouts.
+    '''
+    try:
+        return importlib.metadata.version(PACKAGE)
+    except importlib.metadata.PackageNotFoundError:
+        return None
+
+
+def is_pip_installed():
+    '''
+    Can pip upgrade the running installation? (Not for editable installs.)
+    '''
+    try:
+        direct_url = importlib.metadata.distribution(PACKAGE).read_text('direct_url.json')
+    except importlib.metadata.PackageNotFoundError:
+        return False
+    if direct_url:
+        return not json.loads(direct_url).get('dir_info', {}).get('editable', False)
+    return True
+
+
+UPGRADE_COMMAND = [sys.executable, '-m', 'pip', 'install', '--upgrade', PACKAGE]
+
+
+class CmdSelfCheck(Command):
+    '''
+    Check whether a newer release is available.
+
+    Prints upgrade instructions, or with --update upgrades pip installations.
+    '''
+
+    def declare(self, arg):
+        arg('--url', default=USE_RELEASE_URL,
+            help='release metadata URL (default: %(default)s)')
+        arg('--update', default=False, action='store_true',
+            help='upgrade to the latest release, if installed with pip')
+
+    def run(self, args):
+        url = args.url
+        if url is USE_RELEASE_URL:
+            url = os.environ.get(RELEASE_URL_ENV_VAR, PYPI_RELEASE_URL)
+        current = installed_version()
+        if current is None:
+            die('This is a development version, update it with git')
+        try:
+            latest = latest_version(fetch_release_metadata(url))
+        except (OSError, ValueError, KeyError, TypeError) as e:
+            die(f'Could not get release information from {url}: {e}')
+
+        if parse_version(latest) <= parse_version(current):
+            print(f'bead {current} is up to date')
+            return
+
+        print(f'bead {latest} is available (installed: {current})')
+        if not is_pip_installed():
+            print('Upgrade it the same way it was installed.')
+            return
+        if not args.update:
+            print('Upgrade with:')
+            print(f'    {" ".join(UPGRADE_COMMAND)}')
+            print('or run `bead self-check --update`')
+            return
+        info(f'Running {" ".join(UPGRADE_COMMAND)}')
+        if subprocess.call(UPGRADE_COMMAND) != 0:
+            die('Upgrade failed')
+        print(f'Upgraded to bead {latest}')
@@ -0,0 +1,167 @@
+'''
+First run configuration of the environment, how questions are answered,
+and moving the configuration to another machine
+'''
+
+import os
+import sys
+
+from bead.tech import persistence
+from bead.tech.fs import Path, ensure_directory
+from .cmdparse import Command
+from .common import OPTIONAL_ENV, DefaultArgSentinel, die, info, ask, confirm, get_env, warning
+from .common import is_interactive
+from .environment import PROMPT_MODES, ZIP_COMPRESSIONS, DEFAULT_ZIP_COMPRESSION
+
+DEFAULT_BOX_NAME = 'home'
+DEFAULT_BOX_DIRECTORY = '~/BeadBox'
+
+# commands, that make sense without any configuration
+NO_SETUP_NEEDED = {'setup', 'env', 'version', 'self-check', '_complete', '-h', '--help'}
+
+ASK_USER = DefaultArgSentinel('ask interactively')
+
+NO_PROMPT_OPTIONS = {'--yes', '-y', '--no-input'}
+
+
+def setup_environment(env, box_name, box_directory, compression):
+    '''
+    Define the default box - creating its directory - and set the compression.
+    '''
+    box_directory = Path(os.path.expanduser(box_directory))
+    ensure_directory(box_directory)
+    env.add_box(box_name, box_directory.resolve())
+    env.set_zip_compression(compression)
+    env.save()
+
+
+def run_wizard(env, box_name=ASK_USER, box_directory=ASK_USER, compression=ASK_USER):
+    if box_name is ASK_USER:
+        box_name = ask('Name of the default box:', DEFAULT_BOX_NAME)
+    if box_directory is ASK_USER:
+        box_directory = ask('Directory of the default box:', DEFAULT_BOX_DIRECTORY)
+    if compression is ASK_USER:
+        compression = ask(
+            f'Compression of new archives ({", ".join(ZIP_COMPRESSIONS)}):',
+            DEFAULT_ZIP_COMPRESSION)
+    try:
+        setup_environment(env, box_name, box_directory, compression)
+    except (ValueError, OSError) as e:
+        die(f'Setup failed: {e}')
+    info(f'Will store beads in box "{box_name}" at {box_directory}')
+
+
+def offer_first_run_setup(config_dir, argv):
+    '''
+    Offer the setup wizard to interactive users without configuration.
+    '''
+    if not argv or argv[0] in NO_SETUP_NEEDED:
+        return
+    # also not for unattended runs, even if the prompt mode options come later
+    if not is_interactive() or NO_PROMPT_OPTIONS.intersection(argv):
+        return
+    env = get_env(config_dir)()
+    if env.exists:
+        return
+    info('No configuration found, it looks like you are running bead for the first time.')
+    if confirm('Set up a default box now?', default=True):
+        run_wizard(env)
+    else:
+        # do not ask again
+        env.save()
+
+
+class CmdSetup(Command):
+    '''
+    Configure the environment: the default box and the compression of new archives.
+
+    Values not given as options are asked for interactively.
+    '''
+
+    def declare(self, arg):
+        arg('--box-name', default=ASK_USER,
+            help='name of the box to define (default: %(default)s)')
+        arg('--box-directory', default=ASK_USER,
+            help='directory of the box, created if missing (default: %(default)s)')
+        arg('--compression', choices=ZIP_COMPRESSIONS, default=ASK_USER,
+            help='compression of new archives (default: %(default)s)')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        run_wizard(args.get_env(), args.box_name, args.box_directory, args.compression)
+
+
+class CmdPrompts(Command):
+    '''
+    Show or set how questions are answered - also for unattended runs (schedulers, CI).
+
+    ask: ask, when running interactively - use the defaults otherwise
+    yes: answer yes to confirmations, use the defaults for other questions
+    no-input: use the default answers
+
+    The --yes and --no-input options (given before the command) override it for a single run.
+    '''
+
+    def declare(self, arg):
+        arg('mode', nargs='?', choices=PROMPT_MODES, default=None)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        if args.mode is None:
+            print(f'Prompt mode: {env.get_prompt_mode()}')
+            return
+        env.set_prompt_mode(args.mode)
+        env.save()
+        print(f'Prompt mode is set to {args.mode}')
+
+
+class CmdExportEnv(Command):
+    '''
+    Write the configuration (boxes, policies, settings) to a file - for another machine or a lab.
+
+    Machine specific settings (usage log, known workspaces) are not exported.
+    '''
+
+    def declare(self, arg):
+        arg('file', help='file to write, - for the standard output')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        exported = args.get_env().export_settings()
+        if args.file == '-':
+            persistence.dump(exported, sys.stdout)
+            print()
+            return
+        persistence.file_dump(exported, Path(args.file), end='\n')
+        print(f'Configuration is exported to {args.file}')
+
+
+class CmdImportEnv(Command):
+    '''
+    Take over a configuration exported by `bead env export`.
+
+    Boxes are added or redefined by name, the other settings are overwritten.
+    '''
+
+    def declare(self, arg):
+        arg('file', help='exported configuration')
+        arg('--replace', default=False, action='store_true',
+            help='replace the whole configuration, also forgetting boxes not in file')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        try:
+            exported = persistence.file_load(args.file)
+        except (OSError, persistence.ReadError) as e:
+            die(f'Can not read {args.file}: {e}')
+        try:
+            env.import_settings(exported, args.replace)
+        except ValueError as e:
+            die(f'Can not import {args.file}: {e}')
+        env.save()
+        print(f'Configuration is imported from {args.file}')
+        for box in env.get_boxes():
+            if not box.location.is_dir():
+                warning(f'Directory of box "{box.name}" does not exist: {box.location}')
@@ -0,0 +1,62 @@
+'''
+Workspace templates - skeletons copied into new workspaces by `bead new --template`.
+'''
+
+import os
+
+from bead import templates
+from bead.tech.fs import Path
+from .cmdparse import Command
+from .common import OPTIONAL_ENV, REPORT_FORMAT, die
+from .report import Report, emit
+
+
+class CmdList(Command):
+    '''
+    List the templates - of the template directory and the built-in ones.
+    '''
+
+    def declare(self, arg):
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        rows = [
+            dict(
+                name=name,
+                source='built-in' if isinstance(template, str) else os.fspath(template))
+            for name, template
+            in templates.available(args.get_env().get_template_directory()).items()]
+        emit(Report('Templates', ('name', 'source'), rows), args.output_format)
+
+
+class CmdDirectory(Command):
+    '''
+    Show or set the directory of templates - e.g. a directory shared by a lab.
+
+    Every directory in it is a template, named as the directory.
+    '''
+
+    def declare(self, arg):
+        arg('directory', nargs='?', type=Path, default=None)
+        arg('--off', default=False, action='store_true',
+            help='use only the built-in templates')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        if args.off:
+            if args.directory is not None:
+                die('Either a directory or --off can be given, not both')
+            env.set_template_directory(None)
+            env.save()
+        elif args.directory is not None:
+            if not args.directory.is_dir():
+                die(f'{args.directory} is not a directory')
+            env.set_template_directory(os.path.abspath(args.directory))
+            env.save()
+        template_directory = env.get_template_directory()
+        if template_directory is None:
+            print('Template directory: none, only built-in templates are available')
+        else:
+            print(f'Template directory: {template_directory}')
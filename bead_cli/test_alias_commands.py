@@ -0,0 +1,52 @@
+import json
+
+import pytest
+
+
+def test_alias_set_list_rm(robot, bead_a):
+    robot.cli('alias', 'set', 'a-latest', bead_a)
+    assert f'now {bead_a}' in robot.stdout
+
+    robot.cli('alias', 'list', '--format', 'json')
+    [alias] = json.loads(robot.stdout)
+    assert ('a-latest', 'box', bead_a) == (alias['alias'], alias['box'], alias['name'])
+
+    robot.cli('alias', 'rm', 'a-latest')
+    robot.cli('alias', 'list')
+    assert 'No aliases are defined' in robot.stdout
+
+
+def test_alias_can_be_developed(robot, bead_a):
+    robot.cli('alias', 'set', 'a-latest', bead_a)
+
+    robot.cli('develop', 'a-latest', 'developed')
+
+    assert (robot.cwd / 'developed/README').read_text() == bead_a
+
+
+def test_alias_of_unknown_bead_fails(robot, box):
+    with pytest.raises(SystemExit):
+        robot.cli('alias', 'set', 'latest', 'no-such-bead')
+    assert 'Bead not found' in robot.stderr
+
+
+def test_removing_unknown_alias_fails(robot, box):
+    with pytest.raises(SystemExit):
+        robot.cli('alias', 'rm', 'latest')
+    assert 'Unknown alias' in robot.stderr
+
+
+def test_alias_commands_skip_read_only_boxes(robot, bead_a, tmp_path_factory):
+    robot.cli('box', 'add', 'mirror', tmp_path_factory.mktemp('mirror'))
+    robot.cli('box', 'sync', 'box', 'mirror')
+    robot.cli('box', 'read-only', 'box', '--on')
+
+    robot.cli('alias', 'set', 'a-latest', bead_a)
+    assert 'in box mirror' in robot.stdout
+
+    robot.cli('alias', 'rm', 'a-latest')
+    assert 'from box mirror' in robot.stdout
+
+    with pytest.raises(SystemExit):
+        robot.cli('alias', 'set', 'a-latest', bead_a, '--box', 'box')
+    assert 'read-only' in robot.stderr
@@ -0,0 +1,35 @@
+import json
+
+import pytest
+
+
+def test_describe_and_tag_saved_bead(robot, box):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('describe', 'monthly sales', '--author', 'Alice')
+    robot.cli('tag', 'add', 'sales', 'finance')
+    assert 'Tags: finance sales' in robot.stdout
+    robot.cli('save')
+
+    robot.cli('tag', 'find', 'sales', '--format', 'json')
+    [bead] = json.loads(robot.stdout)
+    assert ('bead', 'finance sales') == (bead['name'], bead['tags'])
+
+    robot.cli('tag', 'rm', 'sales')
+    robot.cli('describe')
+    assert 'Description: monthly sales' in robot.stdout
+    assert 'Author: Alice' in robot.stdout
+    assert 'Tags: finance' in robot.stdout
+
+
+def test_invalid_tag_is_refused(robot):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    with pytest.raises(SystemExit):
+        robot.cli('tag', 'add', 'two words')
+    assert 'Invalid tag' in robot.stderr
+
+
+def test_find_unknown_tag(robot, box):
+    robot.cli('tag', 'find', 'nothing')
+    assert 'No beads are tagged nothing' in robot.stdout
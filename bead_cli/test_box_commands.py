@@ -1,8 +1,12 @@
+import json
 import os
 import pytest
 
+from .box import file_mode
 from .test_robot import Robot
 
+from bead import tech
+from bead.exceptions import TransferError
 from bead.tech.timestamp import timestamp as now_ts
 from bead.workspace import Workspace
 
@@ -70,6 +74,34 @@ def test_shared_box_update(alice, bob, bead):
     assert '''Alice's new data''' in datafile2.read_text()
 
 
+def test_shared_group_box_gives_group_access(box, bead):
+    # group ownership is POSIX specific
+    grp = pytest.importorskip('grp')
+    group = grp.getgrgid(os.getgid()).gr_name
+    with Robot() as alice:
+        alice.cli('box', 'add', 'shared', box, '--group', group, '--mode', '0664')
+        assert os.stat(box).st_mode & 0o7777 == 0o2775
+
+        alice.cli('box', 'list')
+        assert f'group {group}, mode 0664' in alice.stdout
+
+        alice.cli('develop', bead)
+        alice.cd('bead')
+        alice.cli('save')
+
+    archives = list(box.glob('*.zip'))
+    assert archives
+    for path in archives + list(box.glob('.bead-index.json')):
+        assert path.stat().st_mode & 0o777 == 0o664
+        assert path.stat().st_gid == os.getgid()
+
+
+@pytest.mark.parametrize('mode', ['rw-rw-r--', '0888', '17777'])
+def test_invalid_file_mode(mode):
+    with pytest.raises(ValueError):
+        file_mode(mode)
+
+
 @pytest.fixture
 def robot():
     with Robot() as robot_instance:
@@ -93,6 +125,36 @@ def test_list_when_there_are_no_boxes(robot):
     assert 'There are no defined boxes' in robot.stdout
 
 
+def test_list_as_json(robot, dir1):
+    robot.cli('box', 'add', 'name1', dir1)
+    robot.cli('--format', 'json', 'box', 'list')
+
+    [box] = json.loads(robot.stdout)
+    assert 'name1' == box['name']
+    assert os.path.samefile(robot.cwd / dir1, box['location'])
+    assert box['compression'] is None
+
+
+def test_list_as_csv(robot, dir1):
+    robot.cli('box', 'add', 'name1', dir1, '--mode', '0664')
+    robot.cli('box', 'list', '--format', 'csv')
+
+    [header, row] = robot.stdout.splitlines()
+    assert 'name,location,compression,file_mode,group,name_matching,read_only' == header
+    assert row.startswith('name1,')
+    assert row.endswith(f',,{0o664},,strict,False')
+
+
+def test_add_with_case_insensitive_name_matching(robot, dir1):
+    robot.cli('box', 'add', 'name1', dir1, '--name-matching', 'ci')
+    robot.cli('box', 'list')
+    assert 'names ignoring case' in robot.stdout
+
+    robot.cli('box', 'list', '--format', 'json')
+    [box] = json.loads(robot.stdout)
+    assert 'ci' == box['name_matching']
+
+
 def test_add_non_existing_directory_fails(robot):
     robot.cli('box', 'add', 'notadded', 'non-existing')
     assert 'ERROR' in robot.stdout
@@ -144,6 +206,32 @@ def test_forget_nonexisting_box(robot):
     assert 'WARNING' in robot.stdout
 
 
+def test_conflicts_policy(robot):
+    robot.cli('box', 'conflicts')
+    assert 'priority' in robot.stdout
+
+    robot.cli('box', 'conflicts', 'error')
+    robot.cli('box', 'conflicts')
+    assert 'error' in robot.stdout
+
+
+def test_search_timeout(robot):
+    robot.cli('box', 'search-timeout')
+    assert 'off' in robot.stdout
+
+    robot.cli('box', 'search-timeout', '2.5')
+    robot.cli('box', 'search-timeout')
+    assert '2.5 seconds' in robot.stdout
+
+    robot.cli('box', 'search-timeout', '--off')
+    assert 'off' in robot.stdout
+
+
+def test_search_timeout_must_be_positive(robot):
+    with pytest.raises(SystemExit):
+        robot.cli('box', 'search-timeout', '0')
+
+
 def test_rewire(robot, dir1):
     # This is a long test, but easy to explain:
     # There are 3 beads a, b, and x stored in a box ('hack-box')
@@ -209,3 +297,171 @@ def test_rewire(robot, dir1):
     assert robot.stderr == ''
     assert 'a' == robot.read_file('input/input-a/README')
     assert 'b' == robot.read_file('input/input-b/README')
+
+
+def test_delete_and_restore(robot, dir1):
+    robot.cli('box', 'add', 'box', dir1)
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('save')
+    robot.cd('..')
+    [archive] = [path.name for path in (robot.cwd / dir1).glob('bead_*.zip')]
+
+    robot.cli('box', 'delete', 'box', archive)
+    assert [] == list((robot.cwd / dir1).glob('bead_*.zip'))
+
+    robot.cli('box', 'restore', 'box')
+    assert archive in robot.stdout
+
+    robot.cli('box', 'restore', 'box', archive)
+    assert (robot.cwd / dir1 / archive).exists()
+
+
+def test_check(robot, dir1):
+    robot.cli('box', 'add', 'box', dir1)
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('save')
+    robot.cd('..')
+
+    robot.cli('box', 'check', 'box')
+    assert 'OK' in robot.stdout
+    robot.cli('box', 'check', 'box')
+    assert 'verified recently' in robot.stdout
+    robot.cli('box', 'check', 'box', '--force')
+    assert 'verified recently' not in robot.stdout
+
+    (robot.cwd / dir1 / 'bead_20000101T000000000000+0000.zip').write_bytes(b'junk')
+    with pytest.raises(SystemExit):
+        robot.cli('box', 'check', 'box')
+    assert 'DAMAGED' in robot.stdout
+    assert '1 damaged archive' in robot.stderr
+
+
+def test_check_as_html(robot, dir1):
+    robot.cli('box', 'add', 'box', dir1)
+    robot.cli('new', 'bead')
+    robot.cli('save', '-w', 'bead')
+    (robot.cwd / dir1 / 'bead_20000101T000000000000+0000.zip').write_bytes(b'junk')
+
+    with pytest.raises(SystemExit):
+        robot.cli('box', 'check', 'box', '--format', 'html')
+    assert robot.stdout.startswith('<!DOCTYPE html>')
+    assert '<td>bead_20000101T000000000000+0000.zip</td><td>DAMAGED</td>' in robot.stdout
+    assert '<td>OK</td>' in robot.stdout
+    assert 'All archives' not in robot.stdout
+
+
+def test_du(robot, dir1):
+    robot.cli('box', 'add', 'box', dir1)
+    robot.cli('new', 'bead')
+    robot.cli('save', '-w', 'bead')
+    robot.cli('save', '-w', 'bead')
+    robot.cli('new', 'other')
+    robot.cli('save', '-w', 'other')
+
+    robot.cli('box', 'du', 'box', '--format', 'json')
+    usage = {row['name']: row for row in json.loads(robot.stdout)}
+    assert {'bead', 'other'} == set(usage)
+    assert 2 == usage['bead']['versions']
+    bead_bytes = sum(
+        path.stat().st_size for path in (robot.cwd / dir1).glob('bead_*.zip'))
+    assert bead_bytes == usage['bead']['bytes']
+
+    robot.cli('box', 'du', 'box')
+    [header, separator, *rows] = robot.stdout.splitlines()
+    assert header.split() == ['name', 'versions', 'bytes', 'newest']
+    assert set(separator) == {'-', ' '}
+    assert [row.split()[0] for row in rows] == ['bead', 'other']
+
+
+def test_nuke_and_gc(robot, dir1):
+    robot.cli('box', 'add', 'box', dir1)
+    robot.cli('new', 'bead')
+    robot.cli('save', '-w', 'bead')
+    robot.cli('new', 'other')
+    robot.cli('save', '-w', 'other')
+    [archive] = [path.name for path in (robot.cwd / dir1).glob('bead_*.zip')]
+    [other] = (robot.cwd / dir1).glob('other_*.zip')
+
+    robot.cli('box', 'nuke', 'box', archive)
+    assert f'Removed {archive}' in robot.stdout
+    assert [] == list((robot.cwd / dir1).glob('bead_*'))
+
+    other.with_suffix('.xmeta').write_text('{}')
+    os.remove(other)
+    robot.cli('box', 'gc', 'box')
+    assert f'Removed {other.with_suffix(".xmeta").name}' in robot.stdout
+    assert f'Removed index entry of {other.name}' in robot.stdout
+
+    robot.cli('box', 'gc', 'box')
+    assert 'clean' in robot.stdout
+
+
+def test_nuke_missing_archive(robot, dir1):
+    robot.cli('box', 'add', 'box', dir1)
+    with pytest.raises(SystemExit):
+        robot.cli('box', 'nuke', 'box', 'bead_20160704T000000000000+0200.zip')
+    assert 'no such archive' in robot.stderr
+
+
+def test_repair(robot, dir1):
+    robot.cli('box', 'add', 'box', dir1)
+    empty = robot.cwd / dir1 / 'bead_20200101T000000000000+0000.zip'
+    empty.write_bytes(b'')
+
+    robot.cli('box', 'repair', 'box', '--dry-run')
+    assert f'Would remove empty archive: {empty.name}' in robot.stdout
+    assert empty.exists()
+
+    robot.cli('box', 'repair', 'box')
+    assert f'Removed empty archive: {empty.name}' in robot.stdout
+    assert not empty.exists()
+
+    robot.cli('box', 'repair', 'box')
+    assert 'needs no repair' in robot.stdout
+
+
+def test_sync(robot, dir1, dir2):
+    robot.cli('box', 'add', 'laptop', dir1)
+    robot.cli('box', 'add', 'server', dir2)
+    robot.cli('new', 'bead')
+    robot.cli('save', 'laptop', '-w', 'bead')
+    robot.cli('new', 'other')
+    robot.cli('save', 'laptop', '-w', 'other')
+
+    robot.cli('box', 'sync', 'laptop', 'server', '--name', 'bead', '--dry-run')
+    assert 'Would copy 1 archive(s)' in robot.stdout
+    assert [] == list((robot.cwd / dir2).glob('*.zip'))
+
+    robot.cli('box', 'sync', 'laptop', 'server')
+    assert 'Copied 2 archive(s) from laptop to server' in robot.stdout
+    assert 2 == len(list((robot.cwd / dir2).glob('*.zip')))
+
+    robot.cli('box', 'sync', 'laptop', 'server')
+    assert 'Copied 0 archive(s)' in robot.stdout
+
+
+def test_sync_damaged_in_transfer(robot, dir1, dir2, monkeypatch):
+    robot.cli('box', 'add', 'laptop', dir1)
+    robot.cli('box', 'add', 'server', dir2)
+    robot.cli('new', 'bead')
+    robot.cli('save', 'laptop', '-w', 'bead')
+    copy_file = tech.transfer.copy_file
+
+    def corrupting_copy(source, target, cancel, hasher):
+        copy_file(source, target, cancel, hasher=hasher)
+        with open(target, 'ab') as f:
+            f.write(b'noise')
+    monkeypatch.setattr(tech.transfer, 'copy_file', corrupting_copy)
+
+    with pytest.raises(SystemExit) as e:
+        robot.cli('box', 'sync', 'laptop', 'server')
+    assert TransferError.exit_code == e.value.code
+    assert 'corrupted' in robot.stderr
+
+
+def test_delete_from_unknown_box(robot):
+    with pytest.raises(SystemExit):
+        robot.cli('box', 'delete', 'unknown-box', 'bead.zip')
+    assert 'ERROR' in robot.stderr
@@ -0,0 +1,53 @@
+import argparse
+import os
+
+from bead.box import Box
+from .complete import bead_name_completer
+
+
+def test_names_come_from_the_index(robot, bead_a, bead_b):
+    robot.cli('_complete', 'names')
+    assert ['bead_a', 'bead_b'] == robot.stdout.splitlines()
+
+
+def test_names_with_prefix(robot, bead_a, bead_with_history):
+    robot.cli('_complete', 'names', 'bead_w')
+    assert ['bead_with_history'] == robot.stdout.splitlines()
+
+
+def test_kinds_are_distinct(robot, bead_with_history):
+    robot.cli('_complete', 'kinds')
+    assert ['KIND:bead_with_history'] == robot.stdout.splitlines()
+
+
+def test_boxes(robot):
+    os.makedirs(robot.cwd / 'other')
+    robot.cli('box', 'add', 'other', robot.cwd / 'other')
+
+    robot.cli('_complete', 'boxes')
+    assert ['box', 'other'] == robot.stdout.splitlines()
+
+
+def test_archives_are_not_opened(robot, box, bead_a):
+    # an archive unknown to the index is not found - and not indexed either
+    os.remove(box.directory / '.bead-index.json')
+
+    robot.cli('_complete', 'names')
+    assert '' == robot.stdout
+    assert [] == Box('box', box.location).indexed_entries()
+
+
+def test_missing_box_directory_is_ignored(robot, bead_a):
+    os.makedirs(robot.cwd / 'gone')
+    robot.cli('box', 'add', 'gone', robot.cwd / 'gone')
+    os.rmdir(robot.cwd / 'gone')
+
+    robot.cli('_complete', 'names')
+    assert ['bead_a'] == robot.stdout.splitlines()
+
+
+def test_bead_name_completer(robot, bead_a, bead_b):
+    with robot.environment as env:
+        parsed_args = argparse.Namespace(get_env=lambda: env)
+        assert ['bead_b'] == bead_name_completer('bead_b', parsed_args)
+    assert [] == bead_name_completer('bead', argparse.Namespace())
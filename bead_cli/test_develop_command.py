@@ -12,6 +12,29 @@ def test_by_name(robot, bead_a):
     assert bead_a in robot.read_file(robot.cwd / bead_a / 'README')
 
 
+def test_by_content_id_prefix(robot, beads, bead_a):
+    robot.cli('develop', beads[bead_a].content_id[:10], 'developed')
+
+    assert bead_a in robot.read_file(robot.cwd / 'developed' / 'README')
+
+
+def test_load_inputs(robot, bead_with_inputs, bead_a):
+    robot.cli('develop', '--load-inputs', bead_with_inputs)
+
+    workspace = Workspace(robot.cwd / bead_with_inputs)
+    assert workspace.is_loaded('input_a')
+    assert workspace.is_loaded('input_b')
+    assert bead_a in robot.read_file(workspace.directory / 'input/input_a/README')
+    assert 'load manually' not in robot.stdout
+
+
+def test_load_inputs_with_markers(robot, bead_with_inputs, bead_a):
+    robot.cli('develop', '--load-inputs', '--input-markers', bead_with_inputs)
+
+    marker = robot.read_file(f'{bead_with_inputs}/input/input_a/.bead-input.json')
+    assert bead_a in marker
+
+
 def test_missing_bead(robot, bead_a):
     robot.cli('box', 'forget', 'box')
     with pytest.raises(SystemExit):
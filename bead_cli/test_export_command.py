@@ -0,0 +1,25 @@
+import tarfile
+
+import pytest
+
+
+def test_export(robot, bead_a):
+    robot.cli('export', bead_a, '--out', 'exported')
+
+    assert bead_a == robot.read_file('exported/README')
+
+
+def test_export_to_tarball_with_code(robot, bead_a):
+    robot.cli('export', bead_a, '--out', 'exported.tar.gz', '--code')
+
+    with tarfile.open(robot.cwd / 'exported.tar.gz') as tar:
+        names = {member.name for member in tar.getmembers() if member.isfile()}
+    assert {'exported/code/README', 'exported/data/README'} == names
+
+
+def test_export_refuses_existing_destination(robot, bead_a):
+    robot.cli('export', bead_a, '--out', 'exported')
+
+    with pytest.raises(SystemExit):
+        robot.cli('export', bead_a, '--out', 'exported')
+    assert 'already exists' in robot.stderr
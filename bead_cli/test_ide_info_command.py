@@ -0,0 +1,32 @@
+import json
+import os
+
+import pytest
+
+
+def test_ide_info(robot, beads, bead_with_inputs, bead_a):
+    robot.cli('develop', bead_with_inputs)
+    robot.cd(bead_with_inputs)
+    robot.cli('input', 'load', 'input_a')
+
+    robot.cli('ide-info')
+    info = json.loads(robot.stdout)
+
+    directory = robot.cwd.resolve()
+    assert bead_with_inputs == info['name']
+    assert beads[bead_with_inputs].kind == info['kind']
+    assert str(directory) == info['directory']
+    assert str(directory / 'output') == info['layout']['output']
+    assert ['input_a', 'input_b'] == [input['name'] for input in info['inputs']]
+    [input_a, input_b] = info['inputs']
+    assert input_a['loaded'] and not input_b['loaded']
+    assert beads[bead_a].content_id == input_a['content_id']
+    assert [str(directory / 'input' / 'input_a')] == info['read_only']
+    assert os.path.isdir(info['read_only'][0])
+    assert str(directory / 'temp') in info['excluded']
+    assert str(directory / 'input') == info['environment']['BEAD_INPUT']
+
+
+def test_ide_info_outside_workspace(robot):
+    with pytest.raises(SystemExit):
+        robot.cli('ide-info')
@@ -0,0 +1,35 @@
+import os
+
+import pytest
+
+
+def test_import_directory_as_new_bead(robot, box):
+    os.makedirs(robot.cwd / 'dataset/2024')
+    robot.write_file('dataset/2024/prices.csv', 'price\n1\n')
+    robot.write_file('dataset/notes.pyc', 'not ignored')
+    robot.cli('import', 'dataset', '--description', 'third party prices')
+    assert 'Imported dataset as bead dataset' in robot.stdout
+
+    robot.cli('new', 'analysis')
+    robot.cd('analysis')
+    robot.cli('input', 'add', 'prices', 'dataset')
+
+    assert 'price\n1\n' == robot.read_file('input/prices/2024/prices.csv')
+    assert 'not ignored' == robot.read_file('input/prices/notes.pyc')
+
+
+def test_imports_are_of_new_kinds(robot, box):
+    os.makedirs(robot.cwd / 'dataset')
+    robot.write_file('dataset/data.csv', 'a\n1\n')
+    robot.cli('import', 'dataset', '--name', 'first')
+    robot.cli('import', 'dataset', '--name', 'second')
+
+    robot.cli('search', '*', '--format', 'csv')
+    kinds = {line.split(',')[3] for line in robot.stdout.splitlines()[1:]}
+    assert 2 == len(kinds)
+
+
+def test_import_of_missing_directory_fails(robot, box):
+    with pytest.raises(SystemExit):
+        robot.cli('import', 'missing')
+    assert 'not a directory' in robot.stderr
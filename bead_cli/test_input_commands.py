@@ -1,5 +1,9 @@
+import csv
+import io
+import json
 import os
 import pytest
+from bead.tech.securehash import digest_of
 from bead.workspace import Workspace
 
 
@@ -86,6 +90,21 @@ def test_deleted_box_does_not_stop_load(robot, bead_with_inputs, tmp_path_factor
     robot.cli('input', 'load')
 
 
+def test_load_without_permission_support_warns(robot, bead_with_inputs, bead_a, check,
+                                                monkeypatch):
+    robot.cli('develop', bead_with_inputs)
+    robot.cd(bead_with_inputs)
+
+    def chmod(path, mode):
+        raise PermissionError(1, 'Operation not permitted', str(path))
+    monkeypatch.setattr(os, 'chmod', chmod)
+    robot.cli('input', 'load', 'input_a')
+
+    check.loaded('input_a', bead_a)
+    assert 'WARNING' in robot.stderr
+    assert 'read-only' in robot.stderr
+
+
 def test_add_with_unrecognized_bead_name_exits_with_error(robot, bead_a):
     robot.cli('develop', bead_a)
     robot.cd(bead_a)
@@ -153,6 +172,41 @@ def test_update_to_previous_version(robot, bead_with_history, check, times):
     check.loaded('input1', times.TS2)
 
 
+def test_update_as_of_time(robot, bead_with_history, check, times):
+    robot.cli('new', 'test-workspace')
+    robot.cd('test-workspace')
+    robot.cli('input', 'add', 'input1', 'bead_with_history', '--time', times.TS1)
+
+    # TS3 is closer to the time, but it was not there yet
+    robot.cli('input', 'update', 'input1', '--as-of', '20150901T151016900000+0200')
+    check.loaded('input1', times.TS2)
+
+    robot.cli('input', 'update', '--as-of', times.TS4)
+    check.loaded('input1', times.TS4)
+
+
+def test_update_to_nth_newest_version(robot, bead_with_history, check, times):
+    robot.cli('new', 'test-workspace')
+    robot.cd('test-workspace')
+    robot.cli('input', 'add', 'input1', 'bead_with_history', '--time', times.TS1)
+
+    robot.cli('input', 'update', 'input1', '--newest', '2')
+    check.loaded('input1', times.TS4)
+
+    robot.cli('input', 'update', '--newest', '5')
+    check.loaded('input1', times.TS1)
+
+
+def test_update_with_both_newest_and_prev_is_error(robot, bead_with_history, times):
+    robot.cli('new', 'test-workspace')
+    robot.cd('test-workspace')
+    robot.cli('input', 'add', 'input1', 'bead_with_history', '--time', times.TS3)
+
+    with pytest.raises(SystemExit):
+        robot.cli('input', 'update', 'input1', '--newest', '2', '--prev')
+    assert 'ERROR' in robot.stderr
+
+
 def test_update_up_to_date_inputs_is_noop(robot, bead_a, bead_b):
     robot.cli('new', 'test-workspace')
     robot.cd('test-workspace')
@@ -234,3 +288,180 @@ def test_delete_nonexisting_input(robot, bead_a):
         robot.cli('input', 'delete', 'nonexisting')
     assert 'ERROR' in robot.stderr
     assert 'does not exist' in robot.stderr
+
+
+def test_add_and_update_input_by_shared_alias(robot, bead_a, bead_b, check):
+    aliases_file = robot.cwd / 'bead-names.toml'
+    aliases_file.write_text(f'population = "{bead_a}"\n')
+    robot.cli('new', 'nextbead')
+    robot.cd('nextbead')
+
+    robot.cli('input', 'add', 'population')
+    check.loaded('population', bead_a)
+
+    # repointing the alias affects updates
+    aliases_file.write_text(f'population = "{bead_b}"\n')
+    robot.cli('input', 'update', 'population')
+    check.loaded('population', bead_b)
+
+
+def test_add_input_by_alias_checks_kind(robot, bead_a):
+    (robot.cwd / 'bead-names.toml').write_text(
+        f'[population]\nbead = "{bead_a}"\nkind = "another kind"\n')
+    robot.cli('new', 'nextbead')
+    robot.cd('nextbead')
+
+    with pytest.raises(SystemExit):
+        robot.cli('input', 'add', 'population')
+    assert 'alias "population"' in robot.stderr
+
+
+def test_update_input_by_alias_checks_kind(robot, bead_a, bead_b, check):
+    aliases_file = robot.cwd / 'bead-names.toml'
+    aliases_file.write_text(f'population = "{bead_a}"\n')
+    robot.cli('new', 'nextbead')
+    robot.cd('nextbead')
+    robot.cli('input', 'add', 'population')
+
+    aliases_file.write_text(f'[population]\nbead = "{bead_b}"\nkind = "another kind"\n')
+    with pytest.raises(SystemExit):
+        robot.cli('input', 'update', 'population')
+    assert 'alias "population"' in robot.stderr
+    robot.cli('input', 'update')
+    assert 'Skipping update of "population"' in robot.stderr
+    check.loaded('population', bead_a)
+
+
+def test_input_set_explicitly_is_not_repointed_by_alias(robot, bead_a, bead_b, check):
+    (robot.cwd / 'bead-names.toml').write_text(f'population = "{bead_a}"\n')
+    robot.cli('new', 'nextbead')
+    robot.cd('nextbead')
+    robot.cli('input', 'add', 'population', bead_b)
+
+    robot.cli('input', 'update', 'population')
+    check.loaded('population', bead_b)
+
+
+def test_list(robot, bead_with_inputs, bead_a):
+    robot.cli('develop', bead_with_inputs)
+    robot.cd(bead_with_inputs)
+    robot.cli('input', 'load', 'input_a')
+
+    robot.cli('input', 'list')
+    lines = robot.stdout.splitlines()
+    assert [line for line in lines if line.startswith(f'input_a: {bead_a} #')]
+    [input_b] = [line for line in lines if line.startswith('input_b:')]
+    assert 'NOT LOADED' in input_b
+
+    robot.cli('input', 'list', '--format', 'json')
+    inputs = {input['name']: input for input in json.loads(robot.stdout)}
+    assert {'input_a', 'input_b'} == set(inputs)
+    assert inputs['input_a']['is_loaded']
+    assert bead_a == inputs['input_a']['bead_name']
+
+    robot.cli('input', 'list', '--format', 'csv')
+    rows = list(csv.DictReader(io.StringIO(robot.stdout)))
+    assert ['input_a', 'input_b'] == sorted(row['name'] for row in rows)
+
+
+def test_list_shows_short_content_ids(robot, beads, bead_with_inputs, bead_a):
+    robot.cli('develop', bead_with_inputs)
+    robot.cd(bead_with_inputs)
+
+    robot.cli('input', 'list')
+
+    content_id = beads[bead_a].content_id
+    [input_a] = [line for line in robot.stdout.splitlines() if line.startswith('input_a:')]
+    assert f' {digest_of(content_id)[:8]} ' in input_a
+    assert content_id not in robot.stdout
+
+
+def test_list_without_inputs(robot):
+    robot.cli('new', 'bead')
+    robot.cli('input', 'list', '-w', 'bead')
+    assert 'No inputs defined' in robot.stdout
+
+
+def test_rename(robot, bead_with_inputs, bead_a):
+    robot.cli('develop', bead_with_inputs)
+    robot.cd(bead_with_inputs)
+    robot.cli('input', 'load', 'input_a')
+
+    robot.cli('input', 'rename', 'input_a', 'renamed_a')
+    assert 'renamed' in robot.stdout
+    robot.cli('input', 'list')
+    assert f'renamed_a: {bead_a} #' in robot.stdout
+    assert bead_a in robot.read_file('input/renamed_a/README')
+
+    with pytest.raises(SystemExit):
+        robot.cli('input', 'rename', 'renamed_a', 'input_b')
+    assert 'already exists' in robot.stderr
+
+
+def test_load_partially(robot):
+    robot.cli('new', 'tables')
+    robot.cd('tables')
+    os.makedirs(robot.cwd / 'output/tables')
+    robot.write_file('output/tables/a.csv', 'a')
+    robot.write_file('output/tables/b.json', 'b')
+    robot.write_file('output/c.csv', 'c')
+    robot.cli('save')
+    robot.cd('..')
+    robot.cli('new', 'consumer')
+    robot.cd('consumer')
+
+    robot.cli('input', 'add', 'tables', '--only', 'tables/*.csv', '--only', 'c.csv')
+    assert ['a.csv'] == os.listdir(robot.cwd / 'input/tables/tables')
+    assert os.path.exists(robot.cwd / 'input/tables/c.csv')
+
+    robot.cli('input', 'list')
+    assert 'partially loaded: tables/*.csv c.csv' in robot.stdout
+    robot.cli('status')
+    assert 'partially loaded (tables/*.csv c.csv)' in robot.stdout
+
+    # the selection is kept on reloading the input
+    robot.cli('input', 'update', 'tables', 'tables')
+    assert ['a.csv'] == os.listdir(robot.cwd / 'input/tables/tables')
+
+    robot.cli('input', 'unload', 'tables')
+    robot.cli('input', 'load', 'tables')
+    assert {'a.csv', 'b.json'} == set(os.listdir(robot.cwd / 'input/tables/tables'))
+    robot.cli('input', 'list', '--format', 'json')
+    [input] = json.loads(robot.stdout)
+    assert input['loaded_patterns'] is None
+
+
+def test_load_reloads_with_other_patterns(robot):
+    robot.cli('new', 'tables')
+    robot.cd('tables')
+    robot.write_file('output/a.csv', 'a')
+    robot.write_file('output/b.json', 'b')
+    robot.cli('save')
+    robot.cd('..')
+    robot.cli('new', 'consumer')
+    robot.cd('consumer')
+    robot.cli('input', 'add', 'tables')
+
+    robot.cli('input', 'load', 'tables', '--only', '*.csv')
+    assert ['a.csv'] == os.listdir(robot.cwd / 'input/tables')
+
+    # loading all inputs keeps partial loads
+    robot.cli('input', 'load')
+    assert ['a.csv'] == os.listdir(robot.cwd / 'input/tables')
+
+    # the same selection is not loaded again
+    robot.cli('input', 'load', 'tables', '--only', '*.csv')
+    assert 'already loaded - skipping' in robot.stdout
+
+    robot.cli('input', 'load', 'tables')
+    assert {'a.csv', 'b.json'} == set(os.listdir(robot.cwd / 'input/tables'))
+
+
+def test_load_partially_without_matching_files_is_error(robot, bead_a):
+    robot.cli('new', 'consumer')
+    robot.cd('consumer')
+
+    with pytest.raises(SystemExit):
+        robot.cli('input', 'add', 'input_a', bead_a, '--only', 'missing')
+    assert 'no data files match missing' in robot.stderr
+    assert not os.path.exists(robot.cwd / 'input/input_a')
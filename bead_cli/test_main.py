@@ -0,0 +1,88 @@
+import json
+
+from bead.exceptions import BoxError, InvalidArchive, WorkspaceLocked
+from bead.workspace import Workspace
+from . import workspace
+from .sandbox import Sandbox
+
+
+def test_unhandled_bead_errors_exit_with_their_code(monkeypatch, capsys):
+    def run(self, args):
+        raise BoxError('box is gone', operation='status', path='/box')
+    monkeypatch.setattr(workspace.CmdStatus, 'run', run)
+
+    with Sandbox() as sandbox:
+        assert BoxError.exit_code == sandbox.cli('status')
+
+    assert 'ERROR: box is gone (operation: status, path: /box)' in capsys.readouterr().err
+
+
+def test_damaged_archives_have_distinct_exit_code(monkeypatch):
+    def run(self, args):
+        raise InvalidArchive(path='a.zip')
+    monkeypatch.setattr(workspace.CmdStatus, 'run', run)
+
+    with Sandbox() as sandbox:
+        assert InvalidArchive.exit_code == sandbox.cli('status')
+
+
+def test_errors_are_json_in_json_mode(monkeypatch, capsys):
+    def run(self, args):
+        raise BoxError('box is gone', operation='status', path='/box')
+    monkeypatch.setattr(workspace.CmdStatus, 'run', run)
+
+    with Sandbox() as sandbox:
+        assert BoxError.exit_code == sandbox.cli('--format', 'json', 'status')
+
+    error = json.loads(capsys.readouterr().err)['error']
+    assert error == dict(
+        code=BoxError.exit_code, error='BoxError', message='box is gone',
+        context=dict(operation='status', path='/box'), suggestions=[])
+
+
+def test_errors_reported_by_commands_are_json(tmp_path, capsys):
+    with Sandbox() as sandbox:
+        assert 0 != sandbox.cli('--format', 'json', 'ide-info', '-w', tmp_path / 'no-workspace')
+
+    error = json.loads(capsys.readouterr().err)['error']
+    assert error['error'] == 'InvalidWorkspace'
+    assert error['context']['path'] == str(tmp_path / 'no-workspace')
+    assert error['suggestions']
+
+
+def test_locked_workspace_suggests_waiting(monkeypatch, capsys):
+    def run(self, args):
+        raise WorkspaceLocked('Workspace ws is being modified by host 42')
+    monkeypatch.setattr(workspace.CmdStatus, 'run', run)
+
+    with Sandbox() as sandbox:
+        assert WorkspaceLocked.exit_code == sandbox.cli('status')
+
+    assert 'Try: bead --wait 60 status' in capsys.readouterr().err
+
+
+def test_wait_sets_the_workspace_lock_timeout(monkeypatch):
+    lock_timeouts = []
+
+    def run(self, args):
+        lock_timeouts.append(args.workspace.lock_timeout)
+    monkeypatch.setattr(workspace.CmdStatus, 'run', run)
+
+    with Sandbox() as sandbox:
+        sandbox.cli('--wait', '5', 'status')
+        sandbox.cli('status')
+        assert 0 == Workspace(sandbox.cwd).lock_timeout
+
+    assert [5, 0] == lock_timeouts
+
+
+def test_unsupported_global_format_is_refused(monkeypatch, capsys):
+    runs = []
+    monkeypatch.setattr(workspace.CmdIdeInfo, 'run', lambda self, args: runs.append(args))
+
+    with Sandbox() as sandbox:
+        assert 0 != sandbox.cli('--format', 'csv', 'ide-info')
+        assert 0 != sandbox.cli('--format', 'json', 'version')
+
+    assert [] == runs
+    assert 'does not support --format csv' in capsys.readouterr().err
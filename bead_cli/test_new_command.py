@@ -1,6 +1,7 @@
 import os
 import pytest
 
+from bead.workspace import Workspace
 from .test_robot import Robot
 
 
@@ -26,3 +27,41 @@ def test_new_fails_if_directory_exists(cli, cwd, robot):
         cli('new', 'workspace')
     assert 'ERROR' in robot.stderr
     assert 'workspace' not in robot.stdout
+
+
+def test_new_vcs_friendly(cli, cwd):
+    cli('new', 'workspace', '--vcs-friendly')
+    assert Workspace(cwd / 'workspace').vcs_friendly
+
+
+def test_new_input_markers(cli, cwd):
+    cli('new', 'workspace', '--input-markers')
+    assert Workspace(cwd / 'workspace').input_markers
+
+
+def test_new_from_builtin_template(cli, cwd):
+    cli('new', 'workspace', '--template', 'make')
+
+    workspace = Workspace(cwd / 'workspace')
+    assert workspace.is_valid
+    assert (cwd / 'workspace/Makefile').is_file()
+    assert (cwd / 'workspace/README.md').read_text().startswith('# workspace\n')
+
+
+def test_new_from_template_directory(cli, cwd, robot):
+    os.makedirs(cwd / 'templates/lab/src')
+    robot.write_file('templates/lab/src/analysis.R', '# analysis')
+    cli('template', 'directory', 'templates')
+    cli('template', 'list')
+    assert 'lab' in robot.stdout
+
+    cli('new', 'workspace', '--template', 'lab')
+
+    assert '# analysis' == robot.read_file('workspace/src/analysis.R')
+
+
+def test_new_from_unknown_template_fails(cli, cwd, robot):
+    with pytest.raises(SystemExit):
+        cli('new', 'workspace', '--template', 'unknown')
+    assert 'Unknown template' in robot.stderr
+    assert not (cwd / 'workspace').exists()
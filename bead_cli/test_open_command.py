@@ -0,0 +1,56 @@
+import pytest
+
+from bead.tech.fs import read_file
+import bead_cli.workspace
+
+
+@pytest.fixture
+def opened(monkeypatch):
+    opened = []
+    monkeypatch.setattr(bead_cli.workspace, 'open_in_file_manager', opened.append)
+    return opened
+
+
+def test_output_is_opened_by_default(robot, opened):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('open')
+
+    assert [robot.cwd / 'output'] == opened
+
+
+def test_loaded_input_is_opened(robot, opened, bead_a):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('input', 'add', 'data', bead_a)
+    robot.cli('open', 'data')
+
+    assert [robot.cwd / 'input' / 'data'] == opened
+
+
+def test_not_loaded_input_is_an_error(robot, opened, bead_a):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('input', 'add', 'data', bead_a)
+    robot.cli('input', 'unload', 'data')
+
+    with pytest.raises(SystemExit):
+        robot.cli('open', 'data')
+    assert 'ERROR' in robot.stderr
+    assert [] == opened
+
+
+def test_bead_is_extracted_for_viewing(robot, opened, bead_a):
+    robot.cli('open', bead_a)
+
+    [directory] = opened
+    assert directory.name == bead_a
+    assert read_file(directory / 'output' / 'README') == bead_a
+    assert (directory / 'README').exists()
+
+
+def test_unknown_bead_is_an_error(robot, opened):
+    with pytest.raises(SystemExit):
+        robot.cli('open', 'no-such-bead')
+    assert 'ERROR' in robot.stderr
+    assert [] == opened
@@ -0,0 +1,41 @@
+import io
+
+import pytest
+
+from . import progress as m
+
+
+@pytest.mark.parametrize(
+    'byte_count, expected', [
+        (0, '0 B'),
+        (999, '999 B'),
+        (1500, '1.5 KB'),
+        (2_700_000_000, '2.7 GB'),
+        (3 * 1000 ** 4, '3.0 TB'),
+    ])
+def test_format_bytes(byte_count, expected):
+    assert expected == m.format_bytes(byte_count)
+
+
+def test_progress_bar():
+    stream = io.StringIO()
+    bar = m.ProgressBar(stream)
+
+    bar.start(total_bytes=2000, total_files=2)
+    bar.file_started('data/file1')
+    bar.advance(1000)
+    bar.file_done('data/file1')
+    bar.finish()
+
+    last_line = stream.getvalue().split('\r')[-1]
+    assert '50%' in last_line
+    assert '1/2 files' in last_line
+    assert last_line.endswith('\n')
+
+
+def test_progress_bar_without_files_stays_silent():
+    stream = io.StringIO()
+    bar = m.ProgressBar(stream)
+    bar.start(0, 0)
+    bar.finish()
+    assert '' == stream.getvalue()
@@ -0,0 +1,29 @@
+import os
+
+import pytest
+
+
+def test_save_to_read_only_box_fails(robot, bead_a):
+    robot.cli('box', 'read-only', 'box', '--on')
+    assert 'read-only' in robot.stdout
+    box_dir = robot.cwd / 'box'
+    archives = os.listdir(box_dir)
+
+    robot.cli('develop', bead_a)
+    robot.cd(bead_a)
+    with pytest.raises(SystemExit):
+        robot.cli('save')
+
+    assert 'read-only' in robot.stderr
+    assert archives == os.listdir(box_dir)
+
+
+def test_read_only_box_can_be_made_writable(robot, bead_a):
+    robot.cli('box', 'read-only', 'box', '--on')
+    robot.cli('box', 'list')
+    assert 'read-only' in robot.stdout
+
+    robot.cli('box', 'read-only', 'box', '--off')
+    assert 'writable' in robot.stdout
+    robot.cli('box', 'list')
+    assert 'read-only' not in robot.stdout
@@ -0,0 +1,31 @@
+import zipfile
+
+from bead import layouts
+from bead.archive import Archive
+from bead.ziparchive import manifest_sidecar_path
+
+
+def test_intact_manifest_is_not_repaired(robot, bead_a, beads):
+    archive_filename = beads[bead_a].archive_filename
+
+    robot.cli('repair-manifest', archive_filename)
+
+    assert 'intact' in robot.stdout
+    assert not manifest_sidecar_path(archive_filename).exists()
+
+
+def test_missing_manifest_is_rebuilt(robot, bead_a, beads):
+    content_id = beads[bead_a].content_id
+    broken = robot.cwd / 'broken.zip'
+    with zipfile.ZipFile(beads[bead_a].archive_filename) as source:
+        with zipfile.ZipFile(broken, 'w') as target:
+            for info in source.infolist():
+                if info.filename != layouts.Archive.MANIFEST:
+                    target.writestr(info, source.read(info))
+
+    robot.cli('repair-manifest', broken)
+
+    assert content_id in robot.stdout
+    robot.cli('verify', broken)
+    assert 'OK' in robot.stdout
+    assert content_id == Archive(broken).content_id
@@ -0,0 +1,40 @@
+import io
+import json
+
+from .report import Report, emit
+
+REPORT = Report(
+    'Beads <&>', ('name', 'size'),
+    [dict(name='a<b>', size=1), dict(name='c,d', size=None, extra='ignored')])
+
+
+def render(output_format):
+    output = io.StringIO()
+    emit(REPORT, output_format, output)
+    return output.getvalue()
+
+
+def test_table():
+    assert render('text').splitlines() == [
+        'name  size',
+        '----  ----',
+        'a<b>  1',
+        'c,d',
+    ]
+
+
+def test_json():
+    assert json.loads(render('json')) == REPORT.rows
+
+
+def test_csv():
+    assert render('csv') == 'name,size\na<b>,1\n"c,d",\n'
+
+
+def test_html_is_escaped():
+    html = render('html')
+    assert '<title>Beads &lt;&amp;&gt;</title>' in html
+    assert '<tr><th>name</th><th>size</th></tr>' in html
+    assert '<tr><td>a&lt;b&gt;</td><td>1</td></tr>' in html
+    assert '<tr><td>c,d</td><td></td></tr>' in html
+    assert 'ignored' not in html
@@ -0,0 +1,24 @@
+import pytest
+
+
+def test_sample(robot, box):
+    robot.cli('new', 'bead')
+    robot.write_file(
+        'bead/output/table.csv', 'a,b\n' + ''.join(f'{i},{i}\n' for i in range(50)))
+    robot.cli('save', '-w', 'bead')
+
+    robot.cli('sample', 'bead', '--n', '5', '--out', 'sample')
+
+    assert 'table.csv: 5 rows' in robot.stdout
+    assert 6 == len(robot.read_file('sample/table.csv').splitlines())
+
+
+def test_sample_refuses_non_empty_directory(robot, box):
+    robot.cli('new', 'bead')
+    robot.write_file('bead/output/table.csv', 'a\n1\n')
+    robot.cli('save', '-w', 'bead')
+    robot.cli('sample', 'bead', '--out', 'sample')
+
+    with pytest.raises(SystemExit):
+        robot.cli('sample', 'bead', '--out', 'sample')
+    assert 'not empty' in robot.stderr
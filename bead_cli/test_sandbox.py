@@ -0,0 +1,47 @@
+import os
+
+import pytest
+
+from bead.box import UnionBox
+from .main import main
+from .sandbox import Sandbox, CONFIG_DIR_ENV_VAR
+
+
+def test_sandbox_save_and_develop():
+    with Sandbox(boxes=['main', 'other']) as sandbox:
+        assert sandbox.cli('new', 'analysis') == 0
+        with open(sandbox.cwd / 'analysis/output/result', 'w') as f:
+            f.write('42')
+        assert sandbox.cli('save', 'main', cwd=sandbox.cwd / 'analysis') == 0
+
+        [bead] = UnionBox(sandbox.environment.get_boxes()).all_beads()
+        assert bead.name == 'analysis'
+        assert bead.box_name == 'main'
+        assert not list(sandbox.box('other').all_beads())
+
+        assert sandbox.cli('develop', 'analysis', 'copy', '-x') == 0
+        assert (sandbox.cwd / 'copy/output/result').read_text() == '42'
+        directory = sandbox.directory
+
+    assert not os.path.exists(directory)
+
+
+def test_sandbox_reports_failure():
+    with Sandbox() as sandbox:
+        assert sandbox.cli('develop', 'no-such-bead') != 0
+
+
+def test_sandbox_environ_is_used_by_main(monkeypatch):
+    config_dirs = []
+
+    def run(config_dir, argv):
+        config_dirs.append(config_dir)
+        return 0
+
+    with Sandbox() as sandbox:
+        monkeypatch.setenv(CONFIG_DIR_ENV_VAR, sandbox.environ[CONFIG_DIR_ENV_VAR])
+        monkeypatch.setattr('sys.argv', ['bead', 'box', 'list'])
+        with pytest.raises(SystemExit):
+            main(run=run)
+
+        assert config_dirs == [sandbox.config_dir.as_posix()]
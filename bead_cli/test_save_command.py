@@ -1,8 +1,12 @@
+import json
 import os
+import sys
+import zipfile
 import pytest
 
-from bead.workspace import Workspace
+from bead.workspace import Workspace, zip_compression_method
 from bead.box import Box
+from .environment import ZIP_COMPRESSIONS
 from .test_robot import Robot
 
 
@@ -121,3 +125,326 @@ def test_save_to_box_without_backing_directory(robot_multi_box, box1, box2):
         robot.cli('save', box2.name, '-w', 'bead')
     assert 'ERROR' in robot.stderr
     assert 'does not exist' in robot.stderr
+
+
+def _save_another_kind_with_same_name(robot):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('save')
+    robot.cd('..')
+    robot.cli('zap', 'bead')
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+
+
+def test_name_collision_warns_by_default(robot, box):
+    _save_another_kind_with_same_name(robot)
+    robot.cli('save')
+    assert 'WARNING' in robot.stderr
+    assert 'different kind' in robot.stderr
+    assert 2 == bead_count(box)
+
+
+def test_name_collision_refused_by_option(robot, box):
+    _save_another_kind_with_same_name(robot)
+    with pytest.raises(SystemExit):
+        robot.cli('save', '--on-name-collision', 'refuse')
+    assert 'ERROR' in robot.stderr
+    assert 1 == bead_count(box)
+
+
+def test_name_collision_refused_by_environment(robot, box):
+    with robot.environment as env:
+        env.set_name_collision_policy('refuse')
+        env.save()
+    _save_another_kind_with_same_name(robot)
+    with pytest.raises(SystemExit):
+        robot.cli('save')
+    assert 'ERROR' in robot.stderr
+    assert 1 == bead_count(box)
+
+
+def test_save_policy_violation_refuses_save(robot, box):
+    with robot.environment as env:
+        env.set_save_policies({'non-empty-output': True, 'required-files': ['README.md']})
+        env.save()
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    with pytest.raises(SystemExit):
+        robot.cli('save')
+    assert 'output is empty' in robot.stderr
+    assert 'README.md is missing' in robot.stderr
+    assert 0 == bead_count(box)
+
+    robot.cli('save', '--override')
+    assert 'WARNING' in robot.stderr
+    assert 1 == bead_count(box)
+
+
+def test_rejecting_validator_aborts_save(robot, box):
+    with robot.environment as env:
+        env.set_validators([[sys.executable, '-c', 'import sys; sys.exit("no README")']])
+        env.save()
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    with pytest.raises(SystemExit):
+        robot.cli('save')
+    assert 'no README' in robot.stderr
+    assert 0 == bead_count(box)
+
+
+@pytest.mark.skipif(os.name != 'posix', reason='hooks are shell scripts here')
+def test_failing_pre_save_hook_aborts_save(robot, box):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    os.makedirs(robot.cwd / '.bead-meta/hooks')
+    robot.write_file('.bead-meta/hooks/pre-save', '#!/bin/sh\nexit 1\n')
+    os.chmod(robot.cwd / '.bead-meta/hooks/pre-save', 0o755)
+    robot.cli('hooks', 'trust')
+
+    with pytest.raises(SystemExit):
+        robot.cli('save')
+    assert 'pre-save hook failed' in robot.stderr
+    assert 0 == bead_count(box)
+
+    robot.cli('save', '--no-hooks')
+    assert 1 == bead_count(box)
+
+
+@pytest.mark.skipif(os.name != 'posix', reason='hooks are shell scripts here')
+def test_hooks_run_only_when_trusted(robot, box):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    os.makedirs(robot.cwd / '.bead-meta/hooks')
+    robot.write_file('.bead-meta/hooks/pre-save', '#!/bin/sh\necho hooked >> output/log\n')
+    os.chmod(robot.cwd / '.bead-meta/hooks/pre-save', 0o755)
+
+    # e.g. a cloned workspace
+    robot.cli('save')
+    assert 'not trusted' in robot.stderr
+    assert not os.path.exists(robot.cwd / 'output/log')
+
+    robot.cli('hooks', 'trust')
+    robot.cli('save')
+    assert 'hooked\n' == robot.read_file('output/log')
+
+    robot.write_file('.bead-meta/hooks/pre-save', '#!/bin/sh\necho changed >> output/log\n')
+    robot.cli('save')
+    assert 'not trusted' in robot.stderr
+    assert 'hooked\n' == robot.read_file('output/log')
+
+    robot.cli('hooks', 'trust')
+    robot.cli('hooks', 'untrust')
+    robot.cli('save')
+    assert 'hooked\n' == robot.read_file('output/log')
+    assert 4 == bead_count(box)
+
+
+def test_same_kind_is_not_a_name_collision(robot, box):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('save', '--on-name-collision', 'refuse')
+    robot.cli('save', '--on-name-collision', 'refuse')
+    assert 'WARNING' not in robot.stderr
+    assert 2 == bead_count(box)
+
+
+def test_clock_skew_is_detected_and_freeze_time_adjusted(robot, box):
+    future = '29991231T235959999999+0000'
+    robot.cli('new', 'bead')
+    with robot.environment:
+        box.store(Workspace('bead'), future)
+    robot.cd('bead')
+
+    robot.cli('save')
+
+    assert 'WARNING' in robot.stderr
+    assert future in robot.stderr
+    freeze_times = sorted(bead.freeze_time_str for bead in box.all_beads())
+    assert [future, '30000101T000000000000+0000'] == freeze_times
+
+
+def test_save_skips_unchanged_output(robot, box):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.write_file('output/data', 'data')
+    robot.cli('save', '--skip-unchanged-output')
+    assert 1 == bead_count(box)
+
+    robot.write_file('output/data', 'data')
+    robot.cli('save', '--skip-unchanged-output')
+    assert 'unchanged since last save' in robot.stdout
+    assert 1 == bead_count(box)
+
+    robot.write_file('code', 'changed code, same output')
+    robot.cli('save', '--skip-unchanged-output')
+    assert 2 == bead_count(box)
+
+    robot.write_file('output/data', 'new data')
+    robot.cli('save', '--skip-unchanged-output')
+    assert 3 == bead_count(box)
+
+
+def compress_types(box):
+    [bead] = box.all_beads()
+    with zipfile.ZipFile(bead.archive_filename) as zf:
+        return {info.compress_type for info in zf.infolist()}
+
+
+def test_box_compression_is_used_on_save(robot_multi_box, tmp_path_factory):
+    robot = robot_multi_box
+    directory = tmp_path_factory.mktemp('stored_box')
+    robot.cli('box', 'add', 'stored', directory, '--compression', 'stored')
+    robot.cli('box', 'list')
+    assert 'stored compression' in robot.stdout
+    robot.cli('new', 'bead')
+    robot.cli('save', '-w', 'bead')
+
+    assert {zipfile.ZIP_STORED} == compress_types(Box('stored', directory))
+
+
+def test_save_compression_option_overrides_box(robot_multi_box, tmp_path_factory):
+    robot = robot_multi_box
+    directory = tmp_path_factory.mktemp('stored_box')
+    robot.cli('box', 'add', 'stored', directory, '--compression', 'stored')
+    robot.cli('new', 'bead')
+    robot.cli('save', '-w', 'bead', '--compression', 'deflated')
+
+    assert {zipfile.ZIP_DEFLATED} == compress_types(Box('stored', directory))
+
+
+@pytest.mark.skipif(hasattr(zipfile, 'ZIP_ZSTANDARD'), reason='zstd is supported')
+def test_zstd_is_not_offered_without_support():
+    assert 'zstd' not in ZIP_COMPRESSIONS
+    # e.g. set on a newer Python
+    assert zipfile.ZIP_DEFLATED == zip_compression_method('zstd')
+
+
+@pytest.mark.skipif(not hasattr(zipfile, 'ZIP_ZSTANDARD'), reason='zstd is not supported')
+def test_save_with_zstd(robot, box):
+    robot.cli('new', 'bead')
+    robot.write_file('bead/output/data', 'data' * 100)
+    robot.cli('save', '-w', 'bead', '--compression', 'zstd')
+
+    assert zipfile.ZIP_ZSTANDARD in compress_types(box)
+    robot.cli('develop', 'bead', 'copy', '-x')
+    assert robot.read_file('copy/output/data') == 'data' * 100
+
+
+NOTEBOOK = '''{"cells": [{"cell_type": "code", "execution_count": 1, "metadata": {},
+ "outputs": [{"output_type": "stream", "name": "stdout", "text": ["secret result"]}],
+ "source": ["print(1)"]}], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}'''
+
+
+def saved_notebook(box):
+    [archive] = box.all_beads()
+    with zipfile.ZipFile(archive.archive_path) as z:
+        return z.read('code/analysis.ipynb').decode('utf-8')
+
+
+def test_notebook_outputs_are_kept_by_default(robot, box):
+    robot.cli('new', 'bead')
+    robot.write_file('bead/analysis.ipynb', NOTEBOOK)
+    robot.cli('save', '-w', 'bead')
+
+    assert 'secret result' in saved_notebook(box)
+
+
+def test_notebook_outputs_stripped_by_environment(robot, box):
+    with robot.environment as env:
+        env.set_notebook_outputs('strip')
+        env.save()
+    robot.cli('new', 'bead')
+    robot.write_file('bead/analysis.ipynb', NOTEBOOK)
+    robot.cli('save', '-w', 'bead')
+
+    assert 'secret result' not in saved_notebook(box)
+    # the workspace is not changed
+    assert 'secret result' in robot.read_file('bead/analysis.ipynb')
+
+
+def test_notebook_outputs_option_overrides_environment(robot, box):
+    with robot.environment as env:
+        env.set_notebook_outputs('strip')
+        env.save()
+    robot.cli('new', 'bead')
+    robot.write_file('bead/analysis.ipynb', NOTEBOOK)
+    robot.cli('save', '-w', 'bead', '--notebook-outputs', 'keep')
+
+    assert 'secret result' in saved_notebook(box)
+
+
+def test_save_with_other_hash_algorithm(robot, box):
+    robot.cli('new', 'bead')
+    robot.write_file('bead/output/data', 'data')
+    robot.cli('save', '-w', 'bead', '--hash', 'blake2b')
+
+    [archive] = box.all_beads()
+    assert archive.content_id.startswith('blake2b:')
+    robot.cli('verify', 'bead')
+    assert 'OK' in robot.stdout
+    robot.cli('develop', 'bead', 'copy', '-x')
+    assert robot.read_file('copy/output/data') == 'data'
+
+
+def test_save_by_pipeline_runner(robot, box):
+    robot.cli('new', 'upstream')
+    robot.cli('save', '-w', 'upstream')
+    robot.cli('new', 'bead')
+    robot.cli('input', 'add', 'raw', 'upstream', '-w', 'bead')
+    robot.cli('save', '-w', 'bead', '--runner', 'nightly@ci', '--plan-hash', 'plan-1')
+
+    [upstream] = [bead for bead in box.all_beads() if bead.name == 'upstream']
+    [archive] = [bead for bead in box.all_beads() if bead.name == 'bead']
+    provenance = archive.provenance
+    assert 'nightly@ci' == provenance.runner
+    assert 'plan-1' == provenance.plan_hash
+    assert {'raw': upstream.content_id} == provenance.upstream
+    assert upstream.provenance is None
+
+
+def test_plan_hash_needs_runner(robot, box):
+    robot.cli('new', 'bead')
+    with pytest.raises(SystemExit):
+        robot.cli('save', '-w', 'bead', '--plan-hash', 'plan-1')
+    assert '--runner' in robot.stderr
+
+
+def test_runner_needs_plan_hash(robot, box):
+    robot.cli('new', 'bead')
+    with pytest.raises(SystemExit):
+        robot.cli('save', '-w', 'bead', '--runner', 'nightly@ci')
+    assert '--plan-hash' in robot.stderr
+    assert [] == list(box.all_beads())
+
+
+def test_automated_freezes_are_marked_in_status(robot, box):
+    robot.cli('new', 'manual')
+    robot.cli('save', '-w', 'manual')
+    robot.cli('new', 'automated')
+    robot.cli('save', '-w', 'automated', '--runner', 'nightly@ci', '--plan-hash', 'plan-1')
+    robot.cli('new', 'bead')
+    robot.cli('input', 'add', 'a', 'automated', '-w', 'bead')
+    robot.cli('input', 'add', 'm', 'manual', '-w', 'bead')
+
+    robot.cli('status', '-w', 'bead')
+
+    lines = robot.stdout.splitlines()
+    [automated_line] = [line for line in lines if 'automated:' in line]
+    assert '[automated: nightly@ci]' in automated_line
+    assert 1 == sum('-r ' in line for line in lines if 'automated:' not in line)
+
+
+def test_automated_freezes_are_shown_in_listings(robot, box):
+    robot.cli('new', 'manual')
+    robot.cli('save', '-w', 'manual')
+    robot.cli('new', 'automated')
+    robot.cli('save', '-w', 'automated', '--runner', 'nightly@ci', '--plan-hash', 'plan-1')
+
+    robot.cli('search', '*', '--format', 'json')
+    runners = {bead['name']: bead['automated_by'] for bead in json.loads(robot.stdout)}
+    assert {'manual': None, 'automated': 'nightly@ci'} == runners
+
+    robot.cli('--format', 'json', 'web', 'color')
+    runners = {bead['name']: bead['runner'] for bead in json.loads(robot.stdout)['beads']}
+    assert {'manual': None, 'automated': 'nightly@ci'} == runners
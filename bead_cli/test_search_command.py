@@ -0,0 +1,27 @@
+import json
+
+import pytest
+
+from bead.tech.securehash import digest_of
+
+
+def test_search(robot, bead_a, bead_b):
+    robot.cli('search', 'bead_*')
+    assert bead_a in robot.stdout
+    assert bead_b in robot.stdout
+
+    robot.cli('search', '--regex', '.*_a', '--format', 'json')
+    assert [bead_a] == [bead['name'] for bead in json.loads(robot.stdout)]
+
+    robot.cli('search', bead_a, '--format', 'json')
+    [found] = json.loads(robot.stdout)
+    robot.cli('search', bead_a)
+    assert digest_of(found['content_id'])[:8] in robot.stdout
+    assert found['content_id'] not in robot.stdout
+
+    robot.cli('search', 'nothing-*')
+    assert 'No beads match' in robot.stdout
+
+    with pytest.raises(SystemExit):
+        robot.cli('search', '--regex', 'bead[')
+    assert 'Invalid regular expression' in robot.stderr
@@ -0,0 +1,71 @@
+import pytest
+
+from . import self_check
+
+
+@pytest.fixture
+def release(monkeypatch):
+    urls = []
+
+    def set_release(latest, installed='0.8.2', pip_installed=True):
+        def fetch(url):
+            urls.append(url)
+            return {'info': {'version': latest}}
+        monkeypatch.setattr(self_check, 'fetch_release_metadata', fetch)
+        monkeypatch.setattr(self_check, 'installed_version', lambda: installed)
+        monkeypatch.setattr(self_check, 'is_pip_installed', lambda: pip_installed)
+    set_release.urls = urls
+    return set_release
+
+
+def test_parse_version():
+    assert self_check.parse_version('0.8.2') < self_check.parse_version('0.10.0')
+    assert self_check.parse_version('1.0+local') == self_check.parse_version('1.0')
+
+
+def test_latest_version_formats():
+    assert '1.2' == self_check.latest_version({'info': {'version': '1.2'}})
+    assert '1.2' == self_check.latest_version({'version': '1.2'})
+
+
+def test_up_to_date(robot, release):
+    release('0.8.2')
+    robot.cli('self-check')
+    assert 'up to date' in robot.stdout
+    assert [self_check.PYPI_RELEASE_URL] == release.urls
+
+
+def test_newer_release_prints_instructions(robot, release):
+    release('0.9.0')
+    robot.cli('self-check', '--url', 'https://example.com/bead.json')
+    assert '0.9.0 is available' in robot.stdout
+    assert 'pip install --upgrade bead' in robot.stdout
+    assert ['https://example.com/bead.json'] == release.urls
+
+
+def test_update(robot, release, monkeypatch):
+    release('0.9.0')
+    calls = []
+    monkeypatch.setattr(self_check.subprocess, 'call', lambda cmd: calls.append(cmd) or 0)
+    robot.cli('self-check', '--update')
+    assert [self_check.UPGRADE_COMMAND] == calls
+    assert 'Upgraded to bead 0.9.0' in robot.stdout
+
+
+def test_update_is_not_attempted_for_non_pip_installs(robot, release, monkeypatch):
+    release('0.9.0', pip_installed=False)
+    calls = []
+    monkeypatch.setattr(self_check.subprocess, 'call', lambda cmd: calls.append(cmd) or 0)
+    robot.cli('self-check', '--update')
+    assert [] == calls
+    assert 'same way it was installed' in robot.stdout
+
+
+def test_unreachable_endpoint_is_an_error(robot, monkeypatch):
+    def fetch(url):
+        raise OSError('network is down')
+    monkeypatch.setattr(self_check, 'fetch_release_metadata', fetch)
+    monkeypatch.setattr(self_check, 'installed_version', lambda: '0.8.2')
+    with pytest.raises(SystemExit):
+        robot.cli('self-check')
+    assert 'network is down' in robot.stderr
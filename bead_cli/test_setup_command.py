@@ -0,0 +1,170 @@
+import io
+import json
+import zipfile
+
+import pytest
+
+from bead.archive import Archive
+from .common import ask, confirm
+from .test_robot import Robot
+
+
+@pytest.fixture
+def robot_no_box():
+    with Robot() as robot_instance:
+        yield robot_instance
+
+
+class InteractiveStdin(io.StringIO):
+    def isatty(self):
+        return True
+
+
+def test_setup_uses_defaults_when_not_interactive(robot_no_box):
+    robot = robot_no_box
+    robot.cli('setup')
+
+    with robot.environment as env:
+        box = env.get_box('home')
+        assert env.get_zip_compression() == 'deflated'
+    assert box.location == (robot.home / 'BeadBox').resolve()
+    assert box.location.is_dir()
+
+
+def test_setup_compression_is_used_on_save(robot_no_box, tmp_path):
+    robot = robot_no_box
+    robot.cli('setup', '--box-name', 'main', '--box-directory', tmp_path / 'box',
+              '--compression', 'stored')
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.write_file('output/data', 'data' * 100)
+    robot.cli('save')
+
+    with robot.environment as env:
+        [bead] = env.get_box('main').all_beads()
+    assert isinstance(bead, Archive)
+    with zipfile.ZipFile(bead.archive_filename) as zf:
+        assert {zipfile.ZIP_STORED} == {info.compress_type for info in zf.infolist()}
+
+
+def test_first_run_setup_is_not_offered_when_not_interactive(robot_no_box):
+    robot = robot_no_box
+    robot.cli('box', 'list')
+
+    assert 'There are no defined boxes' in robot.stdout
+    with robot.environment as env:
+        assert not env.exists
+
+
+def test_first_run_setup_is_offered_interactively(robot_no_box, monkeypatch):
+    robot = robot_no_box
+    monkeypatch.setattr('sys.stdin', InteractiveStdin())
+    monkeypatch.setattr('builtins.input', lambda prompt: '')
+    robot.cli('box', 'list')
+
+    assert 'home:' in robot.stdout
+    with robot.environment as env:
+        assert env.exists
+
+
+def test_completion_does_not_offer_setup(robot_no_box, monkeypatch):
+    robot = robot_no_box
+    monkeypatch.setattr('sys.stdin', InteractiveStdin())
+    monkeypatch.setattr('builtins.input', no_input)
+    robot.cli('_complete', 'names')
+
+    with robot.environment as env:
+        assert not env.exists
+
+
+def test_first_run_setup_can_be_declined(robot_no_box, monkeypatch):
+    robot = robot_no_box
+    monkeypatch.setattr('sys.stdin', InteractiveStdin())
+    monkeypatch.setattr('builtins.input', lambda prompt: 'n')
+    robot.cli('box', 'list')
+
+    assert 'There are no defined boxes' in robot.stdout
+    with robot.environment as env:
+        assert env.exists
+        assert [] == env.get_boxes()
+
+
+def no_input(prompt):
+    raise AssertionError(f'Unexpected question: {prompt}')
+
+
+@pytest.mark.parametrize('option', ['--yes', '--no-input'])
+def test_prompt_options_prevent_questions(robot_no_box, monkeypatch, option):
+    robot = robot_no_box
+    monkeypatch.setattr('sys.stdin', InteractiveStdin())
+    monkeypatch.setattr('builtins.input', no_input)
+    robot.cli(option, 'setup')
+
+    with robot.environment as env:
+        assert env.get_box('home') is not None
+
+
+def test_prompt_mode_is_configurable(robot_no_box, monkeypatch):
+    robot = robot_no_box
+    robot.cli('prompts')
+    assert 'ask' in robot.stdout
+    robot.cli('prompts', 'no-input')
+
+    monkeypatch.setattr('sys.stdin', InteractiveStdin())
+    monkeypatch.setattr('builtins.input', no_input)
+    robot.cli('setup')
+    robot.cli('prompts')
+    assert 'no-input' in robot.stdout
+
+
+def test_yes_answers_confirmations(robot_no_box, monkeypatch):
+    robot = robot_no_box
+    monkeypatch.setattr('sys.stdin', InteractiveStdin())
+    monkeypatch.setattr('builtins.input', no_input)
+    robot.cli('--yes', 'prompts')
+    assert confirm('Really?', default=False)
+
+    robot.cli('--no-input', 'prompts')
+    assert not confirm('Really?', default=False)
+    assert ask('Name?', 'default') == 'default'
+
+
+def test_env_export_and_import(robot_no_box, tmp_path):
+    robot = robot_no_box
+    robot.cli('box', 'add', 'lab', tmp_path)
+    robot.cli('prompts', 'no-input')
+    robot.cli('workspaces', '--enable')
+    robot.cli('env', 'export', tmp_path / 'lab.json')
+
+    with Robot() as other:
+        other.cli('box', 'add', 'own', other.cwd)
+        other.cli('env', 'import', tmp_path / 'lab.json')
+
+        with other.environment as env:
+            assert ['own', 'lab'] == [box.name for box in env.get_boxes()]
+            assert 'no-input' == env.get_prompt_mode()
+            assert not env.is_workspace_registry_enabled
+
+        other.cli('env', 'import', '--replace', tmp_path / 'lab.json')
+        with other.environment as env:
+            assert ['lab'] == [box.name for box in env.get_boxes()]
+
+
+def test_env_import_refuses_invalid_settings(robot_no_box, tmp_path):
+    robot = robot_no_box
+    robot.cli('prompts', 'yes')
+    robot.cli('env', 'export', tmp_path / 'env.json')
+    exported = json.loads((tmp_path / 'env.json').read_text())
+    exported['settings']['prompts'] = 'maybe'
+    (tmp_path / 'env.json').write_text(json.dumps(exported))
+
+    with pytest.raises(SystemExit):
+        robot.cli('env', 'import', tmp_path / 'env.json')
+    assert 'Unknown prompt mode maybe' in robot.stderr
+    with robot.environment as env:
+        assert 'yes' == env.get_prompt_mode()
+
+    (tmp_path / 'env.json').write_text('{}')
+    with pytest.raises(SystemExit):
+        robot.cli('env', 'import', tmp_path / 'env.json')
+    assert 'Not an exported bead environment' in robot.stderr
@@ -1,3 +1,8 @@
+import json
+
+import pytest
+
+from bead.tech.securehash import digest_of
 
 
 def test_status(robot, beads, bead_with_inputs, bead_a):
@@ -30,7 +35,7 @@ def test_verbose(robot, beads, bead_with_inputs, bead_a):
     assert bead_with_inputs.kind in robot.stdout
     assert bead_a.kind in robot.stdout
     assert bead_a.freeze_time_str in robot.stdout
-    assert bead_a.content_id in robot.stdout
+    assert digest_of(bead_a.content_id)[:8] in robot.stdout
 
 
 def test_inputs_not_in_known_boxes(robot, beads, bead_with_inputs, bead_a):
@@ -62,9 +67,120 @@ def test_verbose_inputs_not_in_known_boxes(robot, beads, bead_with_inputs, bead_
     assert bead_with_inputs.kind in robot.stdout
     assert bead_a.kind in robot.stdout
     assert bead_a.freeze_time_str in robot.stdout
-    assert bead_a.content_id in robot.stdout
+    assert f'Content id:  {digest_of(bead_a.content_id)[:8]}\n' in robot.stdout
 
 
 def test_invalid_workspace(robot):
     robot.cli('status')
     assert 'WARNING' in robot.stderr
+
+
+def test_json(robot, beads, bead_with_inputs, bead_a, times):
+    robot.cli('develop', bead_with_inputs)
+    robot.cd(bead_with_inputs)
+    robot.cli('input', 'load', 'input_a')
+    robot.cli('status', '--json')
+
+    status = json.loads(robot.stdout)
+    assert bead_with_inputs == status['name']
+    assert times.TS1 == status['last_saved_freeze_time_str']
+    inputs = {input['name']: input for input in status['inputs']}
+    assert {'input_a', 'input_b'} == set(inputs)
+    assert inputs['input_a']['is_loaded']
+    assert not inputs['input_b']['is_loaded']
+    assert beads[bead_a].content_id == inputs['input_a']['content_id']
+    assert inputs['input_a']['newer_freeze_time_str'] is None
+
+
+def test_global_json_format(robot, bead_with_inputs):
+    robot.cli('develop', bead_with_inputs)
+    robot.cd(bead_with_inputs)
+
+    robot.cli('--format', 'json', 'status')
+    assert bead_with_inputs == json.loads(robot.stdout)['name']
+
+    robot.cli('status', '--format', 'json')
+    assert bead_with_inputs == json.loads(robot.stdout)['name']
+
+    robot.cli('--format', 'json', 'status', '--format', 'text')
+    assert f'Bead Name: {bead_with_inputs}' in robot.stdout
+
+
+def test_recursive(robot, beads, bead_with_inputs, bead_a):
+    robot.cli('develop', bead_with_inputs, 'project/analysis')
+    robot.cli('new', 'project/cleaning')
+    robot.cli('status', '--recursive', '-w', 'project', '--format', 'json')
+
+    workspaces = {row['name']: row for row in json.loads(robot.stdout)}
+    assert {'analysis', 'cleaning'} == set(workspaces)
+    assert 2 == workspaces['analysis']['inputs']
+    assert workspaces['cleaning']['last_saved'] is None
+
+    robot.cli('status', '--recursive', '-w', 'project', '--format', 'html')
+    assert '<td>cleaning</td>' in robot.stdout
+
+
+def test_report_formats_need_recursive(robot, bead_with_inputs):
+    robot.cli('develop', bead_with_inputs)
+    robot.cd(bead_with_inputs)
+
+    with pytest.raises(SystemExit):
+        robot.cli('status', '--format', 'csv')
+    assert 'only with --recursive' in robot.stderr
+
+
+def test_newer_input_version_is_shown(robot, bead_with_history, times):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('input', 'add', 'history', bead_with_history, '--time', times.TS1)
+    robot.cli('status')
+
+    assert f'Newer:       {bead_with_history} # {times.TS5}' in robot.stdout
+
+
+def test_output_changes_since_last_save(robot, bead_a):
+    robot.cli('develop', '-x', bead_a)
+    robot.cd(bead_a)
+    robot.cli('status')
+    assert 'Output unchanged since last save' in robot.stdout
+
+    robot.write_file('output/new-file', 'new content')
+    robot.cli('status')
+    assert 'Output changed since last save' in robot.stdout
+
+
+def test_output_rewritten_with_same_content_is_unchanged(robot, bead_a):
+    robot.cli('develop', '-x', bead_a)
+    robot.cd(bead_a)
+    for path in (robot.cwd / 'output').iterdir():
+        content = path.read_bytes()
+        path.unlink()
+        path.write_bytes(content)
+    robot.cli('status')
+    assert 'Output unchanged since last save' in robot.stdout
+
+
+def test_output_not_extracted_is_a_change(robot, bead_a):
+    robot.cli('develop', bead_a)
+    robot.cd(bead_a)
+    robot.cli('status')
+    assert 'Output changed since last save' in robot.stdout
+
+
+def test_code_changes_since_developed_version(robot, bead_a):
+    robot.cli('develop', bead_a)
+    robot.cd(bead_a)
+    robot.cli('status')
+    assert 'Code unchanged since the developed version' in robot.stdout
+
+    robot.write_file('new-script.py', 'print(42)')
+    robot.cli('status')
+    assert 'Code changed since the developed version' in robot.stdout
+    assert 'added: new-script.py' in robot.stdout
+
+
+def test_never_saved(robot):
+    robot.cli('new', 'bead')
+    robot.cd('bead')
+    robot.cli('status')
+    assert 'Last saved: never' in robot.stdout
@@ -0,0 +1,47 @@
+import os
+import socket
+import subprocess
+import sys
+
+import pytest
+
+
+def write_lock(path, pid):
+    with open(path, 'w') as f:
+        f.write(f'{socket.gethostname()} {pid} 20200101T000000000000+0000\n')
+
+
+@pytest.mark.skipif(sys.platform == 'win32', reason='process ids are not checked on Windows')
+def test_stale_workspace_lock_is_removed(robot):
+    robot.cli('new', 'bead')
+    process = subprocess.Popen([sys.executable, '-c', 'pass'])
+    process.wait()
+    write_lock(robot.cwd / 'bead/.bead-meta/lock', process.pid)
+
+    robot.cli('unlock', '-w', 'bead')
+
+    assert 'Removed stale lock' in robot.stdout
+    assert not os.path.exists(robot.cwd / 'bead/.bead-meta/lock')
+
+
+def test_lock_of_running_process_needs_force(robot):
+    robot.cli('new', 'bead')
+    write_lock(robot.cwd / 'bead/.bead-meta/lock', os.getpid())
+
+    with pytest.raises(SystemExit):
+        robot.cli('unlock', '-w', 'bead')
+    assert 'might still be running' in robot.stderr
+    assert os.path.exists(robot.cwd / 'bead/.bead-meta/lock')
+
+    robot.cli('unlock', '-w', 'bead', '--force')
+    assert not os.path.exists(robot.cwd / 'bead/.bead-meta/lock')
+
+
+def test_unlock_box(robot, box):
+    write_lock(box.directory / '.bead-box.lock', os.getpid())
+
+    robot.cli('unlock', '--box', box.name, '--force')
+    assert not os.path.exists(box.directory / '.bead-box.lock')
+
+    robot.cli('unlock', '--box', box.name)
+    assert 'Not locked' in robot.stdout
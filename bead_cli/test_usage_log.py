@@ -0,0 +1,52 @@
+import json
+
+import pytest
+
+
+def read_records(path):
+    with open(path) as f:
+        return [json.loads(line) for line in f]
+
+
+def test_usage_is_not_recorded_by_default(robot):
+    robot.cli('usage-log')
+    assert 'not recorded' in robot.stdout
+
+
+def test_commands_are_recorded_without_arguments(robot, box, tmp_path):
+    log = tmp_path / 'usage.log'
+    robot.cli('usage-log', log)
+    robot.cli('new', 'secret-name')
+    robot.cd('secret-name')
+    robot.write_file('output/data', 'secret content')
+    robot.cli('save')
+    robot.cli('input', 'add', 'secret-input', 'secret-name')
+
+    records = read_records(log)
+    assert ['new', 'save', 'input add'] == [record['command'] for record in records]
+    assert records[1]['bytes']['saved'] > 0
+    assert records[2]['bytes']['loaded'] == records[1]['bytes']['saved']
+    assert all(record['exit'] == 0 for record in records)
+    assert all(record['seconds'] >= 0 for record in records)
+    assert records[2]['zip_directories']['parsed'] + records[2]['zip_directories']['cached'] > 0
+    assert 'secret' not in log.read_text()
+
+
+def test_failures_are_recorded(robot, tmp_path):
+    log = tmp_path / 'usage.log'
+    robot.cli('usage-log', log)
+    with pytest.raises(SystemExit):
+        robot.cli('save')
+
+    [record] = read_records(log)
+    assert 'save' == record['command']
+    assert 1 == record['exit']
+
+
+def test_usage_log_can_be_disabled(robot, tmp_path):
+    log = tmp_path / 'usage.log'
+    robot.cli('usage-log', log)
+    robot.cli('usage-log', '--disable')
+    robot.cli('box', 'list')
+
+    assert ['usage-log'] == [record['command'] for record in read_records(log)]
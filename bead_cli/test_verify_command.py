@@ -0,0 +1,107 @@
+import json
+import os
+import shutil
+import subprocess
+
+import pytest
+
+from bead import signing
+
+needs_openssh = pytest.mark.skipif(
+    shutil.which(signing.SSH_KEYGEN) is None, reason='OpenSSH is not available')
+
+
+@pytest.fixture
+def key(tmp_path):
+    key = tmp_path / 'key'
+    subprocess.run(
+        [signing.SSH_KEYGEN, '-t', 'ed25519', '-N', '', '-q', '-f', key], check=True)
+    return key
+
+
+def test_verify_unsigned_bead(robot, bead_a):
+    robot.cli('verify', bead_a)
+    assert 'OK' in robot.stdout
+
+
+def test_verify_report(robot, beads, bead_a):
+    robot.cli('verify', bead_a, '--format', 'json')
+
+    [row] = json.loads(robot.stdout)
+    assert str(beads[bead_a].archive_filename) == row['archive']
+    assert 'OK' == row['content']
+    assert 'unsigned' == row['signature']
+    assert 'Verifying' in robot.stderr
+
+
+def test_verify_resume_skips_verified_files(robot, beads, bead_a):
+    archive = beads[bead_a]
+    with robot.environment as env:
+        checkpoint = env.verify_checkpoint(archive.archive_path)
+    checkpoint.add('code/README')
+    checkpoint.close()
+
+    robot.cli('verify', bead_a, '--resume')
+    assert '1 files are already verified' in robot.stderr
+    assert 'OK' in robot.stdout
+    # complete verification leaves no checkpoint behind
+    assert not checkpoint.path.exists()
+
+
+def test_verify_checkpoint_of_modified_archive_is_ignored(robot, beads, bead_a):
+    archive = beads[bead_a]
+    with robot.environment as env:
+        checkpoint = env.verify_checkpoint(archive.archive_path)
+    checkpoint.add('code/README')
+    checkpoint.close()
+    stat = os.stat(archive.archive_path)
+    os.utime(archive.archive_path, ns=(stat.st_atime_ns, stat.st_mtime_ns + 1))
+
+    robot.cli('verify', bead_a, '--resume')
+    assert 'already verified' not in robot.stderr
+    assert 'OK' in robot.stdout
+
+
+def test_verify_without_resume_starts_over(robot, beads, bead_a):
+    archive = beads[bead_a]
+    with robot.environment as env:
+        checkpoint = env.verify_checkpoint(archive.archive_path)
+    checkpoint.add('code/README')
+    checkpoint.close()
+
+    robot.cli('verify', bead_a)
+    assert 'already verified' not in robot.stderr
+
+
+def test_recently_verified_bead_is_not_verified_again(robot, bead_a):
+    robot.cli('verify', bead_a)
+    assert 'Verifying' in robot.stdout
+
+    robot.cli('verify', bead_a)
+    assert 'verified recently' in robot.stdout
+    assert 'Verifying' not in robot.stdout
+
+    robot.cli('verify', bead_a, '--force')
+    assert 'Verifying' in robot.stdout
+
+
+@needs_openssh
+def test_save_signed_and_verify(robot, box, key):
+    robot.cli('new', 'bead')
+    robot.cli('save', '-w', 'bead', '--sign', key)
+
+    robot.cli('verify', 'bead', '--key', key.with_suffix('.pub'))
+    assert 'Signature OK' in robot.stdout
+
+
+@needs_openssh
+def test_verify_with_wrong_key_fails(robot, box, key, tmp_path_factory):
+    other_key = tmp_path_factory.mktemp('other') / 'key'
+    subprocess.run(
+        [signing.SSH_KEYGEN, '-t', 'ed25519', '-N', '', '-q', '-f', other_key], check=True)
+    robot.cli('new', 'bead')
+    robot.cli('save', '-w', 'bead', '--sign', key)
+
+    with pytest.raises(SystemExit):
+        robot.cli('verify', 'bead', '--key', other_key.with_suffix('.pub'))
+    assert 'Invalid signature' in robot.stderr
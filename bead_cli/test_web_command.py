@@ -1,3 +1,4 @@
+import json
 import re
 import pytest
 from bead.tech.fs import read_file, rmtree, write_file
@@ -14,6 +15,27 @@ def test_dot_output(robot, bead_with_inputs):
     assert (robot.cwd / 'all.dot').exists()
 
 
+def test_dot_output_shows_short_content_ids(robot, beads, bead_with_inputs):
+    robot.cli('web dot all.dot')
+    dot = read_file(robot.cwd / 'all.dot')
+    content_id = beads[bead_with_inputs].content_id
+    assert f'<FONT POINT-SIZE="8">{content_id[:8]}</FONT>' in dot
+
+
+def test_dot_output_with_tagged_content_ids(robot, box):
+    robot.cli('new', 'tagged')
+    robot.cli('save', '-w', 'tagged', '--hash', 'blake2b')
+    [bead] = box.all_beads()
+    assert bead.content_id.startswith('blake2b:')
+
+    robot.cli('web dot all.dot')
+
+    dot = read_file(robot.cwd / 'all.dot')
+    digest = bead.content_id.partition(':')[2]
+    assert f'<FONT POINT-SIZE="8">{digest[:8]}</FONT>' in dot
+    assert 'blake2b:' not in dot
+
+
 @needs_dot
 def test_svg_output(robot, bead_with_inputs):
     robot.cli('web svg all.svg')
@@ -246,3 +268,80 @@ def test_rewire(robot, renamed_e_web_file, remap_e_to_renamed_e_json):
     assert f.input_map == {'e': 'renamed_e'}, f
     assert 'WARNING' in robot.stderr
     assert "Selected name 'renamed_e'" in robot.stderr
+
+
+def test_graph_is_colored(robot, bead_with_inputs):
+    robot.cli('web graph all.dot')
+    graph = read_file(robot.cwd / 'all.dot')
+
+    assert bead_with_inputs in graph
+    assert 'green' in graph
+
+
+def test_html_output(robot, bead_with_inputs):
+    robot.cli('web html all.html')
+    page = read_file(robot.cwd / 'all.html')
+
+    assert page.startswith('<!DOCTYPE html>')
+    assert f'"name": "{bead_with_inputs}"' in page
+    assert '"freshness": "up_to_date"' in page
+
+
+@needs_dot
+def test_graph_svg_output(robot, bead_with_inputs):
+    robot.cli('web graph all.svg')
+    assert (robot.cwd / 'all.svg').exists()
+
+
+def test_graph_with_unsupported_format_is_reported(robot):
+    with pytest.raises(SystemExit):
+        robot.cli('web graph all.jpeg')
+    assert 'all.jpeg' in robot.stderr
+
+
+def test_stale_report(robot, bead_with_inputs, bead_a):
+    robot.cli('web stale')
+    assert 'All inputs are up to date' in robot.stdout
+
+    robot.cli('develop', bead_a)
+    robot.cd(bead_a)
+    robot.write_file('output/new-data', 'new data')
+    robot.cli('save')
+    robot.cd('..')
+
+    robot.cli('web stale')
+    assert f'bead_with_inputs [box]: input input_a ({bead_a} ' in robot.stdout
+    assert 'input_b' not in robot.stdout
+
+
+def test_workspace_in_graph(robot, bead_with_inputs, bead_a):
+    robot.cli('develop', bead_with_inputs, 'ws')
+    robot.cli('web', 'workspace', 'ws', 'save', 'all.web')
+
+    sketch = Sketch.from_file(robot.cwd / 'all.web')
+    [workspace] = [bead for bead in sketch.beads if bead.box_name == '<UNSAVED>']
+    assert 'ws' == workspace.name
+    assert {'input_a', 'input_b'} == {
+        edge.label for edge in sketch.edges if edge.dest_ref == workspace.ref}
+
+
+def test_workspace_in_graph_needs_a_workspace(robot):
+    with pytest.raises(SystemExit):
+        robot.cli('web', 'workspace', 'not-a-workspace', 'save', 'all.web')
+    assert 'Could not parse' in robot.stderr
+
+
+def test_json_output(robot, beads, bead_with_inputs, bead_a):
+    robot.cli('--format', 'json', 'web', 'color')
+
+    graph = json.loads(robot.stdout)
+    assert 'Loaded' in robot.stderr
+    beads_by_name = {}
+    for bead in graph['beads']:
+        beads_by_name.setdefault(bead['name'], []).append(bead)
+    assert bead_with_inputs in beads_by_name
+    assert {'input_a', 'input_b'} == {
+        input['name'] for input in beads_by_name[bead_with_inputs][0]['inputs']}
+    assert any(
+        edge['sink']['name'] == bead_with_inputs and edge['input'] == 'input_a'
+        for edge in graph['edges'])
@@ -0,0 +1,58 @@
+import json
+import os
+
+import pytest
+
+from bead.tech.fs import rmtree
+
+
+def test_workspaces_are_not_recorded_by_default(robot):
+    robot.cli('new', 'bead')
+    robot.cli('workspaces')
+    assert 'not recorded' in robot.stdout
+
+    with pytest.raises(SystemExit):
+        robot.cli('goto', 'bead')
+    assert 'Unknown workspace' in robot.stderr
+
+
+def test_new_develop_and_save_record_workspaces(robot, box, bead_a):
+    robot.cli('workspaces', '--enable')
+    robot.cli('new', 'bead')
+    robot.cli('develop', bead_a)
+
+    robot.cli('goto', 'bead')
+    assert os.path.samefile(robot.cwd / 'bead', robot.stdout.strip())
+    robot.cli('goto', bead_a)
+    assert os.path.samefile(robot.cwd / bead_a, robot.stdout.strip())
+
+    robot.cli('save', '-w', 'bead')
+    robot.cli('workspaces', '--format', 'json')
+    known = {w['name']: w for w in json.loads(robot.stdout)}
+    assert {'bead', bead_a} == set(known)
+    assert known['bead']['last_freeze_time_str'] is not None
+    assert known[bead_a]['last_freeze_time_str'] is None
+
+
+def test_prune_forgets_missing_workspaces(robot):
+    robot.cli('workspaces', '--enable')
+    robot.cli('new', 'kept')
+    robot.cli('new', 'removed')
+    rmtree(robot.cwd / 'removed')
+
+    robot.cli('workspaces', '--prune')
+    assert 'Forgot 1 workspace' in robot.stdout
+
+    robot.cli('workspaces')
+    assert 'kept' in robot.stdout
+    assert 'removed' not in robot.stdout
+
+
+def test_disable_forgets_workspaces(robot):
+    robot.cli('workspaces', '--enable')
+    robot.cli('new', 'bead')
+    robot.cli('workspaces', '--disable')
+    robot.cli('workspaces', '--enable')
+
+    robot.cli('workspaces')
+    assert 'No workspaces are known' in robot.stdout
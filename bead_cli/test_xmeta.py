@@ -1,9 +1,9 @@
 from bead.archive import Archive
-from bead.tech.fs import read_file, write_file
+from bead.tech.fs import Path, write_file
 
 
 def test_meta_attributes_are_available_without_reading_the_archive(
-    robot, bead_with_inputs, beads
+    robot, bead_with_inputs, beads, monkeypatch
 ):
     archive = beads[bead_with_inputs]
     archive_filename = archive.archive_filename
@@ -21,10 +21,22 @@ def test_meta_attributes_are_available_without_reading_the_archive(
     archive_attributes = get_meta(archive)
 
     robot.cli('xmeta', archive_filename)
-    # damage the archive, so that all data must come from the xmeta file
-    with robot.environment:
-        write_file(archive_filename, '')
-        assert read_file(archive_filename) == ''
 
+    # all data must come from the xmeta file
+    def no_zip_reading(*args, **kwargs):
+        raise AssertionError('zip archive was opened')
+    monkeypatch.setattr('bead.archive.ZipArchive', no_zip_reading)
     xmeta_archive = Archive(archive_filename)
     assert archive_attributes == get_meta(xmeta_archive)
+
+
+def test_xmeta_of_changed_archive_is_ignored(robot, bead_with_inputs, bead_a, beads):
+    archive_filename = beads[bead_with_inputs].archive_filename
+    robot.cli('xmeta', archive_filename)
+    # replace the archive with another one
+    with robot.environment:
+        write_file(archive_filename, Path(beads[bead_a].archive_filename).read_bytes())
+
+    archive = Archive(archive_filename)
+    assert beads[bead_a].content_id == archive.content_id
+    assert beads[bead_a].kind == archive.kind
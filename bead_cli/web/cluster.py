@@ -57,9 +57,9 @@ class Cluster:
         for bead in beads[1:]:
             bead.set_freshness(Freshness.SUPERSEDED)
 
-    @property
-    def as_dot(self):
-        return ''.join(graphviz.dot_cluster_as_fragments(self.name, self.beads()))
+    def as_dot(self, content_id_length):
+        return ''.join(
+            graphviz.dot_cluster_as_fragments(self.name, self.beads(), content_id_length))
 
     def __len__(self):
         return len(self.beads_by_content_id)
@@ -1,14 +1,17 @@
 import argparse
+import contextlib
 import os
 import subprocess
+import sys
 import textwrap
 from typing import Set
 import webbrowser
 
 from bead import tech
 from bead.box import UnionBox
+from bead.workspace import Workspace
 
-from ..common import OPTIONAL_ENV, die
+from ..common import OPTIONAL_ENV, OUTPUT_FORMAT, JSON, die, print_json
 from ..cmdparse import Command
 from .io import read_beads, write_beads
 from .sketch import Sketch
@@ -54,10 +57,33 @@ class CmdWeb(Command):
     svg filename.svg
         Save connections as image in SVG format
 
+    graph filename.{dot,svg,png}
+        Assign freshness to nodes and save the colored connections
+        in the format given by the file extension:
+        green - up to date, orange - out of date (has newer inputs),
+        grey - superseded by a newer version, red - missing (phantom).
+
+    html filename.html
+        Assign freshness to nodes and save the graph as a self-contained,
+        interactive HTML page - showing metadata of the clicked bead.
+        For sharing the state of the project with collaborators.
+
+    json filename.json
+        Save the graph as beads and edges - the same as the output of
+        `bead --format json web` - for analysis tools (pandas, networkx, Gephi).
+
+    csv filename.csv
+        Save the input connections as an edge list - one row per edge,
+        with the attributes of both the source and the sink bead.
+
     color
         Assign freshness to nodes, which are visualized as colors.
         Answers the question: "Are all input at the latest version?"
 
+    stale
+        Report the latest beads with inputs referencing older versions than the
+        newest available bead of the same kind - these need an `input update`.
+
     auto-rewire
         A hackish way to fix connections after renaming beads, thus breaking links.
         It is hackish, because it selects the first candidate, which might
@@ -73,6 +99,10 @@ class CmdWeb(Command):
         Rewrite/patch the input maps as specified in the file.
         In case of multiple options for an input, the first option is selected.
 
+    workspace directory
+        Add the workspace in directory to the graph - connected to its inputs,
+        as if it was saved.
+
     heads
         Reduce graph to include only most recent computations per
         cluster and possibly a few older ones, that are referenced
@@ -85,6 +115,7 @@ class CmdWeb(Command):
     FORMATTER_CLASS = argparse.RawDescriptionHelpFormatter
 
     def declare(self, arg):
+        arg(OUTPUT_FORMAT)
         arg(OPTIONAL_ENV)
         arg(
             'words',
@@ -110,6 +141,13 @@ class CmdWeb(Command):
             die(msg)
 
         sketch = Sketch.from_beads([])
+        if args.output_format == JSON:
+            # stdout is for the JSON graph only
+            with contextlib.redirect_stdout(sys.stderr):
+                for command in commands:
+                    sketch = command(sketch)
+            print_json(sketch.as_dict())
+            return
         for command in commands:
             sketch = command(sketch)
 
@@ -201,6 +239,48 @@ class WriteSvg(ProcessorWithFileName):
         return sketch
 
 
+class WriteGraph(ProcessorWithFileName):
+    FORMATS = ('dot', 'svg', 'png')
+
+    def __init__(self, args):
+        super().__init__(args)
+        self.format = self.file_name.suffix.lstrip('.').lower()
+        if self.format not in self.FORMATS:
+            raise ValueError(f'Unsupported graph format: {self.file_name}')
+
+    def __call__(self, sketch):
+        sketch.color_beads()
+        dot_str = sketch.as_dot()
+        if self.format == 'dot':
+            tech.fs.write_file(self.file_name, dot_str)
+        else:
+            print(f"Creating {self.format.upper()}: {self.file_name}")
+            graphviz_dot(dot_str, self.file_name, format=self.format)
+        return sketch
+
+
+class WriteHtml(ProcessorWithFileName):
+    def __call__(self, sketch):
+        sketch.color_beads()
+        print(f"Creating HTML: {self.file_name}")
+        tech.fs.write_file(self.file_name, sketch.as_html())
+        return sketch
+
+
+class WriteJson(ProcessorWithFileName):
+    def __call__(self, sketch):
+        print(f"Creating JSON: {self.file_name}")
+        tech.fs.write_file(self.file_name, sketch.as_json())
+        return sketch
+
+
+class WriteCsvEdges(ProcessorWithFileName):
+    def __call__(self, sketch):
+        print(f"Creating CSV: {self.file_name}")
+        tech.fs.write_file(self.file_name, sketch.as_csv_edges())
+        return sketch
+
+
 class View(ProcessorWithFileName):
     def __call__(self, sketch):
         print(f"Viewing {self.file_name}")
@@ -244,6 +324,31 @@ class SetFreshness(SketchProcessor):
         return sketch
 
 
+class ReportStale(SketchProcessor):
+    def __call__(self, sketch):
+        stale_inputs = sketch.stale_inputs()
+        if not stale_inputs:
+            print('All inputs are up to date')
+        for stale_input in stale_inputs:
+            bead, current, newest = stale_input.bead, stale_input.current, stale_input.newest
+            box = f' [{bead.box_name}]' if bead.box_name else ''
+            print(
+                f'{bead.name}{box}: input {stale_input.input_name}'
+                + f' ({current.name} {current.freeze_time_str})'
+                + f' is older than {newest.name} {newest.freeze_time_str}')
+        return sketch
+
+
+class AddWorkspace(SketchProcessor):
+    def __init__(self, args):
+        self.workspace = Workspace(args.pop())
+        if not self.workspace.is_valid:
+            raise ValueError(f'Not a workspace: {self.workspace.directory}')
+
+    def __call__(self, sketch):
+        return Sketch.from_beads(sketch.beads + (Dummy.from_bead(self.workspace),))
+
+
 class KeepOnlyHeads(SketchProcessor):
     def __call__(self, sketch):
         return web_sketch.heads_of(sketch).drop_deleted_inputs()
@@ -280,9 +385,15 @@ SUBCOMMANDS = {
     'dot': WriteDot,
     'png': WritePng,
     'svg': WriteSvg,
+    'graph': WriteGraph,
+    'html': WriteHtml,
+    'json': WriteJson,
+    'csv': WriteCsvEdges,
     '/': Filter,
     'color': SetFreshness,
     'heads': KeepOnlyHeads,
+    'workspace': AddWorkspace,
+    'stale': ReportStale,
     'view': View,
     'auto-rewire': AutoRewire,
     'rewire-options': RewireWriteOptions,
@@ -1,10 +1,12 @@
-from typing import Iterable, Dict, List, TypeVar
+from typing import Iterable, Dict, List, Optional, TypeVar
 
 import attr
 from cached_property import cached_property
 
+from bead.archive import Archive
 from bead.meta import InputSpec, InputName, BeadName
-from bead.tech.timestamp import time_from_timestamp
+from bead.tech.timestamp import time_from_legacy_timestamp
+from ..common import automated_by
 from .freshness import Freshness
 
 
@@ -36,10 +38,12 @@ class Dummy:
     input_map: InputMap = attr.ib(kw_only=True, factory=dict, converter=input_map_converter)
     freshness: Freshness = attr.ib(kw_only=True, default=Freshness.SUPERSEDED, converter=Freshness)
     box_name: str = attr.ib(kw_only=True, default='')
+    # identity of the pipeline runner, that saved the bead - None if it was saved manually
+    runner: Optional[str] = attr.ib(kw_only=True, default=None)
 
     @cached_property
     def freeze_time(self):
-        return time_from_timestamp(self.freeze_time_str)
+        return time_from_legacy_timestamp(self.freeze_time_str)
 
     @cached_property
     def ref(self) -> 'Ref':
@@ -55,7 +59,8 @@ class Dummy:
             inputs=bead.inputs,
             input_map=bead.input_map,
             freshness=getattr(bead, 'freshness', Freshness.SUPERSEDED),
-            box_name=bead.box_name)
+            box_name=bead.box_name,
+            runner=automated_by(bead) if isinstance(bead, Archive) else None)
 
     @classmethod
     def phantom_from_input(cls, bead: 'Dummy', inputspec: InputSpec):
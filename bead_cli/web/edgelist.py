@@ -0,0 +1,45 @@
+"""
+The graph as plain data files - for analysis tools like pandas, networkx or Gephi.
+
+Both are made from Sketch.as_dict(), the graph of `bead --format json web`:
+JSON is that as is - beads with their attributes and inputs,
+edges referring to their source and sink beads by name and content id.
+CSV is an edge list: one row per input connection, with the attributes of both ends.
+"""
+
+import csv
+import io
+import json
+
+NODE_ATTRIBUTES = ('name', 'kind', 'content_id', 'freeze_time', 'box', 'runner')
+
+
+def _key(bead_or_ref):
+    return bead_or_ref['name'], bead_or_ref['content_id']
+
+
+def as_json(sketch) -> str:
+    return json.dumps(sketch.as_dict(), indent=2)
+
+
+CSV_COLUMNS = (
+    tuple(f'source_{attribute}' for attribute in NODE_ATTRIBUTES)
+    + tuple(f'sink_{attribute}' for attribute in NODE_ATTRIBUTES)
+    + ('input',))
+
+
+def as_csv_edges(sketch) -> str:
+    graph = sketch.as_dict()
+    bead_by_key = {_key(bead): bead for bead in graph['beads']}
+    edges = sorted(
+        graph['edges'], key=lambda edge: (_key(edge['source']), _key(edge['sink']), edge['input']))
+    output = io.StringIO()
+    writer = csv.DictWriter(output, CSV_COLUMNS, lineterminator='\n')
+    writer.writeheader()
+    for edge in edges:
+        row = {'input': edge['input']}
+        for end in ('source', 'sink'):
+            bead = bead_by_key[_key(edge[end])]
+            row.update({f'{end}_{attribute}': bead[attribute] for attribute in NODE_ATTRIBUTES})
+        writer.writerow(row)
+    return output.getvalue()
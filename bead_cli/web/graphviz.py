@@ -1,4 +1,7 @@
 import html
+
+from bead.tech import securehash
+
 from .freshness import Freshness
 
 
@@ -45,12 +48,19 @@ def bead_color(bead):
 
 class Port:
     def __init__(self, bead):
-        content_id = bead.content_id
+        # the `:` of tagged content ids would separate node and port
+        content_id = securehash.safe_name(bead.content_id)
         self.input = f"in_{content_id}"
         self.output = f"out_{content_id}"
 
 
-def dot_cluster_as_fragments(cluster_name, beads, indent='  '):
+def short_content_id(bead, content_id_length):
+    if bead.is_not_phantom:
+        return securehash.digest_of(bead.content_id)[:content_id_length]
+    return ''
+
+
+def dot_cluster_as_fragments(cluster_name, beads, content_id_length, indent='  '):
     assert beads
     # beads are sorted in descending order by freeze_time
     freeze_times = [b.freeze_time for b in beads]
@@ -78,6 +88,10 @@ def dot_cluster_as_fragments(cluster_name, beads, indent='  '):
         yield f'<TD PORT="{Port(bead).input}" {color}></TD>'
         yield f'<TD PORT="{Port(bead).output}" {color}>'
         yield f'{bead.freeze_time}'
+        yield f' <FONT POINT-SIZE="8">{short_content_id(bead, content_id_length)}</FONT>'
+        if bead.runner is not None:
+            # automated freezes
+            yield f' <FONT POINT-SIZE="8"><I>{html.escape(bead.runner)}</I></FONT>'
         yield '</TD>'
         yield '</TR>\n'
     yield indent
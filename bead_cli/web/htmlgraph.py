@@ -0,0 +1,157 @@
+"""
+Self-contained, interactive HTML page of the graph - for sharing the state of a project.
+
+The page embeds the graph as JSON and draws it with a few lines of JavaScript,
+so it can be sent by mail or put on a shared drive: no server or network access is needed.
+Beads are laid out in columns by their distance from the roots, colored by freshness.
+Clicking a bead shows its metadata.
+"""
+
+import html
+import json
+
+from .graphviz import BEAD_COLOR
+
+
+def _script_safe_json(value) -> str:
+    # the JSON is in a <script> element, which would end at the first "</"
+    return (
+        json.dumps(value, sort_keys=True)
+        .replace('&', '\\u0026')
+        .replace('<', '\\u003c')
+        .replace('>', '\\u003e'))
+
+
+def as_html(sketch, title='Bead graph') -> str:
+    data = sketch.as_dict()
+    data['colors'] = {
+        freshness.name.lower(): color for freshness, color in BEAD_COLOR.items()}
+    return (
+        HTML_GRAPH_TEMPLATE
+        .replace('@TITLE@', html.escape(title))
+        .replace('@DATA@', _script_safe_json(data)))
+
+
+HTML_GRAPH_TEMPLATE = """\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>@TITLE@</title>
+<style>
+body { font-family: sans-serif; margin: 0; display: flex; height: 100vh; }
+#graph { flex: 1; overflow: auto; }
+#details { width: 320px; padding: 8px 16px; border-left: 1px solid #ccc; overflow: auto; }
+#details td { vertical-align: top; padding-right: 8px; word-break: break-all; }
+.node { cursor: pointer; }
+.node text { font-size: 12px; pointer-events: none; }
+.legend span { display: inline-block; width: 12px; height: 12px; margin: 0 4px 0 12px; }
+</style>
+</head>
+<body>
+<div id="graph"><h2>@TITLE@</h2><div class="legend" id="legend"></div></div>
+<div id="details"><p>Click a bead to see its metadata.</p></div>
+<script type="application/json" id="graph-data">@DATA@</script>
+<script>
+(function () {
+  const data = JSON.parse(document.getElementById('graph-data').textContent);
+  const SVG = 'http://www.w3.org/2000/svg';
+  const WIDTH = 200, HEIGHT = 36, COLUMN = 260, ROW = 52, MARGIN = 20;
+  const key = ref => ref.name + '\\u0000' + ref.content_id;
+
+  const inputsOf = new Map();
+  for (const edge of data.edges) {
+    const sink = key(edge.sink);
+    if (!inputsOf.has(sink)) inputsOf.set(sink, []);
+    inputsOf.get(sink).push(key(edge.source));
+  }
+  const depths = new Map();
+  function depth(k) {
+    if (!depths.has(k)) {
+      depths.set(k, 0);
+      depths.set(k, Math.max(0, ...(inputsOf.get(k) || []).map(i => depth(i) + 1)));
+    }
+    return depths.get(k);
+  }
+
+  const columns = [];
+  const beads = [...data.beads].sort(
+    (a, b) => a.name.localeCompare(b.name) || b.freeze_time.localeCompare(a.freeze_time));
+  for (const bead of beads) {
+    const column = depth(key(bead));
+    (columns[column] = columns[column] || []).push(bead);
+  }
+  const position = new Map();
+  columns.forEach((column, x) => column.forEach((bead, y) => position.set(
+    key(bead), {x: MARGIN + x * COLUMN, y: MARGIN + y * ROW, bead: bead})));
+
+  const element = (name, attributes) => {
+    const e = document.createElementNS(SVG, name);
+    for (const [attribute, value] of Object.entries(attributes)) e.setAttribute(attribute, value);
+    return e;
+  };
+  const svg = element('svg', {
+    width: MARGIN * 2 + Math.max(1, columns.length) * COLUMN,
+    height: MARGIN * 2 + Math.max(1, ...columns.map(c => c.length)) * ROW});
+  const color = bead => data.colors[bead.freshness];
+
+  for (const edge of data.edges) {
+    const source = position.get(key(edge.source)), sink = position.get(key(edge.sink));
+    const x1 = source.x + WIDTH, y1 = source.y + HEIGHT / 2;
+    const x2 = sink.x, y2 = sink.y + HEIGHT / 2, middle = (x1 + x2) / 2;
+    const path = element('path', {
+      d: `M${x1},${y1} C${middle},${y1} ${middle},${y2} ${x2},${y2}`,
+      fill: 'none', stroke: color(source.bead)});
+    path.appendChild(element('title', {})).textContent = edge.input;
+    svg.appendChild(path);
+  }
+  for (const {x, y, bead} of position.values()) {
+    const node = element('g', {class: 'node', transform: `translate(${x},${y})`});
+    node.appendChild(element('rect', {
+      width: WIDTH, height: HEIGHT, rx: 4, fill: color(bead), 'fill-opacity': 0.3,
+      stroke: color(bead)}));
+    node.appendChild(element('text', {x: 6, y: 15})).textContent = bead.name;
+    node.appendChild(element('text', {x: 6, y: 29})).textContent = bead.freeze_time;
+    node.addEventListener('click', () => showDetails(bead));
+    svg.appendChild(node);
+  }
+  document.getElementById('graph').appendChild(svg);
+
+  for (const [freshness, c] of Object.entries(data.colors)) {
+    const span = document.createElement('span');
+    span.style.background = c;
+    document.getElementById('legend').append(span, freshness.replace(/_/g, ' '));
+  }
+
+  function showDetails(bead) {
+    const details = document.getElementById('details');
+    details.replaceChildren();
+    details.appendChild(document.createElement('h3')).textContent = bead.name;
+    const table = details.appendChild(document.createElement('table'));
+    const row = (label, value) => {
+      const tr = table.insertRow();
+      tr.insertCell().textContent = label;
+      tr.insertCell().textContent = value;
+    };
+    row('freshness', bead.freshness.replace(/_/g, ' '));
+    row('freeze time', bead.freeze_time);
+    row('box', bead.box);
+    row('kind', bead.kind);
+    row('content id', bead.content_id);
+    if (bead.runner) {
+      row('automated by', bead.runner);
+    }
+    if (bead.inputs.length) {
+      details.appendChild(document.createElement('h4')).textContent = 'Inputs';
+      const list = details.appendChild(document.createElement('ul'));
+      for (const input of bead.inputs) {
+        list.appendChild(document.createElement('li')).textContent =
+          `${input.name}: ${input.bead_name} (${input.freeze_time})`;
+      }
+    }
+  }
+})();
+</script>
+</body>
+</html>
+"""
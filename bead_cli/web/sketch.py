@@ -4,12 +4,17 @@ from typing import Set, Dict, List, Tuple, Sequence, Iterable
 import attr
 from cached_property import cached_property
 
+from bead.box import UnionBox
+from bead.tech import securehash
+from bead.tech.identifier import unique_prefix_length
 from bead.tech.timestamp import EPOCH_STR
 from .freshness import UP_TO_DATE, OUT_OF_DATE
 from .dummy import Dummy
 from .cluster import Cluster, create_cluster_index
 from .io import read_beads, write_beads
 from . import graphviz
+from . import edgelist
+from . import htmlgraph
 from .graph import (
     Edge,
     Ref,
@@ -18,12 +23,24 @@ from .graph import (
     group_by_dest,
     toposort,
     closure,
+    reverse,
     bead_index_from_edges,
     refs_from_beads,
     refs_from_edges,
 )
 
 
+@attr.s(frozen=True, auto_attribs=True)
+class StaleInput:
+    """
+    Input of bead referencing an older version than the newest one of its kind.
+    """
+    bead: Dummy
+    input_name: str
+    current: Dummy
+    newest: Dummy
+
+
 @attr.s(frozen=True, auto_attribs=True)
 class Sketch:
     beads: Tuple[Dummy, ...]
@@ -46,6 +63,13 @@ class Sketch:
         beads = bead_index_from_edges(edges).values()
         return cls(tuple(beads), tuple(edges))
 
+    @classmethod
+    def from_boxes(cls, boxes):
+        """
+        Discover all beads in boxes with their input connections.
+        """
+        return cls.from_beads([Dummy.from_bead(bead) for bead in UnionBox(boxes).all_beads()])
+
     @classmethod
     def from_file(cls, file_name):
         beads = read_beads(file_name)
@@ -54,6 +78,36 @@ class Sketch:
     def to_file(self, file_name):
         write_beads(file_name, self.beads)
 
+    def as_dict(self):
+        '''
+        The graph as plain data - for JSON output.
+        '''
+        def bead_dict(bead):
+            return dict(
+                name=bead.name,
+                kind=bead.kind,
+                content_id=bead.content_id,
+                freeze_time=bead.freeze_time_str,
+                box=bead.box_name,
+                runner=bead.runner,
+                freshness=bead.freshness.name.lower(),
+                inputs=[
+                    dict(
+                        name=input.name,
+                        bead_name=bead.get_input_bead_name(input.name),
+                        kind=input.kind,
+                        content_id=input.content_id,
+                        freeze_time=input.freeze_time_str)
+                    for input in bead.inputs])
+        return dict(
+            beads=[bead_dict(bead) for bead in self.beads],
+            edges=[
+                dict(
+                    source=attr.asdict(edge.src_ref),
+                    sink=attr.asdict(edge.dest_ref),
+                    input=edge.label)
+                for edge in self.edges])
+
     @cached_property
     def cluster_by_name(self) -> Dict[str, Cluster]:
         return create_cluster_index(self.beads)
@@ -62,12 +116,84 @@ class Sketch:
     def clusters(self):
         return tuple(self.cluster_by_name.values())
 
+    @cached_property
+    def bead_by_ref(self) -> Dict[Ref, Dummy]:
+        return {bead.ref: bead for bead in self.beads}
+
+    def ancestors(self, bead: Dummy) -> Tuple[Dummy, ...]:
+        """
+        Direct and indirect inputs of bead.
+        """
+        return self._reachable_from(bead, group_by_src(reverse(self.edges)))
+
+    def descendants(self, bead: Dummy) -> Tuple[Dummy, ...]:
+        """
+        Beads having bead as direct or indirect input.
+        """
+        return self._reachable_from(bead, group_by_src(self.edges))
+
+    def roots(self) -> Tuple[Dummy, ...]:
+        """
+        Beads without inputs in the graph - e.g. imported, raw data.
+        """
+        refs_with_input = {edge.dest_ref for edge in self.edges}
+        return self._sorted(b for b in self.beads if b.ref not in refs_with_input)
+
+    @cached_property
+    def newest_by_kind(self) -> Dict[str, Dummy]:
+        newest: Dict[str, Dummy] = {}
+        for bead in self.beads:
+            if bead.is_not_phantom:
+                if bead.kind not in newest or bead.freeze_time > newest[bead.kind].freeze_time:
+                    newest[bead.kind] = bead
+        return newest
+
+    def stale_inputs(self) -> Tuple[StaleInput, ...]:
+        """
+        Inputs of the latest beads, for which a newer version of the same kind is available.
+
+        Older versions of beads are not considered - they are superseded anyway.
+        """
+        heads = {cluster.head.ref for cluster in self.clusters if cluster.head.is_not_phantom}
+        stale = []
+        for edge in self.edges:
+            if edge.dest_ref not in heads:
+                continue
+            newest = self.newest_by_kind.get(edge.src.kind)
+            if newest is not None and newest.freeze_time > edge.src.freeze_time:
+                stale.append(StaleInput(edge.dest, edge.label, edge.src, newest))
+        return tuple(
+            sorted(stale, key=lambda stale_input: (stale_input.bead.name, stale_input.input_name)))
+
+    def stale_beads(self) -> Tuple[Dummy, ...]:
+        """
+        Latest beads having inputs that need an update.
+        """
+        stale = {stale_input.bead.ref: stale_input.bead for stale_input in self.stale_inputs()}
+        return self._sorted(stale.values())
+
+    def _reachable_from(self, bead, edges_by_src) -> Tuple[Dummy, ...]:
+        refs = closure([bead.ref], edges_by_src) - {bead.ref}
+        return self._sorted(self.bead_by_ref[ref] for ref in refs)
+
+    def _sorted(self, beads: Iterable[Dummy]) -> Tuple[Dummy, ...]:
+        return tuple(sorted(beads, key=lambda bead: (bead.name, bead.freeze_time)))
+
     def color_beads(self):
         color_beads(self)
 
     def as_dot(self):
         return plot_clusters_as_dot(self)
 
+    def as_html(self):
+        return htmlgraph.as_html(self)
+
+    def as_json(self):
+        return edgelist.as_json(self)
+
+    def as_csv_edges(self):
+        return edgelist.as_csv_edges(self)
+
     def drop_deleted_inputs(self) -> "Sketch":
         return drop_deleted_inputs(self)
 
@@ -236,7 +362,10 @@ def plot_clusters_as_dot(sketch: Sketch):
     Generate GraphViz .dot file content, which describe the connections between beads
     and their up-to-date status.
     """
-    formatted_bead_clusters = '\n\n'.join(c.as_dot for c in sketch.clusters)
+    content_id_length = unique_prefix_length(
+        securehash.digest_of(bead.content_id) for bead in sketch.beads)
+    formatted_bead_clusters = '\n\n'.join(
+        c.as_dot(content_id_length) for c in sketch.clusters)
     graphviz_context = graphviz.Context()
 
     def format_inputs():
@@ -1,22 +1,44 @@
 from bead.exceptions import InvalidArchive
+import contextlib
 import os
+import sys
+import tempfile
 
-from bead import tech
-from bead.workspace import Workspace
+from bead import export
+from bead import hooks
 from bead import layouts
-from bead.exceptions import BoxError
+from bead import sample
+from bead import save_policy
+from bead.save_policy import NAME_COLLISION_POLICIES, SaveRefused
+from bead import templates
+from bead import tech
+from bead.box import DEFAULT_VERIFICATION_MAX_AGE
+from bead.workspace import DataDirectory, Workspace
+from bead.exceptions import BoxError, SignatureError
+from bead.validators import ValidatorError
 import bead.spec as bead_spec
 
 from .cmdparse import Command
-from .common import assert_valid_workspace, die, warning, info
+from .environment import ZIP_COMPRESSIONS
+from .environment import HASH_ALGORITHMS, NOTEBOOK_OUTPUT_MODES
+from .common import assert_valid_workspace, die, die_with, warning, info, confirm
 from .common import DefaultArgSentinel
-from .common import OPTIONAL_WORKSPACE, OPTIONAL_ENV
-from .common import BEAD_REF_BASE, BEAD_TIME, resolve_bead
-from .common import verify_with_feedback
+from .common import OPTIONAL_WORKSPACE, OPTIONAL_ENV, JSON_FLAG, JSON_OUTPUT, REPORT_FORMAT
+from .common import TEXT, JSON, print_json
+from .common import BEAD_REF_BASE, BEAD_REFERENCE, BEAD_TIME
+from .common import automated_by, resolve_bead, resolve_reference, short_content_ids
+from .common import verify_with_feedback, open_in_file_manager
+from .box import get_box
+from .input import load_all
+from .progress import progress_bar
+from . import registry
+from .report import Report, emit
+from .usage import count_bytes
 from . import arg_metavar
 from . import arg_help
 
 timestamp = tech.timestamp.timestamp
+time_from_timestamp = tech.timestamp.time_from_timestamp
 
 
 def assert_may_be_valid_name(name):
@@ -34,6 +56,20 @@ def assert_may_be_valid_name(name):
         die(f'Invalid name "{name}"')
 
 
+def VCS_FRIENDLY(parser):
+    parser.arg(
+        '--vcs-friendly', dest='vcs_friendly', default=False, action='store_true',
+        help=('Keep .bead-meta files friendly for version control (e.g. git):'
+              ' no volatile values, clean diffs'))
+
+
+def INPUT_MARKERS(parser):
+    parser.arg(
+        '--input-markers', dest='input_markers', default=False, action='store_true',
+        help=(f'Write {layouts.Workspace.INPUT_MARKER} into loaded input directories,'
+              ' telling which version of which bead is there'))
+
+
 class CmdNew(Command):
     '''
     Create and initialize new workspace directory for a new bead.
@@ -42,6 +78,11 @@ class CmdNew(Command):
     def declare(self, arg):
         arg('workspace', type=Workspace, metavar=arg_metavar.WORKSPACE,
             help='bead and directory to create')
+        arg(VCS_FRIENDLY)
+        arg(INPUT_MARKERS)
+        arg('--template', default=None,
+            help='copy the files of this template (see `bead template list`) or directory')
+        arg(OPTIONAL_ENV)
 
     def run(self, args):
         workspace: Workspace = args.workspace
@@ -49,8 +90,20 @@ class CmdNew(Command):
         if os.path.exists(workspace.directory):
             die(f'Directory {workspace.name} already exists.')
 
+        env = args.get_env()
         kind = tech.identifier.uuid()
-        workspace.create(kind)
+        if args.template is None:
+            workspace.create(
+                kind, vcs_friendly=args.vcs_friendly, input_markers=args.input_markers)
+        else:
+            try:
+                template = templates.resolve(args.template, env.get_template_directory())
+            except LookupError:
+                die(f'Unknown template: {args.template}', suggestions=['bead template list'])
+            workspace.create_from_template(
+                kind, template, vcs_friendly=args.vcs_friendly,
+                input_markers=args.input_markers)
+        registry.register(env, workspace)
         print(f'Created "{workspace.name}"')
 
 
@@ -66,6 +119,14 @@ def WORKSPACE_defaulting_to(default_workspace):
 USE_THE_ONLY_BOX = DefaultArgSentinel(
     'if there is exactly one box,' +
     ' store there, otherwise it MUST be specified')
+USE_ENV_NAME_COLLISION_POLICY = DefaultArgSentinel(
+    'as configured in the environment, "warn" if not configured')
+USE_BOX_COMPRESSION = DefaultArgSentinel(
+    'as configured for the box, or in the environment')
+USE_ENV_NOTEBOOK_OUTPUTS = DefaultArgSentinel(
+    'as configured in the environment, "keep" if not configured')
+USE_ENV_HASH_ALGORITHM = DefaultArgSentinel(
+    'as configured in the environment, "sha512" if not configured')
 
 
 class CmdSave(Command):
@@ -76,43 +137,163 @@ class CmdSave(Command):
     def declare(self, arg):
         arg('box_name', nargs='?', default=USE_THE_ONLY_BOX, type=str,
             metavar=arg_metavar.BOX, help=arg_help.BOX)
+        arg('--on-name-collision', dest='name_collision_policy',
+            choices=NAME_COLLISION_POLICIES, default=USE_ENV_NAME_COLLISION_POLICY,
+            help='what to do, when the box has beads with the same name, but different kind')
+        arg('--compression', choices=ZIP_COMPRESSIONS, default=USE_BOX_COMPRESSION,
+            help='compression of the archive (default: %(default)s)')
+        arg('--sign', dest='signing_key', type=tech.fs.Path, default=None, metavar='KEY',
+            help='store a detached signature made with this ssh private key (e.g. ed25519)')
+        arg('--override', default=False, action='store_true',
+            help='save even if the configured save policies are violated')
+        arg('--notebook-outputs', choices=NOTEBOOK_OUTPUT_MODES,
+            default=USE_ENV_NOTEBOOK_OUTPUTS,
+            help='keep or strip cell outputs of Jupyter notebooks in code')
+        arg('--hash', dest='hash_algorithm', choices=HASH_ALGORITHMS,
+            default=USE_ENV_HASH_ALGORITHM,
+            help='algorithm hashing the archive content')
+        arg('--runner', default=None, metavar='IDENTITY',
+            help='record, that this pipeline runner saves the bead (automated freeze),'
+            + ' needs --plan-hash')
+        arg('--plan-hash', default=None, metavar='HASH',
+            help='hash of the pipeline plan executed by the runner (needs --runner)')
+        arg('--skip-unchanged-output', default=False, action='store_true',
+            help='do not save, if the output, code and inputs are the same'
+            + ' as in the last saved version')
+        arg('--no-hooks', dest='run_hooks', default=True, action='store_false',
+            help='do not run the pre-save and post-save hooks of the workspace')
+        arg('--no-hash-cache', dest='use_hash_cache', default=True, action='store_false',
+            help='hash all files, even the ones unchanged (same size and modification time)'
+            + ' since the last save')
         arg(OPTIONAL_WORKSPACE)
         arg(OPTIONAL_ENV)
 
     def run(self, args):
-        box_name = args.box_name
         workspace = args.workspace
         env = args.get_env()
         assert_valid_workspace(workspace)
-        # XXX: (usability) save - support saving directly to a directory outside of workspace
-        if box_name is USE_THE_ONLY_BOX:
-            boxes = env.get_boxes()
-            if not boxes:
-                warning('No boxes have been defined')
-                beadbox = tech.fs.Path(os.path.expanduser('~/BeadBox'))
-                info(f'Creating and using a new one with name `home` and location {beadbox}')
-                tech.fs.ensure_directory(beadbox)
-                env.add_box('home', beadbox)
-                env.save()
-                # continue with newly created box
-                boxes = env.get_boxes()
-                assert len(boxes) == 1
-            if len(boxes) > 1:
-                die(
-                    'BOX parameter is not optional!\n' +
-                    '(more than one boxes exists)')
-            box = boxes[0]
-        else:
-            box = env.get_box(box_name)
-            if box is None:
-                die(f'Unknown box: {box_name}')
-        try:
-            location = box.store(workspace, timestamp())
-        except BoxError as e:
-            die(f'Error saving: {e}')
+        box = box_to_save_to(env, args.box_name)
+        freeze_time = check_clock_skew(box, workspace, timestamp())
+        archive = store(env, box, workspace, freeze_time, args)
+        if archive is None:
+            last_saved = workspace.last_saved([box])
+            print(
+                'Output, code and inputs unchanged since last save'
+                + f' ({last_saved.freeze_time_str}), not saving.')
+            return
+        location = archive.archive_path
+        count_bytes('saved', os.path.getsize(location))
+        registry.register(env, workspace, freeze_time)
         print(f'Successfully stored bead at {location}.')
 
 
+def save_context(env, args):
+    '''
+    BeadContext saving with the notebook output mode, hash algorithm
+    and name collision policy of args.
+    '''
+    notebook_outputs = args.notebook_outputs
+    if notebook_outputs is USE_ENV_NOTEBOOK_OUTPUTS:
+        notebook_outputs = None
+    if args.hash_algorithm is USE_ENV_HASH_ALGORITHM:
+        hash_algorithm = env.get_hash_algorithm()
+    else:
+        hash_algorithm = tech.securehash.HashAlgo(args.hash_algorithm)
+    if not tech.securehash.is_available(hash_algorithm):
+        die(f'{hash_algorithm.value} hashing is not available (is the package installed?)')
+    name_collision_policy = args.name_collision_policy
+    if name_collision_policy is USE_ENV_NAME_COLLISION_POLICY:
+        name_collision_policy = None
+    return env.get_bead_context(
+        notebook_outputs=notebook_outputs, hash_algorithm=hash_algorithm,
+        name_collision_policy=name_collision_policy)
+
+
+def save_provenance(workspace, args):
+    if args.plan_hash is not None and args.runner is None:
+        die('--plan-hash needs --runner')
+    if args.runner is None:
+        return None
+    if args.plan_hash is None:
+        die('--runner needs --plan-hash')
+    return workspace.provenance(args.runner, args.plan_hash)
+
+
+def store(env, box, workspace, freeze_time, args):
+    '''
+    Save workspace to box - None, if it is unchanged and args asked to skip saving it then.
+    '''
+    compression = args.compression
+    if compression is USE_BOX_COMPRESSION:
+        compression = None
+    provenance = save_provenance(workspace, args)
+    context = save_context(env, args)
+    try:
+        return context.save(
+            workspace, box.name, freeze_time, progress=progress_bar(),
+            provenance=provenance, run_hooks=args.run_hooks,
+            use_hash_cache=args.use_hash_cache, compression=compression,
+            signing_key=args.signing_key, skip_unchanged=args.skip_unchanged_output,
+            override=args.override)
+    except SaveRefused as e:
+        die_with(e)
+    except hooks.HookError as e:
+        die(f'Not saving: {e}')
+    except (BoxError, SignatureError, ValidatorError) as e:
+        die(f'Error saving: {e}')
+    except ValueError as e:
+        # e.g. file names, that can not be stored in the archive
+        die(f'Error saving: {e}')
+
+
+def box_to_save_to(env, box_name):
+    '''
+    The box named box_name - or with USE_THE_ONLY_BOX the only box, made if there is none.
+    '''
+    if box_name is not USE_THE_ONLY_BOX:
+        box = env.get_box(box_name)
+        if box is None:
+            die(
+                f'Unknown box: {box_name}', error='UnknownBox', context=dict(box=box_name),
+                suggestions=['bead box list'])
+        return box
+    boxes = env.get_boxes()
+    if not boxes:
+        warning('No boxes have been defined')
+        beadbox = tech.fs.Path(os.path.expanduser('~/BeadBox'))
+        info(f'Creating and using a new one with name `home` and location {beadbox}')
+        tech.fs.ensure_directory(beadbox)
+        env.add_box('home', beadbox)
+        env.save()
+        # continue with newly created box
+        boxes = env.get_boxes()
+        assert len(boxes) == 1
+    if len(boxes) > 1:
+        die(
+            'BOX parameter is not optional!\n' +
+            '(more than one boxes exists)')
+    return boxes[0]
+
+
+def check_clock_skew(box, workspace, freeze_time):
+    '''
+    Make sure, that the new bead will be the newest with its name in the box.
+
+    A clock running behind would make the new bead look older than existing ones.
+    Returns the freeze time to use.
+    '''
+    newest_freeze_time = save_policy.clock_skew(box, workspace, freeze_time)
+    if newest_freeze_time is None:
+        return freeze_time
+    adjusted_freeze_time = tech.timestamp.timestamp_after(newest_freeze_time)
+    warning(
+        f'The clock is behind: "{workspace.name}" in box "{box.name}" was frozen at'
+        + f' {newest_freeze_time}, which is later than now ({freeze_time}).')
+    if confirm(f'Save with adjusted freeze time {adjusted_freeze_time}?', default=True):
+        return adjusted_freeze_time
+    return freeze_time
+
+
 DERIVE_FROM_BEAD_NAME = DefaultArgSentinel('derive one from bead name')
 
 
@@ -121,23 +302,28 @@ class CmdDevelop(Command):
     Unpack a bead as a source tree.
 
     Bead directory layout is created, but only the source files are
-    extracted.
+    extracted. The bead can be given also by a prefix of its content id
+    or by its kind.
     '''
 
     def declare(self, arg):
-        arg(BEAD_REF_BASE)
+        arg(BEAD_REFERENCE)
         arg(BEAD_TIME)
         arg(WORKSPACE_defaulting_to(DERIVE_FROM_BEAD_NAME))
         arg('-x', '--extract-output', dest='extract_output',
             default=False, action='store_true',
             help='Extract output data as well (normally it is not needed!).')
+        arg('--load-inputs', dest='load_inputs', default=False, action='store_true',
+            help='Load the data of inputs found in the boxes.')
+        arg(VCS_FRIENDLY)
+        arg(INPUT_MARKERS)
         arg(OPTIONAL_ENV)
 
     def run(self, args):
         extract_output = args.extract_output
         env = args.get_env()
         try:
-            bead = resolve_bead(env, args.bead_ref_base, args.bead_time)
+            bead = resolve_reference(env, args.bead_ref_base, args.bead_time)
         except LookupError:
             die('Bead not found!')
         try:
@@ -152,25 +338,161 @@ class CmdDevelop(Command):
         if os.path.exists(workspace.directory):
             die(f'Workspace "{workspace.name}" directory already exists'
                 ' - do you have an old checkout?')
-        bead.unpack_to(workspace)
-        assert workspace.is_valid
-
-        if extract_output:
-            output_directory = workspace.directory / layouts.Workspace.OUTPUT
-            bead.unpack_data_to(output_directory)
+        workspace = Workspace.from_archive(
+            bead, workspace.directory, extract_output, vcs_friendly=args.vcs_friendly,
+            progress=progress_bar(), input_markers=args.input_markers)
+        count_bytes('developed', os.path.getsize(bead.archive_filename))
+        registry.register(env, workspace)
 
         print(f'Extracted source into {workspace.directory}')
-        # XXX: try to load smaller inputs?
-        if workspace.inputs:
+        if args.load_inputs:
+            load_all(env, workspace)
+        elif workspace.inputs:
             print('Input data not loaded, update if needed and load manually')
 
 
-def print_inputs(env, workspace, verbose):
+class CmdVerify(Command):
+    '''
+    Check that a bead is not damaged, and with --key that it is signed by the key's owner.
+
+    Unchanged archives in boxes verified recently are not verified again, unless forced.
+    '''
+
+    def declare(self, arg):
+        arg(BEAD_REF_BASE)
+        arg(BEAD_TIME)
+        arg('--key', dest='public_key', type=tech.fs.Path, default=None,
+            help='ssh public key file of the expected signer')
+        arg('--resume', default=False, action='store_true',
+            help='continue an interrupted verification, skipping files already verified')
+        arg('--force', default=False, action='store_true',
+            help='verify the content even if it was verified recently')
+        arg(REPORT_FORMAT)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        env = args.get_env()
+        try:
+            bead = resolve_bead(env, args.bead_ref_base, args.bead_time)
+        except LookupError:
+            die('Bead not found!')
+        # stdout is for the report only
+        feedback = sys.stdout if args.output_format == TEXT else sys.stderr
+        with contextlib.redirect_stdout(feedback):
+            content = verify_content(env, bead, args.resume, args.force)
+            signature = verify_signature(bead, args.public_key)
+        if args.output_format != TEXT:
+            emit(
+                Report(
+                    f'Verification of {bead.archive_filename}',
+                    ('name', 'freeze_time', 'archive', 'content', 'signature'),
+                    [dict(
+                        name=bead.name, freeze_time=bead.freeze_time_str,
+                        archive=str(bead.archive_filename), content=content,
+                        signature=signature)]),
+                args.output_format)
+
+
+def verify_content(env, bead, resume, force):
+    '''
+    Verify the files of the bead, unless verified recently - die if it is damaged.
+
+    Returns how the content was found OK.
+    '''
+    # verification results are remembered only for archives in boxes
+    box = env.get_box(bead.box_name) if bead.box_name else None
+    since = time_from_timestamp(timestamp()) - DEFAULT_VERIFICATION_MAX_AGE
+    if not force and box is not None and box.is_verified_since(bead.archive_path, since):
+        print(f'Archive {bead.archive_filename} was verified recently - OK'
+              ' (use --force to verify again)')
+        return 'OK (verified recently)'
+    verified_at = timestamp()
+    # verified files are recorded, so that an interrupted verification can be resumed
+    checkpoint = env.verify_checkpoint(bead.archive_path)
+    if not resume:
+        checkpoint.remove()
+    elif len(checkpoint):
+        info(f'Resuming: {len(checkpoint)} files are already verified')
+    try:
+        verify_with_feedback(bead, progress_bar(), checkpoint)
+    except InvalidArchive as e:
+        checkpoint.close()
+        die(f'Bead is damaged: {e}', e.exit_code)
+    checkpoint.remove()
+    if box is not None:
+        box.set_verified(bead, verified_at)
+    return 'OK'
+
+
+def verify_signature(bead, public_key):
+    '''
+    Check the signature of the bead with public_key, if given - die if it is not valid.
+
+    Returns the outcome.
+    '''
+    if public_key is None:
+        if bead.is_signed:
+            info(f'Signature is not checked, use --key to verify {bead.signature_path}')
+            return 'not checked'
+        return 'unsigned'
+    try:
+        bead.verify_signature(public_key)
+    except SignatureError as e:
+        die(f'{e}')
+    print(f'Signature OK ({public_key})')
+    return 'OK'
+
+
+def automated_marker(bead):
+    '''
+    Listing suffix telling apart beads saved by pipeline runners from manually saved ones.
+    '''
+    runner = automated_by(bead)
+    if runner is None:
+        return ''
+    return f' [automated: {runner}]'
+
+
+def load_status(workspace, input_name):
+    if not workspace.is_loaded(input_name):
+        return '**NOT LOADED**'
+    patterns = workspace.loaded_patterns(input_name)
+    if patterns is not None:
+        return f'partially loaded ({" ".join(patterns)})'
+    return 'loaded'
+
+
+def print_input_candidates(boxes, input, input_bead_name):
+    print('\tBox[es]:')
+    has_box = False
+    for box in boxes:
+        try:
+            context = box.get_context(bead_spec.BEAD_NAME, input_bead_name, input.freeze_time)
+        except LookupError:
+            # not in this box
+            continue
+        bead = context.best
+        has_box = True
+        exact_match = bead.content_id == input.content_id
+        print(
+            f'\t {"*" if exact_match else "?"} -r {box.name} # {bead.freeze_time_str}'
+            + automated_marker(bead))
+    if not has_box:
+        print('\t - no candidates :(')
+        print('\t   Maybe it has been renamed? or is it in an unreachable box?')
+
+
+def print_inputs(env, workspace, verbose, status=None):
     assert_valid_workspace(workspace)
     inputs = sorted(workspace.inputs)
+    newer_freeze_times = {}
+    if status is not None:
+        newer_freeze_times = {
+            input.name: input.newer_freeze_time_str for input in status.inputs}
 
     if inputs:
         boxes = env.get_boxes()
+        content_ids = short_content_ids(input.content_id for input in inputs)
 
         print('Inputs:')
         has_not_loaded = False
@@ -181,28 +503,15 @@ def print_inputs(env, workspace, verbose):
             is_not_loaded = not workspace.is_loaded(input.name)
             has_not_loaded = has_not_loaded or is_not_loaded
             print(f'input/{input.name}')
-            print(f'\tStatus:      {"**NOT LOADED**" if is_not_loaded else "loaded"}')
+            print(f'\tStatus:      {load_status(workspace, input.name)}')
             input_bead_name = workspace.get_input_bead_name(input.name)
             print(f'\tBead:        {input_bead_name} # {input.freeze_time_str}')
+            if newer_freeze_times.get(input.name):
+                print(f'\tNewer:       {input_bead_name} # {newer_freeze_times[input.name]}')
             if verbose:
                 print(f'\tKind:        {input.kind}')
-                print(f'\tContent id:  {input.content_id}')
-            print('\tBox[es]:')
-            has_box = False
-            for box in boxes:
-                try:
-                    context = box.get_context(
-                        bead_spec.BEAD_NAME, input_bead_name, input.freeze_time)
-                except LookupError:
-                    # not in this box
-                    continue
-                bead = context.best
-                has_box = True
-                exact_match = bead.content_id == input.content_id
-                print(f'\t {"*" if exact_match else "?"} -r {box.name} # {bead.freeze_time_str}')
-            if not has_box:
-                print('\t - no candidates :(')
-                print('\t   Maybe it has been renamed? or is it in an unreachable box?')
+                print(f'\tContent id:  {content_ids[input.content_id]}')
+            print_input_candidates(boxes, input, input_bead_name)
             is_not_first_input = True
 
         print('')
@@ -216,12 +525,18 @@ def print_inputs(env, workspace, verbose):
 class CmdStatus(Command):
     '''
     Show workspace status - name of bead, inputs and their unpack status.
+
+    With --recursive a summary of all workspaces under the directory is shown.
     '''
 
     def declare(self, arg):
         arg(OPTIONAL_WORKSPACE)
         arg('-v', '--verbose', default=False, action='store_true',
             help='show more detailed information')
+        arg('-r', '--recursive', default=False, action='store_true',
+            help='summarize all workspaces under the directory')
+        arg(JSON_FLAG)
+        arg(REPORT_FORMAT)
         arg(OPTIONAL_ENV)
 
     def run(self, args):
@@ -229,16 +544,345 @@ class CmdStatus(Command):
         verbose = args.verbose
         env = args.get_env()
         kind_needed = verbose
+        if args.recursive:
+            emit(recursive_status(env, workspace.directory), args.output_format)
+            return
+        if args.output_format not in (TEXT, JSON):
+            die(f'--format {args.output_format} is supported only with --recursive')
         if workspace.is_valid:
+            status = workspace.status(env.get_boxes())
+            if args.output_format == JSON:
+                print_json(status.as_dict())
+                return
             print(f'Bead Name: {workspace.name}')
             if kind_needed:
                 print(f'Bead kind: {workspace.kind}')
+            print_save_status(status)
             print()
-            print_inputs(env, workspace, verbose)
+            print_inputs(env, workspace, verbose, status)
         else:
             warning(f'Invalid workspace ({workspace.directory})')
 
 
+def recursive_status(env, directory) -> Report:
+    boxes = env.get_boxes()
+    rows = []
+    for workspace in Workspace.find_all(directory):
+        status = workspace.status(boxes)
+        code_changes = status.code_changes
+        rows.append(dict(
+            name=status.name,
+            directory=status.directory,
+            last_saved=status.last_saved_freeze_time_str,
+            output_changed=status.has_output_changes,
+            code_changed=None if code_changes is None else code_changes.is_changed,
+            inputs=len(status.inputs),
+            outdated_inputs=sum(
+                1 for input in status.inputs if input.newer_freeze_time_str is not None)))
+    return Report(
+        f'Workspaces under {directory}',
+        ('name', 'directory', 'last_saved', 'output_changed', 'code_changed',
+         'inputs', 'outdated_inputs'),
+        rows)
+
+
+# format of `bead ide-info` - change it on incompatible changes
+IDE_INFO_VERSION = 1
+
+
+def ide_info(workspace):
+    '''
+    Workspace layout for editors: where code, inputs, outputs are and what to run with.
+    '''
+    directory = workspace.directory.resolve()
+    input_dir = directory / layouts.Workspace.INPUT
+    output_dir = directory / layouts.Workspace.OUTPUT
+    temp_dir = directory / layouts.Workspace.TEMP
+    inputs = []
+    for input in sorted(workspace.inputs):
+        inputs.append(dict(
+            name=input.name,
+            path=str(input_dir / input.name),
+            loaded=workspace.is_loaded(input.name),
+            bead_name=workspace.get_input_bead_name(input.name),
+            kind=input.kind,
+            content_id=input.content_id,
+            freeze_time=input.freeze_time_str))
+    return dict(
+        version=IDE_INFO_VERSION,
+        name=workspace.name,
+        kind=workspace.kind,
+        directory=str(directory),
+        layout=dict(
+            input=str(input_dir),
+            output=str(output_dir),
+            temp=str(temp_dir),
+            meta=str(directory / layouts.Workspace.META)),
+        inputs=inputs,
+        # loaded input data must not be edited
+        read_only=[input['path'] for input in inputs if input['loaded']],
+        # not part of the code, not worth indexing
+        excluded=[str(input_dir), str(temp_dir), str(directory / layouts.Workspace.META)],
+        environment={
+            'BEAD_WORKSPACE': str(directory),
+            'BEAD_INPUT': str(input_dir),
+            'BEAD_OUTPUT': str(output_dir),
+            'BEAD_TEMP': str(temp_dir)})
+
+
+class CmdIdeInfo(Command):
+    '''
+    Print workspace layout, input paths and environment variables as JSON for editors/IDEs.
+    '''
+
+    def declare(self, arg):
+        arg(OPTIONAL_WORKSPACE)
+        arg(JSON_OUTPUT)
+
+    def run(self, args):
+        workspace = args.workspace
+        assert_valid_workspace(workspace)
+        print(tech.persistence.dumps(ide_info(workspace)))
+
+
+def print_save_status(status):
+    if status.last_saved_freeze_time_str is None:
+        print('Last saved: never')
+    else:
+        print(f'Last saved: {status.last_saved_freeze_time_str}')
+        if status.has_output_changes:
+            print('Output changed since last save')
+        else:
+            print('Output unchanged since last save')
+    code_changes = status.code_changes
+    if code_changes is None:
+        return
+    if not code_changes.is_changed:
+        print('Code unchanged since the developed version')
+        return
+    print('Code changed since the developed version:')
+    for change, paths in (
+        ('added', code_changes.added),
+        ('modified', code_changes.modified),
+        ('deleted', code_changes.deleted),
+    ):
+        for path in paths:
+            print(f'  {change}: {path}')
+
+
+OPEN_OUTPUT = DefaultArgSentinel('output directory of workspace')
+
+
+class CmdOpen(Command):
+    '''
+    Open a directory in the system file manager.
+
+    Without arguments the output directory of the workspace is opened.
+    An input name opens the loaded data of that input, while any other
+    bead reference is extracted into a temporary directory, which is opened.
+    '''
+
+    def declare(self, arg):
+        arg('target', metavar=f'{arg_metavar.INPUT_NICK}|{arg_metavar.BEAD_REF}',
+            nargs='?', default=OPEN_OUTPUT,
+            help='input name or bead to open (default: %(default)s)')
+        arg(BEAD_TIME)
+        arg(OPTIONAL_WORKSPACE)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        target = args.target
+        workspace = args.workspace
+        if target is OPEN_OUTPUT:
+            assert_valid_workspace(workspace)
+            directory = workspace.directory / layouts.Workspace.OUTPUT
+        elif workspace.is_valid and workspace.has_input(target):
+            if not workspace.is_loaded(target):
+                die(f'Input "{target}" is not loaded')
+            directory = workspace.directory / layouts.Workspace.INPUT / target
+        else:
+            directory = extract_for_viewing(args.get_env(), target, args.bead_time)
+        print(f'Opening {directory}')
+        open_in_file_manager(directory)
+
+
+def extract_for_viewing(env, bead_ref_base, bead_time):
+    try:
+        bead = resolve_bead(env, bead_ref_base, bead_time)
+    except LookupError:
+        die('Bead not found!')
+    # the temporary directory is left behind, as the file manager is not waited for
+    temp_dir = tech.fs.Path(tempfile.mkdtemp(prefix='bead-open-'))
+    with tech.fs.removed_on_error(temp_dir):
+        try:
+            workspace = Workspace.from_archive(
+                bead, temp_dir / bead.name, extract_output=True, progress=progress_bar())
+        except InvalidArchive:
+            die('Bead is damaged')
+    return workspace.directory
+
+
+class CmdSample(Command):
+    '''
+    Extract a deterministic sample of the rows of tabular output files of a bead.
+
+    The sample depends only on the bead content: reviewers of the same bead
+    see the same rows, without extracting the full data.
+    '''
+
+    def declare(self, arg):
+        arg(BEAD_REF_BASE)
+        arg(BEAD_TIME)
+        arg('-n', '--n', dest='rows', type=int, default=1000,
+            help='number of rows to sample from each file')
+        arg('--files', dest='max_files', type=int, default=None,
+            help='sample only this many files (default: all tabular files)')
+        arg('--out', dest='out_dir', type=tech.fs.Path, required=True, metavar='DIRECTORY',
+            help='directory to write the samples to')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        if args.rows < 1 or (args.max_files is not None and args.max_files < 1):
+            die('--n and --files must be at least 1')
+        if args.out_dir.exists() and os.listdir(args.out_dir):
+            die(f'{args.out_dir} is not empty')
+        try:
+            bead = resolve_bead(args.get_env(), args.bead_ref_base, args.bead_time)
+        except LookupError:
+            die('Bead not found!')
+        result = sample.sample(bead, args.rows, args.out_dir, args.max_files)
+        for path, row_count in result.sampled:
+            print(f'{path}: {row_count} rows')
+        if result.skipped:
+            info(f'{len(result.skipped)} files are not sampled (not tabular or not chosen)')
+        if not result.sampled:
+            warning('No tabular files were sampled')
+
+
+class CmdExport(Command):
+    '''
+    Export the data of a bead (and optionally its code) for people not using bead.
+
+    The destination is a new directory, or a .tar.gz (or .tgz) file.
+    Bead meta data is not exported.
+    '''
+
+    def declare(self, arg):
+        arg(BEAD_REF_BASE)
+        arg(BEAD_TIME)
+        arg('--out', dest='destination', type=tech.fs.Path, required=True,
+            metavar='DIRECTORY|TARBALL', help='new directory or .tar.gz file to export to')
+        arg('--code', dest='include_code', default=False, action='store_true',
+            help='export the code as well (data and code go to data/ and code/)')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        if args.destination.exists():
+            die(f'{args.destination} already exists')
+        try:
+            bead = resolve_bead(args.get_env(), args.bead_ref_base, args.bead_time)
+        except LookupError:
+            die('Bead not found!')
+        try:
+            destination = export.export(
+                bead, args.destination, args.include_code, progress=progress_bar())
+        except InvalidArchive:
+            die('Bead is damaged')
+        print(f'Exported {bead.name} {bead.freeze_time_str} to {destination}')
+
+
+class CmdImport(Command):
+    '''
+    Save a directory of files (e.g. a third party dataset) as a new bead - without a workspace.
+
+    The files become the output of the bead, which has no code and no inputs.
+    Every import makes a bead of a new kind.
+    '''
+
+    def declare(self, arg):
+        arg('directory', type=tech.fs.Path, help='directory of the files to import')
+        arg('box_name', nargs='?', default=USE_THE_ONLY_BOX, type=str,
+            metavar=arg_metavar.BOX, help=arg_help.BOX)
+        arg('--name', default=None,
+            help='name of the new bead (default: the name of the directory)')
+        arg('--description', default=None, help='description of the new bead')
+        arg('--compression', choices=ZIP_COMPRESSIONS, default=USE_BOX_COMPRESSION,
+            help='compression of the archive (default: %(default)s)')
+        arg('--hash', dest='hash_algorithm', choices=HASH_ALGORITHMS,
+            default=USE_ENV_HASH_ALGORITHM,
+            help='algorithm hashing the archive content')
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        if not args.directory.is_dir():
+            die(f'{args.directory} is not a directory')
+        name = args.name or tech.fs.portable_name(args.directory.resolve().name)
+        assert_may_be_valid_name(name)
+        env = args.get_env()
+        box = box_to_save_to(env, args.box_name)
+        data = DataDirectory(
+            args.directory, name, tech.identifier.uuid(), description=args.description)
+        try:
+            save_policy.check_name_collision(box, data, env.get_name_collision_policy())
+        except SaveRefused as e:
+            die_with(e)
+        compression = args.compression
+        if compression is USE_BOX_COMPRESSION:
+            compression = box.compression or env.get_zip_compression()
+        if args.hash_algorithm is USE_ENV_HASH_ALGORITHM:
+            hash_algorithm = env.get_hash_algorithm()
+        else:
+            hash_algorithm = tech.securehash.HashAlgo(args.hash_algorithm)
+        if not tech.securehash.is_available(hash_algorithm):
+            die(f'{hash_algorithm.value} hashing is not available (is the package installed?)')
+        try:
+            archive = box.store(
+                data, timestamp(), compression, progress=progress_bar(),
+                hash_algorithm=hash_algorithm)
+        except (BoxError, ValueError) as e:
+            die(f'Error importing: {e}')
+        count_bytes('saved', os.path.getsize(archive.archive_path))
+        print(f'Imported {args.directory} as bead {name} at {archive.archive_path}')
+        print(f'Load it as input with: bead input add INPUT {name}')
+
+
+class CmdUnlock(Command):
+    '''
+    Remove the lock of a workspace (or box) left behind by a crashed bead process.
+
+    Locks of running processes are kept. Whether the owner of a lock made on another host
+    is still running can not be checked - such locks are removed only with --force.
+    '''
+
+    def declare(self, arg):
+        arg('--box', dest='box_name', default=None, metavar=arg_metavar.BOX,
+            help='unlock this box instead of the workspace')
+        arg('--force', default=False, action='store_true',
+            help='remove the lock, even if its owner might still be running')
+        arg(OPTIONAL_WORKSPACE)
+        arg(OPTIONAL_ENV)
+
+    def run(self, args):
+        if args.box_name is not None:
+            lock = get_box(args.get_env(), args.box_name).lock()
+        else:
+            assert_valid_workspace(args.workspace)
+            lock = tech.lock.FileLock(args.workspace.directory / layouts.Workspace.LOCK)
+        if not lock.path.exists():
+            print('Not locked')
+            return
+        holder = lock.holder()
+        if lock.remove_if_stale():
+            print(f'Removed stale lock of {holder}')
+        elif args.force:
+            lock.remove()
+            print(f'Removed lock of {holder}')
+        else:
+            die(
+                f'{lock.path} is held by {holder}, which might still be running',
+                suggestions=['bead unlock --force  # if it surely is not running'])
+
+
 class CmdZap(Command):
     '''
     Delete the current workspace directory - like rm -rf "$PWD", only more aggressive.
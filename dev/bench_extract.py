@@ -0,0 +1,53 @@
+#!/usr/bin/env python3
+'''
+Benchmark data extraction of a big bead with different thread counts.
+
+Usage: dev/bench_extract.py [SIZE_MB [FILES [THREADS...]]]
+
+E.g. dev/bench_extract.py 4096 64 1 2 4 8
+'''
+
+import os
+import sys
+import tempfile
+import time
+
+sys.path.insert(0, os.path.dirname(os.path.dirname(os.path.abspath(__file__))))
+
+from bead import ziparchive  # noqa: E402
+from bead.archive import Archive  # noqa: E402
+from bead.tech.fs import Path, rmtree  # noqa: E402
+from bead.workspace import Workspace  # noqa: E402
+
+BLOCK = 1024 ** 2
+
+
+def make_bead(directory, size_mb, files):
+    workspace = Workspace(os.path.join(directory, 'big'))
+    workspace.create('benchmark')
+    for i in range(files):
+        with open(workspace.directory / f'output/file{i}', 'wb') as f:
+            for _ in range(size_mb // files):
+                f.write(os.urandom(BLOCK))
+    archive = os.path.join(directory, 'big.zip')
+    workspace.pack(archive, '20200101T000000000000+0000', 'benchmark', compression='stored')
+    rmtree(workspace.directory)
+    return archive
+
+
+def main(size_mb=1024, files=16, *thread_counts):
+    thread_counts = thread_counts or (1, ziparchive.DEFAULT_EXTRACT_THREADS)
+    with tempfile.TemporaryDirectory() as directory:
+        archive = make_bead(directory, int(size_mb), int(files))
+        for threads in thread_counts:
+            os.environ[ziparchive.EXTRACT_THREADS_ENV_VAR] = str(threads)
+            target = Path(directory) / f'data-{threads}'
+            start = time.perf_counter()
+            Archive(archive).unpack_data_to(target)
+            seconds = time.perf_counter() - start
+            print(f'{threads} thread(s): {seconds:.2f}s ({int(size_mb) / seconds:.0f} MB/s)')
+            rmtree(target)
+
+
+if __name__ == '__main__':
+    main(*sys.argv[1:])
@@ -0,0 +1,52 @@
+#!/usr/bin/env python3
+'''
+Benchmark saving a workspace of many files with different hashing thread counts.
+
+Usage: dev/bench_pack.py [FILES [SIZE_KB [THREADS...]]]
+
+E.g. dev/bench_pack.py 10000 64 1 2 4 8
+'''
+
+import os
+import sys
+import tempfile
+import time
+
+sys.path.insert(0, os.path.dirname(os.path.dirname(os.path.abspath(__file__))))
+
+from bead import workspace as bead_workspace  # noqa: E402
+from bead.tech import securehash  # noqa: E402
+from bead.workspace import Workspace  # noqa: E402
+
+
+def make_workspace(directory, files, size_kb):
+    workspace = Workspace(os.path.join(directory, 'many'))
+    workspace.create('benchmark')
+    for i in range(files):
+        subdirectory = workspace.directory / f'output/{i // 1000}'
+        os.makedirs(subdirectory, exist_ok=True)
+        with open(subdirectory / f'file{i}', 'wb') as f:
+            f.write(os.urandom(size_kb * 1024))
+    return workspace
+
+
+def main(files=10000, size_kb=64, *thread_counts):
+    thread_counts = thread_counts or (1, securehash.DEFAULT_HASH_THREADS)
+    size_mb = int(files) * int(size_kb) / 1024
+    # measure the threads, not the threshold
+    bead_workspace.PARALLEL_HASH_MIN_SIZE = 0
+    with tempfile.TemporaryDirectory() as directory:
+        workspace = make_workspace(directory, int(files), int(size_kb))
+        for threads in thread_counts:
+            os.environ[securehash.HASH_THREADS_ENV_VAR] = str(threads)
+            archive = os.path.join(directory, f'many-{threads}.zip')
+            start = time.perf_counter()
+            workspace.pack(
+                archive, '20200101T000000000000+0000', 'benchmark', compression='stored')
+            seconds = time.perf_counter() - start
+            print(f'{threads} thread(s): {seconds:.2f}s ({size_mb / seconds:.0f} MB/s)')
+            os.remove(archive)
+
+
+if __name__ == '__main__':
+    main(*sys.argv[1:])
@@ -3,6 +3,7 @@ import string
 from typing import Dict, Optional
 
 from bead.meta import InputSpec
+from bead.tech.timestamp import format_timestamp
 from bead_cli.web.dummy import Dummy
 from bead_cli.web.graph import Ref
 from bead_cli.web.sketch import Sketch
@@ -80,7 +81,7 @@ class Sketcher:
             name=name.rstrip(string.digits),
             kind=kind,
             content_id=f"content_id_{proto}",
-            freeze_time_str=timestamp.strftime('%Y%m%dT%H%M%S%f%z'),
+            freeze_time_str=format_timestamp(timestamp),
             box_name=box_name,
         )
         # clones share inputs, thus if a new input is added to any of them
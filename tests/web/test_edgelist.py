@@ -0,0 +1,42 @@
+import csv
+import io
+import json
+
+from tests.sketcher import Sketcher
+
+
+def sketch_of_two_inputs():
+    sketcher = Sketcher()
+    sketcher.define('a1 b1')
+    sketcher.define('c1', kind='c-kind', box_name='other')
+    sketcher.compile('a1 -in-a-> c1   b1 -in-b-> c1')
+    return sketcher.sketch
+
+
+def test_json_is_the_graph_as_dict():
+    sketch = sketch_of_two_inputs()
+    data = json.loads(sketch.as_json())
+
+    assert sketch.as_dict() == data
+    [c] = [bead for bead in data['beads'] if bead['name'] == 'c']
+    assert ('c-kind', 'other') == (c['kind'], c['box'])
+    assert {('a', 'c', 'in-a'), ('b', 'c', 'in-b')} == {
+        (edge['source']['name'], edge['sink']['name'], edge['input'])
+        for edge in data['edges']}
+
+
+def test_csv_edges_have_attributes_of_both_ends():
+    rows = list(csv.DictReader(io.StringIO(sketch_of_two_inputs().as_csv_edges())))
+
+    assert [('a', 'c', 'in-a'), ('b', 'c', 'in-b')] == [
+        (row['source_name'], row['sink_name'], row['input']) for row in rows]
+    assert {'other'} == {row['sink_box'] for row in rows}
+    assert {'c-kind'} == {row['sink_kind'] for row in rows}
+
+
+def test_csv_of_graph_without_edges_is_only_a_header():
+    sketcher = Sketcher()
+    sketcher.define('a1')
+
+    [header] = sketcher.sketch.as_csv_edges().splitlines()
+    assert header.startswith('source_name,source_kind,')
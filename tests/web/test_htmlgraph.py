@@ -0,0 +1,41 @@
+import json
+import re
+
+from tests.sketcher import Sketcher
+from bead_cli.web.htmlgraph import as_html
+
+
+def embedded_data(page):
+    [data] = re.findall(
+        r'<script type="application/json" id="graph-data">(.*?)</script>', page, re.DOTALL)
+    return json.loads(data)
+
+
+def test_graph_is_embedded_with_freshness():
+    sketcher = Sketcher()
+    sketcher.define('a1 a2 b1')
+    sketcher.compile('a1 -> b1')
+    sketch = sketcher.sketch
+    sketch.color_beads()
+
+    data = embedded_data(as_html(sketch))
+
+    freshness = {
+        (bead['name'], bead['freeze_time']): bead['freshness'] for bead in data['beads']}
+    assert sorted(freshness.values()) == ['out_of_date', 'superseded', 'up_to_date']
+    [edge] = data['edges']
+    assert ('a', 'b') == (edge['source']['name'], edge['sink']['name'])
+    assert 'green' == data['colors']['up_to_date']
+
+
+def test_names_can_not_break_out_of_the_page():
+    sketcher = Sketcher()
+    sketcher.define('a1')
+    sketch = sketcher.sketch
+    sketch.beads[0].name = '</script><b>'
+
+    page = as_html(sketch, title='<i>')
+
+    assert '</script><b>' not in page
+    assert '<title>&lt;i&gt;</title>' in page
+    assert '</script><b>' == embedded_data(page)['beads'][0]['name']
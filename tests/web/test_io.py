@@ -40,7 +40,8 @@ META_JSON = """\
             }
         ],
         "kind": "kind_ood2",
-        "name": "ood2"
+        "name": "ood2",
+        "runner": null
     },
     {
         "@class": "Dummy",
@@ -67,7 +68,8 @@ META_JSON = """\
             }
         ],
         "kind": "kind_ood1",
-        "name": "ood1"
+        "name": "ood1",
+        "runner": null
     },
     {
         "@class": "Dummy",
@@ -83,7 +85,8 @@ META_JSON = """\
         "input_map": {},
         "inputs": [],
         "kind": "kind_root_2",
-        "name": "root2"
+        "name": "root2",
+        "runner": null
     },
     {
         "@class": "Dummy",
@@ -99,7 +102,8 @@ META_JSON = """\
         "input_map": {},
         "inputs": [],
         "kind": "kind_root_1",
-        "name": "root1"
+        "name": "root1",
+        "runner": null
     },
     {
         "@class": "Dummy",
@@ -115,7 +119,8 @@ META_JSON = """\
         "input_map": {},
         "inputs": [],
         "kind": "kind_root_1",
-        "name": "root1"
+        "name": "root1",
+        "runner": null
     },
     {
         "@class": "Dummy",
@@ -150,7 +155,8 @@ META_JSON = """\
             }
         ],
         "kind": "kind_ood3",
-        "name": "ood3"
+        "name": "ood3",
+        "runner": null
     }
 ]
 """
@@ -172,6 +178,13 @@ def test_written_data_is_unchanged(tmp_path):
     assert new_meta.read_text().splitlines() == META_JSON.splitlines()
 
 
+def test_graphs_written_before_runners_were_recorded_are_read():
+    test_beads = loads(META_JSON.replace(',\n        "runner": null', ''))
+
+    assert test_beads == loads(META_JSON)
+    assert {None} == {bead.runner for bead in test_beads}
+
+
 def test_files(tmp_path):
     meta = tmp_path / 'new_meta'
 
@@ -0,0 +1,82 @@
+from bead.box import Box
+from bead.workspace import Workspace
+from bead_cli.web.sketch import Sketch
+
+from tests.sketcher import Sketcher
+
+
+def names(beads):
+    return [bead.name for bead in beads]
+
+
+def sketch_with_fork():
+    sketcher = Sketcher()
+    sketcher.define('a1 b1 c1 d1 e1 x1')
+    sketcher.compile('a1 -> b1 -> c1 -> d1')
+    sketcher.compile('            c1 -:fork:-> e1')
+    sketcher.compile('      x1 -:other:-> d1')
+    return sketcher, sketcher.sketch
+
+
+def test_ancestors():
+    sketcher, sketch = sketch_with_fork()
+
+    assert ['a', 'b', 'c', 'x'] == names(sketch.ancestors(sketcher['d1']))
+    assert ['a', 'b', 'c'] == names(sketch.ancestors(sketcher['e1']))
+    assert [] == names(sketch.ancestors(sketcher['a1']))
+
+
+def test_descendants():
+    sketcher, sketch = sketch_with_fork()
+
+    assert ['b', 'c', 'd', 'e'] == names(sketch.descendants(sketcher['a1']))
+    assert ['d'] == names(sketch.descendants(sketcher['x1']))
+    assert [] == names(sketch.descendants(sketcher['e1']))
+
+
+def test_roots():
+    _sketcher, sketch = sketch_with_fork()
+
+    assert ['a', 'x'] == names(sketch.roots())
+
+
+def test_from_boxes(tmp_path):
+    box_dir = tmp_path / 'box'
+    box_dir.mkdir()
+    box = Box('box', box_dir)
+    src = Workspace(tmp_path / 'src')
+    src.create('src-kind')
+    src_archive = box.store(src, '20200101T000000000000+0000')
+    dest = Workspace(tmp_path / 'dest')
+    dest.create('dest-kind')
+    dest.load('src', src_archive)
+    box.store(dest, '20200102T000000000000+0000')
+
+    sketch = Sketch.from_boxes([box])
+
+    assert ['dest', 'src'] == sorted(names(sketch.beads))
+    [dest_bead] = [bead for bead in sketch.beads if bead.name == 'dest']
+    assert ['src'] == names(sketch.ancestors(dest_bead))
+
+
+def test_from_boxes_follows_input_map_through_develop(tmp_path):
+    box_dir = tmp_path / 'box'
+    box_dir.mkdir()
+    box = Box('box', box_dir)
+    src = Workspace(tmp_path / 'src')
+    src.create('src-kind')
+    src_archive = box.store(src, '20200101T000000000000+0000')
+    dest = Workspace(tmp_path / 'dest')
+    dest.create('dest-kind')
+    dest.load('raw', src_archive)
+    dest.set_input_bead_name('raw', 'src')
+    dest_archive = box.store(dest, '20200102T000000000000+0000')
+    # the input map survives a develop - save cycle
+    developed = Workspace.from_archive(dest_archive, tmp_path / 'developed')
+    assert {'raw': 'src'} == developed.input_map
+    box.store(developed, '20200103T000000000000+0000')
+
+    sketch = Sketch.from_boxes([box])
+
+    assert ['dest', 'developed', 'src'] == sorted(names(sketch.beads))
+    assert {('src', 'raw')} == {(edge.src.name, edge.label) for edge in sketch.edges}
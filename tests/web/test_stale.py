@@ -0,0 +1,52 @@
+from tests.sketcher import Sketcher
+
+
+def names(beads):
+    return [bead.name for bead in beads]
+
+
+def test_newer_input_version_makes_bead_stale():
+    sketcher = Sketcher()
+    sketcher.define('a1 a2', kind='kind-a')
+    sketcher.define('b1 c1')
+    sketcher.compile('a1 -> b1')
+    sketcher.compile('a2 -> c1')
+
+    sketch = sketcher.sketch
+
+    assert ['b'] == names(sketch.stale_beads())
+    [stale_input] = sketch.stale_inputs()
+    assert 'a' == stale_input.input_name
+    assert sketcher['a1'].content_id == stale_input.current.content_id
+    assert sketcher['a2'].content_id == stale_input.newest.content_id
+
+
+def test_newest_of_the_same_kind_counts_across_names():
+    sketcher = Sketcher()
+    sketcher.define('a1', kind='kind-a')
+    sketcher.define('x5', kind='kind-a')
+    sketcher.define('b9')
+    sketcher.compile('a1 -> b9')
+
+    [stale_input] = sketcher.sketch.stale_inputs()
+    assert 'x' == stale_input.newest.name
+
+
+def test_superseded_beads_are_not_reported():
+    sketcher = Sketcher()
+    sketcher.define('a1 a2', kind='kind-a')
+    sketcher.define('b1 b2')
+    sketcher.compile('a1 -> b1')
+    sketcher.compile('a2 -:a:-> b2')
+
+    assert [] == names(sketcher.sketch.stale_beads())
+
+
+def test_up_to_date_inputs():
+    sketcher = Sketcher()
+    sketcher.define('a1', kind='kind-a')
+    sketcher.define('b1', kind='kind-b')
+    sketcher.define('c1', kind='kind-c')
+    sketcher.compile('a1 -> b1 -> c1')
+
+    assert () == sketcher.sketch.stale_inputs()